@@ -11,41 +11,47 @@
 use std::ffi::CString;
 use std::os::raw::c_char;
 
+use futures::Future;
 use indy_sys;
+use serde_json;
 
+use client::SovtokenClient;
 use logic::api_internals::{
     add_request_fees,
     create_address
 };
 use logic::address;
+use logic::address::PaymentAddress;
+use logic::balance_check::extract_balance_check_from_extra;
 use logic::build_payment;
 use logic::config::{
     get_fees_config::GetFeesRequest,
-    get_utxo_config:: *,
 };
 use logic::did::Did;
-use logic::indy_sdk_api::crypto_api::CryptoSdk;
+use logic::merkle_proof;
 use logic::indy_sdk_api::ledger;
 use logic::minting;
 use logic::verify;
 use logic::parsers::{
     parse_get_utxo_response,
-    parse_response_with_fees_handler,
     parse_verify,
-    parse_get_utxo_response::{ParseGetUtxoResponse, ParseGetUtxoReply},
-    parse_payment_response::{ParsePaymentResponse, ParsePaymentReply, from_response},
-    parse_response_with_fees_handler::{ParseResponseWithFees, ParseResponseWithFeesReply},
-    parse_get_txn_fees::{parse_fees_from_get_txn_fees_response, get_fees_state_proof_extractor}
+    parse_get_utxo_response::ParseGetUtxoReply,
+    parse_payment_response::ParsePaymentReply,
+    parse_response_with_fees_handler::ParseResponseWithFeesReply,
+    parse_get_txn_fees::get_fees_state_proof_extractor,
+    parse_get_txn_author_agreement::{parse_get_acceptance_mechanisms_response, parse_get_txn_author_agreement_response}
 };
-use logic::payments::CreatePaymentHandler;
 use logic::set_fees;
-use logic::xfer_payload::XferPayload;
+use logic::state_proof;
+use logic::type_aliases::TxnSeqNo;
 
 use utils::constants::general::{JsonCallback, PAYMENT_METHOD_NAME, LEDGER_ID};
+use utils::current_error::{self, missing_parameter};
+use utils::txn_author_agreement;
 use ErrorCode;
 use utils::constants::txn_types::{GET_FEES, GET_UTXO};
 use utils::ffi_support::{str_from_char_ptr, string_from_char_ptr, c_pointer_from_string};
-use utils::json_conversion::{JsonDeserialize, JsonSerialize};
+use utils::json_conversion::JsonSerialize;
 use utils::general::ResultExtension;
 use utils::callbacks::ClosureHandler;
 use utils::results::ResultHandler;
@@ -66,6 +72,11 @@ use indy_sys::{ResponseBoolCB, ResponseSliceCB};
 /// config_str: payment address config as json:
 ///   {
 ///     seed: <str>, // allows deterministic creation of payment address
+///     path: <str>, // optional, derives the address at this path ("m/0'/5'") from seed as master seed
+///     index: <int>, // optional, derives the address of this index from seed as master seed
+///     mnemonic: <str>, // optional, BIP-39 mnemonic whose seed is the master seed instead of seed,
+///                      // derives the address at m/0'/0' without path and index
+///     passphrase: <str>, // optional, ASCII BIP-39 passphrase of mnemonic
 ///   }
 /// cb: description
 ///
@@ -91,8 +102,15 @@ pub extern "C" fn create_payment_address_handler(
 
     let payment_closure = create_address::create_address_cb(command_handle, cb);
 
-    let handler = CreatePaymentHandler::new(CryptoSdk {});
-    let ec = handler.create_payment_address_async(wallet_handle, config, payment_closure);
+    let ec = match SovtokenClient::new().create_address_with_callback(wallet_handle, config, Box::new(move |address| {
+        match address {
+            Ok(address) => payment_closure(address.to_string(), ErrorCode::Success),
+            Err(ec) => payment_closure(String::new(), ec),
+        }
+    })) {
+        Ok(()) => ErrorCode::Success,
+        Err(ec) => ec
+    };
     trace!("api::create_payment_address_handler << result: {:?}", ec);
     return ec as i32;
 }
@@ -136,6 +154,20 @@ pub extern "C" fn create_payment_address_handler(
  * ]
  * ```
  * 
+ * ### extra
+ * Optional json object which is added to the request. A `balanceCheck`
+ * field is taken out of it and not sent to the ledger. With it the inputs
+ * and outputs are checked locally against the amounts of the UTXOs being
+ * spent and the fee, see [`BalanceCheck`].
+ * ```JSON
+ * {
+ *      "balanceCheck": {
+ *          "utxos": [<UTXO from parse_get_utxo_response_handler>],
+ *          "fee": <int>
+ *      }
+ * }
+ * ```
+ * 
  * ## Example
  * 
  * ### Parameters
@@ -179,6 +211,8 @@ pub extern "C" fn create_payment_address_handler(
  *      }
  * }
  * ```
+ *
+ * [`BalanceCheck`]: ../logic/balance_check/struct.BalanceCheck.html
  */
 #[no_mangle]
 pub extern "C" fn add_request_fees_handler(
@@ -201,22 +235,21 @@ pub extern "C" fn add_request_fees_handler(
         }
     };
 
-    /*
-        Errors when the request is a XFER request becaause the 
-        fees should be implicit in the operation's inputs and
-        outputs.
-    */
-    if let Err(_) = add_request_fees::validate_type_not_transfer(&request_json_map) {
-        error!("api::add_request_fees_handler Can't add fees to a transfer request");
-        return ErrorCode::CommonInvalidStructure as i32;
-    }
+    let (extra, balance_check) = match extract_balance_check_from_extra(extra) {
+        Ok(tup) => tup,
+        Err(error_code) => {
+            trace!("api::add_request_fees_handler result >> {:?}", error_code);
+            return error_code as i32;
+        }
+    };
 
-    let result = add_request_fees::add_fees_to_request_and_serialize(
+    let result = SovtokenClient::new().add_fees_with_callback(
         wallet_handle,
+        request_json_map,
         inputs,
         outputs,
         extra,
-        request_json_map,
+        balance_check,
         Box::new(add_request_fees::closure_cb_response(command_handle, cb))
     );
 
@@ -248,6 +281,10 @@ pub extern "C" fn add_request_fees_handler(
 /// # Errors
 /// CommonInvalidStructure when any of the inputs are invalid
 /// CommonInvalidState when any processing of inputs produces invalid results
+/// CommonInvalidStructure when the merkle proof of the reply doesn't verify,
+/// if [`sovtoken_set_verify_replies`] turned the check on
+///
+/// [`sovtoken_set_verify_replies`]: fn.sovtoken_set_verify_replies.html
 #[no_mangle]
 pub extern "C" fn parse_response_with_fees_handler(
     command_handle: i32,
@@ -260,7 +297,7 @@ pub extern "C" fn parse_response_with_fees_handler(
 
     if req_json.is_null() {
         trace!("api::parse_response_with_fees_handler << result: {:?}", ErrorCode::CommonInvalidStructure);
-        return ErrorCode::CommonInvalidStructure as i32;
+        return missing_parameter("req_json") as i32;
     }
 
     let resp_json_string = match string_from_char_ptr(req_json) {
@@ -273,14 +310,7 @@ pub extern "C" fn parse_response_with_fees_handler(
 
     debug!("api::parse_response_with_fees_handler >> req_json: {:?}", resp_json_string);
 
-    let response: ParseResponseWithFees = match ParseResponseWithFees::from_json(&resp_json_string).map_err(map_err_err!()) {
-        Ok(r) => r,
-        Err(_) => return ErrorCode::CommonInvalidStructure as i32,
-    };
-
-    // here is where the magic happens--conversion from input structure to output structure
-    // is handled in ParseResponseWithFeesReply::from_response
-    let reply: Option<ParseResponseWithFeesReply> = match parse_response_with_fees_handler::from_response(response) {
+    let reply: Option<ParseResponseWithFeesReply> = match SovtokenClient::new().parse_response_with_fees(&resp_json_string).wait() {
         Ok(rep) => rep,
         Err(ec) => {
             trace!("api::parse_response_with_fees_handler << result: {:?}", ec);
@@ -335,6 +365,20 @@ pub extern "C" fn parse_response_with_fees_handler(
  * ]
  * ```
  * 
+ * ### extra
+ * Optional json object which is added to the request. A `balanceCheck`
+ * field is taken out of it and not sent to the ledger. With it the inputs
+ * and outputs are checked locally against the amounts of the UTXOs being
+ * spent and the fee, see [`BalanceCheck`].
+ * ```JSON
+ * {
+ *      "balanceCheck": {
+ *          "utxos": [<UTXO from parse_get_utxo_response_handler>],
+ *          "fee": <int>
+ *      }
+ * }
+ * ```
+ * 
  * ## Returns
  * Returns a json object of the payment request.
  * ```JSON
@@ -349,6 +393,8 @@ pub extern "C" fn parse_response_with_fees_handler(
  *      }
  * }
  * ```
+ *
+ * [`BalanceCheck`]: ../logic/balance_check/struct.BalanceCheck.html
  */
 #[no_mangle]
 pub extern "C" fn build_payment_req_handler(
@@ -370,12 +416,22 @@ pub extern "C" fn build_payment_req_handler(
             }
         };
 
-    let payload = XferPayload::new(inputs, outputs, extra);
+    let (extra, balance_check) = match extract_balance_check_from_extra(extra) {
+        Ok(tup) => tup,
+        Err(error_code) => {
+            trace!("api::build_payment_req_handler << result: {:?}", error_code);
+            return error_code as i32;
+        }
+    };
 
-    let result = payload.sign_transfer(
-        &CryptoSdk {},
+    let result = SovtokenClient::new().build_payment_with_callback(
         wallet_handle,
-        Box::new(move |result| build_payment::handle_signing(command_handle, result, submitter_did.clone(), cb))
+        inputs,
+        outputs,
+        extra,
+        submitter_did,
+        balance_check,
+        Box::new(add_request_fees::closure_cb_response(command_handle, cb))
     );
 
     let ec = match result {
@@ -400,6 +456,10 @@ pub extern "C" fn build_payment_req_handler(
 /// # Errors
 /// CommonInvalidStructure when any of the inputs are invalid
 /// CommonInvalidState when any processing of inputs produces invalid results
+/// CommonInvalidStructure when the merkle proof of the reply doesn't verify,
+/// if [`sovtoken_set_verify_replies`] turned the check on
+///
+/// [`sovtoken_set_verify_replies`]: fn.sovtoken_set_verify_replies.html
 #[no_mangle]
 pub extern "C" fn parse_payment_response_handler(
     command_handle: i32,
@@ -411,7 +471,7 @@ pub extern "C" fn parse_payment_response_handler(
 
     if resp_json.is_null() {
         trace!("api::parse_payment_response_handler << result: {:?}", ErrorCode::CommonInvalidStructure);
-        return ErrorCode::CommonInvalidStructure as i32;
+        return missing_parameter("resp_json") as i32;
     }
 
     let resp_json_string = match string_from_char_ptr(resp_json) {
@@ -424,15 +484,7 @@ pub extern "C" fn parse_payment_response_handler(
 
     debug!("api::parse_payment_response_handler >> resp_json: {:?}", &resp_json_string);
 
-    let response: ParsePaymentResponse = match ParsePaymentResponse::from_json(&resp_json_string)
-        .map_err(map_err_err!()) {
-        Ok(r) => r,
-        Err(_) => return ErrorCode::CommonInvalidStructure as i32,
-    };
-
-    // here is where the magic happens--conversion from input structure to output structure
-    // is handled in ParsePaymentReply::from_response
-    let reply: ParsePaymentReply = match from_response(response) {
+    let reply: ParsePaymentReply = match SovtokenClient::new().parse_payment(&resp_json_string).wait() {
         Ok(rep) => rep,
         Err(ec) => {
             trace!("api::parse_payment_response_handler << result: {:?}", ec);
@@ -470,9 +522,36 @@ pub extern "C" fn parse_payment_response_handler(
 #[no_mangle]
 pub extern "C" fn build_get_utxo_request_handler(command_handle: i32,
                                                  wallet_handle: i32,
-                                                 _submitter_did: *const c_char,
+                                                 submitter_did: *const c_char,
                                                  payment_address: *const c_char,
                                                  cb: JsonCallback) -> i32 {
+    build_get_utxo_request_with_from_handler(command_handle, wallet_handle, submitter_did, payment_address, -1, cb)
+}
+
+/// Builds a GET_UTXO request which reads the UTXOs starting at seq_no `from`
+///
+/// Ledgers page big UTXO sets. The `next` cursor returned by
+/// [`parse_get_utxo_response_with_next_handler`] is the `from` of the next page.
+///
+/// # Params
+/// command_handle: standard command handle
+/// wallet_handle: wallet handle
+/// submitter_did: unused, the payment address is the identifier of the request
+/// payment_address: address to read the UTXOs of
+/// from: seq_no to start at, -1 to start at the first UTXO
+/// cb: callback called with the request json
+///
+/// # Errors
+/// CommonInvalidStructure when the payment address or the callback is missing
+///
+/// [`parse_get_utxo_response_with_next_handler`]: fn.parse_get_utxo_response_with_next_handler.html
+#[no_mangle]
+pub extern "C" fn build_get_utxo_request_with_from_handler(command_handle: i32,
+                                                           wallet_handle: i32,
+                                                           _submitter_did: *const c_char,
+                                                           payment_address: *const c_char,
+                                                           from: i64,
+                                                           cb: JsonCallback) -> i32 {
     trace!("api::build_get_utxo_request_handler called");
     let handle_result = api_result_handler!(< *const c_char >, command_handle, cb);
 
@@ -480,16 +559,23 @@ pub extern "C" fn build_get_utxo_request_handler(command_handle: i32,
         Some(s) => s,
         None => {
             error!("Failed to convert payment_address pointer to string");
-            return ErrorCode::CommonInvalidStructure as i32;
+            return missing_parameter("payment_address") as i32;
+        }
+    };
+    debug!("api::build_get_utxo_request_handler >> wallet_handle: {:?}, payment_address: {:?}, from: {:?}", wallet_handle, secret!(&payment_address), from);
+
+    let payment_address = match PaymentAddress::new(payment_address) {
+        Ok(payment_address) => payment_address,
+        Err(ec) => {
+            trace!("api::build_get_utxo_request_handler << result: {:?}", ec);
+            return ec as i32;
         }
     };
-    debug!("api::build_get_utxo_request_handler >> wallet_handle: {:?}, payment_address: {:?}", wallet_handle, secret!(&payment_address));
 
-    let utxo_request =
-        GetUtxoOperationRequest::new(String::from(payment_address));
-    info!("Built GET_UTXO request: {:?}", utxo_request);
-    let utxo_request = utxo_request.serialize_to_pointer()
-        .map_err(|_| ErrorCode::CommonInvalidStructure);
+    let from = if from < 0 { None } else { Some(from as TxnSeqNo) };
+
+    let utxo_request = SovtokenClient::new().build_get_utxo(&payment_address, from).wait()
+        .map(c_pointer_from_string);
 
     let res = handle_result(utxo_request) as i32;
     trace!("api::build_get_utxo_request_handler << result: {:?}", res);
@@ -511,6 +597,13 @@ pub extern "C" fn build_get_utxo_request_handler(command_handle: i32,
 /// # Errors
 /// CommonInvalidStructure when any of the inputs are invalid
 /// CommonInvalidState when any processing of inputs produces invalid results
+/// CommonInvalidStructure when the state proof of the reply doesn't verify,
+/// if [`sovtoken_set_verify_replies`] turned the check on
+/// LedgerInvalidTransaction when the state proof is older than
+/// [`sovtoken_set_max_state_proof_age`] allows
+///
+/// [`sovtoken_set_verify_replies`]: fn.sovtoken_set_verify_replies.html
+/// [`sovtoken_set_max_state_proof_age`]: fn.sovtoken_set_max_state_proof_age.html
 #[no_mangle]
 pub extern "C" fn parse_get_utxo_response_handler(
     command_handle: i32,
@@ -521,49 +614,87 @@ pub extern "C" fn parse_get_utxo_response_handler(
     trace!("api::parse_get_utxo_response_handler called");
     check_useful_c_callback!(cb, ErrorCode::CommonInvalidStructure as i32);
 
+    match parse_get_utxo_reply(resp_json) {
+        Ok((reply_str, _)) => {
+            cb(command_handle, ErrorCode::Success as i32, c_pointer_from_string(reply_str));
+            trace!("api::parse_get_utxo_response_handler << result: {:?}", ErrorCode::Success);
+            ErrorCode::Success as i32
+        }
+        Err(err) => {
+            trace!("api::parse_get_utxo_response_handler << result: {:?}", err);
+            err as i32
+        }
+    }
+}
+
+/// Parses a paged GET_UTXO reply
+///
+/// Same as [`parse_get_utxo_response_handler`], and also passes the `next`
+/// cursor to the callback.
+///
+/// # Params
+/// command_handle: standard command handle
+/// resp_json: json. For format see https://github.com/sovrin-foundation/libsovtoken/blob/master/doc/data_structures.md
+/// cb: callback called with the utxo json and `next`
+///
+/// # Returns
+/// utxo_json: json. For format see https://github.com/sovrin-foundation/libsovtoken/blob/master/doc/data_structures.md
+/// next: seq_no to pass as `from` to [`build_get_utxo_request_with_from_handler`]
+/// for the next page, -1 when this was the last page
+///
+/// # Errors
+/// CommonInvalidStructure when any of the inputs are invalid
+/// CommonInvalidState when any processing of inputs produces invalid results
+///
+/// [`parse_get_utxo_response_handler`]: fn.parse_get_utxo_response_handler.html
+/// [`build_get_utxo_request_with_from_handler`]: fn.build_get_utxo_request_with_from_handler.html
+#[no_mangle]
+pub extern "C" fn parse_get_utxo_response_with_next_handler(
+    command_handle: i32,
+    resp_json: *const c_char,
+    cb: Option<extern "C" fn(command_handle_: i32, err: i32, utxo_json: *const c_char, next: i64) -> i32>
+) -> i32 {
+
+    trace!("api::parse_get_utxo_response_with_next_handler called");
+    check_useful_c_callback!(cb, ErrorCode::CommonInvalidStructure as i32);
+
+    match parse_get_utxo_reply(resp_json) {
+        Ok((reply_str, next)) => {
+            let next = next.map(|next| next as i64).unwrap_or(-1);
+            cb(command_handle, ErrorCode::Success as i32, c_pointer_from_string(reply_str), next);
+            trace!("api::parse_get_utxo_response_with_next_handler << result: {:?}", ErrorCode::Success);
+            ErrorCode::Success as i32
+        }
+        Err(err) => {
+            trace!("api::parse_get_utxo_response_with_next_handler << result: {:?}", err);
+            err as i32
+        }
+    }
+}
+
+fn parse_get_utxo_reply(resp_json: *const c_char) -> Result<(String, Option<TxnSeqNo>), ErrorCode> {
     if resp_json.is_null() {
-        trace!("api::parse_get_utxo_response_handler << result: {:?}", ErrorCode::CommonInvalidStructure);
-        return ErrorCode::CommonInvalidStructure as i32;
+        return Err(missing_parameter("resp_json"));
     }
 
     let resp_json_string = match string_from_char_ptr(resp_json) {
         Some(s) => s,
         None => {
             error!("Failed to convert inputs_json pointer to string");
-            trace!("api::parse_get_utxo_response_handler << result: {:?}", ErrorCode::CommonInvalidStructure);
-            return ErrorCode::CommonInvalidStructure as i32;
+            return Err(ErrorCode::CommonInvalidStructure);
         }
     };
 
     debug!("api::parse_get_utxo_response_handler >> resp_json: {:?}", resp_json_string);
 
-    let response: ParseGetUtxoResponse = match ParseGetUtxoResponse::from_json(&resp_json_string)
-        .map_err(map_err_err!()) {
-        Ok(r) => r,
-        Err(_) => return ErrorCode::CommonInvalidStructure as i32,
-    };
-
-    // here is where the magic happens--conversion from input structure to output structure
-    // is handled in ParseGetUtxoReply::from_response
-    let reply: ParseGetUtxoReply = match parse_get_utxo_response::from_response(response) {
-        Ok(reply) => reply,
-        Err(err) => {
-            trace!("api::parse_get_utxo_response_handler << result: {:?}", err);
-            return err as i32
-        }
-    };
-
-    let reply_str: String = match reply.to_json().map_err(map_err_err!()) {
-        Ok(j) => j,
-        Err(_) => return ErrorCode::CommonInvalidState as i32,
-    };
-    info!("Parsed GET_UTXO response, received: {:?}", reply_str);
+    let (reply, next): (ParseGetUtxoReply, Option<TxnSeqNo>) = SovtokenClient::new().parse_get_utxo(&resp_json_string).wait()?;
 
-    let reply_str_ptr: *const c_char = c_pointer_from_string(reply_str);
+    let reply_str: String = reply.to_json()
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidState))?;
+    info!("Parsed GET_UTXO response, received: {:?}, next: {:?}", reply_str, next);
 
-    cb(command_handle, ErrorCode::Success as i32, reply_str_ptr);
-    trace!("api::parse_get_utxo_response_handler << result: {:?}", ErrorCode::Success);
-    return ErrorCode::Success as i32;
+    Ok((reply_str, next))
 }
 
 /**
@@ -695,7 +826,13 @@ pub extern "C" fn build_get_txn_fees_handler(
 /// fees_json: JSON String. Structure of JSON available in libsovtoken/docs/data_structures.md
 ///
 /// # Errors
+/// CommonInvalidStructure when the state proof of the reply doesn't verify,
+/// if [`sovtoken_set_verify_replies`] turned the check on
+/// LedgerInvalidTransaction when the state proof is older than
+/// [`sovtoken_set_max_state_proof_age`] allows
 ///
+/// [`sovtoken_set_verify_replies`]: fn.sovtoken_set_verify_replies.html
+/// [`sovtoken_set_max_state_proof_age`]: fn.sovtoken_set_max_state_proof_age.html
 #[no_mangle]
 pub extern "C" fn parse_get_txn_fees_response_handler(
     command_handle: i32,
@@ -706,7 +843,7 @@ pub extern "C" fn parse_get_txn_fees_response_handler(
 
     trace!("api::parse_get_txn_fees_response_handler called");
     if resp_json.is_null() {
-        return ErrorCode::CommonInvalidStructure as i32;
+        return missing_parameter("resp_json") as i32;
     }
     let resp_json_string = match string_from_char_ptr(resp_json) {
         Some(s) => s,
@@ -721,7 +858,8 @@ pub extern "C" fn parse_get_txn_fees_response_handler(
     debug!("Deserialized parse_get_txn_fees_response_handler arguments");
 
     let fees_json_obj =
-        match parse_fees_from_get_txn_fees_response(resp_json_string) {
+        match SovtokenClient::new().parse_get_txn_fees(&resp_json_string).wait()
+            .and_then(|fees| serde_json::to_string(&fees).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))) {
             Ok(s) => {
                 s
             },
@@ -787,8 +925,8 @@ pub extern "C" fn build_mint_txn_handler(
 
     debug!("Deserialized build_mint_txn_handler arguments.");
 
-    let mint_request = match minting::build_mint_request(did, outputs, extra) {
-        Ok(json) => json,
+    let mint_request = match SovtokenClient::new().build_mint(did, outputs, extra).wait() {
+        Ok(json) => c_pointer_from_string(json),
         Err(e) => {
             trace!("api::build_mint_txn_handle << res: {:?}", e);
             return e as i32
@@ -870,7 +1008,7 @@ pub extern "C" fn parse_verify_response_handler(
 
     trace!("api::parse_verify_response_handler called");
     if resp_json.is_null() {
-        return ErrorCode::CommonInvalidStructure as i32;
+        return missing_parameter("resp_json") as i32;
     }
 
     let resp_json_string = match string_from_char_ptr(resp_json) {
@@ -1009,6 +1147,120 @@ fn _check_address_is_vk(address: *const c_char) -> Result<String, ErrorCode> {
     }
 }
 
+/**
+    Turns the check of the merkle audit paths of the replies parsed by
+    [`parse_payment_response_handler`] and [`parse_response_with_fees_handler`],
+    and of the state proofs of the replies parsed by
+    [`parse_get_utxo_response_handler`] and [`parse_get_txn_fees_response_handler`],
+    on or off. It is off until turned on.
+
+    # Params
+    verify: whether replies whose proof doesn't verify are rejected
+
+    # Returns
+    Success
+
+    [`parse_payment_response_handler`]: fn.parse_payment_response_handler.html
+    [`parse_response_with_fees_handler`]: fn.parse_response_with_fees_handler.html
+    [`parse_get_utxo_response_handler`]: fn.parse_get_utxo_response_handler.html
+    [`parse_get_txn_fees_response_handler`]: fn.parse_get_txn_fees_response_handler.html
+*/
+#[no_mangle]
+pub extern "C" fn sovtoken_set_verify_replies(verify: bool) -> i32 {
+    trace!("api::sovtoken_set_verify_replies >> verify: {:?}", verify);
+    merkle_proof::set_verify_replies(verify);
+    ErrorCode::Success as i32
+}
+
+/**
+    Sets how many seconds old the state proofs of the replies parsed by
+    [`parse_get_utxo_response_handler`] and [`parse_get_txn_fees_response_handler`]
+    may be, by the timestamp of their multi signature. Proofs of any age are
+    accepted until set.
+
+    # Params
+    max_age: seconds, 0 accepts proofs of any age
+
+    # Returns
+    Success
+
+    [`parse_get_utxo_response_handler`]: fn.parse_get_utxo_response_handler.html
+    [`parse_get_txn_fees_response_handler`]: fn.parse_get_txn_fees_response_handler.html
+*/
+#[no_mangle]
+pub extern "C" fn sovtoken_set_max_state_proof_age(max_age: u64) -> i32 {
+    trace!("api::sovtoken_set_max_state_proof_age >> max_age: {:?}", max_age);
+    state_proof::set_max_proof_age(if max_age == 0 { None } else { Some(max_age) });
+    ErrorCode::Success as i32
+}
+
+/**
+    Sets the Transaction Author Agreement the TAA acceptances in the `extra`
+    of payments and fees are checked against before the inputs get signed.
+    Requests without acceptance are refused while an agreement is set.
+
+    # Params
+    get_taa_response_json: reply to a GET_TXN_AUTHR_AGRMT request, null stops the checks
+    get_aml_response_json: reply to a GET_TXN_AUTHR_AGRMT_AML request, optional
+
+    # Returns
+    Success
+
+    # Errors
+    CommonInvalidStructure when a reply is invalid or the digest of the agreement doesn't match
+*/
+#[no_mangle]
+pub extern "C" fn sovtoken_set_txn_author_agreement(get_taa_response_json: *const c_char, get_aml_response_json: *const c_char) -> i32 {
+    trace!("api::sovtoken_set_txn_author_agreement >> get_taa_response_json: {:?}, get_aml_response_json: {:?}", get_taa_response_json, get_aml_response_json);
+
+    let response = match string_from_char_ptr(get_taa_response_json) {
+        Some(response) => response,
+        None => {
+            txn_author_agreement::set_current_agreement(None);
+            return ErrorCode::Success as i32;
+        }
+    };
+
+    let current = parse_get_txn_author_agreement_response(&response).and_then(|agreement| {
+        let mechanisms = match string_from_char_ptr(get_aml_response_json) {
+            Some(response) => parse_get_acceptance_mechanisms_response(&response)?,
+            None => None,
+        };
+        Ok(agreement.map(|agreement| (agreement, mechanisms)))
+    });
+
+    let ec = match current {
+        Ok(current) => {
+            txn_author_agreement::set_current_agreement(current);
+            ErrorCode::Success
+        }
+        Err(ec) => ec,
+    };
+    trace!("api::sovtoken_set_txn_author_agreement << result: {:?}", ec);
+    ec as i32
+}
+
+/**
+    Gets the details of the last error of the calling thread, like
+    `indy_get_current_error` does for libindy.
+
+    The json has the `message` of the error, the `parameter` which was
+    invalid if one was, its `causes` with the outermost first and, for
+    requests rejected by the ledger, the `details` of the rejection.
+
+    # Params
+    error_json_p: set to the json, or to null when the thread had no error.
+    The string is owned by the library and stays valid until the next error
+    on the same thread.
+*/
+#[no_mangle]
+pub extern "C" fn sovtoken_get_current_error(error_json_p: *mut *const c_char) {
+    trace!("api::sovtoken_get_current_error >> error_json_p: {:?}", error_json_p);
+    if let Some(error_json_p) = unsafe { error_json_p.as_mut() } {
+        *error_json_p = current_error::current_error_c_json();
+    }
+}
+
 /**
     exported method indy-sdk will call for us to register our payment methods with indy-sdk
 
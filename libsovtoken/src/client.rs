@@ -0,0 +1,643 @@
+/*!
+    Rust API of the payment handlers.
+
+    [`SovtokenClient`] exposes the operations of the C ABI as futures which
+    resolve to typed results instead of JSON strings. The handlers in [`api`]
+    only convert their C arguments and results, and call the
+    `*_with_callback` methods of a client backed by [`CryptoSdk`].
+
+    ```rust,no_run
+    extern crate futures;
+    extern crate sovtoken;
+
+    use futures::Future;
+    use sovtoken::client::SovtokenClient;
+    use sovtoken::logic::config::payment_address_config::PaymentAddressConfig;
+
+    # fn main() {
+    let wallet_handle = 1;
+    let client = SovtokenClient::new();
+
+    let address = client.create_address(wallet_handle, PaymentAddressConfig { seed: String::new(), ..Default::default() }).wait().unwrap();
+    let get_utxo_request = client.build_get_utxo(&address, None).wait().unwrap();
+    # }
+    ```
+
+    [`SovtokenClient`]: struct.SovtokenClient.html
+    [`api`]: ../api/index.html
+    [`CryptoSdk`]: ../logic/indy_sdk_api/crypto_api/struct.CryptoSdk.html
+*/
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::Future;
+use futures::future;
+use futures::sync::oneshot;
+use serde_json;
+
+use {ErrorCode, IndyHandle};
+use logic::address::PaymentAddress;
+use logic::api_internals::add_request_fees::{self, SerdeMap};
+use logic::balance_check::BalanceCheck;
+use logic::build_payment;
+use logic::config::get_txn_config::GetTxnOperationRequest;
+use logic::config::get_utxo_config::GetUtxoOperationRequest;
+use logic::config::payment_address_config::PaymentAddressConfig;
+use logic::did::Did;
+use logic::history::TokenTransaction;
+use logic::indy_sdk_api::crypto_api::{CryptoAPI, CryptoSdk};
+use logic::input::Inputs;
+use logic::merkle_proof;
+use logic::minting;
+use logic::output::Outputs;
+use logic::parsers::parse_get_txn::{self, ParseGetTxnResponse};
+use logic::parsers::parse_get_txn_fees;
+use logic::parsers::parse_get_utxo_response::{self, ParseGetUtxoResponse, ParseGetUtxoReply};
+use logic::parsers::parse_payment_response::{self, ParsePaymentResponse, ParsePaymentReply};
+use logic::parsers::parse_response_with_fees_handler::{self, ParseResponseWithFees, ParseResponseWithFeesReply};
+use logic::payments::CreatePaymentHandler;
+use logic::state_proof;
+use logic::type_aliases::{TokenAmount, TxnSeqNo};
+use logic::xfer_payload::{Extra, XferPayload};
+use utils::current_error::{invalid_reply, set_current_error, ErrorDetails};
+use utils::json_conversion::JsonDeserialize;
+
+pub type SovtokenFuture<T> = Box<Future<Item=T, Error=ErrorCode> + Send>;
+pub type ResultCallback<T> = Box<Fn(Result<T, ErrorCode>) + Send + Sync>;
+
+/**
+    Builds and parses payment requests, signing with the keys of `A`.
+
+    Requests are returned as json, ready to be signed by the submitter and
+    sent to the ledger.
+*/
+pub struct SovtokenClient<A: CryptoAPI + 'static> {
+    crypto_api: &'static A,
+    verify_proofs: bool,
+    max_proof_age: Option<u64>,
+}
+
+impl SovtokenClient<CryptoSdk> {
+    /**
+        A client signing with the keys of libindy wallets.
+    */
+    pub fn new() -> SovtokenClient<CryptoSdk> {
+        SovtokenClient::with_crypto_api(&CryptoSdk {})
+    }
+}
+
+impl Default for SovtokenClient<CryptoSdk> {
+    fn default() -> Self {
+        SovtokenClient::new()
+    }
+}
+
+impl<A: CryptoAPI + 'static> SovtokenClient<A> {
+    pub fn with_crypto_api(crypto_api: &'static A) -> SovtokenClient<A> {
+        SovtokenClient {
+            crypto_api,
+            verify_proofs: merkle_proof::verify_replies(),
+            max_proof_age: state_proof::max_proof_age(),
+        }
+    }
+
+    /**
+        Whether [`parse_payment`] and [`parse_response_with_fees`] reject
+        replies whose merkle audit paths don't verify, and [`parse_get_utxo`]
+        and [`parse_get_txn_fees`] replies whose state proofs don't. Defaults
+        to [`merkle_proof::verify_replies`].
+
+        [`parse_payment`]: #method.parse_payment
+        [`parse_response_with_fees`]: #method.parse_response_with_fees
+        [`parse_get_utxo`]: #method.parse_get_utxo
+        [`parse_get_txn_fees`]: #method.parse_get_txn_fees
+        [`merkle_proof::verify_replies`]: ../logic/merkle_proof/fn.verify_replies.html
+    */
+    pub fn verify_proofs(mut self, verify_proofs: bool) -> SovtokenClient<A> {
+        self.verify_proofs = verify_proofs;
+        self
+    }
+
+    /**
+        How many seconds old the state proofs of the replies parsed by
+        [`parse_get_utxo`] and [`parse_get_txn_fees`] may be. Older replies
+        fail with `LedgerInvalidTransaction`. Defaults to
+        [`state_proof::max_proof_age`].
+
+        [`parse_get_utxo`]: #method.parse_get_utxo
+        [`parse_get_txn_fees`]: #method.parse_get_txn_fees
+        [`state_proof::max_proof_age`]: ../logic/state_proof/fn.max_proof_age.html
+    */
+    pub fn max_proof_age(mut self, max_proof_age: Option<u64>) -> SovtokenClient<A> {
+        self.max_proof_age = max_proof_age;
+        self
+    }
+
+    /**
+        Creates a payment address whose key is stored in the wallet.
+    */
+    pub fn create_address(&self, wallet_handle: IndyHandle, config: PaymentAddressConfig) -> SovtokenFuture<PaymentAddress> {
+        callback_future(|cb| self.create_address_with_callback(wallet_handle, config, cb))
+    }
+
+    pub fn create_address_with_callback(
+        &self,
+        wallet_handle: IndyHandle,
+        config: PaymentAddressConfig,
+        cb: ResultCallback<PaymentAddress>
+    ) -> Result<(), ErrorCode> {
+        let handler = CreatePaymentHandler::new(self.crypto_api);
+        let ec = handler.create_payment_address_async(wallet_handle, config, move |address: String, error_code: ErrorCode| {
+            if error_code != ErrorCode::Success {
+                cb(Err(error_code));
+                return;
+            }
+            cb(PaymentAddress::new(&address));
+        });
+
+        match ec {
+            ErrorCode::Success => Ok(()),
+            ec => Err(ec),
+        }
+    }
+
+    /**
+        Builds a signed XFER request.
+
+        The verkey of the first input is the identifier of the request when
+        there is no `identifier`. When a [`BalanceCheck`] is given, the inputs
+        and outputs are checked against it before anything is signed.
+
+        [`BalanceCheck`]: ../logic/balance_check/struct.BalanceCheck.html
+    */
+    pub fn build_payment(
+        &self,
+        wallet_handle: IndyHandle,
+        inputs: Inputs,
+        outputs: Outputs,
+        extra: Option<Extra>,
+        identifier: Option<Did>,
+        balance_check: Option<BalanceCheck>
+    ) -> SovtokenFuture<String> {
+        callback_future(|cb| self.build_payment_with_callback(wallet_handle, inputs, outputs, extra, identifier, balance_check, cb))
+    }
+
+    pub fn build_payment_with_callback(
+        &self,
+        wallet_handle: IndyHandle,
+        inputs: Inputs,
+        outputs: Outputs,
+        extra: Option<Extra>,
+        identifier: Option<Did>,
+        balance_check: Option<BalanceCheck>,
+        cb: ResultCallback<String>
+    ) -> Result<(), ErrorCode> {
+        if let Some(balance_check) = balance_check {
+            balance_check.check(&inputs, &outputs)?;
+        }
+
+        let payload = XferPayload::new(inputs, outputs, extra);
+
+        payload.sign_transfer(
+            self.crypto_api,
+            wallet_handle,
+            Box::new(move |result| cb(build_payment::build_payment_request(identifier.clone(), result)))
+        )
+    }
+
+    /**
+        Signs the fees and adds them to a non XFER request.
+
+        When a [`BalanceCheck`] is given, the inputs and outputs are checked
+        against it, with its fee, before anything is signed.
+
+        [`BalanceCheck`]: ../logic/balance_check/struct.BalanceCheck.html
+    */
+    pub fn add_fees(
+        &self,
+        wallet_handle: IndyHandle,
+        request_json: &str,
+        inputs: Inputs,
+        outputs: Outputs,
+        extra: Option<Extra>,
+        balance_check: Option<BalanceCheck>
+    ) -> SovtokenFuture<String> {
+        callback_future(|cb| {
+            let request_json_map = serde_json::from_str::<serde_json::Value>(request_json)
+                .map_err(map_err_err!())
+                .ok()
+                .and_then(|request| request.as_object().cloned())
+                .ok_or(ErrorCode::CommonInvalidStructure)?;
+
+            self.add_fees_with_callback(wallet_handle, request_json_map, inputs, outputs, extra, balance_check, cb)
+        })
+    }
+
+    pub fn add_fees_with_callback(
+        &self,
+        wallet_handle: IndyHandle,
+        request_json_map: SerdeMap,
+        inputs: Inputs,
+        outputs: Outputs,
+        extra: Option<Extra>,
+        balance_check: Option<BalanceCheck>,
+        cb: ResultCallback<String>
+    ) -> Result<(), ErrorCode> {
+        /*
+            Errors when the request is a XFER request becaause the
+            fees should be implicit in the operation's inputs and
+            outputs.
+        */
+        if let Err(ec) = add_request_fees::validate_type_not_transfer(&request_json_map) {
+            error!("Can't add fees to a transfer request");
+            set_current_error(ErrorDetails::new("Invalid req_json").parameter("req_json")
+                .cause("fees can only be added to a request which has an operation type other than XFER_PUBLIC"));
+            return Err(ec);
+        }
+
+        if let Some(balance_check) = balance_check {
+            balance_check.check(&inputs, &outputs)?;
+        }
+
+        add_request_fees::sign_fees_and_serialize(self.crypto_api, wallet_handle, inputs, outputs, extra, request_json_map, cb)
+    }
+
+    /**
+        Builds a GET_UTXO request, starting at seq_no `from` when given.
+    */
+    pub fn build_get_utxo(&self, address: &PaymentAddress, from: Option<TxnSeqNo>) -> SovtokenFuture<String> {
+        let utxo_request = GetUtxoOperationRequest::new(address.qualified().to_string(), from);
+        info!("Built GET_UTXO request: {:?}", utxo_request);
+
+        let utxo_request = utxo_request.serialize_to_string()
+            .map_err(|_| ErrorCode::CommonInvalidStructure);
+        Box::new(future::result(utxo_request))
+    }
+
+    /**
+        Parses a GET_UTXO reply into its UTXOs and the `from` of the next page,
+        if there is one.
+
+        Fails with `LedgerInvalidTransaction` when the state proof is older than
+        [`max_proof_age`](#method.max_proof_age).
+    */
+    pub fn parse_get_utxo(&self, response_json: &str) -> SovtokenFuture<(ParseGetUtxoReply, Option<TxnSeqNo>)> {
+        let reply = self.verify_state_proof(response_json, parse_get_utxo_response::verify_get_utxo_state_proof)
+            .and_then(|_| {
+                ParseGetUtxoResponse::from_json(response_json)
+                    .map_err(invalid_reply)
+            })
+            .and_then(parse_get_utxo_response::from_response_with_next);
+        Box::new(future::result(reply))
+    }
+
+    /**
+        Parses the UTXOs created by a XFER request out of its reply.
+    */
+    pub fn parse_payment(&self, response_json: &str) -> SovtokenFuture<ParsePaymentReply> {
+        let reply = self.verify_proof(response_json, merkle_proof::verify_payment_reply)
+            .and_then(|_| {
+                ParsePaymentResponse::from_json(response_json)
+                    .map_err(invalid_reply)
+            })
+            .and_then(parse_payment_response::from_response);
+        Box::new(future::result(reply))
+    }
+
+    /**
+        Parses the UTXOs created by the fees of a request out of its reply.
+
+        Resolves to `None` when the reply has no fees.
+    */
+    pub fn parse_response_with_fees(&self, response_json: &str) -> SovtokenFuture<Option<ParseResponseWithFeesReply>> {
+        let reply = self.verify_proof(response_json, merkle_proof::verify_reply_with_fees)
+            .and_then(|_| {
+                ParseResponseWithFees::from_json(response_json)
+                    .map_err(invalid_reply)
+            })
+            .and_then(parse_response_with_fees_handler::from_response);
+        Box::new(future::result(reply))
+    }
+
+    /**
+        Parses the fee of each transaction type out of a GET_FEES reply.
+
+        Fails with `LedgerInvalidTransaction` when the state proof is older than
+        [`max_proof_age`](#method.max_proof_age).
+    */
+    pub fn parse_get_txn_fees(&self, response_json: &str) -> SovtokenFuture<HashMap<String, TokenAmount>> {
+        let fees = self.verify_state_proof(response_json, parse_get_txn_fees::verify_get_fees_state_proof)
+            .and_then(|_| parse_get_txn_fees::fees_from_get_txn_fees_response(response_json));
+        Box::new(future::result(fees))
+    }
+
+    /**
+        Builds the GET_TXN requests for the token ledger transactions from
+        seq_no `from` to `to`, both included.
+    */
+    pub fn build_get_txns(&self, from: TxnSeqNo, to: TxnSeqNo, did: Option<Did>) -> SovtokenFuture<Vec<String>> {
+        let requests = GetTxnOperationRequest::range(from, to, did)
+            .and_then(|requests| {
+                requests.iter()
+                    .map(|request| request.serialize_to_string().map_err(|_| ErrorCode::CommonInvalidStructure))
+                    .collect()
+            });
+        Box::new(future::result(requests))
+    }
+
+    /**
+        Parses the token ledger transaction out of a GET_TXN reply. Resolves to
+        `None` when there is no transaction with the seq_no or it doesn't move
+        tokens. See [`history`] for folding the transactions.
+
+        [`history`]: ../logic/history/index.html
+    */
+    pub fn parse_get_txn(&self, response_json: &str) -> SovtokenFuture<Option<TokenTransaction>> {
+        let txn = ParseGetTxnResponse::from_json(response_json)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))
+            .and_then(parse_get_txn::from_response);
+        Box::new(future::result(txn))
+    }
+
+    /**
+        Builds a MINT request. It has to be signed by a quorum of trustees.
+    */
+    pub fn build_mint(&self, did: Option<Did>, outputs: Outputs, extra: Option<String>) -> SovtokenFuture<String> {
+        Box::new(future::result(minting::build_mint_request_json(did, outputs, extra)))
+    }
+
+    fn verify_proof(&self, response_json: &str, verify: fn(&serde_json::Value) -> Result<(), ErrorCode>) -> Result<(), ErrorCode> {
+        if !self.verify_proofs {
+            return Ok(());
+        }
+
+        let reply: serde_json::Value = serde_json::from_str(response_json).map_err(invalid_reply)?;
+        verify(&reply)
+    }
+
+    fn verify_state_proof(&self, response_json: &str, verify: fn(&serde_json::Value) -> Result<(), ErrorCode>) -> Result<(), ErrorCode> {
+        if let Some(max_age) = self.max_proof_age {
+            let reply: serde_json::Value = serde_json::from_str(response_json).map_err(invalid_reply)?;
+            state_proof::check_reply_freshness(&reply, max_age)?;
+        }
+
+        self.verify_proof(response_json, verify)
+    }
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/**
+    Starts an operation reporting its result to a callback, and turns the
+    callback into a future. Errors returned by `start` resolve the future at once.
+*/
+fn callback_future<T, F>(start: F) -> SovtokenFuture<T>
+    where T: Send + 'static, F: FnOnce(ResultCallback<T>) -> Result<(), ErrorCode>
+{
+    let (sender, receiver) = oneshot::channel();
+    let sender = Mutex::new(Some(sender));
+
+    let started = start(Box::new(move |result| {
+        if let Some(sender) = sender.lock().unwrap().take() {
+            let _ = sender.send(result);
+        }
+    }));
+
+    match started {
+        Ok(()) => Box::new(receiver.then(|received| {
+            received.unwrap_or(Err(ErrorCode::CommonInvalidState))
+        })),
+        Err(ec) => Box::new(future::err(ec)),
+    }
+}
+
+#[cfg(test)]
+mod sovtoken_client_tests {
+    use super::*;
+    use serde_json::Value;
+    use logic::input::Input;
+    use logic::output::Output;
+    use logic::signature_verification::{verify_fees_request, verify_transfer_request};
+    use utils::current_error::current_error;
+    use utils::test::crypto_api::CryptoApiHandler;
+    use utils::test::default::utxo;
+
+    fn client() -> SovtokenClient<CryptoApiHandler> {
+        SovtokenClient::with_crypto_api(&CryptoApiHandler {})
+    }
+
+    fn address() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap()
+    }
+
+    fn recipient() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V").unwrap()
+    }
+
+    fn balance_check(amount: TokenAmount, fee: TokenAmount) -> BalanceCheck {
+        BalanceCheck::new(vec![utxo(address().qualified(), 1, amount)], fee)
+    }
+
+    #[test]
+    fn create_address() {
+        let address = client().create_address(1, PaymentAddressConfig { seed: String::new(), ..Default::default() }).wait().unwrap();
+
+        assert!(address.is_qualified());
+    }
+
+    #[test]
+    fn build_payment() {
+        let inputs = vec![Input::new(address(), 1)];
+        let outputs = vec![Output::new(recipient(), 10)];
+
+        let request = client().build_payment(1, inputs, outputs, None, None, None).wait().unwrap();
+        let request: Value = serde_json::from_str(&request).unwrap();
+
+        assert_eq!(json!(address().verkey()), request["identifier"]);
+        assert!(verify_transfer_request(&CryptoApiHandler {}, &request.to_string()).unwrap().is_valid());
+    }
+
+    #[test]
+    fn build_payment_without_outputs() {
+        let inputs = vec![Input::new(address(), 1)];
+
+        let error = client().build_payment(1, inputs, Vec::new(), None, None, None).wait().unwrap_err();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, error);
+    }
+
+    #[test]
+    fn build_payment_with_balance_check() {
+        let inputs = vec![Input::new(address(), 1)];
+        let outputs = vec![Output::new(recipient(), 10)];
+
+        let result = client().build_payment(1, inputs.clone(), outputs.clone(), None, None, Some(balance_check(10, 0)));
+        assert!(result.wait().is_ok());
+
+        let error = client().build_payment(1, inputs.clone(), outputs.clone(), None, None, Some(balance_check(10, 1))).wait().unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, error);
+
+        let error = client().build_payment(1, inputs, outputs, None, None, Some(balance_check(11, 0))).wait().unwrap_err();
+        assert_eq!(ErrorCode::PaymentExtraFundsError, error);
+    }
+
+    #[test]
+    fn add_fees() {
+        let inputs = vec![Input::new(address(), 1)];
+        let outputs = vec![Output::new(recipient(), 10)];
+
+        let request = client().add_fees(1, r#"{"operation": {"type": "3"}}"#, inputs, outputs, None, None).wait().unwrap();
+        let request: Value = serde_json::from_str(&request).unwrap();
+
+        assert_eq!(1, request["fees"][2].as_array().unwrap().len());
+        assert!(verify_fees_request(&CryptoApiHandler {}, &request.to_string()).unwrap().is_valid());
+    }
+
+    #[test]
+    fn add_fees_with_balance_check() {
+        let inputs = vec![Input::new(address(), 1)];
+        let outputs = vec![Output::new(recipient(), 10)];
+        let request_json = r#"{"operation": {"type": "3"}}"#;
+
+        let result = client().add_fees(1, request_json, inputs.clone(), outputs.clone(), None, Some(balance_check(11, 1)));
+        assert!(result.wait().is_ok());
+
+        let error = client().add_fees(1, request_json, inputs.clone(), outputs.clone(), None, Some(balance_check(11, 2))).wait().unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, error);
+
+        let error = client().add_fees(1, request_json, inputs.clone(), outputs.clone(), None, Some(BalanceCheck::new(Vec::new(), 1))).wait().unwrap_err();
+        assert_eq!(ErrorCode::PaymentSourceDoesNotExistError, error);
+
+        let error = client().add_fees(1, request_json, inputs, outputs, None, Some(balance_check(11, u64::max_value()))).wait().unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, error);
+    }
+
+    #[test]
+    fn add_fees_to_transfer() {
+        let inputs = vec![Input::new(address(), 1)];
+        let outputs = vec![Output::new(recipient(), 10)];
+
+        let error = client().add_fees(1, r#"{"operation": {"type": "10001"}}"#, inputs.clone(), outputs.clone(), None, None).wait().unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, error);
+
+        let error = client().add_fees(1, "[]", inputs, outputs, None, None).wait().unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, error);
+    }
+
+    #[test]
+    fn build_get_utxo_from() {
+        let request = client().build_get_utxo(&address(), Some(5)).wait().unwrap();
+        let request: Value = serde_json::from_str(&request).unwrap();
+
+        assert_eq!(json!(address().unqualified()), request["operation"]["address"]);
+        assert_eq!(json!(5), request["operation"]["from"]);
+    }
+
+    #[test]
+    fn build_and_parse_get_txns() {
+        let requests = client().build_get_txns(1, 2, None).wait().unwrap();
+        let request: Value = serde_json::from_str(&requests[1]).unwrap();
+        assert_eq!(json!({"type": "3", "ledgerId": 1001, "data": 2}), request["operation"]);
+
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "seqNo": 2,
+                "type": "3",
+                "data": {
+                    "txn": {
+                        "data": {"outputs": [{"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "amount": 13}]},
+                        "metadata": {},
+                        "type": "10000"
+                    },
+                    "txnMetadata": {"seqNo": 2}
+                }
+            }
+        }).to_string();
+        let txn = client().parse_get_txn(&response).wait().unwrap().unwrap();
+        assert_eq!((2, None, 13), (txn.seq_no, txn.txn_time, txn.outputs[0].amount));
+    }
+
+    #[test]
+    fn parse_get_txn_fees() {
+        let response = r#"{"op": "REPLY", "result": {"identifier": "6ouriXMZkLeHsuXrN1X1fd", "reqId": 47660, "type": "20001", "fees": {"1": 1, "10001": 10}}}"#;
+
+        let fees = client().parse_get_txn_fees(response).wait().unwrap();
+
+        assert_eq!(Some(&10), fees.get("10001"));
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().parse_get_txn_fees("{}").wait().unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().verify_proofs(true).parse_get_txn_fees(response).wait().unwrap_err());
+    }
+
+    #[test]
+    fn parse_get_txn_fees_with_stale_proof() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+                "reqId": 47660,
+                "type": "20001",
+                "fees": {"1": 1, "10001": 10},
+                "state_proof": {
+                    "root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms",
+                    "multi_signature": {
+                        "participants": ["Gamma", "Delta", "Beta"],
+                        "signature": "Qk67ePVhxdjHivAf8H4Loy1hN5zfb1dq79VSJKYx485EAXmj44PASpp8gj2faysdN8CNzSoUVvXgd3U4P2CA7VkwD7FHKUuviAFJfRQ68FnpUS8hVuqn6PAuv9RGUobohcJnKJ8CVKxr5i3Zn2JNXbk7AqeYRZQ2egq8fdoP3woPW7",
+                        "value": {
+                            "timestamp": 1530059419,
+                            "state_root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms",
+                            "ledger_id": 2,
+                            "txn_root_hash": "AKboMiJZJm247Sa7GsKQo5Ba8ukgxTQ3DsLc2pyVuDkU",
+                            "pool_state_root_hash": "J3ATG63R2JKHDCdpKpQf81FTNyQg2Vgz7Pu1ZHZw6zNy"
+                        }
+                    }
+                }
+            }
+        }).to_string();
+
+        assert!(client().max_proof_age(None).parse_get_txn_fees(&response).wait().is_ok());
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, client().max_proof_age(Some(600)).parse_get_txn_fees(&response).wait().unwrap_err());
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, client().max_proof_age(Some(600)).parse_get_utxo(&response).wait().unwrap_err());
+    }
+
+    #[test]
+    fn parse_payment_with_unverified_proof() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "txn": {
+                    "data": {
+                        "inputs": [{"address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", "seqNo": 1}],
+                        "outputs": [{"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "amount": 13}]
+                    },
+                    "metadata": {"digest": "228af6a0c773cbbd575bf4e16f9144c2eaa615fa81fdcc3d06b83e20a92e5989", "from": "6baBEYA94sAphWBA5efEsaA6X2wCdyaH7PXuBtv2H5S1", "reqId": 1529682415},
+                    "protocolVersion": 2,
+                    "type": "10001"
+                },
+                "reqSignature": {"type": "ED25519", "values": []},
+                "txnMetadata": {"seqNo": 2, "txnTime": 1529682415},
+                "ver": "1",
+                "auditPath": ["5NtSQUXaZvETP1KEWi8LaxSb9gGa2Qj31xKQoimNxCAT"],
+                "rootHash": "GJFwiQt9r7n25PqM1oXBtRceXCeoqoCBcJmRH1c8fVTs"
+            }
+        }).to_string();
+
+        assert_eq!(1, client().verify_proofs(false).parse_payment(&response).wait().unwrap().len());
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().verify_proofs(true).parse_payment(&response).wait().unwrap_err());
+    }
+
+    #[test]
+    fn parse_invalid_replies() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().parse_get_utxo("{}").wait().unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().parse_payment("{}").wait().unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().parse_response_with_fees("{}").wait().unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, client().parse_get_txn_fees("{").wait().unwrap_err());
+        assert_eq!("Invalid reply", current_error().unwrap().message);
+    }
+}
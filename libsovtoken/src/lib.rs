@@ -9,12 +9,15 @@
 // ------------------------------------------
 extern crate base64;
 extern crate bs58;
+extern crate crypto;
+extern crate futures;
 extern crate hex;
 extern crate libc;
 extern crate openssl;
 extern crate rand;
 extern crate serde;
 extern crate sha2;
+extern crate sha3;
 extern crate time;
 // ------------------------------------------
 // crates from crate.io etc that require macro
@@ -44,6 +47,7 @@ extern crate core;                      // lib-sdk rust wrapper to get ErrorCode
 #[macro_use]
 pub mod utils;
 pub mod api;
+pub mod client;
 pub mod logic;
 pub mod libraries;
 
@@ -16,14 +16,18 @@
 
 */
 
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json;
-use std::{io, str};
+use std::{fmt, io, str};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 use ErrorCode;
 use logic::parsers::common::TXO;
 use utils::json_conversion::{JsonDeserialize, JsonSerialize};
 use utils::constants::general::{PAYMENT_ADDRESS_QUALIFIER, TXO_QUALIFIER};
 use utils::base58::{IntoBase58, FromBase58};
+use utils::current_error::{set_current_error, ErrorDetails};
 
 // Following lengths are in bytes
 pub const VERKEY_LEN: usize = 32;
@@ -37,6 +41,187 @@ pub const ADDRESS_CHECKSUM_LEN: usize = 4;
 
 pub const ADDRESS_LEN: usize = VERKEY_LEN + ADDRESS_CHECKSUM_LEN + ADDRESS_QUAL_LEN;
 
+/**
+    A payment address which can only be built through validation.
+
+    Keeps the qualified form, the unqualified form and the verkey of the
+    address. It serializes to a plain string in the form it was built from
+    (qualified or unqualified), so the JSON is the same as for a `String`.
+    Deserializing an invalid address fails.
+
+    ```
+    use sovtoken::logic::address::PaymentAddress;
+    let address = PaymentAddress::new("pay:sov:WqXg36yxheP7wzUZnhnkUY6Qeaib5uyUZuyaujr7atPHRH3d2").unwrap();
+    assert_eq!(address.unqualified(), "WqXg36yxheP7wzUZnhnkUY6Qeaib5uyUZuyaujr7atPHRH3d2");
+    assert_eq!(address.verkey(), "5ZTeJT5ykaWmZErwkM6qdF3RYN7gVXRTmVn4QdpzZ7BJ");
+
+    let unqualified = PaymentAddress::new("WqXg36yxheP7wzUZnhnkUY6Qeaib5uyUZuyaujr7atPHRH3d2").unwrap();
+    assert_eq!(unqualified.qualified(), "pay:sov:WqXg36yxheP7wzUZnhnkUY6Qeaib5uyUZuyaujr7atPHRH3d2");
+    assert_eq!(unqualified.with_qualifier(), address);
+
+    assert!(PaymentAddress::new("pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja").is_err());
+    ```
+*/
+#[derive(Debug, Clone)]
+pub struct PaymentAddress {
+    qualified: String,
+    verkey: String,
+    is_qualified: bool,
+}
+
+impl PaymentAddress {
+    /**
+        Validates a qualified (`pay:sov:<verkey><checksum>`) or
+        unqualified (`<verkey><checksum>`) address.
+    */
+    pub fn new(address: &str) -> Result<PaymentAddress, ErrorCode> {
+        if address.starts_with(PAYMENT_ADDRESS_QUALIFIER) {
+            let verkey = validate_address(address)?;
+            return Ok(PaymentAddress { qualified: address.to_string(), verkey, is_qualified: true });
+        }
+
+        let verkey = match verkey_from_unqualified_address(address) {
+            Ok(verkey) => verkey,
+            Err(err) => {
+                set_current_error(ErrorDetails::new("Invalid payment address")
+                    .cause(format!("{} is not the base58check of a verkey", address)));
+                return Err(err);
+            }
+        };
+        Ok(PaymentAddress { qualified: add_qualifer_to_address(address), verkey, is_qualified: false })
+    }
+
+    /**
+        Builds a qualified address from a verkey.
+    */
+    pub fn from_verkey(verkey: &str) -> Result<PaymentAddress, ErrorCode> {
+        let address = qualified_address_from_verkey(verkey)?;
+        PaymentAddress::new(&address)
+    }
+
+    /**
+        `pay:sov:<verkey><checksum>`
+    */
+    pub fn qualified(&self) -> &str {
+        &self.qualified
+    }
+
+    /**
+        `<verkey><checksum>`
+    */
+    pub fn unqualified(&self) -> &str {
+        &self.qualified[ADDRESS_QUAL_LEN..]
+    }
+
+    pub fn verkey(&self) -> &str {
+        &self.verkey
+    }
+
+    /**
+        true if the address was built from (and serializes to) its qualified form.
+    */
+    pub fn is_qualified(&self) -> bool {
+        self.is_qualified
+    }
+
+    /**
+        The address in the form it serializes to.
+    */
+    pub fn as_str(&self) -> &str {
+        if self.is_qualified {
+            self.qualified()
+        } else {
+            self.unqualified()
+        }
+    }
+
+    /**
+        Returns the same address in its qualified form.
+    */
+    pub fn with_qualifier(&self) -> PaymentAddress {
+        PaymentAddress { is_qualified: true, .. self.clone() }
+    }
+
+    /**
+        Returns the same address in its unqualified form.
+        Errors when the address is not qualified, the same way
+        [`unqualified_address_from_address`] does.
+
+        [`unqualified_address_from_address`]: fn.unqualified_address_from_address.html
+    */
+    pub fn strip_qualifier(&self) -> Result<PaymentAddress, ErrorCode> {
+        if !self.is_qualified {
+            error!("Payment address should start with a correct qualifier {}", PAYMENT_ADDRESS_QUALIFIER);
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        Ok(PaymentAddress { is_qualified: false, .. self.clone() })
+    }
+}
+
+/**
+    Addresses are compared on their qualified form, so an address equals
+    itself whether it was built qualified or unqualified.
+*/
+impl PartialEq for PaymentAddress {
+    fn eq(&self, other: &PaymentAddress) -> bool {
+        self.qualified == other.qualified
+    }
+}
+
+impl Eq for PaymentAddress {}
+
+impl Hash for PaymentAddress {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.qualified.hash(state);
+    }
+}
+
+impl fmt::Display for PaymentAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PaymentAddress {
+    type Err = ErrorCode;
+
+    fn from_str(address: &str) -> Result<PaymentAddress, ErrorCode> {
+        PaymentAddress::new(address)
+    }
+}
+
+impl PartialEq<str> for PaymentAddress {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for PaymentAddress {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for PaymentAddress {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Serialize for PaymentAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for PaymentAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<PaymentAddress, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        PaymentAddress::new(&address)
+            .map_err(|ec| de::Error::custom(format!("Invalid payment address {}: error code {:?}", address, ec)))
+    }
+}
+
 /**
     Removes the "pay:sov:" from an address.
     Leaves the verkey with the checksum.
@@ -128,11 +313,20 @@ pub fn qualified_address_from_verkey(verkey: &str) -> Result<String, ErrorCode>
 pub fn validate_address(fully_qualified_address: &str) -> Result<String, ErrorCode> {
     if !fully_qualified_address.starts_with(&PAYMENT_ADDRESS_QUALIFIER) {
         error!("Payment address should start with a correct qualifier {}", PAYMENT_ADDRESS_QUALIFIER);
+        set_current_error(ErrorDetails::new("Invalid payment address")
+            .cause(format!("{} doesn't start with {}", fully_qualified_address, PAYMENT_ADDRESS_QUALIFIER)));
         return Err(ErrorCode::CommonInvalidStructure);
     }
 
     let address = strip_qualifier_from_address(&fully_qualified_address);
-    verkey_from_unqualified_address(&address)
+    match verkey_from_unqualified_address(&address) {
+        Ok(verkey) => Ok(verkey),
+        Err(err) => {
+            set_current_error(ErrorDetails::new("Invalid payment address")
+                .cause(format!("{} is not the base58check of a verkey", fully_qualified_address)));
+            Err(err)
+        }
+    }
 }
 
 pub fn verkey_from_unqualified_address(unqualified_address: &str) -> Result<String, ErrorCode> {
@@ -227,6 +421,7 @@ pub fn strip_qualifier_from_address(address : &str) -> String {
 pub mod address_tests {
     use utils::random::rand_bytes;
     use utils::constants::general::PAYMENT_ADDRESS_QUALIFIER;
+    use utils::current_error::current_error;
 
     use super::*;
 
@@ -399,6 +594,86 @@ pub mod address_tests {
         }
     }
 
+    #[test]
+    fn test_payment_address_from_qualified_address() {
+        let verkey = gen_random_base58_verkey();
+        let qualified_address = qualified_address_from_verkey(&verkey).unwrap();
+        let address = PaymentAddress::new(&qualified_address).unwrap();
+
+        assert!(address.is_qualified());
+        assert_eq!(address.qualified(), qualified_address);
+        assert_eq!(address.unqualified(), strip_qualifier_from_address(&qualified_address));
+        assert_eq!(address.verkey(), verkey);
+        assert_eq!(address.to_string(), qualified_address);
+    }
+
+    #[test]
+    fn test_payment_address_from_unqualified_address() {
+        let verkey = gen_random_base58_verkey();
+        let unqualified_address = unqualified_address_from_verkey(&verkey).unwrap();
+        let address = PaymentAddress::new(&unqualified_address).unwrap();
+
+        assert!(!address.is_qualified());
+        assert_eq!(address.qualified(), format!("{}{}", PAYMENT_ADDRESS_QUALIFIER, unqualified_address));
+        assert_eq!(address.verkey(), verkey);
+        assert_eq!(address.to_string(), unqualified_address);
+        assert_eq!(address, PaymentAddress::from_verkey(&verkey).unwrap().strip_qualifier().unwrap());
+    }
+
+    #[test]
+    fn test_payment_address_equal_in_both_forms() {
+        use std::collections::HashSet;
+
+        let verkey = gen_random_base58_verkey();
+        let qualified = PaymentAddress::from_verkey(&verkey).unwrap();
+        let unqualified = qualified.strip_qualifier().unwrap();
+
+        assert_eq!(qualified, unqualified);
+        assert_ne!(qualified, PaymentAddress::from_verkey(&gen_random_base58_verkey()).unwrap());
+
+        let mut addresses = HashSet::new();
+        addresses.insert(qualified);
+        assert!(!addresses.insert(unqualified));
+    }
+
+    #[test]
+    fn test_payment_address_invalid() {
+        let address = gen_random_base58_address();
+        let addr_len = address.len();
+        let bad_checksum = replace_char_at(&address, addr_len - 1, if address.ends_with('a') { 'b' } else { 'a' });
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, PaymentAddress::new(&format!("pat:sov:{}", address)).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, PaymentAddress::new(&bad_checksum).unwrap_err());
+        assert_eq!("Invalid payment address", current_error().unwrap().message);
+        assert_eq!(ErrorCode::CommonInvalidStructure, PaymentAddress::new(&rand_bytes(VERKEY_LEN + 1).into_base58_check()).unwrap_err());
+    }
+
+    #[test]
+    fn test_payment_address_strip_qualifier_of_unqualified_address() {
+        let address = PaymentAddress::new(&gen_random_base58_address()).unwrap();
+        assert_eq!(ErrorCode::CommonInvalidStructure, address.strip_qualifier().unwrap_err());
+    }
+
+    #[test]
+    fn test_payment_address_serde_keeps_form() {
+        let unqualified_address = gen_random_base58_address();
+        let qualified_address = format!("{}{}", PAYMENT_ADDRESS_QUALIFIER, unqualified_address);
+
+        for address in vec![unqualified_address, qualified_address] {
+            let json = json!(address).to_string();
+            let payment_address: PaymentAddress = serde_json::from_str(&json).unwrap();
+            assert_eq!(payment_address, address);
+            assert_eq!(serde_json::to_string(&payment_address).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_payment_address_deserialize_invalid() {
+        let json = json!("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81f").to_string();
+        let err = serde_json::from_str::<PaymentAddress>(&json).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid payment address"));
+    }
+
     #[test]
     fn string_to_txo_works() {
         let txo_str = "txo:sov:fkjZEd8eTBnYJsw7m7twMph3UYD7j2SoWcDM45DkmRx8eq2SkQnzxoLxyMT1RBAat9x86MwXNJH88Pxf9u7JsM5m8ApXn3bvgbtS5cegZzNp7WmMSpWL";
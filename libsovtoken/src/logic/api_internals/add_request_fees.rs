@@ -7,15 +7,16 @@ use logic::input::Inputs;
 use logic::output::Outputs;
 use serde_json;
 use utils::ffi_support::{string_from_char_ptr, c_pointer_from_string, c_pointer_from_str};
-use logic::indy_sdk_api::crypto_api::CryptoSdk;
-use utils::constants::txn_types::XFER_PUBLIC;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use utils::constants::txn_types::TxnType;
 use utils::constants::txn_fields::FEES;
 use utils::constants::general::JsonCallbackUnwrapped;
+use utils::current_error::{invalid_parameter, missing_parameter};
 use utils::txn_author_agreement::TaaAcceptance;
 use sha2::{Sha256, Digest};
 use hex::ToHex;
 
-type SerdeMap = serde_json::Map<String, serde_json::value::Value>;
+pub type SerdeMap = serde_json::Map<String, serde_json::value::Value>;
 type AddRequestFeesCb = extern fn(command_handle_: i32, err: i32, req_with_fees_json: *const c_char) -> i32;
 type DeserializedArguments = (Inputs, Outputs, Option<Extra>, SerdeMap, AddRequestFeesCb);
 
@@ -31,35 +32,35 @@ pub fn deserialize_inputs (
 ) -> Result<DeserializedArguments, ErrorCode> {
     debug!("logic::add_request_fees::deserialize_inputs >> req_json: {:?}, inputs_json: {:?}, outputs_json: {:?}", secret!(&req_json), secret!(&inputs_json), secret!(&outputs_json));
 
-    let cb = cb.ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+    let cb = cb.ok_or_else(|| missing_parameter("cb"))?;
 
-    let request_json = string_from_char_ptr(req_json).ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+    let request_json = string_from_char_ptr(req_json).ok_or_else(|| missing_parameter("req_json"))?;
     debug!("Converted request_json pointer into string >>> {:?}", secret!(&request_json));
 
-    let inputs_json = string_from_char_ptr(inputs_json).ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+    let inputs_json = string_from_char_ptr(inputs_json).ok_or_else(|| missing_parameter("inputs_json"))?;
     debug!("Converted inputs_json pointer to string >>> {:?}", secret!(&inputs_json));
 
-    let outputs_json = string_from_char_ptr(outputs_json).ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+    let outputs_json = string_from_char_ptr(outputs_json).ok_or_else(|| missing_parameter("outputs_json"))?;
     debug!("Converted outputs_json pointer to string >>> {:?}", secret!(&outputs_json));
 
     let extra = string_from_char_ptr(extra);
     debug!("Converted extra pointer to string >>> {:?}", extra);
 
-    let inputs: Inputs = serde_json::from_str(&inputs_json).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))?;
+    let inputs: Inputs = serde_json::from_str(&inputs_json).map_err(invalid_parameter("inputs_json"))?;
     debug!("Deserialized input_json >>> {:?}", secret!(&inputs));
 
-    let outputs: Outputs = serde_json::from_str(&outputs_json).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))?;
+    let outputs: Outputs = serde_json::from_str(&outputs_json).map_err(invalid_parameter("outputs_json"))?;
     debug!("Deserialized output_json >>> {:?}", secret!(&outputs));
 
     let extra: Option<Extra> = if let Some(extra_) = extra {
-        serde_json::from_str(&extra_).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))?
+        serde_json::from_str(&extra_).map_err(invalid_parameter("extra"))?
     } else { None };
     debug!("Deserialized extra >>> {:?}", secret!(&extra));
 
-    let request_json_object: serde_json::Value = serde_json::from_str(&request_json).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))?;
+    let request_json_object: serde_json::Value = serde_json::from_str(&request_json).map_err(invalid_parameter("req_json"))?;
     trace!("Converted request_json to serde::json::Value");
 
-    let request_json_map = request_json_object.as_object().ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+    let request_json_map = request_json_object.as_object().ok_or("the request is not a JSON object").map_err(invalid_parameter("req_json"))?;
     trace!("Converted request_json to hash_map");
 
     debug!("Deserialized values: inputs: {:?}, outputs: {:?}, request_json_map: {:?}", secret!(&inputs), secret!(&outputs), secret!(&request_json_map));
@@ -82,14 +83,18 @@ pub fn validate_type_not_transfer(request_json_map: &SerdeMap) -> Result<(), Err
         .ok_or(ErrorCode::CommonInvalidStructure)?;
     debug!("Request transaction type was >>> {}", transaction_type);
 
-    if transaction_type == XFER_PUBLIC {
+    if transaction_type.as_str().and_then(TxnType::from_code) == Some(TxnType::XferPublic) {
         return Err(ErrorCode::CommonInvalidStructure);
     } else {
         return Ok(());
     };
 }
 
-pub fn add_fees_to_request_and_serialize(
+/**
+    Signs the fees with `crypto_api` and adds them to the request.
+*/
+pub fn sign_fees_and_serialize<A: CryptoAPI>(
+    crypto_api: &'static A,
     wallet_handle: i32,
     inputs: Inputs,
     outputs: Outputs,
@@ -97,16 +102,13 @@ pub fn add_fees_to_request_and_serialize(
     request_json_map: SerdeMap,
     cb: Box<Fn(Result<String, ErrorCode>) + Send + Sync>
 ) -> Result<(), ErrorCode> {
-    trace!("logic::add_request_fees::add_fees_to_request_and_serialize >> wallet_handle: {:?}, inputs: {:?}, outputs: {:?}, request_json_map: {:?}", wallet_handle, secret!(&inputs), secret!(&outputs), secret!(&request_json_map));
-    let res = add_fees(wallet_handle, inputs, outputs, extra, request_json_map, Box::new(move |request_json_map_updated|{
+    add_fees(crypto_api, wallet_handle, inputs, outputs, extra, request_json_map, Box::new(move |request_json_map_updated|{
         let rm_fees = request_json_map_updated.map(|request_json_map_with_fees| serialize_request_with_fees(request_json_map_with_fees));
         match rm_fees {
             Ok(some) => cb(some),
             Err(e) => cb(Err(e))
         }
-    }));
-    trace!("logic::add_request_fees::add_fees_to_request_and_serialize >> result: {:?}", res);
-    res
+    }))
 }
 
 /**
@@ -122,6 +124,23 @@ pub fn closure_cb_response(command_handle: i32, cb: JsonCallbackUnwrapped) -> im
     }    
 }
 
+/**
+    The digest of a request which the fee inputs sign.
+*/
+pub fn txn_digest(request_json_map: &SerdeMap) -> Result<String, ErrorCode> {
+    let txn_serialized = serialize_signature(request_json_map.clone().into())?;
+    let mut hasher = Sha256::default();
+    hasher.input(txn_serialized.as_bytes());
+    Ok(hasher.result().to_hex())
+}
+
+/**
+    Adds signed fees to a request.
+*/
+pub fn request_with_fees(mut request_json_map: SerdeMap, fees: &XferPayload) -> SerdeMap {
+    request_json_map.insert(FEES.to_string(), json!([fees.inputs, fees.outputs, fees.signatures]));
+    request_json_map
+}
 
 /*
     Methods "private" (aka not exported from this module)
@@ -129,18 +148,13 @@ pub fn closure_cb_response(command_handle: i32, cb: JsonCallbackUnwrapped) -> im
     KEEP all public methods above
 */
 
-fn add_fees(wallet_handle: i32, inputs: Inputs, outputs: Outputs, extra: Option<serde_json::Value>, request_json_map: SerdeMap, cb: Box<Fn(Result<SerdeMap, ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
-    let txn_serialized = serialize_signature(request_json_map.clone().into())?;
-    let mut hasher = Sha256::default();
-    hasher.input(txn_serialized.as_bytes());
-    let txn_digest = Some(hasher.result().to_hex());
-    signed_fees(wallet_handle, inputs, outputs, extra, &txn_digest, Box::new(move |fees| {
+fn add_fees<A: CryptoAPI>(crypto_api: &'static A, wallet_handle: i32, inputs: Inputs, outputs: Outputs, extra: Option<serde_json::Value>, request_json_map: SerdeMap, cb: Box<Fn(Result<SerdeMap, ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
+    let txn_digest = Some(txn_digest(&request_json_map)?);
+    signed_fees(crypto_api, wallet_handle, inputs, outputs, extra, &txn_digest, Box::new(move |fees| {
         trace!("Added fees to request_json.");
         match fees {
             Ok((fees, _)) => {
-                let mut map = request_json_map.clone();
-                map.insert(FEES.to_string(), json!([fees.inputs, fees.outputs, fees.signatures]));
-                cb(Ok(map.clone()));
+                cb(Ok(request_with_fees(request_json_map.clone(), &fees)));
             }
             Err(err) => {
                 cb(Err(err))
@@ -160,9 +174,9 @@ fn serialize_request_with_fees(request_json_map_with_fees: SerdeMap) -> Result<S
     return Ok(serialized_request_with_fees);
 } 
 
-fn signed_fees(wallet_handle: i32, inputs: Inputs, outputs: Outputs, extra: Option<Extra>, txn_digest: &Option<String>, cb: Box<Fn(Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
+fn signed_fees<A: CryptoAPI>(crypto_api: &'static A, wallet_handle: i32, inputs: Inputs, outputs: Outputs, extra: Option<Extra>, txn_digest: &Option<String>, cb: Box<Fn(Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
     let fees = XferPayload::new(inputs, outputs, extra);
-    fees.sign_fees(&CryptoSdk{}, wallet_handle, txn_digest, cb)?;
+    fees.sign_fees(crypto_api, wallet_handle, txn_digest, cb)?;
     Ok(())
 }
 
@@ -174,6 +188,7 @@ mod test_deserialize_inputs {
     use serde_json;
     use std::ptr;
     use utils::constants::txn_types::XFER_PUBLIC;
+    use utils::current_error::current_error;
     use utils::test::default;
 
     use super::{deserialize_inputs, AddRequestFeesCb, DeserializedArguments};
@@ -252,7 +267,7 @@ mod test_deserialize_inputs {
         // no `address` field
         let invalid_json = json_c_pointer!([
             {
-                "add": "pay:sov:d0kitWxupHvZ4i0NHJhoj79RcUeyt3YlwAc8Hbcy87iRLSZC",
+                "add": "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd",
                 "seqNo": 4
             }
         ]);
@@ -261,7 +276,7 @@ mod test_deserialize_inputs {
         // negative `seqNo`
         let invalid_json = json_c_pointer!([
             {
-                "address": "pay:sov:d0kitWxupHvZ4i0NHJhoj79RcUeyt3YlwAc8Hbcy87iRLSZC",
+                "address": "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd",
                 "seqNo": -4
             }
         ]);
@@ -270,7 +285,7 @@ mod test_deserialize_inputs {
         // no `seqNo` field
         let invalid_json = json_c_pointer!([
             {
-                "address": "pay:sov:d0kitWxupHvZ4i0NHJhoj79RcUeyt3YlwAc8Hbcy87iRLSZC"
+                "address": "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd"
             }
         ]);
         error_deserialize_inputs_inputs(invalid_json, ErrorCode::CommonInvalidStructure);
@@ -281,7 +296,7 @@ mod test_deserialize_inputs {
         // no `address` field
         let invalid_json = json_c_pointer!([
             {
-                "add": "pay:sov:ql33nBkjGw6szxPT6LLRUIejn9TZAYk",
+                "add": "pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A",
                 "amount": 10
             }
         ]);
@@ -290,7 +305,7 @@ mod test_deserialize_inputs {
         // negative `amount`
         let invalid_json = json_c_pointer!([
             {
-                "recipient": "pay:sov:ql33nBkjGw6szxPT6LLRUIejn9TZAYk",
+                "recipient": "pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A",
                 "amount": -10
             }
         ]);
@@ -299,7 +314,7 @@ mod test_deserialize_inputs {
         // no `amount` field
         let invalid_json = json_c_pointer!([
             {
-                "recipient": "pay:sov:ql33nBkjGw6szxPT6LLRUIejn9TZAYk",
+                "recipient": "pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A",
             }
         ]);
         error_deserialize_inputs_ouputs(invalid_json, ErrorCode::CommonInvalidStructure);
@@ -309,6 +324,7 @@ mod test_deserialize_inputs {
     fn deserialize_inputs_invalid_request_json() {
         let invalid_json = json_c_pointer!([]);
         error_deserialize_inputs_request(invalid_json, ErrorCode::CommonInvalidStructure);
+        assert_eq!(Some("req_json".to_string()), current_error().unwrap().parameter);
     }
 
     #[test]
@@ -4,11 +4,13 @@ Logic for the [`create_payment_address_handler`].
 [`create_payment_address_handler`]: sovtoken::api::create_payment_address_handler
 */
 
+use serde_json;
 use std::os::raw::c_char;
 
 use ErrorCode;
 use logic::config::payment_address_config::PaymentAddressConfig;
 use utils::constants::general::{JsonCallback, JsonCallbackUnwrapped};
+use utils::current_error::{invalid_parameter, missing_parameter};
 use utils::ffi_support::{string_from_char_ptr, cstring_from_str, c_pointer_from_str};
 use utils::json_conversion::JsonDeserialize;
 
@@ -23,18 +25,21 @@ pub fn deserialize_arguments(
     json_config: *const c_char,
     cb: JsonCallback,
 ) -> Result<DeserializedArguments, ErrorCode> {
-    let cb = cb.ok_or(ErrorCode::CommonInvalidStructure)?;
+    let cb = cb.ok_or_else(|| missing_parameter("cb"))?;
 
     let json_config_string : String = string_from_char_ptr(json_config)
-        .ok_or(ErrorCode::CommonInvalidStructure)
-        .map_err(map_err_err!())?;
+        .ok_or_else(|| missing_parameter("config_str"))?;
 
     debug!("api::create_payment_address_handler json_config_string >> {:?}", secret!(&json_config_string));
 
     // TODO: Only continue when seed is missing, not on any error.
-    let config = PaymentAddressConfig::from_json(&json_config_string)
-        .map_err(map_err_trace!())
-        .unwrap_or(PaymentAddressConfig { seed: "".to_string() });
+    let derived = names_derivation(&json_config_string);
+    let config = match (PaymentAddressConfig::from_json(&json_config_string).map_err(map_err_trace!()), derived) {
+        (Ok(config), _) => config,
+        // a derived address must never silently become a random one
+        (Err(err), true) => return Err(invalid_parameter("config_str")(err)),
+        (Err(_), false) => PaymentAddressConfig { seed: "".to_string(), ..Default::default() },
+    };
 
     debug!("api::create_payment_address_handler PaymentAddressConfig >> {:?}", secret!(&config));
 
@@ -60,10 +65,23 @@ pub fn create_address_cb(command_handle: i32, cb: JsonCallbackUnwrapped) -> impl
     }
 }
 
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn names_derivation(json_config: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(json_config)
+        .map(|config| ["path", "index", "mnemonic", "passphrase"].iter().any(|key| config.get(key).is_some()))
+        .unwrap_or(false)
+}
+
 #[cfg(test)]
 mod deserialize_arguments_test {
     use super::*;
     use std::ptr;
+    use utils::current_error::current_error;
     use utils::test::default;
 
     pub fn call_deserialize_arguments(
@@ -122,6 +140,39 @@ mod deserialize_arguments_test {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_config_with_invalid_derivation()
+    {
+        let config_pointer = json_c_pointer!({
+            "seed": "000102030405060708090a0b0c0d0e0f",
+            "index": -1
+        });
+        let result = call_deserialize_arguments(Some(config_pointer), None);
+        assert_eq!(ErrorCode::CommonInvalidStructure, result.unwrap_err());
+        assert_eq!(Some("config_str".to_string()), current_error().unwrap().parameter);
+    }
+
+    #[test]
+    fn test_config_with_invalid_mnemonic()
+    {
+        let config_pointer = json_c_pointer!({
+            "mnemonic": ["abandon", "about"]
+        });
+        let result = call_deserialize_arguments(Some(config_pointer), None);
+        assert_eq!(ErrorCode::CommonInvalidStructure, result.unwrap_err());
+    }
+
+    #[test]
+    fn test_config_with_mnemonic_only()
+    {
+        let config_pointer = json_c_pointer!({
+            "mnemonic": "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "passphrase": "TREZOR"
+        });
+        let (config, _) = call_deserialize_arguments(Some(config_pointer), None).unwrap();
+        assert_eq!(Some("TREZOR".to_string()), config.passphrase);
+        assert_eq!(64, config.key_config().unwrap().seed.len());
+    }
 
     #[test]
     fn test_valid_arguments()
@@ -5,4 +5,5 @@ Contains logic used in the [`api`].
 */
 
 pub mod add_request_fees;
+pub mod pay_request_fees;
 pub mod create_address;
\ No newline at end of file
@@ -0,0 +1,220 @@
+/*!
+    Pays the fee of a request in one go.
+
+    [`add_request_fees`] needs the fee, the inputs and the change output up
+    front. [`pay_request_fees`] works them out: the fee with a
+    [`FeeCalculator`], the inputs and change with [`select_coins`]. It then
+    signs the fees and attaches them to the request.
+
+    [`add_request_fees`]: ../add_request_fees/index.html
+    [`pay_request_fees`]: fn.pay_request_fees.html
+    [`FeeCalculator`]: ../../fee_calculator/struct.FeeCalculator.html
+    [`select_coins`]: ../../coin_selection/fn.select_coins.html
+*/
+
+use serde_json;
+
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::api_internals::add_request_fees::{sign_fees_and_serialize, validate_type_not_transfer};
+use logic::balance_check::BalanceCheck;
+use logic::coin_selection::{select_coins, SelectionStrategy};
+use logic::fee_calculator::FeeCalculator;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::Inputs;
+use logic::output::Output;
+use logic::parsers::parse_get_utxo_response::UTXO;
+use logic::type_aliases::TokenAmount;
+
+/**
+    A request with its fees attached.
+
+    `inputs` are the sources which were spent and `change` is the output which
+    got the rest of them. Both are empty when the request has no fee.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PaidRequest {
+    pub request_json: String,
+    pub fee: TokenAmount,
+    pub inputs: Inputs,
+    pub change: Option<Output>,
+}
+
+/**
+    Selects inputs out of `utxos` to pay the fee of `request_json` and signs
+    them with `crypto_api`.
+
+    `signer_role` is passed to [`FeeCalculator::fee_for_request`]. Inputs are
+    selected with `SelectionStrategy::ExactMatch`, so no change output is
+    made when it finds sources which add up to the fee exactly. Otherwise the change
+    goes to `change_address`. When the request has no fee, `cb` is called with
+    the request as it is.
+
+    Errors with `PaymentInsufficientFundsError` when `utxos` can't cover the
+    fee and with `CommonInvalidStructure` for XFER requests, which carry their
+    fee in their own inputs and outputs.
+
+    [`FeeCalculator::fee_for_request`]: ../../fee_calculator/struct.FeeCalculator.html#method.fee_for_request
+*/
+pub fn pay_request_fees<A: CryptoAPI>(
+    crypto_api: &'static A,
+    wallet_handle: i32,
+    request_json: &str,
+    utxos: &[UTXO],
+    fee_calculator: &FeeCalculator,
+    signer_role: Option<&str>,
+    change_address: &PaymentAddress,
+    cb: Box<Fn(Result<PaidRequest, ErrorCode>) + Send + Sync>
+) -> Result<(), ErrorCode> {
+    trace!("logic::pay_request_fees::pay_request_fees >> wallet_handle: {:?}, request_json: {:?}, utxos: {:?}, signer_role: {:?}", wallet_handle, secret!(request_json), secret!(utxos), signer_role);
+
+    let request: serde_json::Value = serde_json::from_str(request_json)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    let request_json_map = request.as_object()
+        .ok_or(ErrorCode::CommonInvalidStructure)
+        .map_err(map_err_err!())?
+        .to_owned();
+    validate_type_not_transfer(&request_json_map)?;
+
+    let fee = fee_calculator.fee_for_request(request_json, signer_role)?.amount;
+    debug!("Fee of the request >>> {:?}", fee);
+
+    if fee == 0 {
+        cb(Ok(PaidRequest { request_json: request_json.to_string(), fee, inputs: Vec::new(), change: None }));
+        return Ok(());
+    }
+
+    let (inputs, outputs) = select_coins(utxos, 0, fee, change_address, SelectionStrategy::ExactMatch)?;
+    let change = outputs.first().cloned();
+    debug!("Selected inputs >>> {:?}, change >>> {:?}", secret!(&inputs), secret!(&change));
+
+    BalanceCheck::new(utxos.to_vec(), fee).check(&inputs, &outputs)?;
+    let spent = inputs.clone();
+
+    let res = sign_fees_and_serialize(
+        crypto_api,
+        wallet_handle,
+        inputs,
+        outputs,
+        None,
+        request_json_map,
+        Box::new(move |request_with_fees| {
+            let paid = request_with_fees.map(|request_json| PaidRequest {
+                request_json,
+                fee,
+                inputs: spent.clone(),
+                change: change.clone(),
+            });
+            cb(paid);
+        })
+    );
+
+    trace!("logic::pay_request_fees::pay_request_fees << result: {:?}", res);
+    res
+}
+
+#[cfg(test)]
+mod pay_request_fees_tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::mpsc::channel;
+    use logic::config::payment_address_config::PaymentAddressConfig;
+    use logic::parsers::parse_get_auth_rule::AuthRule;
+    use logic::signature_verification::verify_fees_request;
+    use utils::test::default::{utxo, KEYSTORE};
+
+    fn fee_calculator(fee: TokenAmount) -> FeeCalculator {
+        let rules: Vec<AuthRule> = serde_json::from_value(json!([{
+            "auth_type": "101",
+            "auth_action": "ADD",
+            "field": "*",
+            "new_value": "*",
+            "constraint": {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "metadata": {"fees": "add_schema"}}
+        }])).unwrap();
+        let mut fees = HashMap::new();
+        fees.insert("add_schema".to_string(), fee);
+        FeeCalculator::new(rules, fees)
+    }
+
+    fn change_address() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A").unwrap()
+    }
+
+    fn utxos(amounts: &[TokenAmount]) -> Vec<UTXO> {
+        utxos_of("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd", amounts)
+    }
+
+    fn utxos_of(address: &str, amounts: &[TokenAmount]) -> Vec<UTXO> {
+        amounts.iter().enumerate().map(|(index, amount)| utxo(address, index as u64 + 1, *amount)).collect()
+    }
+
+    fn schema_request() -> String {
+        json!({
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "operation": {"type": "101", "data": {"name": "gvt", "version": "1.0", "attr_names": ["name"]}}
+        }).to_string()
+    }
+
+    fn call_pay_request_fees(request_json: &str, utxos: &[UTXO], fee: TokenAmount) -> Result<Option<PaidRequest>, ErrorCode> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+
+        pay_request_fees(&*KEYSTORE, 1, request_json, utxos, &fee_calculator(fee), None, &change_address(), Box::new(move |paid| {
+            sender.lock().unwrap().send(paid).unwrap();
+        }))?;
+
+        Ok(receiver.try_recv().ok().map(|paid| paid.unwrap()))
+    }
+
+    #[test]
+    fn pay_request_without_fee() {
+        let request_json = schema_request();
+
+        let paid = call_pay_request_fees(&request_json, &utxos(&[10]), 0).unwrap().unwrap();
+
+        assert_eq!(request_json, paid.request_json);
+        assert_eq!(0, paid.fee);
+        assert!(paid.inputs.is_empty());
+        assert_eq!(None, paid.change);
+    }
+
+    #[test]
+    fn pay_request_with_fee() {
+        let verkey = KEYSTORE.indy_create_key(1, PaymentAddressConfig::default()).unwrap();
+        let address = PaymentAddress::from_verkey(&verkey).unwrap().with_qualifier();
+        let utxos = utxos_of(&address.to_string(), &[2, 3, 10]);
+
+        let paid = call_pay_request_fees(&schema_request(), &utxos, 6).unwrap().unwrap();
+        assert_eq!(6, paid.fee);
+        assert_eq!(vec![(address.unqualified().to_string(), 3)], paid.inputs.iter().map(|input| (input.address.unqualified().to_string(), input.seq_no)).collect::<Vec<_>>());
+        assert_eq!(Some(Output::new(change_address(), 4)), paid.change);
+
+        let request: serde_json::Value = serde_json::from_str(&paid.request_json).unwrap();
+        assert_eq!(json!([{"address": address.unqualified(), "seqNo": 3}]), request["fees"][0]);
+        assert_eq!(json!([{"address": change_address().unqualified(), "amount": 4}]), request["fees"][1]);
+        assert_eq!(1, request["fees"][2].as_array().unwrap().len());
+        assert!(verify_fees_request(&*KEYSTORE, &paid.request_json).unwrap().is_valid());
+    }
+
+    #[test]
+    fn pay_request_insufficient_funds() {
+        let err = call_pay_request_fees(&schema_request(), &utxos(&[2, 3]), 6).unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+    }
+
+    #[test]
+    fn pay_transfer_request() {
+        let request_json = json!({"operation": {"type": "10001"}}).to_string();
+        let err = call_pay_request_fees(&request_json, &utxos(&[10]), 1).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn pay_invalid_request() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, call_pay_request_fees("[]", &utxos(&[10]), 1).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, call_pay_request_fees("{", &utxos(&[10]), 1).unwrap_err());
+    }
+}
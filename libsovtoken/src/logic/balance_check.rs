@@ -0,0 +1,299 @@
+/*!
+    Local pre-flight balance check for transfers and fee payments.
+
+    The ledger rejects unbalanced transactions only after a full round trip.
+    When the caller knows the amounts of the sources it spends (the UTXOs from
+    [`parse_get_utxo_response_handler`]) and the expected fee, [`BalanceCheck`]
+    finds the same problems locally and returns the error code the ledger reply
+    would be mapped to.
+
+    [`parse_get_utxo_response_handler`]: ../../api/fn.parse_get_utxo_response_handler.html
+    [`BalanceCheck`]: struct.BalanceCheck.html
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json;
+
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::input::Inputs;
+use logic::output::Outputs;
+use logic::parsers::common::TXO;
+use logic::parsers::parse_get_utxo_response::UTXO;
+use logic::type_aliases::{TokenAmount, TxnSeqNo};
+use logic::xfer_payload::Extra;
+use utils::current_error::invalid_parameter;
+
+/**
+    Field of the `extra` of [`build_payment_req_handler`] and
+    [`add_request_fees_handler`] which holds a [`BalanceCheck`].
+
+    [`build_payment_req_handler`]: ../../api/fn.build_payment_req_handler.html
+    [`add_request_fees_handler`]: ../../api/fn.add_request_fees_handler.html
+    [`BalanceCheck`]: struct.BalanceCheck.html
+*/
+pub const BALANCE_CHECK_FIELD_NAME: &str = "balanceCheck";
+
+/**
+    Amounts of the sources being spent and the expected fee.
+
+    ```text
+    {
+        "utxos": [{
+            "paymentAddress": "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd",
+            "source": "txo:sov:<base58 encoded address and seqNo>",
+            "amount": 10,
+            "extra": ""
+        }],
+        "fee": 1
+    }
+    ```
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceCheck {
+    pub utxos: Vec<UTXO>,
+    pub fee: TokenAmount,
+}
+
+impl BalanceCheck {
+    pub fn new(utxos: Vec<UTXO>, fee: TokenAmount) -> BalanceCheck {
+        BalanceCheck { utxos, fee }
+    }
+
+    /**
+        Checks `inputs` and `outputs` the way the ledger does.
+
+        Errors with
+        - `CommonInvalidStructure` for duplicate inputs, zero amount outputs,
+          duplicate recipients or amounts which overflow.
+        - `PaymentSourceDoesNotExistError` when an input is not one of the `utxos`.
+        - `PaymentInsufficientFundsError` when sum(inputs) < sum(outputs) + fee.
+        - `PaymentExtraFundsError` when sum(inputs) > sum(outputs) + fee.
+    */
+    pub fn check(&self, inputs: &Inputs, outputs: &Outputs) -> Result<(), ErrorCode> {
+        trace!("logic::balance_check::check >> inputs: {:?}, outputs: {:?}, fee: {:?}", secret!(inputs), secret!(outputs), self.fee);
+
+        let mut spent: HashSet<(&str, TxnSeqNo)> = HashSet::new();
+        for input in inputs {
+            if !spent.insert((input.address.unqualified(), input.seq_no)) {
+                error!("Duplicate input {:?}", secret!(input));
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+        }
+
+        let mut recipients: HashSet<&str> = HashSet::new();
+        for output in outputs {
+            if output.amount == 0 {
+                error!("Output to {:?} has no tokens", secret!(&output.recipient));
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+            if !recipients.insert(output.recipient.unqualified()) {
+                error!("Duplicate recipient {:?}", secret!(&output.recipient));
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+        }
+
+        let required = outputs.iter()
+            .try_fold(self.fee, |sum, output| sum.checked_add(output.amount))
+            .ok_or_else(|| {
+                error!("Sum of the outputs and the fee overflows");
+                ErrorCode::CommonInvalidStructure
+            })?;
+
+        let amounts = self.source_amounts()?;
+        let mut available: TokenAmount = 0;
+        for input in inputs {
+            let amount = amounts.get(&(input.address.unqualified().to_string(), input.seq_no))
+                .ok_or_else(|| {
+                    error!("No amount known for input {:?}", secret!(input));
+                    ErrorCode::PaymentSourceDoesNotExistError
+                })?;
+            available = available.checked_add(*amount).ok_or_else(|| {
+                error!("Sum of the inputs overflows");
+                ErrorCode::CommonInvalidStructure
+            })?;
+        }
+
+        let res = if available < required {
+            error!("Insufficient funds, inputs: {}, outputs and fee: {}", available, required);
+            Err(ErrorCode::PaymentInsufficientFundsError)
+        } else if available > required {
+            error!("Extra funds, inputs: {}, outputs and fee: {}", available, required);
+            Err(ErrorCode::PaymentExtraFundsError)
+        } else {
+            Ok(())
+        };
+
+        trace!("logic::balance_check::check << result: {:?}", res);
+        res
+    }
+
+    /*
+        Amount of every source, keyed by unqualified address and seq_no.
+    */
+    fn source_amounts(&self) -> Result<HashMap<(String, TxnSeqNo), TokenAmount>, ErrorCode> {
+        let mut amounts = HashMap::new();
+        for utxo in &self.utxos {
+            let txo = TXO::from_libindy_string(&utxo.source)
+                .map_err(map_err_err!())
+                .or(Err(ErrorCode::CommonInvalidStructure))?;
+            let address = PaymentAddress::new(&txo.address)?;
+            amounts.insert((address.unqualified().to_string(), txo.seq_no), utxo.amount);
+        }
+        Ok(amounts)
+    }
+}
+
+/**
+    Takes the [`BalanceCheck`] out of `extra`, so it isn't sent to the ledger.
+
+    Returns `extra` as it is when it has no `balanceCheck` field, and `None`
+    for `extra` when the balance check was its only field.
+    Errors with `CommonInvalidStructure` when the field isn't a balance check.
+
+    [`BalanceCheck`]: struct.BalanceCheck.html
+*/
+pub fn extract_balance_check_from_extra(extra: Option<Extra>) -> Result<(Option<Extra>, Option<BalanceCheck>), ErrorCode> {
+    match extra {
+        Some(serde_json::Value::Object(mut fields)) => {
+            let balance_check = fields.remove(BALANCE_CHECK_FIELD_NAME);
+            match balance_check {
+                Some(balance_check) => {
+                    let balance_check: BalanceCheck = serde_json::from_value(balance_check).map_err(invalid_parameter("extra"))?;
+                    let extra = if fields.is_empty() { None } else { Some(serde_json::Value::Object(fields)) };
+                    Ok((extra, Some(balance_check)))
+                }
+                None => Ok((Some(serde_json::Value::Object(fields)), None))
+            }
+        }
+        extra => Ok((extra, None))
+    }
+}
+
+#[cfg(test)]
+mod balance_check_tests {
+    use super::*;
+    use logic::input::Input;
+    use logic::output::Output;
+    use utils::test::default::utxo;
+
+    const ADDRESS_1: &str = "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd";
+    const ADDRESS_2: &str = "pay:sov:BUoojqSQTLuvjkun4y2YoseVF76UZ3uYfHF1dbQyZVbCuTwQo";
+    const ADDRESS_3: &str = "pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A";
+
+    fn address(address: &str) -> PaymentAddress {
+        PaymentAddress::new(address).unwrap()
+    }
+
+    fn balance_check(fee: TokenAmount) -> BalanceCheck {
+        BalanceCheck::new(vec![utxo(ADDRESS_1, 1, 10), utxo(ADDRESS_2, 2, 20)], fee)
+    }
+
+    fn inputs() -> Inputs {
+        vec![Input::new(address(ADDRESS_1), 1), Input::new(address(ADDRESS_2), 2)]
+    }
+
+    fn outputs(amounts: &[TokenAmount]) -> Outputs {
+        let recipients = [ADDRESS_3, ADDRESS_1];
+        amounts.iter().zip(recipients.iter()).map(|(amount, recipient)| Output::new(address(recipient), *amount)).collect()
+    }
+
+    #[test]
+    fn check_balanced() {
+        balance_check(5).check(&inputs(), &outputs(&[20, 5])).unwrap();
+    }
+
+    #[test]
+    fn check_balanced_unqualified_inputs() {
+        let inputs: Inputs = inputs().into_iter().map(|input| Input::new(input.address.strip_qualifier().unwrap(), input.seq_no)).collect();
+        balance_check(0).check(&inputs, &outputs(&[30])).unwrap();
+    }
+
+    #[test]
+    fn check_fee_only() {
+        balance_check(30).check(&inputs(), &Vec::new()).unwrap();
+    }
+
+    #[test]
+    fn check_insufficient_funds() {
+        let err = balance_check(1).check(&inputs(), &outputs(&[20, 10])).unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+    }
+
+    #[test]
+    fn check_extra_funds() {
+        let err = balance_check(1).check(&inputs(), &outputs(&[20, 8])).unwrap_err();
+        assert_eq!(ErrorCode::PaymentExtraFundsError, err);
+    }
+
+    #[test]
+    fn check_unknown_source() {
+        let mut inputs = inputs();
+        inputs.push(Input::new(address(ADDRESS_3), 3));
+
+        let err = balance_check(0).check(&inputs, &outputs(&[30])).unwrap_err();
+        assert_eq!(ErrorCode::PaymentSourceDoesNotExistError, err);
+    }
+
+    #[test]
+    fn check_duplicate_inputs() {
+        let mut inputs = inputs();
+        inputs.push(Input::new(address(ADDRESS_1).strip_qualifier().unwrap(), 1));
+
+        let err = balance_check(0).check(&inputs, &outputs(&[30])).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn check_zero_amount_output() {
+        let err = balance_check(0).check(&inputs(), &outputs(&[30, 0])).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn check_duplicate_recipients() {
+        let outputs = vec![Output::new(address(ADDRESS_3), 10), Output::new(address(ADDRESS_3), 20)];
+
+        let err = balance_check(0).check(&inputs(), &outputs).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn extract_balance_check() {
+        let extra = json!({"data": "x", "balanceCheck": balance_check(1)});
+
+        let (extra, check) = extract_balance_check_from_extra(Some(extra)).unwrap();
+        assert_eq!(Some(json!({"data": "x"})), extra);
+        assert_eq!(Some(balance_check(1)), check);
+
+        let (extra, check) = extract_balance_check_from_extra(Some(json!({"balanceCheck": balance_check(1)}))).unwrap();
+        assert_eq!(None, extra);
+        assert_eq!(Some(balance_check(1)), check);
+    }
+
+    #[test]
+    fn extract_balance_check_without_check() {
+        for extra in vec![None, Some(json!({})), Some(json!({"data": "x"})), Some(json!("x"))] {
+            let (extracted, check) = extract_balance_check_from_extra(extra.clone()).unwrap();
+            assert_eq!(extra, extracted);
+            assert_eq!(None, check);
+        }
+    }
+
+    #[test]
+    fn extract_invalid_balance_check() {
+        let err = extract_balance_check_from_extra(Some(json!({"balanceCheck": {"fee": 1}}))).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn check_overflow() {
+        let err = balance_check(1).check(&inputs(), &outputs(&[TokenAmount::max_value()])).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+
+        let check = BalanceCheck::new(vec![utxo(ADDRESS_1, 1, TokenAmount::max_value()), utxo(ADDRESS_2, 2, 1)], 0);
+        let err = check.check(&inputs(), &outputs(&[10])).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+}
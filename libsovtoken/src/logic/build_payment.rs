@@ -7,10 +7,10 @@ use logic::config::payment_config::PaymentRequest;
 use logic::input::Inputs;
 use logic::output::Outputs;
 use logic::xfer_payload::{XferPayload, Extra};
-use utils::base58::{IntoBase58, FromBase58};
 use utils::txn_author_agreement::TaaAcceptance;
+use utils::current_error::{invalid_parameter, missing_parameter};
 use ErrorCode;
-use utils::ffi_support::{string_from_char_ptr, c_pointer_from_str};
+use utils::ffi_support::string_from_char_ptr;
 use logic::did::Did;
 
 
@@ -25,10 +25,10 @@ pub fn deserialize_inputs(
     cb: Option<BuildPaymentRequestCb>
 ) -> Result<DeserializedArguments, ErrorCode> {
     trace!("logic::build_payment::deserialize_inputs >> inputs_json: {:?}, outputs_json: {:?}, extra: {:?}", secret!(&inputs_json), secret!(&outputs_json), secret!(&extra));
-    let cb = cb.ok_or(ErrorCode::CommonInvalidStructure)?;
+    let cb = cb.ok_or_else(|| missing_parameter("cb"))?;
 
     let inputs_json = string_from_char_ptr(inputs_json)
-        .ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+        .ok_or_else(|| missing_parameter("inputs_json"))?;
     debug!("Converted inputs_json pointer to string >>> {:?}", secret!(&inputs_json));
 
     let did = if let Some(did) = Did::from_pointer(did) {
@@ -37,22 +37,20 @@ pub fn deserialize_inputs(
     debug!("Converted did pointer to string >>> {:?}", secret!(&did));
 
     let outputs_json = string_from_char_ptr(outputs_json)
-        .ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+        .ok_or_else(|| missing_parameter("outputs_json"))?;
     debug!("Converted outputs_json pointer to string >>> {:?}", secret!(&outputs_json));
 
-    let inputs: Inputs = serde_json::from_str(&inputs_json).map_err(map_err_err!())
-        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    let inputs: Inputs = serde_json::from_str(&inputs_json).map_err(invalid_parameter("inputs_json"))?;
     debug!("Deserialized input_json >>> {:?}", secret!(&inputs));
 
-    let outputs: Outputs = serde_json::from_str(&outputs_json).map_err(map_err_err!())
-        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    let outputs: Outputs = serde_json::from_str(&outputs_json).map_err(invalid_parameter("outputs_json"))?;
     debug!("Deserialized output_json >>> {:?}", secret!(&outputs));
 
     let extra = string_from_char_ptr(extra);
     debug!("Converted extra pointer to string >>> {:?}", extra);
 
     let extra: Option<Extra> = if let Some(extra_) = extra {
-        serde_json::from_str(&extra_).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidStructure))?
+        serde_json::from_str(&extra_).map_err(invalid_parameter("extra"))?
     } else { None };
     debug!("Deserialized extra >>> {:?}", secret!(&extra));
 
@@ -60,24 +58,13 @@ pub fn deserialize_inputs(
     return Ok((inputs, outputs, extra, did, cb));
 }
 
-pub fn handle_signing(
-    command_handle: i32,
-    result: Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>,
-    identifier: Option<Did>,
-    cb: BuildPaymentRequestCb
-) {
-    let (error_code, pointer) = match build_payment_request_pointer(identifier, result) {
-        Ok(request_pointer) => (ErrorCode::Success, request_pointer),
-        Err(ec) => (ec, c_pointer_from_str("")),
-    };
-
-    cb(command_handle, error_code as i32, pointer);
-}
-
-fn build_payment_request_pointer(
+/**
+    Builds the payment request from the signed payload.
+*/
+pub fn build_payment_request(
     identifier: Option<Did>,
     result: Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>,
-) -> Result<*const c_char, ErrorCode> {
+) -> Result<String, ErrorCode> {
     let (signed_payload, taa_acceptance) = result?;
     debug!("Signed payload >>> {:?}", secret!(&signed_payload));
 
@@ -88,11 +75,7 @@ fn build_payment_request_pointer(
 
     let identifier = match identifier.map(String::from) {
         Some(idr) => idr,
-        None => {
-            let addr = signed_payload.inputs[0].address.clone();
-            let idr = addr.as_bytes().from_base58_check();
-            idr.map(|s| s.into_base58()).map_err(|_| ErrorCode::CommonInvalidStructure)?
-        }
+        None => signed_payload.inputs[0].address.verkey().to_string()
     };
 
     let mut payment_request = PaymentRequest::new(signed_payload)
@@ -103,7 +86,7 @@ fn build_payment_request_pointer(
     debug!("payment_request >>> {:?}", payment_request);
 
     return payment_request
-        .serialize_to_pointer()
+        .serialize_to_string()
         .map_err(|e| {
             map_err_err!()(e);
             return ErrorCode::CommonInvalidState;
@@ -200,37 +183,30 @@ mod test_deserialize_inputs {
 }
 
 #[cfg(test)]
-mod test_handle_signing {
+mod test_build_payment_request {
     use super::*;
     use logic::request::Request;
     use ErrorCode;
-    use utils::results::ResultHandler;
-    use utils::test::{default, callbacks};
-
-    fn call_handle_signing(input_payload: Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>) -> Result<String, ErrorCode> {
-        let (receiver, command_handle, cb) = callbacks::cb_ec_string();
-        handle_signing(command_handle, input_payload, None, cb.unwrap());
-        ResultHandler::one(ErrorCode::Success, receiver)
-    }
+    use utils::test::default;
 
     #[test]
     fn test_error_code() {
         let signed_payload_result = Err(ErrorCode::CommonInvalidParam1);
-        let result = call_handle_signing(signed_payload_result);
+        let result = build_payment_request(None, signed_payload_result);
         assert_eq!(ErrorCode::CommonInvalidParam1, result.unwrap_err());
     }
 
     #[test]
     fn test_xfer_without_signatures() {
         let unsigned_payload = default::xfer_payload_unsigned();
-        let result = call_handle_signing(Ok((unsigned_payload, None)));
+        let result = build_payment_request(None, Ok((unsigned_payload, None)));
         assert_eq!(ErrorCode::CommonInvalidStructure, result.unwrap_err());
     }
 
     #[test]
     fn test_signed_xfer_payload() {
         let signed_payload = default::xfer_payload_signed();
-        let result = call_handle_signing(Ok((signed_payload, None))).unwrap();
+        let result = build_payment_request(None, Ok((signed_payload, None))).unwrap();
         let request: Request<serde_json::value::Value> = serde_json::from_str(&result).unwrap();
         assert_eq!("10001", request.operation.get("type").unwrap());
         assert_eq!(
@@ -241,4 +217,4 @@ mod test_handle_signing {
         );
         assert_eq!(Some("7LSfLv2S6K7zMPrgmJDkZoJNhWvWRzpU7qt9uMR5yz8G".to_string()), request.identifier);
     }
-}
\ No newline at end of file
+}
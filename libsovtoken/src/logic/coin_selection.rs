@@ -0,0 +1,458 @@
+/*!
+    Coin selection.
+
+    Picks sources out of a list of UTXOs (the output of
+    [`parse_get_utxo_response_handler`]) to cover a target amount plus a fee,
+    and builds the change output. The result can be handed to
+    [`XferPayload::new`] for a payment, or used as the inputs and outputs of
+    [`add_request_fees_handler`] (with a target of `0`).
+
+    Insufficient funds are reported locally with
+    `ErrorCode::PaymentInsufficientFundsError`, before anything is sent to the ledger.
+
+    [`parse_get_utxo_response_handler`]: ../../api/fn.parse_get_utxo_response_handler.html
+    [`add_request_fees_handler`]: ../../api/fn.add_request_fees_handler.html
+    [`XferPayload::new`]: ../xfer_payload/struct.XferPayload.html#method.new
+*/
+
+use std::collections::HashSet;
+
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::input::{Input, Inputs};
+use logic::output::{Output, Outputs};
+use logic::parsers::common::TXO;
+use logic::parsers::parse_get_utxo_response::UTXO;
+use logic::type_aliases::TokenAmount;
+
+/**
+    Maximum number of branches [`SelectionStrategy::ExactMatch`] explores
+    before it falls back to [`SelectionStrategy::LargestFirst`].
+
+    [`SelectionStrategy::ExactMatch`]: enum.SelectionStrategy.html#variant.ExactMatch
+    [`SelectionStrategy::LargestFirst`]: enum.SelectionStrategy.html#variant.LargestFirst
+*/
+pub const MAX_BRANCH_AND_BOUND_TRIES: usize = 100_000;
+
+/**
+    How sources are picked from the list of UTXOs.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// Spend the biggest sources first. Uses few inputs.
+    LargestFirst,
+    /// Spend the smallest sources first. Consolidates dust.
+    SmallestFirst,
+    /// Branch-and-bound search for sources which add up exactly to the amount
+    /// needed, so no change output is created. Among the exact matches it
+    /// visits it keeps the one with the fewest sources, but the search stops
+    /// after `MAX_BRANCH_AND_BOUND_TRIES` branches, so it is not guaranteed to
+    /// find the smallest one.
+    ///
+    /// When no exact match is found (there is none or the search stopped
+    /// first) it falls back to `LargestFirst` and the rest goes to the change
+    /// output.
+    ExactMatch,
+}
+
+/**
+    Selects the inputs to cover `target` + `fee`.
+
+    Returns the selected [`Inputs`] and the [`Outputs`] which hold the change
+    output to `change_address`. No change output is added when the selected
+    sources add up exactly to `target` + `fee`.
+
+    Add the outputs for the payees before building the [`XferPayload`], or use
+    [`select_coins_for_outputs`] which does that.
+
+    ```
+    use sovtoken::logic::address::PaymentAddress;
+    use sovtoken::logic::coin_selection::{select_coins, SelectionStrategy};
+    use sovtoken::logic::parsers::common::TXO;
+    use sovtoken::logic::parsers::parse_get_utxo_response::UTXO;
+
+    let address = "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd";
+    let utxos: Vec<UTXO> = vec![(1, 10), (2, 30)].into_iter().map(|(seq_no, amount)| UTXO {
+        payment_address: address.to_string(),
+        source: TXO { address: address.to_string(), seq_no }.to_libindy_string().unwrap(),
+        amount,
+        extra: "".to_string(),
+    }).collect();
+    let change_address = PaymentAddress::new(address).unwrap();
+
+    let (inputs, outputs) = select_coins(&utxos, 25, 2, &change_address, SelectionStrategy::LargestFirst).unwrap();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0].seq_no, 2);
+    assert_eq!(outputs[0].amount, 3);
+    ```
+
+    [`Inputs`]: ../input/type.Inputs.html
+    [`Outputs`]: ../output/type.Outputs.html
+    [`XferPayload`]: ../xfer_payload/struct.XferPayload.html
+    [`select_coins_for_outputs`]: fn.select_coins_for_outputs.html
+*/
+pub fn select_coins(
+    utxos: &[UTXO],
+    target: TokenAmount,
+    fee: TokenAmount,
+    change_address: &PaymentAddress,
+    strategy: SelectionStrategy
+) -> Result<(Inputs, Outputs), ErrorCode> {
+    trace!("logic::coin_selection::select_coins >> utxos: {:?}, target: {:?}, fee: {:?}, strategy: {:?}", secret!(utxos), target, fee, strategy);
+
+    let needed = target.checked_add(fee).ok_or_else(|| {
+        error!("Target {} plus fee {} overflows", target, fee);
+        ErrorCode::CommonInvalidStructure
+    })?;
+
+    let candidates = candidates_from_utxos(utxos)?;
+
+    let available = candidates.iter()
+        .fold(0u128, |sum, candidate| sum + candidate.amount as u128);
+    if available < needed as u128 {
+        error!("Insufficient funds, needed {} but only {} available", needed, available);
+        return Err(ErrorCode::PaymentInsufficientFundsError);
+    }
+
+    let selected = match strategy {
+        SelectionStrategy::LargestFirst => largest_first(candidates, needed),
+        SelectionStrategy::SmallestFirst => smallest_first(candidates, needed),
+        SelectionStrategy::ExactMatch => exact_match_or_largest_first(candidates, needed),
+    }?;
+
+    let total = sum_of(&selected)?;
+    let change = total - needed;
+
+    let inputs: Inputs = selected.into_iter().map(|candidate| candidate.input).collect();
+    let mut outputs: Outputs = Vec::new();
+    if change > 0 {
+        outputs.push(Output::new(change_address.with_qualifier(), change));
+    }
+
+    trace!("logic::coin_selection::select_coins << inputs: {:?}, outputs: {:?}", secret!(&inputs), secret!(&outputs));
+    Ok((inputs, outputs))
+}
+
+/**
+    Selects the inputs to pay `outputs` + `fee`.
+
+    Returns the selected [`Inputs`] and `outputs` followed by the change output,
+    ready for [`XferPayload::new`].
+
+    [`Inputs`]: ../input/type.Inputs.html
+    [`XferPayload::new`]: ../xfer_payload/struct.XferPayload.html#method.new
+*/
+pub fn select_coins_for_outputs(
+    utxos: &[UTXO],
+    outputs: Outputs,
+    fee: TokenAmount,
+    change_address: &PaymentAddress,
+    strategy: SelectionStrategy
+) -> Result<(Inputs, Outputs), ErrorCode> {
+    let target = outputs.iter()
+        .try_fold(0 as TokenAmount, |sum, output| sum.checked_add(output.amount))
+        .ok_or_else(|| {
+            error!("Sum of the outputs overflows");
+            ErrorCode::CommonInvalidStructure
+        })?;
+
+    let (inputs, change) = select_coins(utxos, target, fee, change_address, strategy)?;
+
+    let mut outputs = outputs;
+    outputs.extend(change);
+    Ok((inputs, outputs))
+}
+
+/**
+    Turns UTXOs into qualified inputs paired with their amounts.
+
+    Sources which are listed twice are only used once, and sources without
+    tokens are skipped since they can't cover anything.
+*/
+pub fn spendable_inputs(utxos: &[UTXO]) -> Result<Vec<(Input, TokenAmount)>, ErrorCode> {
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut inputs: Vec<(Input, TokenAmount)> = Vec::new();
+
+    for utxo in utxos {
+        if utxo.amount == 0 || !seen.insert(&utxo.source) {
+            continue;
+        }
+
+        let txo = TXO::from_libindy_string(&utxo.source)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))?;
+        let address = PaymentAddress::new(&txo.address)?.with_qualifier();
+
+        inputs.push((Input::new(address, txo.seq_no), utxo.amount));
+    }
+
+    Ok(inputs)
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+struct Candidate {
+    input: Input,
+    amount: TokenAmount,
+}
+
+fn candidates_from_utxos(utxos: &[UTXO]) -> Result<Vec<Candidate>, ErrorCode> {
+    let candidates = spendable_inputs(utxos)?
+        .into_iter()
+        .map(|(input, amount)| Candidate { input, amount })
+        .collect();
+    Ok(candidates)
+}
+
+fn sum_of(candidates: &[Candidate]) -> Result<TokenAmount, ErrorCode> {
+    candidates.iter()
+        .try_fold(0 as TokenAmount, |sum, candidate| sum.checked_add(candidate.amount))
+        .ok_or_else(|| {
+            error!("Sum of the selected inputs overflows");
+            ErrorCode::CommonInvalidStructure
+        })
+}
+
+fn take_until_covered(candidates: Vec<Candidate>, needed: TokenAmount) -> Result<Vec<Candidate>, ErrorCode> {
+    let mut total: TokenAmount = 0;
+    let mut selected: Vec<Candidate> = Vec::new();
+
+    for candidate in candidates {
+        if total >= needed {
+            break;
+        }
+        total = total.checked_add(candidate.amount).ok_or_else(|| {
+            error!("Sum of the selected inputs overflows");
+            ErrorCode::CommonInvalidStructure
+        })?;
+        selected.push(candidate);
+    }
+
+    Ok(selected)
+}
+
+fn largest_first(mut candidates: Vec<Candidate>, needed: TokenAmount) -> Result<Vec<Candidate>, ErrorCode> {
+    candidates.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.input.seq_no.cmp(&b.input.seq_no)));
+    take_until_covered(candidates, needed)
+}
+
+fn smallest_first(mut candidates: Vec<Candidate>, needed: TokenAmount) -> Result<Vec<Candidate>, ErrorCode> {
+    candidates.sort_by(|a, b| a.amount.cmp(&b.amount).then(a.input.seq_no.cmp(&b.input.seq_no)));
+    take_until_covered(candidates, needed)
+}
+
+fn exact_match_or_largest_first(mut candidates: Vec<Candidate>, needed: TokenAmount) -> Result<Vec<Candidate>, ErrorCode> {
+    candidates.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.input.seq_no.cmp(&b.input.seq_no)));
+
+    let amounts: Vec<TokenAmount> = candidates.iter().map(|candidate| candidate.amount).collect();
+
+    match exact_match(&amounts, needed) {
+        Some(indexes) => {
+            debug!("Found an exact match with {} inputs", indexes.len());
+            Ok(candidates.into_iter()
+                .enumerate()
+                .filter(|&(index, _)| indexes.contains(&index))
+                .map(|(_, candidate)| candidate)
+                .collect())
+        },
+        None => {
+            debug!("No exact match, falling back to largest first");
+            take_until_covered(candidates, needed)
+        }
+    }
+}
+
+/*
+    Depth first search over `amounts` (sorted descending) for the smallest set
+    of indexes which add up exactly to `needed`.
+*/
+fn exact_match(amounts: &[TokenAmount], needed: TokenAmount) -> Option<Vec<usize>> {
+    struct Search<'a> {
+        amounts: &'a [TokenAmount],
+        remaining_after: Vec<u128>,
+        tries: usize,
+        current: Vec<usize>,
+        best: Option<Vec<usize>>,
+    }
+
+    fn search(state: &mut Search, index: usize, missing: TokenAmount) {
+        if missing == 0 {
+            let is_better = state.best.as_ref().map(|best| state.current.len() < best.len()).unwrap_or(true);
+            if is_better {
+                state.best = Some(state.current.clone());
+            }
+            return;
+        }
+
+        state.tries += 1;
+        if index >= state.amounts.len() || state.tries > MAX_BRANCH_AND_BOUND_TRIES {
+            return;
+        }
+
+        // The rest can't cover what is missing.
+        if state.remaining_after[index] < missing as u128 {
+            return;
+        }
+
+        // Can't do better than the best one found so far.
+        if let Some(ref best) = state.best {
+            if state.current.len() + 1 >= best.len() {
+                return;
+            }
+        }
+
+        let amount = state.amounts[index];
+        if amount <= missing {
+            state.current.push(index);
+            search(state, index + 1, missing - amount);
+            state.current.pop();
+        }
+
+        search(state, index + 1, missing);
+    }
+
+    if needed == 0 {
+        return None;
+    }
+
+    let mut remaining_after = vec![0u128; amounts.len() + 1];
+    for index in (0..amounts.len()).rev() {
+        remaining_after[index] = remaining_after[index + 1] + amounts[index] as u128;
+    }
+
+    let mut state = Search { amounts, remaining_after, tries: 0, current: Vec::new(), best: None };
+    search(&mut state, 0, needed);
+    state.best
+}
+
+#[cfg(test)]
+mod coin_selection_tests {
+    use super::*;
+    use utils::test::default::utxo;
+
+    fn address() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd").unwrap()
+    }
+
+    fn change_address() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A").unwrap()
+    }
+
+    fn utxos(amounts: &[TokenAmount]) -> Vec<UTXO> {
+        let address = address().to_string();
+        amounts.iter().enumerate().map(|(index, amount)| utxo(&address, index as u64 + 1, *amount)).collect()
+    }
+
+    fn seq_nos(inputs: &Inputs) -> Vec<u64> {
+        inputs.iter().map(|input| input.seq_no).collect()
+    }
+
+    #[test]
+    fn select_largest_first() {
+        let (inputs, outputs) = select_coins(&utxos(&[5, 20, 10, 1]), 22, 3, &change_address(), SelectionStrategy::LargestFirst).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![2, 3]);
+        assert_eq!(outputs, vec![Output::new(change_address(), 5)]);
+        assert_eq!(inputs[0].address, address());
+    }
+
+    #[test]
+    fn select_smallest_first() {
+        let (inputs, outputs) = select_coins(&utxos(&[5, 20, 10, 1]), 12, 3, &change_address(), SelectionStrategy::SmallestFirst).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![4, 1, 3]);
+        assert_eq!(outputs, vec![Output::new(change_address(), 1)]);
+    }
+
+    #[test]
+    fn select_exact_match() {
+        let (inputs, outputs) = select_coins(&utxos(&[8, 7, 6, 5, 4, 20]), 14, 1, &change_address(), SelectionStrategy::ExactMatch).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![1, 2]);
+        assert!(outputs.is_empty());
+    }
+
+    #[test]
+    fn select_exact_match_prefers_fewer_inputs() {
+        let (inputs, outputs) = select_coins(&utxos(&[1, 2, 3, 4, 10]), 10, 0, &change_address(), SelectionStrategy::ExactMatch).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![5]);
+        assert!(outputs.is_empty());
+    }
+
+    #[test]
+    fn select_exact_match_falls_back_to_largest_first() {
+        let (inputs, outputs) = select_coins(&utxos(&[10, 10, 10]), 15, 0, &change_address(), SelectionStrategy::ExactMatch).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![1, 2]);
+        assert_eq!(outputs, vec![Output::new(change_address(), 5)]);
+    }
+
+    #[test]
+    fn select_insufficient_funds() {
+        let err = select_coins(&utxos(&[5, 20, 10]), 30, 6, &change_address(), SelectionStrategy::LargestFirst).unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+
+        let err = select_coins(&[], 0, 1, &change_address(), SelectionStrategy::ExactMatch).unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+    }
+
+    #[test]
+    fn select_skips_duplicate_sources() {
+        let mut utxos = utxos(&[10]);
+        utxos.push(utxo(&address().to_string(), 1, 10));
+
+        let err = select_coins(&utxos, 15, 0, &change_address(), SelectionStrategy::LargestFirst).unwrap_err();
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+    }
+
+    #[test]
+    fn select_overflowing_target() {
+        let err = select_coins(&utxos(&[10]), TokenAmount::max_value(), 1, &change_address(), SelectionStrategy::LargestFirst).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn select_overflowing_inputs() {
+        let utxos = utxos(&[1, TokenAmount::max_value()]);
+        let err = select_coins(&utxos, TokenAmount::max_value(), 0, &change_address(), SelectionStrategy::SmallestFirst).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn select_invalid_source() {
+        let mut utxos = utxos(&[10]);
+        utxos[0].source = "txo:sov:fkjZEd8eTBnYJsw7m".to_string();
+
+        let err = select_coins(&utxos, 5, 0, &change_address(), SelectionStrategy::LargestFirst).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+
+    #[test]
+    fn select_change_address_is_qualified() {
+        let change_address = change_address().strip_qualifier().unwrap();
+        let (_, outputs) = select_coins(&utxos(&[10]), 5, 0, &change_address, SelectionStrategy::LargestFirst).unwrap();
+
+        assert!(outputs[0].recipient.is_qualified());
+    }
+
+    #[test]
+    fn select_for_outputs() {
+        let payee = Output::new(PaymentAddress::new("pay:sov:YissN67riFhQ8W6viqtJoCRHFkXtqxaxeL9UyvCoz8sXq5B5A").unwrap(), 12);
+        let (inputs, outputs) = select_coins_for_outputs(&utxos(&[5, 20]), vec![payee.clone()], 2, &change_address(), SelectionStrategy::LargestFirst).unwrap();
+
+        assert_eq!(seq_nos(&inputs), vec![2]);
+        assert_eq!(outputs, vec![payee, Output::new(change_address(), 6)]);
+    }
+
+    #[test]
+    fn deserialize_strategy() {
+        let strategy: SelectionStrategy = ::serde_json::from_str(r#""exact_match""#).unwrap();
+        assert_eq!(SelectionStrategy::ExactMatch, strategy);
+    }
+}
@@ -0,0 +1,95 @@
+/*!
+    Structures for the GET_TXN requests the [`history`] of payment addresses
+    is read from the token ledger with
+
+    [`history`]: ../../history/index.html
+ */
+use ErrorCode;
+use logic::did::Did;
+use logic::request::Request;
+use logic::type_aliases::TxnSeqNo;
+use utils::constants::general::TOKEN_LEDGER_ID;
+use utils::constants::txn_types::TxnType;
+
+/**
+    Operation of a GET_TXN request for the transaction `data` of the token ledger.
+
+    ```
+        use sovtoken::logic::config::get_txn_config::GetTxnOperationRequest;
+
+        let requests = GetTxnOperationRequest::range(1, 10, None).unwrap();
+        assert_eq!(10, requests.len());
+        let json = requests[0].serialize_to_string().unwrap();
+    ```
+ */
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTxnOperationRequest {
+    #[serde(rename = "type")]
+    txn_type: String,
+    ledger_id: u32,
+    data: TxnSeqNo,
+}
+
+impl GetTxnOperationRequest {
+    pub fn new(seq_no: TxnSeqNo, identifier: Option<Did>) -> Request<GetTxnOperationRequest> {
+        let req = GetTxnOperationRequest {
+            txn_type: TxnType::GetTxn.to_string(),
+            ledger_id: TOKEN_LEDGER_ID,
+            data: seq_no,
+        };
+        Request::new(req, identifier.map(String::from))
+    }
+
+    /**
+        One request for each seq_no from `from` to `to`, both included.
+
+        Errors with `CommonInvalidStructure` when `from` is 0 or after `to`.
+    */
+    pub fn range(from: TxnSeqNo, to: TxnSeqNo, identifier: Option<Did>) -> Result<Vec<Request<GetTxnOperationRequest>>, ErrorCode> {
+        if from == 0 || from > to {
+            error!("Invalid range of seq_no {} to {}", from, to);
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        let requests = (from..=to)
+            .map(|seq_no| {
+                let mut request = GetTxnOperationRequest::new(seq_no, identifier.clone());
+                // requests built in the same nanosecond would share their req_id
+                request.req_id += seq_no - from;
+                request
+            })
+            .collect();
+        Ok(requests)
+    }
+}
+
+#[cfg(test)]
+mod get_txn_config_tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn serialize_operation() {
+        let request = GetTxnOperationRequest::new(7, None);
+        let operation = serde_json::to_value(&request.operation).unwrap();
+
+        assert_eq!(json!({"type": "3", "ledgerId": 1001, "data": 7}), operation);
+    }
+
+    #[test]
+    fn range_of_requests() {
+        let did = Did::new(String::from("V4SGRU86Z58d6TV7PBUe6f"));
+        let requests = GetTxnOperationRequest::range(3, 5, Some(did)).unwrap();
+
+        assert_eq!(vec![3, 5], vec![requests[0].operation.data, requests[2].operation.data]);
+        assert_eq!(Some(String::from("V4SGRU86Z58d6TV7PBUe6f")), requests[1].identifier);
+        assert!(requests[0].req_id < requests[1].req_id);
+    }
+
+    #[test]
+    fn invalid_range() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, GetTxnOperationRequest::range(0, 5, None).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, GetTxnOperationRequest::range(6, 5, None).unwrap_err());
+    }
+}
@@ -7,28 +7,35 @@
 
 use logic::address::strip_qualifier_from_address;
 use logic::request::Request;
+use logic::type_aliases::TxnSeqNo;
 use utils::constants::txn_types::GET_UTXO;
 use logic::address::verkey_from_unqualified_address;
 
 /**
  *  Json config to customize [`build_get_utxo_txn_handler`]
  *
+ *  `from` is the seq_no the ledger starts reading the UTXOs at. Ledgers which
+ *  page big UTXO sets return the seq_no of the next page as `next`.
+ *
  *  [`build_get_utxo_txn_handler`]: ../../../api/fn.build_get_utxo_txn_handler.html
  */
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub struct GetUtxoOperationRequest {
     address : String,
     #[serde(rename = "type")]
-    req_type: String
+    req_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    from: Option<TxnSeqNo>,
 }
 
 impl GetUtxoOperationRequest {
-    pub fn new(address : String) -> Request<GetUtxoOperationRequest> {
+    pub fn new(address : String, from: Option<TxnSeqNo>) -> Request<GetUtxoOperationRequest> {
         let unqualified_address: String = strip_qualifier_from_address(&address);
         let identifier = verkey_from_unqualified_address(&unqualified_address).ok();
         let req = GetUtxoOperationRequest {
             address : unqualified_address,
             req_type : GET_UTXO.to_string(),
+            from,
         };
         return Request::new(req, identifier);
     }
@@ -39,6 +46,7 @@ impl GetUtxoOperationRequest {
 mod get_utxo_config_tests {
 
     use logic::address::{qualified_address_from_verkey, verkey_from_unqualified_address};
+    use serde_json;
     use super::*;
 
     // This test ensures TOK-239 is fixed
@@ -48,10 +56,33 @@ mod get_utxo_config_tests {
         let ver_key: String = "EFfodscoymgdJDuM885uEWmgCcA25P6VR6TjVqsYZLW3".to_string();
         let payment_address: String = qualified_address_from_verkey(&ver_key).unwrap();
 
-        let utxo_request = GetUtxoOperationRequest::new(String::from(payment_address));
+        let utxo_request = GetUtxoOperationRequest::new(String::from(payment_address), None);
 
         trace!("utxo_request => {:?}", utxo_request);
 
         assert_eq!(ver_key, verkey_from_unqualified_address(&utxo_request.operation.address).unwrap());
     }
+
+    #[test]
+    fn serialize_without_from() {
+        let utxo_request = GetUtxoOperationRequest::new("pay:sov:dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q".to_string(), None);
+        let operation = serde_json::to_value(&utxo_request.operation).unwrap();
+
+        assert_eq!(json!({
+            "address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "type": "10002"
+        }), operation);
+    }
+
+    #[test]
+    fn serialize_with_from() {
+        let utxo_request = GetUtxoOperationRequest::new("pay:sov:dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q".to_string(), Some(25));
+        let operation = serde_json::to_value(&utxo_request.operation).unwrap();
+
+        assert_eq!(json!({
+            "address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "type": "10002",
+            "from": 25
+        }), operation);
+    }
 }
\ No newline at end of file
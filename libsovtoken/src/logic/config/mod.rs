@@ -3,6 +3,7 @@
  */
 
 pub mod get_fees_config;
+pub mod get_txn_config;
 pub mod get_utxo_config;
 pub mod output_mint_config;
 pub mod payment_address_config;
@@ -54,6 +54,7 @@ impl MintRequest {
 mod output_mint_config_test {
     use super::*;
     use serde_json;
+    use logic::address::PaymentAddress;
     use logic::output::OutputConfig;
     use utils::constants::general::PROTOCOL_VERSION;
     use utils::ffi_support::str_from_char_ptr;
@@ -63,7 +64,7 @@ mod output_mint_config_test {
 
     #[test]
     fn serializing_mint_struct_config() {
-        let output = Output::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 10);
+        let output = Output::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(), 10);
         let mint : OutputConfig = OutputConfig {
             ver: 1,
             outputs: vec![output],
@@ -74,7 +75,7 @@ mod output_mint_config_test {
     fn initial_mint_request() -> Request<MintRequest> {
         let identifier: String = rand_string(21);
         let did = Did::new(identifier);
-        let output = Output::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 10);
+        let output = Output::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(), 10);
         let outputs = vec![output];
         return MintRequest::new(outputs, Some(did), None);
     }
@@ -97,7 +98,7 @@ mod output_mint_config_test {
     fn create_request_with_mint_config() {
         let identifier: String = rand_string(21);
         let did = Did::new(identifier);
-        let output = Output::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 10);
+        let output = Output::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(), 10);
         let outputs = vec![output];
         let request = MintRequest::from_config(outputs.clone(), Some(did), None);
         assert_eq!(request.operation.outputs, outputs);
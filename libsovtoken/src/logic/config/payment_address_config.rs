@@ -1,8 +1,12 @@
 //!  defines structure and implementation for PaymentAddressConfig which is used
 //! for generating payment addresses
 
+use hex::ToHex;
 use serde_json;
 use std::ffi::CString;
+use ErrorCode;
+use logic::hd_derivation::{derive_seed, master_seed_from_str, DerivationPath, DEFAULT_ACCOUNT_PATH};
+use logic::mnemonic::seed_from_mnemonic;
 use utils::ffi_support::cstring_from_str;
 use utils::json_conversion::JsonSerialize;
 
@@ -12,10 +16,24 @@ use utils::json_conversion::JsonSerialize;
 
      The seed should be 32 bytes, thats what libsodium requires. Seed can be optional, in that case libsodium generates a random 32 byte seed
 
+     With a `path` or an `index`, `seed` is a master seed and the key of the
+     address is derived from it, see [`key_config`]. A BIP-39 `mnemonic`, with
+     an optional `passphrase`, can give the master seed instead of `seed`.
+
+     [`key_config`]: #method.key_config
 */
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct PaymentAddressConfig {
+    #[serde(default)]
     pub seed : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub passphrase: Option<String>,
 }
 
 impl PaymentAddressConfig {
@@ -27,4 +45,146 @@ impl PaymentAddressConfig {
         let serialized = JsonSerialize::to_json(&self)?;
         return Ok(cstring_from_str(serialized));
     }
+
+    /**
+        The config of the key to create.
+
+        Without `path`, `index` and `mnemonic` this is the config itself.
+        Otherwise the key is created from the seed derived from the master seed
+        at:
+
+        * `path` when there is no `index`
+        * the hardened child `index` of `path`
+        * the hardened child `index` of [`DEFAULT_ACCOUNT_PATH`] when there is
+          no `path`
+        * the first address of [`DEFAULT_ACCOUNT_PATH`] for a `mnemonic`
+          without `path` and `index`
+
+        The master seed is the seed of `mnemonic` and `passphrase` when there
+        is a mnemonic, otherwise `seed`.
+
+        Errors with `CommonInvalidStructure` when the path or the mnemonic is
+        invalid, or there is not exactly one of `seed` and `mnemonic`.
+
+        [`DEFAULT_ACCOUNT_PATH`]: ../../hd_derivation/constant.DEFAULT_ACCOUNT_PATH.html
+    */
+    pub fn key_config(self) -> Result<PaymentAddressConfig, ErrorCode> {
+        let derived = self.path.is_some() || self.index.is_some() || self.mnemonic.is_some() || self.passphrase.is_some();
+        if !derived {
+            return Ok(self);
+        }
+
+        let path = match (self.path.as_ref(), self.index) {
+            (None, None) => DEFAULT_ACCOUNT_PATH.parse::<DerivationPath>()?.child(0)?,
+            (Some(path), None) => path.parse::<DerivationPath>()?,
+            (path, Some(index)) => path.map_or(DEFAULT_ACCOUNT_PATH, String::as_str)
+                .parse::<DerivationPath>()?
+                .child(index)?,
+        };
+
+        let seed = derive_seed(&self.master_seed()?, &path)?;
+        Ok(PaymentAddressConfig { seed: seed.to_hex(), ..Default::default() })
+    }
+
+    /**
+        The master seed the keys of the config are derived from, the seed of
+        `mnemonic` and `passphrase` when there is a mnemonic, otherwise `seed`.
+
+        Errors with `CommonInvalidStructure` when the mnemonic is invalid, or
+        there is not exactly one of `seed` and `mnemonic`.
+    */
+    pub fn master_seed(&self) -> Result<Vec<u8>, ErrorCode> {
+        match (self.seed.is_empty(), self.mnemonic.as_ref()) {
+            (true, Some(mnemonic)) => seed_from_mnemonic(mnemonic, self.passphrase.as_ref().map_or("", String::as_str)),
+            (false, None) if self.passphrase.is_none() => master_seed_from_str(&self.seed),
+            (false, None) => {
+                error!("A passphrase is only used with a mnemonic");
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+            (false, Some(_)) => {
+                error!("A master seed and a mnemonic can't both be given");
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+            (true, None) => {
+                error!("A master seed or a mnemonic is needed to derive keys");
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod payment_address_config_tests {
+    use super::*;
+
+    fn config(seed: &str, path: Option<&str>, index: Option<u32>) -> PaymentAddressConfig {
+        PaymentAddressConfig { seed: seed.to_string(), path: path.map(String::from), index, ..Default::default() }
+    }
+
+    #[test]
+    fn key_config_without_derivation() {
+        let key_config = config("000000000000000000000000Address1", None, None).key_config().unwrap();
+        assert_eq!("000000000000000000000000Address1", key_config.seed);
+    }
+
+    #[test]
+    fn key_config_of_path_or_index() {
+        let seed = "AAECAwQFBgcICQoLDA0ODw==";
+        let m_0_1 = "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2";
+
+        assert_eq!(m_0_1, config(seed, Some("m/0'/1'"), None).key_config().unwrap().seed);
+        assert_eq!(m_0_1, config(seed, None, Some(1)).key_config().unwrap().seed);
+        assert_eq!(m_0_1, config(seed, Some("m/0'"), Some(1)).key_config().unwrap().seed);
+
+        let key_config = config(seed, Some("m/0'"), Some(1)).key_config().unwrap();
+        assert_eq!((None, None), (key_config.path, key_config.index));
+    }
+
+    #[test]
+    fn key_config_errors() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, config("", None, Some(1)).key_config().unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, config("AAECAwQFBgcICQoLDA0ODw==", Some("m/0"), None).key_config().unwrap_err());
+    }
+
+    fn mnemonic_config(mnemonic: &str, passphrase: Option<&str>, index: Option<u32>) -> PaymentAddressConfig {
+        PaymentAddressConfig {
+            mnemonic: Some(mnemonic.to_string()),
+            passphrase: passphrase.map(String::from),
+            index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn key_config_of_mnemonic() {
+        let mnemonic = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let master_seed = seed_from_mnemonic(mnemonic, "TREZOR").unwrap().to_hex();
+
+        let key_config = mnemonic_config(mnemonic, Some("TREZOR"), None).key_config().unwrap();
+        assert_eq!(config(&master_seed, Some("m/0'/0'"), None).key_config().unwrap().seed, key_config.seed);
+        assert_eq!(None, key_config.mnemonic);
+
+        let key_config = mnemonic_config(mnemonic, Some("TREZOR"), Some(4)).key_config().unwrap();
+        assert_eq!(config(&master_seed, None, Some(4)).key_config().unwrap().seed, key_config.seed);
+        assert_ne!(key_config.seed, mnemonic_config(mnemonic, None, Some(4)).key_config().unwrap().seed);
+    }
+
+    #[test]
+    fn key_config_of_mnemonic_errors() {
+        let mnemonic = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+        let with_seed = PaymentAddressConfig { seed: "AAECAwQFBgcICQoLDA0ODw==".to_string(), ..mnemonic_config(mnemonic, None, None) };
+        assert_eq!(ErrorCode::CommonInvalidStructure, with_seed.key_config().unwrap_err());
+
+        let bad_checksum = "legal winner thank year wave sausage worth useful legal winner thank year";
+        assert_eq!(ErrorCode::CommonInvalidStructure, mnemonic_config(bad_checksum, None, None).key_config().unwrap_err());
+
+        let passphrase_only = PaymentAddressConfig { seed: "AAECAwQFBgcICQoLDA0ODw==".to_string(), passphrase: Some("TREZOR".to_string()), ..Default::default() };
+        assert_eq!(ErrorCode::CommonInvalidStructure, passphrase_only.key_config().unwrap_err());
+    }
+
+    #[test]
+    fn deserialize_config() {
+        let config: PaymentAddressConfig = serde_json::from_str(r#"{"seed": "AAECAwQFBgcICQoLDA0ODw==", "index": 3}"#).unwrap();
+        assert_eq!((None, Some(3)), (config.path, config.index));
+    }
 }
@@ -22,6 +22,7 @@ use logic::xfer_payload::XferPayload;
         // pub mod utils;
         use sovtoken::logic::config::payment_config::PaymentRequest;
         use sovtoken::logic::indy_sdk_api::crypto_api::CryptoSdk;
+        use sovtoken::logic::address::PaymentAddress;
         use sovtoken::logic::input::Input;
         use sovtoken::logic::output::Output;
         use sovtoken::logic::xfer_payload::XferPayload;
@@ -30,9 +31,9 @@ use logic::xfer_payload::XferPayload;
         // let wallet = utils::wallet::Wallet::new().unwrap();
 
         let identifier = String::from("hgrhyNXqW4KNTz4wwiV8v");
-        let address1 = String::from("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs");
-        let address2 = String::from("pay:sov:2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb");
-        let address3 = String::from("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm");
+        let address1 = PaymentAddress::new("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs").unwrap();
+        let address2 = PaymentAddress::new("pay:sov:2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb").unwrap();
+        let address3 = PaymentAddress::new("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap();
 
         let inputs = vec![
             Input::new(address1, 2),
@@ -92,6 +93,7 @@ impl PaymentRequest {
 mod payment_request_test {
     use super::*;
     use serde_json;
+    use logic::address::PaymentAddress;
     use logic::input::Input;
     use logic::output::Output;
     use utils::constants::general::PROTOCOL_VERSION;
@@ -101,8 +103,8 @@ mod payment_request_test {
 
     fn initial_xfer_request() -> Request<PaymentRequest> {
         let identifier: String = rand_string(21);
-        let output = Output::new(String::from("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7"), 10);
-        let input = Input::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"),30);
+        let output = Output::new(PaymentAddress::new("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap(), 10);
+        let input = Input::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(),30);
     
         let mut payload = XferPayload::new(vec![input], vec![output], None);
         payload.signatures = Some(vec![String::from("239asdkj3298uadkljasd98u234ijasdlkj")]);
@@ -0,0 +1,211 @@
+/*!
+    UTXO consolidation.
+
+    Addresses which receive a lot of small payments end up with many sources.
+    Spending them all at once makes requests too big for the ledger, so the
+    sources are merged in batches of at most `max_inputs` inputs. Every batch
+    becomes an unsigned XFER_PUBLIC [`XferPayload`] with a single output to the
+    destination address, paying the XFER_PUBLIC fee from the GET_FEES map.
+
+    The outputs of a round only exist once its payloads are on the ledger. When
+    a round leaves more than one output, fetch the UTXOs again and plan the
+    next round until everything is merged.
+
+    [`XferPayload`]: ../xfer_payload/struct.XferPayload.html
+*/
+
+use std::collections::HashMap;
+
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::coin_selection::spendable_inputs;
+use logic::input::Inputs;
+use logic::output::Output;
+use logic::parsers::parse_get_utxo_response::UTXO;
+use logic::type_aliases::TokenAmount;
+use logic::xfer_payload::XferPayload;
+use utils::constants::txn_types::XFER_PUBLIC;
+
+/**
+    Plans one round of consolidation payloads, in the order they should be sent.
+
+    `fees` is the map returned by [`parse_get_txn_fees_response_handler`]. When
+    it has no XFER_PUBLIC entry, transfers are free.
+
+    Sources are batched biggest first, so the dust ends up in the last batches.
+    A batch is left out when it holds a single source, or when it can't pay
+    more than the fee. Errors with `CommonInvalidStructure` when `max_inputs`
+    is smaller than 2.
+
+    ```
+    use std::collections::HashMap;
+    use sovtoken::logic::address::PaymentAddress;
+    use sovtoken::logic::consolidation::plan_consolidation;
+    use sovtoken::logic::parsers::common::TXO;
+    use sovtoken::logic::parsers::parse_get_utxo_response::UTXO;
+
+    let address = "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd";
+    let utxos: Vec<UTXO> = (1..6).map(|seq_no| UTXO {
+        payment_address: address.to_string(),
+        source: TXO { address: address.to_string(), seq_no }.to_libindy_string().unwrap(),
+        amount: 10,
+        extra: "".to_string(),
+    }).collect();
+    let mut fees = HashMap::new();
+    fees.insert("10001".to_string(), 1);
+
+    let destination = PaymentAddress::new(address).unwrap();
+    let payloads = plan_consolidation(&utxos, &destination, 3, &fees).unwrap();
+    assert_eq!(payloads.len(), 2);
+    assert_eq!(payloads[0].outputs[0].amount, 29);
+    assert_eq!(payloads[1].outputs[0].amount, 19);
+    ```
+
+    [`parse_get_txn_fees_response_handler`]: ../../api/fn.parse_get_txn_fees_response_handler.html
+*/
+pub fn plan_consolidation(
+    utxos: &[UTXO],
+    destination: &PaymentAddress,
+    max_inputs: usize,
+    fees: &HashMap<String, TokenAmount>
+) -> Result<Vec<XferPayload>, ErrorCode> {
+    trace!("logic::consolidation::plan_consolidation >> utxos: {:?}, destination: {:?}, max_inputs: {:?}, fees: {:?}", secret!(utxos), secret!(destination), max_inputs, fees);
+
+    if max_inputs < 2 {
+        error!("Can't consolidate with less than 2 inputs per transaction, got {}", max_inputs);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let fee = fees.get(XFER_PUBLIC).cloned().unwrap_or(0);
+    debug!("Fee for {} >>> {}", XFER_PUBLIC, fee);
+
+    let mut sources = spendable_inputs(utxos)?;
+    sources.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.seq_no.cmp(&b.0.seq_no)));
+
+    let mut payloads: Vec<XferPayload> = Vec::new();
+    for batch in sources.chunks(max_inputs) {
+        if batch.len() < 2 {
+            debug!("Leaving out a batch with a single source");
+            continue;
+        }
+
+        let total = batch.iter()
+            .try_fold(0 as TokenAmount, |sum, &(_, amount)| sum.checked_add(amount))
+            .ok_or_else(|| {
+                error!("Sum of the batch overflows");
+                ErrorCode::CommonInvalidStructure
+            })?;
+        if total <= fee {
+            debug!("Leaving out a batch of {} which doesn't cover the fee of {}", total, fee);
+            continue;
+        }
+
+        let inputs: Inputs = batch.iter().map(|(input, _)| input.clone()).collect();
+        let outputs = vec![Output::new(destination.with_qualifier(), total - fee)];
+        payloads.push(XferPayload::new(inputs, outputs, None));
+    }
+
+    trace!("logic::consolidation::plan_consolidation << payloads: {:?}", secret!(&payloads));
+    Ok(payloads)
+}
+
+#[cfg(test)]
+mod consolidation_tests {
+    use super::*;
+    use logic::input::Input;
+    use utils::test::default::utxo;
+
+    fn address() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd").unwrap()
+    }
+
+    fn destination() -> PaymentAddress {
+        PaymentAddress::new("2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A").unwrap()
+    }
+
+    fn utxos(amounts: &[TokenAmount]) -> Vec<UTXO> {
+        let address = address();
+        amounts.iter().enumerate().map(|(index, amount)| utxo(&address.to_string(), index as u64 + 1, *amount)).collect()
+    }
+
+    fn fees(fee: TokenAmount) -> HashMap<String, TokenAmount> {
+        let mut fees = HashMap::new();
+        fees.insert(XFER_PUBLIC.to_string(), fee);
+        fees.insert("1".to_string(), 100);
+        fees
+    }
+
+    fn seq_nos(inputs: &[Input]) -> Vec<u64> {
+        inputs.iter().map(|input| input.seq_no).collect()
+    }
+
+    #[test]
+    fn consolidate_in_batches() {
+        let payloads = plan_consolidation(&utxos(&[1, 5, 3, 4, 2, 6, 7]), &destination(), 3, &fees(1)).unwrap();
+
+        assert_eq!(2, payloads.len());
+        assert_eq!(vec![7, 6, 2], seq_nos(&payloads[0].inputs));
+        assert_eq!(vec![4, 3, 5], seq_nos(&payloads[1].inputs));
+        assert_eq!(17, payloads[0].outputs[0].amount);
+        assert_eq!(8, payloads[1].outputs[0].amount);
+    }
+
+    #[test]
+    fn consolidate_outputs_to_qualified_destination() {
+        let payloads = plan_consolidation(&utxos(&[1, 2]), &destination(), 10, &fees(0)).unwrap();
+
+        assert_eq!(1, payloads.len());
+        assert_eq!(1, payloads[0].outputs.len());
+        assert_eq!(destination().with_qualifier(), payloads[0].outputs[0].recipient);
+        assert!(payloads[0].inputs.iter().all(|input| input.address.is_qualified()));
+        assert!(payloads[0].signatures.is_none());
+    }
+
+    #[test]
+    fn consolidate_without_xfer_fee() {
+        let payloads = plan_consolidation(&utxos(&[1, 2, 3]), &destination(), 3, &HashMap::new()).unwrap();
+        assert_eq!(6, payloads[0].outputs[0].amount);
+    }
+
+    #[test]
+    fn consolidate_leaves_out_single_source() {
+        let payloads = plan_consolidation(&utxos(&[4, 3, 2, 1, 5]), &destination(), 2, &fees(0)).unwrap();
+
+        assert_eq!(2, payloads.len());
+        assert_eq!(vec![5, 1], seq_nos(&payloads[0].inputs));
+        assert_eq!(vec![2, 3], seq_nos(&payloads[1].inputs));
+    }
+
+    #[test]
+    fn consolidate_leaves_out_batch_not_covering_fee() {
+        let payloads = plan_consolidation(&utxos(&[10, 10, 1, 1]), &destination(), 2, &fees(2)).unwrap();
+
+        assert_eq!(1, payloads.len());
+        assert_eq!(18, payloads[0].outputs[0].amount);
+    }
+
+    #[test]
+    fn consolidate_skips_empty_and_duplicate_sources() {
+        let mut utxos = utxos(&[3, 0, 4]);
+        let duplicate = utxos[0].clone();
+        utxos.push(duplicate);
+
+        let payloads = plan_consolidation(&utxos, &destination(), 10, &fees(0)).unwrap();
+        assert_eq!(vec![3, 1], seq_nos(&payloads[0].inputs));
+    }
+
+    #[test]
+    fn consolidate_nothing() {
+        let payloads = plan_consolidation(&utxos(&[10]), &destination(), 10, &fees(1)).unwrap();
+        assert!(payloads.is_empty());
+
+        let payloads = plan_consolidation(&Vec::new(), &destination(), 10, &fees(1)).unwrap();
+        assert!(payloads.is_empty());
+    }
+
+    #[test]
+    fn consolidate_invalid_max_inputs() {
+        let err = plan_consolidation(&utxos(&[1, 2]), &destination(), 1, &fees(0)).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+}
@@ -0,0 +1,429 @@
+/*!
+    Works out the fee the pool charges for a request.
+
+    Fees are set per alias with SET_FEES, and an alias is tied to an action
+    through the `metadata.fees` field of the constraint of its AUTH_RULE (see
+    `doc/fees.md`). [`FeeCalculator`] takes the auth rules from a GET_AUTH_RULE
+    reply and the aliases from a GET_FEES reply, finds the auth rule of a
+    request and returns its alias and amount.
+
+    [`FeeCalculator`]: struct.FeeCalculator.html
+*/
+
+use std::collections::HashMap;
+
+use serde_json;
+
+use ErrorCode;
+use logic::parsers::parse_get_auth_rule::{self, AuthRule, Constraint, ParseGetAuthRuleResponse};
+use logic::parsers::parse_get_txn_fees::fees_from_get_txn_fees_response;
+use logic::type_aliases::TokenAmount;
+use utils::constants::txn_types::TxnType;
+use utils::json_conversion::JsonDeserialize;
+
+const ADD_ACTION: &str = "ADD";
+const ANY_VALUE: &str = "*";
+const ROLE_FIELD: &str = "role";
+
+/**
+    The fee alias of a request and its amount.
+
+    `alias` is `None` when the auth rule of the request has no fee.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RequestFee {
+    pub alias: Option<String>,
+    pub amount: TokenAmount,
+}
+
+/**
+    Maps requests to the fee the pool charges for them.
+
+    ```
+    use std::collections::HashMap;
+    use sovtoken::logic::fee_calculator::{FeeCalculator, RequestFee};
+    use sovtoken::logic::parsers::parse_get_auth_rule::AuthRule;
+    use sovtoken::utils::json_conversion::JsonDeserialize;
+
+    let rule = AuthRule::from_json(r#"{
+        "auth_type": "101",
+        "auth_action": "ADD",
+        "field": "*",
+        "new_value": "*",
+        "constraint": {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "metadata": {"fees": "add_schema"}}
+    }"#).unwrap();
+    let mut fees = HashMap::new();
+    fees.insert("add_schema".to_string(), 10);
+
+    let calculator = FeeCalculator::new(vec![rule], fees);
+    let fee = calculator.fee_for_request(r#"{"operation": {"type": "101", "data": {}}}"#, None).unwrap();
+    assert_eq!(RequestFee { alias: Some("add_schema".to_string()), amount: 10 }, fee);
+    ```
+*/
+#[derive(Debug, Clone)]
+pub struct FeeCalculator {
+    auth_rules: Vec<AuthRule>,
+    fees: HashMap<String, TokenAmount>,
+}
+
+impl FeeCalculator {
+    pub fn new(auth_rules: Vec<AuthRule>, fees: HashMap<String, TokenAmount>) -> FeeCalculator {
+        FeeCalculator { auth_rules, fees }
+    }
+
+    /**
+        Creates the calculator from the replies of GET_AUTH_RULE and GET_FEES.
+    */
+    pub fn from_responses(get_auth_rule_response: &str, get_fees_response: &str) -> Result<FeeCalculator, ErrorCode> {
+        let response = ParseGetAuthRuleResponse::from_json(get_auth_rule_response)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))?;
+        let auth_rules = parse_get_auth_rule::from_response(response)?;
+
+        let fees = fees_from_get_txn_fees_response(get_fees_response)?;
+
+        Ok(FeeCalculator::new(auth_rules, fees))
+    }
+
+    /**
+        Returns the fee alias and amount for the request in `request_json`.
+
+        Requests are taken to add something new, so a NYM is matched by its
+        `role` and every other request by its type. `signer_role` is the role of
+        the signer (`""` for an identity owner). When it is given, only the
+        parts of OR and AND constraints for that role count.
+
+        Errors with
+        - `CommonInvalidStructure` when the request is invalid, no auth rule
+          matches it or the signer is not allowed to send it.
+        - `CommonInvalidState` when the parts of the constraint cost different
+          amounts, so the fee depends on who signs.
+    */
+    pub fn fee_for_request(&self, request_json: &str, signer_role: Option<&str>) -> Result<RequestFee, ErrorCode> {
+        trace!("logic::fee_calculator::fee_for_request >> request_json: {:?}, signer_role: {:?}", secret!(request_json), signer_role);
+
+        let request: serde_json::Value = serde_json::from_str(request_json)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))?;
+        let operation = request.get("operation").ok_or_else(|| {
+            error!("Request has no operation");
+            ErrorCode::CommonInvalidStructure
+        })?;
+        let txn_type = operation.get("type").and_then(|txn_type| txn_type.as_str()).ok_or_else(|| {
+            error!("Operation has no type");
+            ErrorCode::CommonInvalidStructure
+        })?;
+
+        let (field, new_value) = if TxnType::from_code(txn_type) == Some(TxnType::Nym) {
+            let role = operation.get(ROLE_FIELD).and_then(|role| role.as_str()).unwrap_or("");
+            (ROLE_FIELD, role)
+        } else {
+            (ANY_VALUE, ANY_VALUE)
+        };
+
+        let rule = self.find_rule(txn_type, field, new_value).ok_or_else(|| {
+            error!("No auth rule to {} {} with {} {:?}", ADD_ACTION, txn_type, field, new_value);
+            ErrorCode::CommonInvalidStructure
+        })?;
+        debug!("Auth rule for the request >>> {:?}", rule);
+
+        let mut aliases: Vec<Option<String>> = Vec::new();
+        collect_fee_aliases(&rule.constraint, signer_role, &mut aliases);
+        if aliases.is_empty() {
+            error!("No one with role {:?} may send this request", signer_role);
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        let fees: Vec<RequestFee> = aliases.into_iter()
+            .map(|alias| {
+                let amount = alias.as_ref().and_then(|alias| self.fees.get(alias)).cloned().unwrap_or(0);
+                RequestFee { alias, amount }
+            })
+            .collect();
+
+        if fees.iter().any(|fee| fee.amount != fees[0].amount) {
+            error!("The fee depends on who signs the request: {:?}", fees);
+            return Err(ErrorCode::CommonInvalidState);
+        }
+
+        let fee = fees.iter()
+            .find(|fee| fee.alias.is_some())
+            .unwrap_or(&fees[0])
+            .clone();
+
+        trace!("logic::fee_calculator::fee_for_request << fee: {:?}", fee);
+        Ok(fee)
+    }
+
+    /*
+        The rule which matches the action most exactly.
+    */
+    fn find_rule(&self, txn_type: &str, field: &str, new_value: &str) -> Option<&AuthRule> {
+        self.auth_rules.iter()
+            .filter(|rule| rule.auth_type == txn_type && rule.auth_action == ADD_ACTION)
+            .filter_map(|rule| {
+                let rule_new_value = rule.new_value.as_ref().map(String::as_str).unwrap_or("");
+                let field_score = matches(&rule.field, field)?;
+                let new_value_score = matches(rule_new_value, new_value)?;
+                Some((field_score + new_value_score, rule))
+            })
+            .max_by_key(|&(score, _)| score)
+            .map(|(_, rule)| rule)
+    }
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/*
+    1 for an exact match, 0 for a match through `*`.
+*/
+fn matches(rule_value: &str, value: &str) -> Option<u8> {
+    if rule_value == value {
+        Some(1)
+    } else if rule_value == ANY_VALUE {
+        Some(0)
+    } else {
+        None
+    }
+}
+
+fn collect_fee_aliases(constraint: &Constraint, signer_role: Option<&str>, aliases: &mut Vec<Option<String>>) {
+    match *constraint {
+        Constraint::Role(ref role_constraint) => {
+            let role = role_constraint.role.as_ref().map(String::as_str).unwrap_or("");
+            let role_matches = signer_role.map(|signer_role| role == signer_role || role == ANY_VALUE).unwrap_or(true);
+            if role_matches {
+                aliases.push(role_constraint.metadata.as_ref().and_then(|metadata| metadata.fees.clone()));
+            }
+        },
+        Constraint::And(ref combination) | Constraint::Or(ref combination) => {
+            for constraint in &combination.auth_constraints {
+                collect_fee_aliases(constraint, signer_role, aliases);
+            }
+        },
+        Constraint::Forbidden(_) => {},
+    }
+}
+
+#[cfg(test)]
+mod fee_calculator_tests {
+    use super::*;
+
+    fn auth_rules() -> Vec<AuthRule> {
+        let rules = vec![
+            json!({
+                "auth_type": "1",
+                "auth_action": "ADD",
+                "field": "role",
+                "new_value": "",
+                "constraint": {
+                    "constraint_id": "OR",
+                    "auth_constraints": [
+                        {"constraint_id": "ROLE", "role": "0", "sig_count": 1, "metadata": {"fees": "add_identity_owner"}},
+                        {"constraint_id": "ROLE", "role": "2", "sig_count": 1, "metadata": {"fees": "add_identity_owner"}}
+                    ]
+                }
+            }),
+            json!({
+                "auth_type": "1",
+                "auth_action": "ADD",
+                "field": "role",
+                "new_value": "2",
+                "constraint": {
+                    "constraint_id": "OR",
+                    "auth_constraints": [
+                        {"constraint_id": "ROLE", "role": "0", "sig_count": 1, "metadata": {}},
+                        {"constraint_id": "ROLE", "role": "2", "sig_count": 1, "metadata": {"fees": "add_new_steward"}}
+                    ]
+                }
+            }),
+            json!({
+                "auth_type": "1",
+                "auth_action": "ADD",
+                "field": "role",
+                "new_value": "0",
+                "constraint": {"constraint_id": "ROLE", "role": "0", "sig_count": 1, "metadata": {}}
+            }),
+            json!({
+                "auth_type": "100",
+                "auth_action": "ADD",
+                "field": "*",
+                "new_value": "*",
+                "constraint": {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "need_to_be_owner": true, "metadata": {"fees": "add_attrib"}}
+            }),
+            json!({
+                "auth_type": "100",
+                "auth_action": "EDIT",
+                "field": "*",
+                "old_value": "*",
+                "new_value": "*",
+                "constraint": {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "metadata": {"fees": "edit_attrib"}}
+            }),
+            json!({
+                "auth_type": "101",
+                "auth_action": "ADD",
+                "field": "*",
+                "new_value": "*",
+                "constraint": {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "metadata": {"fees": "add_schema"}}
+            }),
+            json!({
+                "auth_type": "102",
+                "auth_action": "ADD",
+                "field": "*",
+                "new_value": "*",
+                "constraint": {
+                    "constraint_id": "AND",
+                    "auth_constraints": [
+                        {"constraint_id": "ROLE", "role": "101", "sig_count": 1, "metadata": {"fees": "add_cred_def"}},
+                        {"constraint_id": "ROLE", "role": "*", "sig_count": 1, "metadata": {"fees": "add_cred_def_owner"}}
+                    ]
+                }
+            }),
+            json!({
+                "auth_type": "113",
+                "auth_action": "ADD",
+                "field": "*",
+                "new_value": "*",
+                "constraint": {"constraint_id": "FORBIDDEN"}
+            }),
+        ];
+        rules.into_iter().map(|rule| serde_json::from_value(rule).unwrap()).collect()
+    }
+
+    fn calculator() -> FeeCalculator {
+        let mut fees = HashMap::new();
+        fees.insert("add_identity_owner".to_string(), 5);
+        fees.insert("add_new_steward".to_string(), 42);
+        fees.insert("add_attrib".to_string(), 2);
+        fees.insert("edit_attrib".to_string(), 20);
+        fees.insert("add_cred_def".to_string(), 50);
+        fees.insert("add_cred_def_owner".to_string(), 50);
+        FeeCalculator::new(auth_rules(), fees)
+    }
+
+    fn fee(alias: &str, amount: TokenAmount) -> RequestFee {
+        RequestFee { alias: Some(alias.to_string()), amount }
+    }
+
+    fn request(operation: serde_json::Value) -> String {
+        json!({
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "operation": operation
+        }).to_string()
+    }
+
+    #[test]
+    fn fee_for_nym() {
+        let request = request(json!({"type": "1", "dest": "VsKV7grR1BUE29mG2Fm2kX"}));
+        assert_eq!(fee("add_identity_owner", 5), calculator().fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn fee_for_nym_with_null_role() {
+        let request = request(json!({"type": "1", "dest": "VsKV7grR1BUE29mG2Fm2kX", "role": null}));
+        assert_eq!(fee("add_identity_owner", 5), calculator().fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn fee_for_nym_depends_on_signer() {
+        let request = request(json!({"type": "1", "dest": "VsKV7grR1BUE29mG2Fm2kX", "role": "2"}));
+        let calculator = calculator();
+
+        assert_eq!(ErrorCode::CommonInvalidState, calculator.fee_for_request(&request, None).unwrap_err());
+        assert_eq!(fee("add_new_steward", 42), calculator.fee_for_request(&request, Some("2")).unwrap());
+        assert_eq!(RequestFee { alias: None, amount: 0 }, calculator.fee_for_request(&request, Some("0")).unwrap());
+    }
+
+    #[test]
+    fn fee_for_nym_without_fee() {
+        let request = request(json!({"type": "1", "dest": "VsKV7grR1BUE29mG2Fm2kX", "role": "0"}));
+        assert_eq!(RequestFee { alias: None, amount: 0 }, calculator().fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn fee_for_nym_signer_not_allowed() {
+        let request = request(json!({"type": "1", "dest": "VsKV7grR1BUE29mG2Fm2kX", "role": "0"}));
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator().fee_for_request(&request, Some("2")).unwrap_err());
+    }
+
+    #[test]
+    fn fee_for_attrib() {
+        let request = request(json!({"type": "100", "dest": "VsKV7grR1BUE29mG2Fm2kX", "raw": "{}"}));
+        assert_eq!(fee("add_attrib", 2), calculator().fee_for_request(&request, Some("")).unwrap());
+    }
+
+    #[test]
+    fn fee_for_schema_without_amount() {
+        let request = request(json!({"type": "101", "data": {"name": "gvt", "version": "1.0", "attr_names": ["name"]}}));
+        assert_eq!(fee("add_schema", 0), calculator().fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn fee_for_cred_def_and_constraint() {
+        let request = request(json!({"type": "102", "ref": 10, "signature_type": "CL", "tag": "tag", "data": {}}));
+        assert_eq!(fee("add_cred_def", 50), calculator().fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn fee_for_forbidden_request() {
+        let request = request(json!({"type": "113"}));
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator().fee_for_request(&request, None).unwrap_err());
+    }
+
+    #[test]
+    fn fee_for_request_without_rule() {
+        let request = request(json!({"type": "104"}));
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator().fee_for_request(&request, None).unwrap_err());
+    }
+
+    #[test]
+    fn fee_for_invalid_request() {
+        let calculator = calculator();
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator.fee_for_request("{", None).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator.fee_for_request(r#"{"reqId": 1}"#, None).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, calculator.fee_for_request(&request(json!({"dest": "VsKV7grR1BUE29mG2Fm2kX"})), None).unwrap_err());
+    }
+
+    #[test]
+    fn calculator_from_responses() {
+        let get_auth_rule_response = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "121",
+                "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+                "reqId": 15424,
+                "data": auth_rules()
+            }
+        }).to_string();
+        let get_fees_response = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "20001",
+                "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+                "reqId": 47660,
+                "fees": {"add_attrib": 3}
+            }
+        }).to_string();
+
+        let calculator = FeeCalculator::from_responses(&get_auth_rule_response, &get_fees_response).unwrap();
+        let request = request(json!({"type": "100", "dest": "VsKV7grR1BUE29mG2Fm2kX", "raw": "{}"}));
+        assert_eq!(fee("add_attrib", 3), calculator.fee_for_request(&request, None).unwrap());
+    }
+
+    #[test]
+    fn calculator_from_rejected_response() {
+        let get_auth_rule_response = json!({"op": "REJECT", "reason": "client request invalid"}).to_string();
+        let get_fees_response = json!({
+            "op": "REPLY",
+            "result": {"type": "20001", "identifier": "6ouriXMZkLeHsuXrN1X1fd", "reqId": 47660, "fees": {}}
+        }).to_string();
+
+        let err = FeeCalculator::from_responses(&get_auth_rule_response, &get_fees_response).unwrap_err();
+        assert_eq!(ErrorCode::CommonInvalidStructure, err);
+    }
+}
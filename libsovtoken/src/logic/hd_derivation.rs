@@ -0,0 +1,301 @@
+/*!
+    Hierarchical deterministic derivation of the seeds of payment addresses,
+    the ed25519 derivation of [SLIP-0010].
+
+    A master seed and a [`DerivationPath`] give the seed of one payment
+    address, so a backup of the master seed recovers every address derived
+    from it. ed25519 keys only have hardened derivation, so every index of a
+    path is hardened.
+
+    [SLIP-0010]: https://github.com/satoshilabs/slips/blob/master/slip-0010.md
+    [`DerivationPath`]: struct.DerivationPath.html
+*/
+
+use base64;
+use hex::FromHex;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::fmt;
+use std::str::FromStr;
+
+use ErrorCode;
+
+/**
+    Added to an index to harden it.
+*/
+pub const HARDENED_OFFSET: u32 = 0x8000_0000;
+
+/**
+    The account whose addresses the `index` of a [`PaymentAddressConfig`]
+    derives, when the config has no `path`.
+
+    [`PaymentAddressConfig`]: ../config/payment_address_config/struct.PaymentAddressConfig.html
+*/
+pub const DEFAULT_ACCOUNT_PATH: &str = "m/0'";
+
+const MASTER_HMAC_KEY: &[u8] = b"ed25519 seed";
+const KEY_LEN: usize = 32;
+const MIN_MASTER_SEED_LEN: usize = 16;
+const MAX_MASTER_SEED_LEN: usize = 64;
+
+/**
+    A path of hardened indexes from the master key, written `m/44'/0'/3'`.
+    `h` can be used instead of `'`.
+
+    ```
+    use sovtoken::logic::hd_derivation::DerivationPath;
+
+    let path: DerivationPath = "m/0'/1h".parse().unwrap();
+    assert_eq!(&[0, 1], path.indexes());
+    assert_eq!("m/0'/1'", path.to_string());
+    assert!("m/0'/1".parse::<DerivationPath>().is_err());
+    ```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DerivationPath {
+    indexes: Vec<u32>,
+}
+
+impl DerivationPath {
+    /**
+        The indexes of the path, without the hardened offset.
+    */
+    pub fn indexes(&self) -> &[u32] {
+        &self.indexes
+    }
+
+    /**
+        The path of the hardened child `index` of this path.
+    */
+    pub fn child(&self, index: u32) -> Result<DerivationPath, ErrorCode> {
+        let mut indexes = self.indexes.clone();
+        indexes.push(checked_index(index)?);
+        Ok(DerivationPath { indexes })
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = ErrorCode;
+
+    fn from_str(path: &str) -> Result<DerivationPath, ErrorCode> {
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            error!("Derivation path {} should start with m", path);
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        let indexes = segments
+            .map(|segment| {
+                if !segment.ends_with('\'') && !segment.ends_with('h') {
+                    error!("ed25519 keys only have hardened derivation, {} should be {}'", segment, segment);
+                    return Err(ErrorCode::CommonInvalidStructure);
+                }
+                let index = &segment[..segment.len() - 1];
+                let index = index.parse::<u32>().map_err(|_| {
+                    error!("Invalid index {} in derivation path {}", segment, path);
+                    ErrorCode::CommonInvalidStructure
+                })?;
+                checked_index(index)
+            })
+            .collect::<Result<Vec<u32>, ErrorCode>>()?;
+
+        Ok(DerivationPath { indexes })
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("m")?;
+        for index in &self.indexes {
+            write!(f, "/{}'", index)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+    An ed25519 private key with the chain code its children are derived with.
+*/
+pub struct ExtendedKey {
+    key: Vec<u8>,
+    chain_code: Vec<u8>,
+}
+
+impl ExtendedKey {
+    /**
+        The master key of `seed`, which has to have 16 to 64 bytes.
+    */
+    pub fn master(seed: &[u8]) -> Result<ExtendedKey, ErrorCode> {
+        if seed.len() < MIN_MASTER_SEED_LEN || seed.len() > MAX_MASTER_SEED_LEN {
+            error!("A master seed should have {} to {} bytes, got {}", MIN_MASTER_SEED_LEN, MAX_MASTER_SEED_LEN, seed.len());
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        ExtendedKey::from_hmac(MASTER_HMAC_KEY, seed)
+    }
+
+    /**
+        The hardened child `index` of this key.
+    */
+    pub fn child(&self, index: u32) -> Result<ExtendedKey, ErrorCode> {
+        let index = checked_index(index)? + HARDENED_OFFSET;
+
+        let mut data = Vec::with_capacity(1 + KEY_LEN + 4);
+        data.push(0);
+        data.extend_from_slice(&self.key);
+        data.extend_from_slice(&index.to_be_bytes());
+        ExtendedKey::from_hmac(&self.chain_code, &data)
+    }
+
+    /**
+        The key at `path` below this key.
+    */
+    pub fn derive(&self, path: &DerivationPath) -> Result<ExtendedKey, ErrorCode> {
+        let mut key = ExtendedKey { key: self.key.clone(), chain_code: self.chain_code.clone() };
+        for index in path.indexes() {
+            key = key.child(*index)?;
+        }
+        Ok(key)
+    }
+
+    /**
+        The 32 bytes of the private key, the seed of an ed25519 key pair.
+    */
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8] {
+        &self.chain_code
+    }
+
+    /*
+        Methods "private" (aka not exported from this module)
+
+        KEEP all public methods above
+    */
+
+    fn from_hmac(hmac_key: &[u8], data: &[u8]) -> Result<ExtendedKey, ErrorCode> {
+        let digest = PKey::hmac(hmac_key)
+            .and_then(|pkey| {
+                let mut signer = Signer::new(MessageDigest::sha512(), &pkey)?;
+                signer.update(data)?;
+                signer.sign_to_vec()
+            })
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidState))?;
+
+        let (key, chain_code) = digest.split_at(KEY_LEN);
+        Ok(ExtendedKey { key: key.to_vec(), chain_code: chain_code.to_vec() })
+    }
+}
+
+/**
+    The seed of the key at `path` below the master key of `master_seed`.
+*/
+pub fn derive_seed(master_seed: &[u8], path: &DerivationPath) -> Result<Vec<u8>, ErrorCode> {
+    trace!("logic::hd_derivation::derive_seed >> path: {}", path);
+    let key = ExtendedKey::master(master_seed)?.derive(path)?;
+    Ok(key.key().to_vec())
+}
+
+/**
+    Reads a master seed the way libindy reads the seed of a single address:
+    32 characters are used as they are, otherwise it is base64 when it ends
+    with `=` and hex when it doesn't.
+*/
+pub fn master_seed_from_str(seed: &str) -> Result<Vec<u8>, ErrorCode> {
+    if seed.len() == KEY_LEN {
+        Ok(seed.as_bytes().to_vec())
+    } else if seed.ends_with('=') {
+        base64::decode(seed)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))
+    } else {
+        Vec::<u8>::from_hex(seed).map_err(|_| {
+            error!("A master seed should have {} characters, be base64 or hex", KEY_LEN);
+            ErrorCode::CommonInvalidStructure
+        })
+    }
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn checked_index(index: u32) -> Result<u32, ErrorCode> {
+    if index >= HARDENED_OFFSET {
+        error!("Index {} should be below {}", index, HARDENED_OFFSET);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod hd_derivation_tests {
+    use super::*;
+    use hex::ToHex;
+
+    fn derive_hex(seed: &str, path: &str) -> (String, String) {
+        let master = ExtendedKey::master(&Vec::<u8>::from_hex(seed).unwrap()).unwrap();
+        let key = master.derive(&path.parse().unwrap()).unwrap();
+        (key.key().to_hex(), key.chain_code().to_hex())
+    }
+
+    // test vector 1 for ed25519 of SLIP-0010
+    #[test]
+    fn derive_slip10_vectors() {
+        let seed = "000102030405060708090a0b0c0d0e0f";
+        let vectors = [
+            ("m", "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7", "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"),
+            ("m/0'", "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3", "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"),
+            ("m/0'/1'/2'", "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9", "2e69929e00b5ab250f49c3fb1c12f252de4fed2c1db88387094a0f8c4c9ccd6c"),
+            ("m/0'/1'/2'/2'/1000000000'", "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793", "68789923a0cac2cd5a29172a475fe9e0fb14cd6adb5ad98a3fa70333e7afa230"),
+        ];
+
+        for &(path, key, chain_code) in vectors.iter() {
+            assert_eq!((key.to_string(), chain_code.to_string()), derive_hex(seed, path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn parse_paths() {
+        assert_eq!(DerivationPath::default(), "m".parse().unwrap());
+        assert_eq!(&[44, 2147483647], "m/44'/2147483647h".parse::<DerivationPath>().unwrap().indexes());
+
+        for invalid in ["", "0'", "m/0", "m/-1'", "m/2147483648'", "m//1'"].iter() {
+            assert_eq!(ErrorCode::CommonInvalidStructure, invalid.parse::<DerivationPath>().unwrap_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn child_path_and_key() {
+        let path: DerivationPath = DEFAULT_ACCOUNT_PATH.parse().unwrap();
+        assert_eq!("m/0'/7'", path.child(7).unwrap().to_string());
+        assert_eq!(ErrorCode::CommonInvalidStructure, path.child(HARDENED_OFFSET).unwrap_err());
+
+        let master = ExtendedKey::master(&[7; 32]).unwrap();
+        assert_eq!(master.derive(&path.child(7).unwrap()).unwrap().key(), master.child(0).unwrap().child(7).unwrap().key());
+    }
+
+    #[test]
+    fn master_seed_formats() {
+        assert_eq!(b"000000000000000000000000Address1".to_vec(), master_seed_from_str("000000000000000000000000Address1").unwrap());
+        assert_eq!(vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15], master_seed_from_str("AAECAwQFBgcICQoLDA0ODw==").unwrap());
+        assert_eq!(vec![0xab; 32], master_seed_from_str(&"ab".repeat(32)).unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, master_seed_from_str("not a seed").unwrap_err());
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, ExtendedKey::master(&[1; 15]).err().unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, ExtendedKey::master(&[1; 65]).err().unwrap());
+    }
+
+    // like libindy, 32 characters are the seed itself even when they are hex digits
+    #[test]
+    fn master_seed_of_32_characters_is_raw() {
+        let seed = "000102030405060708090a0b0c0d0e0f";
+        assert_eq!(seed.as_bytes().to_vec(), master_seed_from_str(seed).unwrap());
+        assert_eq!(vec![0; 17], master_seed_from_str(&"00".repeat(17)).unwrap());
+    }
+}
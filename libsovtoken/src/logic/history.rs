@@ -0,0 +1,298 @@
+/*!
+    History of payment addresses, reconstructed from the transactions of the
+    token ledger.
+
+    The transactions are read with GET_TXN requests built by
+    [`GetTxnOperationRequest`] and parsed by [`parse_get_txn`] into
+    [`TokenTransaction`]s, which [`fold_history`] turns into the incoming and
+    outgoing amounts and the running balance of the addresses.
+
+    A source is spent by naming the address and seq_no of the transaction
+    which created it, so the amount of an input is only known when that
+    transaction is part of the folded transactions.
+
+    [`GetTxnOperationRequest`]: ../config/get_txn_config/struct.GetTxnOperationRequest.html
+    [`parse_get_txn`]: ../parsers/parse_get_txn/index.html
+    [`TokenTransaction`]: struct.TokenTransaction.html
+    [`fold_history`]: fn.fold_history.html
+*/
+
+use std::collections::HashMap;
+
+use logic::address::PaymentAddress;
+use logic::input::{Input, Inputs};
+use logic::output::Outputs;
+use logic::type_aliases::{TokenAmount, TxnSeqNo};
+use utils::constants::txn_types::TxnType;
+
+/**
+    The token ledger transactions which move tokens
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy)]
+pub enum TokenTxnKind {
+    Mint,
+    Xfer,
+    Fees,
+}
+
+impl TokenTxnKind {
+    pub fn from_txn_type(txn_type: &str) -> Option<TokenTxnKind> {
+        match TxnType::from_code(txn_type)? {
+            TxnType::MintPublic => Some(TokenTxnKind::Mint),
+            TxnType::XferPublic => Some(TokenTxnKind::Xfer),
+            TxnType::FeeTxn => Some(TokenTxnKind::Fees),
+            _ => None,
+        }
+    }
+}
+
+/**
+    A transaction of the token ledger
+
+    # parameters
+    seq_no - the seq_no of the transaction in the token ledger
+    txn_time - when the transaction was ordered, genesis transactions have none
+    kind - MINT_PUBLIC, XFER_PUBLIC or the fees of a request
+    inputs - the spent sources, a MINT_PUBLIC has none
+    outputs - the created sources
+    fees - the amount paid as fees, only a fees transaction states it
+    reference - `<ledger_id>:<seq_no>` of the transaction the fees are paid for
+    extra - the extra of a XFER_PUBLIC or MINT_PUBLIC
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenTransaction {
+    pub seq_no: TxnSeqNo,
+    pub txn_time: Option<u64>,
+    pub kind: TokenTxnKind,
+    pub inputs: Inputs,
+    pub outputs: Outputs,
+    pub fees: Option<TokenAmount>,
+    pub reference: Option<String>,
+    pub extra: Option<String>,
+}
+
+/**
+    What a transaction did to the folded addresses
+
+    # parameters
+    incoming - the amount of the outputs to the addresses
+    outgoing - the amount of the sources of the addresses the transaction spent
+    fees - what the addresses paid as fees, when it is known
+    balance - the balance of the addresses after the transaction
+    unknown_inputs - sources of the addresses whose amount is unknown, since
+        they were created by a transaction which wasn't folded
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub seq_no: TxnSeqNo,
+    pub txn_time: Option<u64>,
+    pub kind: TokenTxnKind,
+    pub incoming: TokenAmount,
+    pub outgoing: TokenAmount,
+    pub fees: Option<TokenAmount>,
+    pub balance: TokenAmount,
+    pub unknown_inputs: Inputs,
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    pub balance: TokenAmount,
+}
+
+/**
+    Folds `transactions` into the history of `addresses`, in order of seq_no.
+
+    Only the transactions which spend from or pay to one of the addresses
+    become an entry. A transaction given twice is folded once. Amounts which
+    would overflow stay at `TokenAmount::max_value()`.
+*/
+pub fn fold_history(transactions: &[TokenTransaction], addresses: &[PaymentAddress]) -> History {
+    let mut transactions: Vec<&TokenTransaction> = transactions.iter().collect();
+    transactions.sort_by_key(|txn| txn.seq_no);
+    transactions.dedup_by_key(|txn| txn.seq_no);
+
+    let owned = |address: &PaymentAddress| addresses.iter().any(|owned| owned.qualified() == address.qualified());
+
+    let mut unspent: HashMap<(String, TxnSeqNo), TokenAmount> = HashMap::new();
+    let mut entries = Vec::new();
+    let mut balance: TokenAmount = 0;
+
+    for txn in transactions {
+        let mut outgoing: TokenAmount = 0;
+        let mut unknown_inputs: Inputs = Vec::new();
+        let mut spends_others = false;
+
+        for input in &txn.inputs {
+            if !owned(&input.address) {
+                spends_others = true;
+                continue;
+            }
+            match unspent.remove(&source_key(input)) {
+                Some(amount) => outgoing = outgoing.saturating_add(amount),
+                None => unknown_inputs.push(input.clone()),
+            }
+        }
+
+        let mut incoming: TokenAmount = 0;
+        for output in txn.outputs.iter().filter(|output| owned(&output.recipient)) {
+            incoming = incoming.saturating_add(output.amount);
+            unspent.insert((output.recipient.qualified().to_string(), txn.seq_no), output.amount);
+        }
+
+        let spends_owned = txn.inputs.iter().any(|input| owned(&input.address));
+        if !spends_owned && incoming == 0 {
+            continue;
+        }
+
+        balance = balance.saturating_add(incoming).saturating_sub(outgoing);
+        entries.push(HistoryEntry {
+            seq_no: txn.seq_no,
+            txn_time: txn.txn_time,
+            kind: txn.kind,
+            incoming,
+            outgoing,
+            fees: fees_paid(txn, spends_owned, spends_others || !unknown_inputs.is_empty(), outgoing),
+            balance,
+            unknown_inputs,
+        });
+    }
+
+    History { entries, balance }
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn source_key(input: &Input) -> (String, TxnSeqNo) {
+    (input.address.qualified().to_string(), input.seq_no)
+}
+
+/*
+    A fees transaction states its fees. The fees of a XFER_PUBLIC are what its
+    inputs hold above its outputs, which is only known when all inputs are
+    sources of the folded addresses with a known amount.
+*/
+fn fees_paid(txn: &TokenTransaction, spends_owned: bool, inputs_unknown: bool, outgoing: TokenAmount) -> Option<TokenAmount> {
+    if !spends_owned {
+        return None;
+    }
+
+    match txn.kind {
+        TokenTxnKind::Fees => txn.fees,
+        TokenTxnKind::Xfer if !inputs_unknown => {
+            let paid_out = txn.outputs.iter().try_fold(0 as TokenAmount, |sum, output| sum.checked_add(output.amount))?;
+            outgoing.checked_sub(paid_out)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod history_tests {
+    use super::*;
+    use logic::output::Output;
+
+    fn address(n: u8) -> PaymentAddress {
+        let addresses = [
+            "pay:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es",
+            "pay:sov:dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "pay:sov:2mVXsXyVADzSDw88RAojPpdgxLPQyC1oJUqkrLeU5AdfEq2PmC",
+        ];
+        PaymentAddress::new(addresses[n as usize]).unwrap()
+    }
+
+    fn txn(seq_no: TxnSeqNo, kind: TokenTxnKind, inputs: Vec<(u8, TxnSeqNo)>, outputs: Vec<(u8, TokenAmount)>) -> TokenTransaction {
+        TokenTransaction {
+            seq_no,
+            txn_time: Some(1529682415 + seq_no),
+            kind,
+            inputs: inputs.into_iter().map(|(n, seq_no)| Input::new(address(n), seq_no)).collect(),
+            outputs: outputs.into_iter().map(|(n, amount)| Output::new(address(n), amount)).collect(),
+            fees: None,
+            reference: None,
+            extra: None,
+        }
+    }
+
+    fn ledger() -> Vec<TokenTransaction> {
+        let mut fees = txn(4, TokenTxnKind::Fees, vec![(0, 3)], vec![(0, 5)]);
+        fees.fees = Some(2);
+        fees.reference = Some(String::from("1:13"));
+
+        vec![
+            txn(1, TokenTxnKind::Mint, vec![], vec![(0, 10), (1, 20)]),
+            txn(2, TokenTxnKind::Xfer, vec![(1, 1)], vec![(2, 20)]),
+            txn(3, TokenTxnKind::Xfer, vec![(0, 1)], vec![(2, 2), (0, 7)]),
+            fees,
+        ]
+    }
+
+    #[test]
+    fn fold_history_of_address() {
+        let history = fold_history(&ledger(), &[address(0)]);
+
+        let summary: Vec<(TxnSeqNo, TokenAmount, TokenAmount, Option<TokenAmount>, TokenAmount)> = history.entries.iter()
+            .map(|entry| (entry.seq_no, entry.incoming, entry.outgoing, entry.fees, entry.balance))
+            .collect();
+        assert_eq!(vec![
+            (1, 10, 0, None, 10),
+            (3, 7, 10, Some(1), 7),
+            (4, 5, 7, Some(2), 5),
+        ], summary);
+        assert_eq!(5, history.balance);
+    }
+
+    #[test]
+    fn fold_history_of_addresses() {
+        let mut transactions = ledger();
+        transactions.reverse();
+        let first = transactions[0].clone();
+        transactions.push(first);
+
+        let history = fold_history(&transactions, &[address(1), address(2)]);
+
+        let seq_nos: Vec<TxnSeqNo> = history.entries.iter().map(|entry| entry.seq_no).collect();
+        assert_eq!(vec![1, 2, 3], seq_nos);
+        assert_eq!(Some(0), history.entries[1].fees);
+        assert_eq!(None, history.entries[2].fees);
+        assert_eq!(22, history.balance);
+    }
+
+    #[test]
+    fn fold_history_with_unknown_source() {
+        let history = fold_history(&ledger()[2..], &[address(0)]);
+
+        assert_eq!(vec![Input::new(address(0), 1)], history.entries[0].unknown_inputs);
+        assert_eq!(None, history.entries[0].fees);
+        assert_eq!(5, history.balance);
+    }
+
+    #[test]
+    fn fold_history_with_overflowing_amounts() {
+        let max = TokenAmount::max_value();
+        let transactions = vec![
+            txn(1, TokenTxnKind::Mint, vec![], vec![(0, max), (1, max)]),
+            txn(2, TokenTxnKind::Xfer, vec![(1, 1)], vec![(0, max), (2, max)]),
+        ];
+
+        let history = fold_history(&transactions, &[address(0), address(1)]);
+
+        assert_eq!(max, history.entries[0].incoming);
+        assert_eq!(max, history.entries[0].balance);
+        assert_eq!(None, history.entries[1].fees);
+    }
+
+    #[test]
+    fn kind_from_txn_type() {
+        assert_eq!(Some(TokenTxnKind::Mint), TokenTxnKind::from_txn_type("10000"));
+        assert_eq!(Some(TokenTxnKind::Fees), TokenTxnKind::from_txn_type("10101"));
+        assert_eq!(None, TokenTxnKind::from_txn_type("1"));
+    }
+}
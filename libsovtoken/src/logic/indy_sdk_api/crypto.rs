@@ -4,7 +4,8 @@ use std::ffi::CString;
 
 use indy_sys::crypto;
 use indy_sys::{ResponseStringCB,
-               ResponseSliceCB};
+               ResponseSliceCB,
+               ResponseBoolCB};
 
 use utils::results::ResultHandler;
 use utils::callbacks::ClosureHandler;
@@ -103,5 +104,31 @@ impl Crypto {
                                      cb)
         })
     }
-}
 
+    /// Verify a signature with a verkey
+    /// # Arguments
+    /// * `signer_vk` - verkey of signer of the message
+    /// * `message` - the data that was signed
+    /// * `signature` - the signature of the message
+    /// # Returns
+    /// true if the signature is valid
+    pub fn verify(signer_vk: &str, message: &[u8], signature: &[u8]) -> Result<bool, ErrorCode> {
+        let (receiver, command_handle, cb) = ClosureHandler::cb_ec_bool();
+
+        let err = Crypto::_verify(command_handle, signer_vk, message, signature, cb);
+
+        ResultHandler::one(err, receiver)
+    }
+
+    fn _verify(command_handle: IndyHandle, signer_vk: &str, message: &[u8], signature: &[u8], cb: Option<ResponseBoolCB>) -> ErrorCode {
+        let signer_vk = c_str!(signer_vk);
+        ErrorCode::from(unsafe {
+            crypto::indy_crypto_verify(command_handle, signer_vk.as_ptr(),
+                                       message.as_ptr() as *const u8,
+                                       message.len() as u32,
+                                       signature.as_ptr() as *const u8,
+                                       signature.len() as u32,
+                                       cb)
+        })
+    }
+}
@@ -1,7 +1,8 @@
 //! Indy-sdk crypto functions
 use logic::indy_sdk_api::crypto::{Key, Crypto};
 use logic::config::payment_address_config::PaymentAddressConfig;
-use utils::base58::serialize_bytes;
+use logic::keystore_crypto::verkey_of_seed;
+use utils::base58::{serialize_bytes, FromBase58};
 use utils::json_conversion::JsonSerialize;
 use {ErrorCode, IndyHandle};
 
@@ -15,6 +16,29 @@ pub trait CryptoAPI {
     fn indy_create_key(&self, wallet_id: i32, config: PaymentAddressConfig) -> Result<String, ErrorCode>;
     fn indy_create_key_async<F: 'static>(&self, wallet_id: i32, config: PaymentAddressConfig, closure: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send;
     fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, wallet_handle: i32, verkey: String, message: String, cb: F) -> ErrorCode;
+
+    /**
+        Checks the base58 `signature` of `message` by `verkey`.
+
+        Implementations which can't verify signatures keep this default, which
+        errors with `CommonInvalidState`.
+    */
+    fn indy_crypto_verify(&self, _verkey: String, _message: String, _signature: String) -> Result<bool, ErrorCode> {
+        Err(ErrorCode::CommonInvalidState)
+    }
+
+    /**
+        The verkey of the key [`indy_create_key`] creates out of `seed`,
+        without creating it.
+
+        Implementations which can't derive keys outside of a wallet keep this
+        default, which errors with `CommonInvalidState`.
+
+        [`indy_create_key`]: #tymethod.indy_create_key
+    */
+    fn indy_verkey_of_seed(&self, _seed: &str) -> Result<String, ErrorCode> {
+        Err(ErrorCode::CommonInvalidState)
+    }
 }
 
 // ------------------------------------------------------------------
@@ -80,4 +104,55 @@ impl CryptoAPI for CryptoSdk {
             }
         });
     }
-}
\ No newline at end of file
+
+    /**
+        Verifies a base58 signature made by [`indy_crypto_sign`].
+
+        A signature which isn't base58 is not valid.
+
+        [`indy_crypto_sign`]: #method.indy_crypto_sign
+    */
+    fn indy_crypto_verify(&self, verkey: String, message: String, signature: String) -> Result<bool, ErrorCode> {
+        let signature = match signature.from_base58() {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+        Crypto::verify(&verkey, message.as_bytes(), &signature)
+    }
+
+    /**
+        libindy only derives keys into a wallet, so the verkey is derived
+        here the way libindy derives it.
+    */
+    fn indy_verkey_of_seed(&self, seed: &str) -> Result<String, ErrorCode> {
+        verkey_of_seed(seed)
+    }
+}
+
+/**
+    Lets a borrowed implementation be injected wherever an owned one is expected,
+    e.g. into [`CreatePaymentHandler`].
+
+    [`CreatePaymentHandler`]: ../../payments/struct.CreatePaymentHandler.html
+*/
+impl<'a, A: CryptoAPI> CryptoAPI for &'a A {
+    fn indy_create_key(&self, wallet_id: i32, config: PaymentAddressConfig) -> Result<String, ErrorCode> {
+        (**self).indy_create_key(wallet_id, config)
+    }
+
+    fn indy_create_key_async<F: 'static>(&self, wallet_id: i32, config: PaymentAddressConfig, closure: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
+        (**self).indy_create_key_async(wallet_id, config, closure)
+    }
+
+    fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, wallet_handle: i32, verkey: String, message: String, cb: F) -> ErrorCode {
+        (**self).indy_crypto_sign(wallet_handle, verkey, message, cb)
+    }
+
+    fn indy_crypto_verify(&self, verkey: String, message: String, signature: String) -> Result<bool, ErrorCode> {
+        (**self).indy_crypto_verify(verkey, message, signature)
+    }
+
+    fn indy_verkey_of_seed(&self, seed: &str) -> Result<String, ErrorCode> {
+        (**self).indy_verkey_of_seed(seed)
+    }
+}
@@ -4,6 +4,7 @@
 
 use serde::{de, Deserialize};
 use std::fmt;
+use logic::address::PaymentAddress;
 use logic::parsers::common::TXO;
 use logic::type_aliases::TxnSeqNo;
 
@@ -26,6 +27,11 @@ pub struct InputConfig {
 /**
     Struct which holds an address, seq_no, signature, and extra data.
 
+    The address is a [`PaymentAddress`], so an input with an invalid address
+    can not be deserialized.
+
+    [`PaymentAddress`]: ../address/struct.PaymentAddress.html
+
     # Deserialization
     Input can be deserialized from an array or an object. Both are valid:
 
@@ -34,10 +40,15 @@ pub struct InputConfig {
     When deserializing from an array, the signature is required.
     ```
     use sovtoken::utils::json_conversion::JsonDeserialize;
+    use sovtoken::logic::address::PaymentAddress;
     use sovtoken::logic::input::Input;
-    let json = r#"{"address":"pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja", "seqNo":30}"#;
+    let json = r#"{"address":"pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7", "seqNo":30}"#;
     let input = Input::from_json(json).unwrap();
-    assert_eq!(Input{address: "pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja".to_string(), seq_no: 30}, input);
+    let address = PaymentAddress::new("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap();
+    assert_eq!(Input{address, seq_no: 30}, input);
+
+    let json = r#"{"address":"pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja", "seqNo":30}"#;
+    assert!(Input::from_json(json).is_err());
     ```
 
     ## From Object
@@ -53,7 +64,7 @@ pub struct InputConfig {
     use sovtoken::utils::json_conversion::JsonDeserialize;
     use sovtoken::logic::input::Input;
     let json = r#"{
-        "address": "pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja",
+        "address": "pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7",
         "seqNo": 30,
         "signature": "239asdkj3298uadkljasd98u234ijasdlkj"
     }"#;
@@ -65,18 +76,19 @@ pub struct InputConfig {
 
     ```
     use sovtoken::utils::json_conversion::JsonSerialize;
+    use sovtoken::logic::address::PaymentAddress;
     use sovtoken::logic::input::Input;
-    let address = String::from("pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja");
+    let address = PaymentAddress::new("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap();
     let input = Input::new(address, 30);
 
     let json = Input::to_json(&input).unwrap();
-    assert_eq!(json, r#"{"address":"pay:sov:AesjahdahudgaiuNotARealAKeyygigfuigraiudgfasfhja","seqNo":30}"#);
+    assert_eq!(json, r#"{"address":"pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7","seqNo":30}"#);
     ```
 
 */
 #[derive(Debug, Eq, PartialEq, Clone, Serialize)]
 pub struct Input {
-    pub address: String,
+    pub address: PaymentAddress,
     #[serde(rename = "seqNo")]
     pub seq_no: TxnSeqNo
 }
@@ -88,7 +100,7 @@ impl ToString for Input {
 }
 
 impl Input {
-    pub fn new(address: String, seq_no: TxnSeqNo) -> Input {
+    pub fn new(address: PaymentAddress, seq_no: TxnSeqNo) -> Input {
         return Input { address, seq_no};
     }
 }
@@ -107,16 +119,18 @@ impl<'de> Deserialize<'de> for Input {
             fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
                 let txo = TXO::from_libindy_string(v)
                     .map_err(|ec| de::Error::custom(format!("Error when deserializing txo: error code {:?}", ec)))?;
+                let address = PaymentAddress::new(&txo.address)
+                    .map_err(|ec| de::Error::custom(format!("Invalid payment address {}: error code {:?}", txo.address, ec)))?;
 
-                return Ok(Input::new(txo.address, txo.seq_no ))
+                return Ok(Input::new(address, txo.seq_no ))
             }
 
             fn visit_map<V: de::MapAccess<'de>>(self, mut map: V) -> Result<Input, V::Error> {
                 let mut address = None;
                 let mut seq_no = None;
 
-                while let Some(key) = map.next_key()? {
-                    match key {
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
                         "address" => { address = map.next_value()?; },
                         "seqNo" => { seq_no =  map.next_value()?; },
                         x => { return Err(de::Error::unknown_field(x, FIELDS)) }
@@ -140,6 +154,7 @@ impl<'de> Deserialize<'de> for Input {
 mod input_tests {
     use serde_json;
 
+    use logic::address::PaymentAddress;
     use logic::input::{Input, InputConfig};
     use logic::parsers::common::TXO;
     use utils::json_conversion::{JsonDeserialize, JsonSerialize};
@@ -170,7 +185,7 @@ mod input_tests {
     }
 
     fn valid_input() -> Input {
-        let address = String::from("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7");
+        let address = PaymentAddress::new("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap();
         return Input::new(address, 30);
     }
 
@@ -202,9 +217,33 @@ mod input_tests {
         assert_valid_deserialize(json, input);
     }
 
+    #[test]
+    fn deserialize_input_object_with_invalid_address() {
+        let json = json!(
+            TXO {
+                address: "pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81f".to_string(),
+                seq_no: 30
+            }.to_libindy_string().unwrap()
+        );
+        assert_invalid_deserialize(json, "Invalid payment address");
+    }
+
+    #[test]
+    fn deserialize_input_map_with_invalid_address() {
+        let json = json!({"address": "pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81f", "seqNo": 30});
+        assert_invalid_deserialize(json, "Invalid payment address");
+    }
+
+    #[test]
+    fn deserialize_input_object_from_value() {
+        let json = json!({"address": "pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7", "seqNo": 30});
+        let input: Input = serde_json::from_value(json).unwrap();
+        assert_eq!(valid_input(), input);
+    }
+
     #[test]
     fn serialize_input() {
-        let input = Input::new(String::from("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7"), 5);
+        let input = Input::new(PaymentAddress::new("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap(), 5);
         let expected = json!({"address": "a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7", "seqNo":5});
         assert_valid_serialize(input, expected);
     }
@@ -212,7 +251,7 @@ mod input_tests {
     // this test ensures that the deserialized JSON is serialized correctly
     #[test]
     fn serializing_payload_struct_output_config() {
-        let input = Input::new(String::from("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7"), 30);
+        let input = Input::new(PaymentAddress::new("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap(), 30);
 
         let fee: InputConfig = InputConfig {
             ver: 1,
@@ -0,0 +1,522 @@
+/*!
+    [`CryptoAPI`] backed by an ed25519 keystore of our own.
+
+    [`CryptoSdk`] keeps the keys in a libindy wallet. [`KeystoreCrypto`] keeps
+    them in memory, or in a file encrypted with AES-256-GCM when opened with
+    [`KeystoreCrypto::open`], so addresses can be created and payloads signed
+    without opening a wallet. The `wallet_handle` arguments are ignored.
+
+    Seeds are read the way libindy reads them and ed25519 signatures are
+    deterministic, so a seed gives the same verkey and the same signatures with
+    both implementations. The ed25519 keys are handled by rust-crypto, so
+    this works with any OpenSSL version libsovtoken builds with.
+
+    The key of the file is supplied by the caller and unlocks every seed in
+    it, so it must be kept like the key of a wallet, and never next to the
+    file.
+
+    [`CryptoAPI`]: ../indy_sdk_api/crypto_api/trait.CryptoAPI.html
+    [`CryptoSdk`]: ../indy_sdk_api/crypto_api/struct.CryptoSdk.html
+    [`KeystoreCrypto`]: struct.KeystoreCrypto.html
+    [`KeystoreCrypto::open`]: struct.KeystoreCrypto.html#method.open
+*/
+
+use base64;
+use crypto::ed25519;
+use hex::FromHex;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde_json;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use {ErrorCode, IndyHandle};
+use logic::address::VERKEY_LEN;
+use logic::config::payment_address_config::PaymentAddressConfig;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use utils::base58::{serialize_bytes, FromBase58};
+
+const SEED_LEN: usize = 32;
+const FILE_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const SIGNATURE_LEN: usize = 64;
+
+/**
+    Seeds by verkey, both base58.
+*/
+type Keys = HashMap<String, String>;
+
+/**
+    The content of a keystore file: the json of its keys encrypted with
+    AES-256-GCM, all base58.
+*/
+#[derive(Serialize, Deserialize, Debug)]
+struct SealedKeys {
+    nonce: String,
+    tag: String,
+    ciphertext: String,
+}
+
+/**
+    Where the keys of a keystore are saved, and the key they are encrypted with.
+*/
+struct KeystoreFile {
+    path: PathBuf,
+    key: Vec<u8>,
+}
+
+/**
+    ed25519 keystore implementing [`CryptoAPI`].
+
+    `XferPayload` signs with a `&'static` [`CryptoAPI`], so a keystore used for
+    signing has to live for the whole program.
+
+    ```
+    use sovtoken::logic::config::payment_address_config::PaymentAddressConfig;
+    use sovtoken::logic::keystore_crypto::KeystoreCrypto;
+    use sovtoken::logic::payments::CreatePaymentHandler;
+
+    let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+    let config = PaymentAddressConfig { seed: "000000000000000000000000Address1".to_string(), ..Default::default() };
+    let address = handler.create_payment_address(0, config).unwrap();
+    assert!(address.starts_with("pay:sov:"));
+    ```
+
+    [`CryptoAPI`]: ../indy_sdk_api/crypto_api/trait.CryptoAPI.html
+*/
+pub struct KeystoreCrypto {
+    keys: Mutex<Keys>,
+    file: Option<KeystoreFile>,
+}
+
+impl KeystoreCrypto {
+    /**
+        An empty keystore which only lives in memory.
+    */
+    pub fn new() -> KeystoreCrypto {
+        KeystoreCrypto { keys: Mutex::new(Keys::new()), file: None }
+    }
+
+    /**
+        Opens the keystore saved at `path` with the 32 bytes `key`, or an
+        empty one when there is no file yet. New keys are encrypted with `key`
+        and written to the file as they are created.
+
+        Errors with `CommonInvalidStructure` when `key` isn't 32 bytes or the
+        file isn't a keystore, and with `WalletAccessFailed` when `key` doesn't
+        decrypt it.
+    */
+    pub fn open(path: &Path, key: &[u8]) -> Result<KeystoreCrypto, ErrorCode> {
+        if key.len() != FILE_KEY_LEN {
+            error!("The key of a keystore file should have {} bytes", FILE_KEY_LEN);
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        let keys = if path.exists() {
+            let content = fs::read_to_string(path)
+                .map_err(map_err_err!())
+                .or(Err(ErrorCode::CommonIOError))?;
+            let sealed: SealedKeys = serde_json::from_str(&content)
+                .map_err(map_err_err!())
+                .or(Err(ErrorCode::CommonInvalidStructure))?;
+            unseal(&sealed, key)?
+        } else {
+            Keys::new()
+        };
+
+        let file = KeystoreFile { path: path.to_path_buf(), key: key.to_vec() };
+        Ok(KeystoreCrypto { keys: Mutex::new(keys), file: Some(file) })
+    }
+
+    /**
+        Verkeys of the keys in the keystore.
+    */
+    pub fn verkeys(&self) -> Result<Vec<String>, ErrorCode> {
+        let keys = self.keys.lock().or(Err(ErrorCode::CommonInvalidState))?;
+        Ok(keys.keys().cloned().collect())
+    }
+
+    /*
+        Methods "private" (aka not exported from this module)
+
+        KEEP all public methods above
+    */
+
+    fn add_key(&self, seed: Vec<u8>) -> Result<String, ErrorCode> {
+        let verkey = serialize_bytes(&public_key(&seed)?);
+
+        let mut keys = self.keys.lock().or(Err(ErrorCode::CommonInvalidState))?;
+        if keys.contains_key(&verkey) {
+            error!("Key {:?} is already in the keystore", verkey);
+            return Err(ErrorCode::WalletItemAlreadyExists);
+        }
+        keys.insert(verkey.clone(), serialize_bytes(&seed));
+
+        if let Some(ref file) = self.file {
+            if let Err(err) = save(file, &keys) {
+                keys.remove(&verkey);
+                return Err(err);
+            }
+        }
+
+        Ok(verkey)
+    }
+
+    fn private_key(&self, verkey: &str) -> Result<Vec<u8>, ErrorCode> {
+        let keys = self.keys.lock().or(Err(ErrorCode::CommonInvalidState))?;
+        let seed = keys.get(verkey).ok_or_else(|| {
+            error!("Key {:?} is not in the keystore", secret!(verkey));
+            ErrorCode::WalletItemNotFound
+        })?;
+        seed.from_base58().or(Err(ErrorCode::CommonInvalidState))
+    }
+}
+
+/**
+    The verkey of the key of `seed`, read the way libindy reads seeds, without
+    creating the key anywhere.
+*/
+pub fn verkey_of_seed(seed: &str) -> Result<String, ErrorCode> {
+    Ok(serialize_bytes(&public_key(&seed_from_str(seed)?)?))
+}
+
+impl Default for KeystoreCrypto {
+    fn default() -> Self {
+        KeystoreCrypto::new()
+    }
+}
+
+impl CryptoAPI for KeystoreCrypto {
+    /**
+        Creates a key out of the seed of `config`, or a random one when the
+        seed is empty. Errors with `WalletItemAlreadyExists` when the key is
+        already in the keystore.
+    */
+    fn indy_create_key(&self, _wallet_id: IndyHandle, config: PaymentAddressConfig) -> Result<String, ErrorCode> {
+        trace!("logic::keystore_crypto::indy_create_key >> config: {:?}", secret!(&config));
+        let seed = if config.seed.is_empty() {
+            random_seed()?
+        } else {
+            seed_from_str(&config.seed)?
+        };
+
+        let res = self.add_key(seed);
+        trace!("logic::keystore_crypto::indy_create_key << result: {:?}", res);
+        res
+    }
+
+    fn indy_create_key_async<F: 'static>(&self, wallet_id: IndyHandle, config: PaymentAddressConfig, mut closure: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
+        match self.indy_create_key(wallet_id, config) {
+            Ok(verkey) => closure(ErrorCode::Success, verkey),
+            Err(err) => closure(err, String::new()),
+        };
+        ErrorCode::Success
+    }
+
+    /**
+        Signs `message` with the key of `verkey`. Errors with
+        `WalletItemNotFound` when the key isn't in the keystore.
+    */
+    fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, _wallet_handle: IndyHandle, verkey: String, message: String, mut cb: F) -> ErrorCode {
+        trace!("logic::keystore_crypto::indy_crypto_sign >> verkey: {:?}, message: {:?}", secret!(&verkey), secret!(&message));
+        let signature = self.private_key(&verkey)
+            .and_then(|seed| sign(&seed, message.as_bytes()))
+            .map(|signature| serialize_bytes(&signature));
+
+        match signature {
+            Ok(signature) => {
+                cb(Ok(signature));
+                ErrorCode::Success
+            }
+            Err(err) => err,
+        }
+    }
+
+    fn indy_crypto_verify(&self, verkey: String, message: String, signature: String) -> Result<bool, ErrorCode> {
+        let verkey = verkey.from_base58().or(Err(ErrorCode::CommonInvalidStructure))?;
+        let signature = match signature.from_base58() {
+            Ok(signature) => signature,
+            Err(_) => return Ok(false),
+        };
+        verify(&verkey, message.as_bytes(), &signature)
+    }
+
+    fn indy_verkey_of_seed(&self, seed: &str) -> Result<String, ErrorCode> {
+        verkey_of_seed(seed)
+    }
+}
+
+/**
+    Reads a seed the way libindy does: 32 characters are used as they are,
+    otherwise the seed is base64 when it ends with `=` and hex when it has 64
+    characters.
+*/
+fn seed_from_str(seed: &str) -> Result<Vec<u8>, ErrorCode> {
+    let bytes = if seed.len() == SEED_LEN {
+        seed.as_bytes().to_vec()
+    } else if seed.ends_with('=') {
+        base64::decode(seed).or(Err(ErrorCode::CommonInvalidStructure))?
+    } else if seed.len() == SEED_LEN * 2 {
+        Vec::<u8>::from_hex(seed).or(Err(ErrorCode::CommonInvalidStructure))?
+    } else {
+        error!("Seed should have {} bytes", SEED_LEN);
+        return Err(ErrorCode::CommonInvalidStructure);
+    };
+
+    if bytes.len() != SEED_LEN {
+        error!("Seed should have {} bytes, got {}", SEED_LEN, bytes.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    Ok(bytes)
+}
+
+fn random_seed() -> Result<Vec<u8>, ErrorCode> {
+    let mut seed = vec![0u8; SEED_LEN];
+    rand_bytes(&mut seed).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidState))?;
+    Ok(seed)
+}
+
+fn public_key(seed: &[u8]) -> Result<Vec<u8>, ErrorCode> {
+    if seed.len() != SEED_LEN {
+        error!("Seed should have {} bytes, got {}", SEED_LEN, seed.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    let (_, public_key) = ed25519::keypair(seed);
+    Ok(public_key.to_vec())
+}
+
+fn sign(seed: &[u8], message: &[u8]) -> Result<Vec<u8>, ErrorCode> {
+    if seed.len() != SEED_LEN {
+        error!("Seed should have {} bytes, got {}", SEED_LEN, seed.len());
+        return Err(ErrorCode::CommonInvalidState);
+    }
+    let (secret_key, _) = ed25519::keypair(seed);
+    Ok(ed25519::signature(message, &secret_key).to_vec())
+}
+
+fn verify(verkey: &[u8], message: &[u8], signature: &[u8]) -> Result<bool, ErrorCode> {
+    if verkey.len() != VERKEY_LEN {
+        error!("Verkey should have {} bytes, got {}", VERKEY_LEN, verkey.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    if signature.len() != SIGNATURE_LEN {
+        return Ok(false);
+    }
+    Ok(ed25519::verify(message, verkey, signature))
+}
+
+fn seal(keys: &Keys, key: &[u8]) -> Result<SealedKeys, ErrorCode> {
+    let plaintext = serde_json::to_vec(keys).or(Err(ErrorCode::CommonInvalidState))?;
+    let mut nonce = [0u8; NONCE_LEN];
+    rand_bytes(&mut nonce).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidState))?;
+
+    let mut tag = [0u8; TAG_LEN];
+    let ciphertext = encrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), &[], &plaintext, &mut tag)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::WalletEncryptionError))?;
+
+    Ok(SealedKeys {
+        nonce: serialize_bytes(&nonce),
+        tag: serialize_bytes(&tag),
+        ciphertext: serialize_bytes(&ciphertext),
+    })
+}
+
+fn unseal(sealed: &SealedKeys, key: &[u8]) -> Result<Keys, ErrorCode> {
+    let nonce = sealed.nonce.from_base58().or(Err(ErrorCode::CommonInvalidStructure))?;
+    let tag = sealed.tag.from_base58().or(Err(ErrorCode::CommonInvalidStructure))?;
+    let ciphertext = sealed.ciphertext.from_base58().or(Err(ErrorCode::CommonInvalidStructure))?;
+
+    let plaintext = decrypt_aead(Cipher::aes_256_gcm(), key, Some(&nonce), &[], &ciphertext, &tag)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::WalletAccessFailed))?;
+    serde_json::from_slice(&plaintext)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))
+}
+
+/*
+    Writes the keys to a temporary file next to the keystore file, syncs it
+    to disk and renames it over the keystore file, so a failed write never
+    leaves a truncated keystore behind.
+*/
+fn save(file: &KeystoreFile, keys: &Keys) -> Result<(), ErrorCode> {
+    let content = serde_json::to_string(&seal(keys, &file.key)?).or(Err(ErrorCode::CommonInvalidState))?;
+    let temp_path = temp_path_of(&file.path);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let res = options.open(&temp_path)
+        .and_then(|mut temp_file| {
+            temp_file.write_all(content.as_bytes())?;
+            temp_file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, &file.path));
+
+    if res.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    res.map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonIOError))
+}
+
+fn temp_path_of(path: &Path) -> PathBuf {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    PathBuf::from(temp_path)
+}
+
+#[cfg(test)]
+mod keystore_crypto_tests {
+    use super::*;
+    use std::env;
+    use std::sync::mpsc::channel;
+    use hex::ToHex;
+    use logic::address::PaymentAddress;
+    use logic::input::Input;
+    use logic::output::Output;
+    use logic::payments::CreatePaymentHandler;
+    use logic::signature_verification::verify_payload;
+    use logic::xfer_payload::XferPayload;
+
+    // RFC 8032, section 7.1, test 2
+    const SEED_HEX: &str = "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb";
+    const PUBLIC_KEY_HEX: &str = "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c";
+    const SIGNATURE_HEX: &str = "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00";
+
+    fn config(seed: &str) -> PaymentAddressConfig {
+        PaymentAddressConfig { seed: seed.to_string(), ..Default::default() }
+    }
+
+    fn sign_sync(crypto: &KeystoreCrypto, verkey: &str, message: &str) -> Result<String, ErrorCode> {
+        let (sender, receiver) = channel();
+        let ec = crypto.indy_crypto_sign(1, verkey.to_string(), message.to_string(), move |signature| {
+            sender.send(signature).unwrap();
+        });
+        if ec != ErrorCode::Success {
+            return Err(ec);
+        }
+        receiver.recv().unwrap()
+    }
+
+    #[test]
+    fn create_key_and_sign_rfc8032_vector() {
+        let crypto = KeystoreCrypto::new();
+        let verkey = crypto.indy_create_key(1, config(SEED_HEX)).unwrap();
+        assert_eq!(PUBLIC_KEY_HEX, verkey.from_base58().unwrap().to_hex());
+
+        let signature = sign_sync(&crypto, &verkey, "r").unwrap();
+        assert_eq!(SIGNATURE_HEX, signature.from_base58().unwrap().to_hex());
+        assert!(crypto.indy_crypto_verify(verkey.clone(), "r".to_string(), signature.clone()).unwrap());
+        assert!(!crypto.indy_crypto_verify(verkey.clone(), "s".to_string(), signature.clone()).unwrap());
+        assert!(!crypto.indy_crypto_verify(verkey.clone(), "r".to_string(), signature[1..].to_string()).unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, crypto.indy_crypto_verify(verkey[..10].to_string(), "r".to_string(), signature).unwrap_err());
+    }
+
+    #[test]
+    fn seed_formats() {
+        let seed_hex = "3030303030303030303030303030303030303030303030304164647265737331";
+        let seed_base64 = "MDAwMDAwMDAwMDAwMDAwMDAwMDAwMDAwQWRkcmVzczE=";
+        let seed = "000000000000000000000000Address1";
+
+        let verkey = KeystoreCrypto::new().indy_create_key(1, config(seed)).unwrap();
+        assert_eq!(verkey, KeystoreCrypto::new().indy_create_key(1, config(seed_hex)).unwrap());
+        assert_eq!(verkey, KeystoreCrypto::new().indy_create_key(1, config(seed_base64)).unwrap());
+        assert_eq!(verkey, KeystoreCrypto::new().indy_verkey_of_seed(seed_hex).unwrap());
+    }
+
+    #[test]
+    fn create_key_with_invalid_seed() {
+        let crypto = KeystoreCrypto::new();
+        assert_eq!(ErrorCode::CommonInvalidStructure, crypto.indy_create_key(1, config("too short")).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, crypto.indy_create_key(1, config(&"zz".repeat(32))).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, crypto.indy_create_key(1, config("c2hvcnQ=")).unwrap_err());
+    }
+
+    #[test]
+    fn create_key_twice() {
+        let crypto = KeystoreCrypto::new();
+        crypto.indy_create_key(1, config(SEED_HEX)).unwrap();
+        assert_eq!(ErrorCode::WalletItemAlreadyExists, crypto.indy_create_key(1, config(SEED_HEX)).unwrap_err());
+        assert_eq!(1, crypto.verkeys().unwrap().len());
+    }
+
+    #[test]
+    fn create_random_keys() {
+        let crypto = KeystoreCrypto::new();
+        let first = crypto.indy_create_key(1, config("")).unwrap();
+        let second = crypto.indy_create_key(1, config("")).unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn sign_with_unknown_key() {
+        let crypto = KeystoreCrypto::new();
+        let verkey = KeystoreCrypto::new().indy_create_key(1, config(SEED_HEX)).unwrap();
+        assert_eq!(ErrorCode::WalletItemNotFound, sign_sync(&crypto, &verkey, "r").unwrap_err());
+    }
+
+    #[test]
+    fn create_payment_address_with_keystore() {
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+        let address = handler.create_payment_address(1, config(SEED_HEX)).unwrap();
+        let verkey = KeystoreCrypto::new().indy_create_key(1, config(SEED_HEX)).unwrap();
+        assert_eq!(verkey, PaymentAddress::new(&address).unwrap().verkey());
+    }
+
+    #[test]
+    fn sign_and_verify_transfer() {
+        let crypto: &'static KeystoreCrypto = Box::leak(Box::new(KeystoreCrypto::new()));
+        let verkey = crypto.indy_create_key(1, config(SEED_HEX)).unwrap();
+        let input = Input::new(PaymentAddress::from_verkey(&verkey).unwrap().with_qualifier(), 1);
+        let output = Output::new(PaymentAddress::new("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs").unwrap(), 10);
+
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        XferPayload::new(vec![input], vec![output], None).sign_transfer(crypto, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        })).unwrap();
+        let (signed, _) = receiver.recv().unwrap().unwrap();
+
+        assert!(verify_payload(crypto, &signed, &None, &None).unwrap().is_valid());
+    }
+
+    #[test]
+    fn keystore_file() {
+        let path = env::temp_dir().join(format!("sovtoken_keystore_{}.json", serialize_bytes(&random_seed().unwrap())));
+
+        let key = random_seed().unwrap();
+
+        let verkey = KeystoreCrypto::open(&path, &key).unwrap().indy_create_key(1, config(SEED_HEX)).unwrap();
+        let reopened = KeystoreCrypto::open(&path, &key).unwrap();
+        assert_eq!(vec![verkey.clone()], reopened.verkeys().unwrap());
+        assert!(sign_sync(&reopened, &verkey, "r").is_ok());
+
+        let second = reopened.indy_create_key(1, config("")).unwrap();
+        let mut verkeys = KeystoreCrypto::open(&path, &key).unwrap().verkeys().unwrap();
+        verkeys.sort();
+        let mut expected = vec![verkey.clone(), second];
+        expected.sort();
+        assert_eq!(expected, verkeys);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(!temp_path_of(&path).exists());
+        assert!(!content.contains(&verkey));
+        assert!(!content.contains(&serialize_bytes(&Vec::<u8>::from_hex(SEED_HEX).unwrap())));
+
+        assert_eq!(ErrorCode::WalletAccessFailed, KeystoreCrypto::open(&path, &random_seed().unwrap()).err().unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, KeystoreCrypto::open(&path, &key[1..]).err().unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,291 @@
+/*!
+    Merkle audit paths of written transactions.
+
+    The reply to a write carries the `rootHash` of the ledger right after the
+    transaction got written and the `auditPath` of its leaf. The transaction
+    is then the last of the `seqNo` leaves of the tree. The leaf is the
+    msgpack of the transaction record, which is the result of the reply
+    without its `rootHash` and `auditPath`.
+
+    Checking the replies parsed by [`parse_payment_response_handler`] and
+    [`parse_response_with_fees_handler`] is off until [`set_verify_replies`]
+    turns it on.
+
+    [`parse_payment_response_handler`]: ../../api/fn.parse_payment_response_handler.html
+    [`parse_response_with_fees_handler`]: ../../api/fn.parse_response_with_fees_handler.html
+    [`set_verify_replies`]: fn.set_verify_replies.html
+*/
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde_json::{self, Value};
+
+use ErrorCode;
+use logic::hash::Hash;
+use logic::type_aliases::TxnSeqNo;
+use utils::base58::{FromBase58, IntoBase58};
+use utils::constants::txn_fields::{AUDIT_PATH, FEES, RESULT, ROOT_HASH, SEQ_NO, TXN_METADATA};
+use utils::msgpack::to_msgpack;
+
+static VERIFY_REPLIES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_verify_replies(verify: bool) {
+    VERIFY_REPLIES.store(verify, Ordering::SeqCst);
+}
+
+pub fn verify_replies() -> bool {
+    VERIFY_REPLIES.load(Ordering::SeqCst)
+}
+
+/**
+    Walks the `audit_path` of the leaf at `leaf_index` up to the root of a
+    tree of `tree_size` leaves. The path and the returned root are base58.
+*/
+pub fn root_hash_from_audit_path(leaf: &[u8], leaf_index: u64, tree_size: u64, audit_path: &[String]) -> Result<String, ErrorCode> {
+    if leaf_index >= tree_size {
+        error!("Leaf {} is not in a tree of {} leaves", leaf_index, tree_size);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let mut node_index = leaf_index;
+    let mut last_node = tree_size - 1;
+    let mut node_hash = Hash::hash_leaf(&leaf)?.to_vec();
+    let mut path = audit_path.iter();
+
+    while last_node > 0 {
+        if node_index % 2 == 1 || node_index < last_node {
+            let sibling = match path.next() {
+                Some(sibling) => sibling.from_base58().map_err(|_| ErrorCode::CommonInvalidStructure)?,
+                None => {
+                    error!("The audit path is too short");
+                    return Err(ErrorCode::CommonInvalidStructure);
+                }
+            };
+
+            node_hash = if node_index % 2 == 1 {
+                Hash::hash_nodes(&sibling, &node_hash)?.to_vec()
+            } else {
+                Hash::hash_nodes(&node_hash, &sibling)?.to_vec()
+            };
+        }
+        node_index /= 2;
+        last_node /= 2;
+    }
+
+    if path.next().is_some() {
+        error!("The audit path is too long");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    Ok(node_hash.into_base58())
+}
+
+/**
+    Checks that `record` was the last transaction of the ledger when its root
+    was `root_hash`.
+*/
+pub fn verify_inclusion(record: &Value, seq_no: TxnSeqNo, root_hash: &str, audit_path: &[String]) -> Result<bool, ErrorCode> {
+    if seq_no == 0 {
+        error!("Transactions start at seq_no 1");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let root = root_hash_from_audit_path(&to_msgpack(record), seq_no - 1, seq_no, audit_path)?;
+    trace!("logic::merkle_proof::verify_inclusion >> root: {:?}, expected: {:?}", root, root_hash);
+    Ok(root == root_hash)
+}
+
+/**
+    Checks the proof carried by the result of a write. The fields in
+    `ignored` don't belong to the transaction record.
+
+    Errors with `CommonInvalidStructure` when the proof is missing or
+    doesn't verify.
+*/
+pub fn verify_written_txn(result: &Value, ignored: &[&str]) -> Result<(), ErrorCode> {
+    let result = result.as_object().ok_or(ErrorCode::CommonInvalidStructure)?;
+
+    let root_hash = result.get(ROOT_HASH)
+        .and_then(Value::as_str)
+        .ok_or(ErrorCode::CommonInvalidStructure)?;
+    let audit_path: Vec<String> = result.get(AUDIT_PATH)
+        .and_then(|audit_path| serde_json::from_value(audit_path.clone()).ok())
+        .ok_or(ErrorCode::CommonInvalidStructure)?;
+    let seq_no = result.get(TXN_METADATA)
+        .and_then(|metadata| metadata.get(SEQ_NO))
+        .and_then(Value::as_u64)
+        .ok_or(ErrorCode::CommonInvalidStructure)?;
+
+    let record: serde_json::Map<String, Value> = result.iter()
+        .filter(|&(key, _)| key != ROOT_HASH && key != AUDIT_PATH && !ignored.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+
+    if !verify_inclusion(&Value::Object(record), seq_no, root_hash, &audit_path)? {
+        error!("Transaction {} is not in the ledger with root {}", seq_no, root_hash);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    Ok(())
+}
+
+/**
+    Checks the proof of the XFER in a reply to a payment request.
+
+    Only a `REPLY` has a proof.
+*/
+pub fn verify_payment_reply(reply: &Value) -> Result<(), ErrorCode> {
+    if reply["op"] != "REPLY" {
+        return Ok(());
+    }
+
+    verify_written_txn(&reply[RESULT], &[])
+}
+
+/**
+    Checks the proofs of the transaction and, when there are fees, of the
+    fees in a reply to a request with fees.
+
+    Only a `REPLY` has a proof.
+*/
+pub fn verify_reply_with_fees(reply: &Value) -> Result<(), ErrorCode> {
+    if reply["op"] != "REPLY" {
+        return Ok(());
+    }
+
+    let result = &reply[RESULT];
+    verify_written_txn(result, &[FEES])?;
+
+    match result.get(FEES) {
+        Some(fees) if !fees.is_null() => verify_written_txn(fees, &[]),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod merkle_proof_tests {
+    use super::*;
+
+    fn split(size: usize) -> usize {
+        let mut k = 1;
+        while k * 2 < size {
+            k *= 2;
+        }
+        k
+    }
+
+    // RFC 6962 MTH
+    fn tree_hash(leaves: &[Vec<u8>]) -> Vec<u8> {
+        if leaves.len() == 1 {
+            return Hash::hash_leaf(&leaves[0]).unwrap().to_vec();
+        }
+        let k = split(leaves.len());
+        Hash::hash_nodes(&tree_hash(&leaves[..k]), &tree_hash(&leaves[k..])).unwrap().to_vec()
+    }
+
+    // RFC 6962 PATH
+    fn audit_path(index: usize, leaves: &[Vec<u8>]) -> Vec<String> {
+        if leaves.len() == 1 {
+            return Vec::new();
+        }
+        let k = split(leaves.len());
+        let (mut path, sibling) = if index < k {
+            (audit_path(index, &leaves[..k]), tree_hash(&leaves[k..]))
+        } else {
+            (audit_path(index - k, &leaves[k..]), tree_hash(&leaves[..k]))
+        };
+        path.push(sibling.into_base58());
+        path
+    }
+
+    fn leaves(size: usize) -> Vec<Vec<u8>> {
+        (0..size).map(|i| format!("leaf {}", i).into_bytes()).collect()
+    }
+
+    fn reply(amount: u64) -> Value {
+        let record = json!({
+            "txn": {
+                "type": "10001",
+                "protocolVersion": 2,
+                "data": {
+                    "inputs": [{"address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", "seqNo": 1}],
+                    "outputs": [{"address": "2mVXsXyVADzSDw88RAojPpdgxLPQyC1oJUqkrLeU5AdfEq2PmC", "amount": amount}]
+                },
+                "metadata": {"digest": "228af6a0c773cbbd575bf4e16f9144c2eaa615fa81fdcc3d06b83e20a92e5989", "reqId": 152968241}
+            },
+            "reqSignature": {"type": "ED25519", "values": []},
+            "txnMetadata": {"seqNo": 3, "txnTime": 1529682415},
+            "ver": "1"
+        });
+
+        let mut ledger = leaves(2);
+        ledger.push(to_msgpack(&record));
+
+        let mut result = record;
+        result[ROOT_HASH] = json!(tree_hash(&ledger).into_base58());
+        result[AUDIT_PATH] = json!(audit_path(2, &ledger));
+        json!({"op": "REPLY", "result": result})
+    }
+
+    #[test]
+    fn root_hash_of_each_leaf() {
+        for size in 1..10 {
+            let leaves = leaves(size);
+            let root = tree_hash(&leaves).into_base58();
+
+            for index in 0..size {
+                let path = audit_path(index, &leaves);
+                let computed = root_hash_from_audit_path(&leaves[index], index as u64, size as u64, &path).unwrap();
+                assert_eq!(root, computed, "leaf {} of {}", index, size);
+            }
+        }
+    }
+
+    #[test]
+    fn root_hash_of_audit_path_with_wrong_length() {
+        let leaves = leaves(5);
+        let mut path = audit_path(4, &leaves);
+
+        let first = path[0].clone();
+        path.push(first);
+        assert_eq!(ErrorCode::CommonInvalidStructure, root_hash_from_audit_path(&leaves[4], 4, 5, &path).unwrap_err());
+
+        path.clear();
+        assert_eq!(ErrorCode::CommonInvalidStructure, root_hash_from_audit_path(&leaves[4], 4, 5, &path).unwrap_err());
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, root_hash_from_audit_path(&leaves[4], 5, 5, &[]).unwrap_err());
+    }
+
+    #[test]
+    fn verify_payment_reply_with_valid_proof() {
+        verify_payment_reply(&reply(10)).unwrap();
+    }
+
+    #[test]
+    fn verify_payment_reply_with_changed_txn() {
+        let mut reply = reply(10);
+        reply[RESULT]["txn"]["data"]["outputs"][0]["amount"] = json!(11);
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_payment_reply(&reply).unwrap_err());
+    }
+
+    #[test]
+    fn verify_payment_reply_without_proof() {
+        let mut reply = reply(10);
+        reply[RESULT].as_object_mut().unwrap().remove(AUDIT_PATH);
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_payment_reply(&reply).unwrap_err());
+        verify_payment_reply(&json!({"op": "REJECT", "reason": "InsufficientFundsError"})).unwrap();
+    }
+
+    #[test]
+    fn verify_reply_with_fees_checks_both_proofs() {
+        let mut reply = reply(10);
+        let fees = reply[RESULT].clone();
+        reply[RESULT][FEES] = fees;
+        verify_reply_with_fees(&reply).unwrap();
+
+        reply[RESULT][FEES][ROOT_HASH] = json!("FePFuqEX6iJ1SP5DkYn9WTXQrThxqevEkxYXyCxyX4Fd");
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_reply_with_fees(&reply).unwrap_err());
+    }
+}
@@ -1,11 +1,11 @@
 use ErrorCode;
 use libc::c_char;
-use logic::address;
 use logic::config::output_mint_config::MintRequest;
 use logic::did::Did;
 use serde_json;
 use utils::constants::general::{JsonCallback, JsonCallbackUnwrapped};
-use utils::ffi_support::{string_from_char_ptr};
+use utils::current_error::{invalid_parameter, missing_parameter};
+use utils::ffi_support::{string_from_char_ptr, c_pointer_from_string};
 use logic::output::Outputs;
 
 type DeserializedArguments = (Option<Did>, Outputs, Option<String>, JsonCallbackUnwrapped);
@@ -17,7 +17,7 @@ pub fn deserialize_inputs(
     cb: JsonCallback
 ) -> Result<DeserializedArguments, ErrorCode> {
     trace!("logic::minting::deserialize_inputs >> did: {:?}, outputs_json: {:?}, extra: {:?}", secret!(&did), secret!(&outputs_json), secret!(&extra));
-    let cb = cb.ok_or(ErrorCode::CommonInvalidStructure)?;
+    let cb = cb.ok_or_else(|| missing_parameter("cb"))?;
     trace!("Unwrapped callback.");
 
     let did = Did::from_pointer(did).map(
@@ -29,11 +29,11 @@ pub fn deserialize_inputs(
     debug!("Converted did pointer to string >>> {:?}", secret!(&did));
 
     let outputs_json = string_from_char_ptr(outputs_json)
-        .ok_or(ErrorCode::CommonInvalidStructure)?;
+        .ok_or_else(|| missing_parameter("outputs_json"))?;
     debug!("Converted outputs_json pointer to string >>> {:?}", secret!(&outputs_json));
 
     let outputs: Outputs = serde_json::from_str(&outputs_json)
-        .or(Err(ErrorCode::CommonInvalidStructure))?;
+        .map_err(invalid_parameter("outputs_json"))?;
     debug!("Deserialized output_json >>> {:?}", secret!(&outputs));
 
     let extra = string_from_char_ptr(extra);
@@ -45,31 +45,44 @@ pub fn deserialize_inputs(
 
 pub fn build_mint_request(
     did: Option<Did>,
-    mut outputs: Outputs,
+    outputs: Outputs,
     extra: Option<String>,
 ) -> Result<*const c_char, ErrorCode> {
+    build_mint_request_json(did, outputs, extra).map(c_pointer_from_string)
+}
+
+/**
+    Builds the mint request json.
+
+    The outputs are stripped of their `pay:sov:` qualifier.
+*/
+pub fn build_mint_request_json(
+    did: Option<Did>,
+    mut outputs: Outputs,
+    extra: Option<String>,
+) -> Result<String, ErrorCode> {
     trace!("logic::minting::build_mint_request >> did: {:?}, outputs: {:?}", secret!(&did), secret!(&outputs));
 
     for output in &mut outputs {
-        let address = address::unqualified_address_from_address(&output.recipient)?;
-        output.recipient = address;
+        output.recipient = output.recipient.strip_qualifier()?;
     }
     trace!("Stripped pay:sov: from outputs");
 
     let mint_request = MintRequest::from_config(outputs, did, extra);
     info!("Built a mint request >>> {:?}", secret!(&mint_request));
 
-    let ptr = mint_request.serialize_to_pointer()
+    let json = mint_request.serialize_to_string()
         .or(Err(ErrorCode::CommonInvalidStructure));
 
-    trace!("logic::minting::build_mint_request << res: {:?}", ptr);
-    ptr
+    trace!("logic::minting::build_mint_request << res: {:?}", secret!(&json));
+    json
 }
 
 #[cfg(test)]
 mod test_build_mint_request {
     use super::*;
     use std::ptr::null;
+    use logic::address::PaymentAddress;
     use logic::output::Output;
     use utils::base58::IntoBase58;
     use utils::constants::txn_types::MINT_PUBLIC;
@@ -92,8 +105,19 @@ mod test_build_mint_request {
 
     #[test]
     fn build_mint_request_invalid_address() {
+        let output_config_pointer = json_c_pointer!([{
+            "address": "pad:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm",
+            "amount": 12
+        }]);
+
+        let result = call_deserialize_inputs(None, Some(output_config_pointer), None, None);
+        assert_eq!(ErrorCode::CommonInvalidStructure, result.unwrap_err());
+    }
+
+    #[test]
+    fn build_mint_request_unqualified_address() {
         let outputs = vec![
-            Output::new(String::from("pad:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 12)
+            Output::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(), 12)
         ];
 
         let did = Did::new("en32ansFeZNERIouv2xA".to_string());
@@ -0,0 +1,235 @@
+/*!
+    [BIP-39] mnemonics with the English wordlist, the seed phrases payment
+    addresses are backed up and restored with.
+
+    A mnemonic encodes 16 to 32 bytes of entropy with a checksum, in 12 to 24
+    words. Its seed, stretched with an optional passphrase, is the master seed
+    the keys of the addresses are derived from with [`hd_derivation`].
+
+    The passphrase is used as it is, so it has to be ASCII to give the seed
+    other BIP-39 wallets give.
+
+    [BIP-39]: https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+    [`hd_derivation`]: ../hd_derivation/index.html
+*/
+
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+
+use ErrorCode;
+
+/**
+    The word counts of the mnemonics of 16, 20, 24, 28 and 32 bytes of
+    entropy.
+*/
+pub const WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+const ENGLISH: &str = include_str!("english.txt");
+const BITS_PER_WORD: usize = 11;
+const SEED_ITERATIONS: usize = 2048;
+const SEED_LEN: usize = 64;
+const SALT_PREFIX: &str = "mnemonic";
+
+lazy_static! {
+    static ref WORDS: Vec<&'static str> = ENGLISH.lines().collect();
+}
+
+/**
+    A new mnemonic of `word_count` words, out of random entropy.
+
+    Errors with `CommonInvalidStructure` when `word_count` isn't one of
+    [`WORD_COUNTS`].
+
+    [`WORD_COUNTS`]: constant.WORD_COUNTS.html
+*/
+pub fn generate_mnemonic(word_count: usize) -> Result<String, ErrorCode> {
+    if !WORD_COUNTS.contains(&word_count) {
+        error!("A mnemonic has one of {:?} words, not {}", WORD_COUNTS, word_count);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let mut entropy = vec![0u8; word_count * BITS_PER_WORD * 32 / 33 / 8];
+    rand_bytes(&mut entropy).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidState))?;
+    mnemonic_from_entropy(&entropy)
+}
+
+/**
+    The mnemonic of `entropy`, which has 16 to 32 bytes in steps of 4.
+*/
+pub fn mnemonic_from_entropy(entropy: &[u8]) -> Result<String, ErrorCode> {
+    if entropy.len() < 16 || entropy.len() > 32 || entropy.len() % 4 != 0 {
+        error!("Mnemonic entropy should have 16 to 32 bytes in steps of 4, got {}", entropy.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let mut bits = bits_of(entropy);
+    let checksum = checksum_bits(entropy)?;
+    bits.extend_from_slice(&checksum);
+
+    let words: Vec<&str> = bits.chunks(BITS_PER_WORD)
+        .map(|chunk| WORDS[chunk.iter().fold(0, |index, bit| (index << 1) | usize::from(*bit))])
+        .collect();
+    Ok(words.join(" "))
+}
+
+/**
+    The entropy `mnemonic` encodes.
+
+    Errors with `CommonInvalidStructure` when it doesn't have one of
+    [`WORD_COUNTS`] words, has a word which isn't in the wordlist or its
+    checksum doesn't match.
+
+    [`WORD_COUNTS`]: constant.WORD_COUNTS.html
+*/
+pub fn entropy_from_mnemonic(mnemonic: &str) -> Result<Vec<u8>, ErrorCode> {
+    let words: Vec<&str> = mnemonic.split_whitespace().collect();
+    if !WORD_COUNTS.contains(&words.len()) {
+        error!("A mnemonic has one of {:?} words, not {}", WORD_COUNTS, words.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let mut bits: Vec<bool> = Vec::with_capacity(words.len() * BITS_PER_WORD);
+    for (position, word) in words.iter().enumerate() {
+        let index = WORDS.binary_search(word).map_err(|_| {
+            error!("Word {} of the mnemonic is not in the wordlist", position + 1);
+            ErrorCode::CommonInvalidStructure
+        })?;
+        bits.extend((0..BITS_PER_WORD).rev().map(|shift| (index >> shift) & 1usize == 1));
+    }
+
+    let (entropy_bits, checksum) = bits.split_at(bits.len() * 32 / 33);
+    let entropy: Vec<u8> = entropy_bits.chunks(8)
+        .map(|byte| byte.iter().fold(0u8, |value, bit| (value << 1) | u8::from(*bit)))
+        .collect();
+
+    if checksum != &checksum_bits(&entropy)?[..] {
+        error!("The checksum of the mnemonic doesn't match");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    Ok(entropy)
+}
+
+pub fn validate_mnemonic(mnemonic: &str) -> Result<(), ErrorCode> {
+    entropy_from_mnemonic(mnemonic).map(|_| ())
+}
+
+/**
+    The 64 bytes seed of a valid `mnemonic` and `passphrase`, which can be
+    empty.
+*/
+pub fn seed_from_mnemonic(mnemonic: &str, passphrase: &str) -> Result<Vec<u8>, ErrorCode> {
+    validate_mnemonic(mnemonic)?;
+    if !passphrase.is_ascii() {
+        error!("The passphrase of a mnemonic should be ASCII");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let normalized = mnemonic.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let salt = format!("{}{}", SALT_PREFIX, passphrase);
+    let mut seed = vec![0u8; SEED_LEN];
+    pbkdf2_hmac(normalized.as_bytes(), salt.as_bytes(), SEED_ITERATIONS, MessageDigest::sha512(), &mut seed)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidState))?;
+    Ok(seed)
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn bits_of(bytes: &[u8]) -> Vec<bool> {
+    bytes.iter()
+        .flat_map(|byte| (0..8u8).rev().map(move |shift| (byte >> shift) & 1u8 == 1))
+        .collect()
+}
+
+/*
+    The first bit of the sha256 of the entropy for each 4 bytes of it.
+*/
+fn checksum_bits(entropy: &[u8]) -> Result<Vec<bool>, ErrorCode> {
+    let digest = hash(MessageDigest::sha256(), entropy)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidState))?;
+    Ok(bits_of(&digest)[..entropy.len() / 4].to_vec())
+}
+
+#[cfg(test)]
+mod mnemonic_tests {
+    use super::*;
+    use hex::{FromHex, ToHex};
+
+    // test vectors of the reference implementation, with the passphrase TREZOR
+    const VECTORS: [(&str, &str, &str); 4] = [
+        (
+            "00000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        ),
+        (
+            "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+            "legal winner thank year wave sausage worth useful legal winner thank yellow",
+            "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+        ),
+        (
+            "ffffffffffffffffffffffffffffffff",
+            "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong",
+            "ac27495480225222079d7be181583751e86f571027b0497b5b5d11218e0a8a13332572917f0f8e5a589620c6f15b11c61dee327651a14c34e18231052e48c069",
+        ),
+        (
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+            "bda85446c68413707090a52022edd26a1c9462295029f2e60cd7c4f2bbd3097170af7a4d73245cafa9c3cca8d561a7c3de6f5d4a10be8ed2a5e608d68f92fcc8",
+        ),
+    ];
+
+    #[test]
+    fn wordlist() {
+        assert_eq!(2048, WORDS.len());
+        assert!(WORDS.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn reference_vectors() {
+        for &(entropy, mnemonic, seed) in VECTORS.iter() {
+            let entropy = Vec::<u8>::from_hex(entropy).unwrap();
+            assert_eq!(mnemonic, mnemonic_from_entropy(&entropy).unwrap());
+            assert_eq!(entropy, entropy_from_mnemonic(mnemonic).unwrap());
+            assert_eq!(seed, seed_from_mnemonic(mnemonic, "TREZOR").unwrap().to_hex());
+        }
+    }
+
+    #[test]
+    fn generate_and_restore() {
+        for &word_count in WORD_COUNTS.iter() {
+            let mnemonic = generate_mnemonic(word_count).unwrap();
+            assert_eq!(word_count, mnemonic.split(' ').count());
+            assert!(validate_mnemonic(&mnemonic).is_ok());
+        }
+        assert_eq!(ErrorCode::CommonInvalidStructure, generate_mnemonic(13).unwrap_err());
+    }
+
+    #[test]
+    fn invalid_mnemonics() {
+        let invalid = [
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon",
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon aboot",
+            "Abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        ];
+        for mnemonic in invalid.iter() {
+            assert_eq!(ErrorCode::CommonInvalidStructure, validate_mnemonic(mnemonic).unwrap_err(), "{}", mnemonic);
+        }
+        assert_eq!(ErrorCode::CommonInvalidStructure, mnemonic_from_entropy(&[0; 15]).unwrap_err());
+    }
+
+    #[test]
+    fn seed_of_spaced_mnemonic_and_passphrase() {
+        let spaced = " abandon abandon abandon abandon abandon abandon\tabandon abandon abandon abandon abandon  about\n";
+        assert_eq!(seed_from_mnemonic(VECTORS[0].1, "").unwrap(), seed_from_mnemonic(spaced, "").unwrap());
+        assert_ne!(seed_from_mnemonic(VECTORS[0].1, "").unwrap(), seed_from_mnemonic(VECTORS[0].1, "TREZOR").unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, seed_from_mnemonic(VECTORS[0].1, "pässword").unwrap_err());
+    }
+}
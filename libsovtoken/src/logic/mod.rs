@@ -6,18 +6,33 @@
 
 pub mod address;
 pub mod api_internals;
+pub mod balance_check;
 pub mod build_payment;
+pub mod coin_selection;
 pub mod config;
+pub mod consolidation;
 pub mod did;
+pub mod fee_calculator;
 pub mod hash;
+pub mod hd_derivation;
+pub mod history;
 pub mod indy_sdk_api;
 pub mod input;
+pub mod keystore_crypto;
+pub mod merkle_proof;
 pub mod output;
 pub mod minting;
+pub mod mnemonic;
+pub mod offline_signing;
 pub mod payments;
 pub mod parsers;
+pub mod partial_signing;
+pub mod recovery;
 pub mod request;
 pub mod set_fees;
+pub mod signature_verification;
+pub mod state_proof;
+pub mod sweep;
 pub mod type_aliases;
 pub mod verify;
 pub mod xfer_payload;
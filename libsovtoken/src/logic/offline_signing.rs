@@ -0,0 +1,393 @@
+/*!
+    Signing of XFER and fee payloads on another machine.
+
+    The keys of some payment addresses are kept in a wallet on a machine which
+    is never online. Signing is then done in three steps:
+
+    1. The online side exports the unsigned payload as a [`SigningEnvelope`],
+       with [`SigningEnvelope::for_transfer`] or [`SigningEnvelope::for_fees`].
+    2. The offline side signs the envelope with [`SigningEnvelope::sign`] and
+       sends back the [`Signatures`].
+    3. The online side merges them into the final request, with
+       [`SigningEnvelope::transfer_request`] or [`SigningEnvelope::fees_request`].
+
+    The envelope and the signatures are plain json. Every step builds the
+    messages with [`signature_message`], the same way [`XferPayload`] does, so
+    the signatures are the ones the online path would make. An envelope whose
+    messages don't match its content is refused.
+
+    [`SigningEnvelope`]: struct.SigningEnvelope.html
+    [`SigningEnvelope::for_transfer`]: struct.SigningEnvelope.html#method.for_transfer
+    [`SigningEnvelope::for_fees`]: struct.SigningEnvelope.html#method.for_fees
+    [`SigningEnvelope::sign`]: struct.SigningEnvelope.html#method.sign
+    [`SigningEnvelope::transfer_request`]: struct.SigningEnvelope.html#method.transfer_request
+    [`SigningEnvelope::fees_request`]: struct.SigningEnvelope.html#method.fees_request
+    [`Signatures`]: type.Signatures.html
+    [`signature_message`]: ../xfer_payload/fn.signature_message.html
+    [`XferPayload`]: ../xfer_payload/struct.XferPayload.html
+*/
+
+use serde_json;
+use std::collections::HashMap;
+
+use IndyHandle;
+use ErrorCode;
+use logic::api_internals::add_request_fees::{request_with_fees, txn_digest, validate_type_not_transfer, SerdeMap};
+use logic::build_payment::build_payment_request;
+use logic::did::Did;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::Inputs;
+use logic::output::Outputs;
+use logic::xfer_payload::{signature_message, Extra, InputSigner, XferPayload};
+use utils::txn_author_agreement::TaaAcceptance;
+
+/**
+    Signatures of an envelope, by input.
+*/
+pub type Signatures = HashMap<String, String>;
+
+/**
+    An unsigned payload in the form its inputs are signed in.
+
+    `inputs` and `outputs` are unqualified and the TAA acceptance has been
+    taken out of `extra`. `txn_digest` is only set for fees. `messages` holds
+    the message signed for each input, in the order of `inputs`.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SigningEnvelope {
+    pub inputs: Inputs,
+    pub outputs: Outputs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Extra>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taa_acceptance: Option<TaaAcceptance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub txn_digest: Option<String>,
+    pub messages: Vec<String>,
+}
+
+impl<A: CryptoAPI> InputSigner<A> for SigningEnvelope {}
+
+impl SigningEnvelope {
+    /**
+        Exports a payload to be signed by [`XferPayload::sign_transfer`].
+
+        Errors with `CommonInvalidStructure` when it has no inputs or no
+        outputs.
+
+        [`XferPayload::sign_transfer`]: ../xfer_payload/struct.XferPayload.html#method.sign_transfer
+    */
+    pub fn for_transfer(payload: XferPayload) -> Result<SigningEnvelope, ErrorCode> {
+        trace!("logic::offline_signing::for_transfer >> payload: {:?}", secret!(&payload));
+        if payload.inputs.is_empty() || payload.outputs.is_empty() {
+            error!("Can't sign a transfer without inputs or outputs");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        SigningEnvelope::new(payload, None)
+    }
+
+    /**
+        Exports the fees of `request_json` to be signed by [`XferPayload::sign_fees`].
+
+        Errors with `CommonInvalidStructure` when the payload has no inputs,
+        or for an XFER request.
+
+        [`XferPayload::sign_fees`]: ../xfer_payload/struct.XferPayload.html#method.sign_fees
+    */
+    pub fn for_fees(payload: XferPayload, request_json: &str) -> Result<SigningEnvelope, ErrorCode> {
+        trace!("logic::offline_signing::for_fees >> payload: {:?}, request_json: {:?}", secret!(&payload), secret!(request_json));
+        if payload.inputs.is_empty() {
+            error!("Can't sign fees without inputs");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        let request_json_map = parse_request(request_json)?;
+        SigningEnvelope::new(payload, Some(txn_digest(&request_json_map)?))
+    }
+
+    /**
+        Signs every input of the envelope with the keys in the wallet.
+
+        `cb` gets the signatures once all of them are made.
+    */
+    pub fn sign<A: CryptoAPI>(&self, crypto_api: &'static A, wallet_handle: IndyHandle, cb: Box<Fn(Result<Signatures, ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
+        trace!("logic::offline_signing::sign >> envelope: {:?}, wallet_handle: {:?}", secret!(self), wallet_handle);
+        self.check_messages()?;
+        if self.inputs.is_empty() {
+            error!("Envelope has no inputs to sign");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        let res = SigningEnvelope::sign_inputs(crypto_api, wallet_handle, &self.inputs, &self.outputs, &self.txn_digest, &self.extra, &self.taa_acceptance, cb);
+        trace!("logic::offline_signing::sign << result: {:?}", res);
+        res
+    }
+
+    /**
+        Builds the XFER_PUBLIC request out of a transfer envelope and its signatures.
+
+        The identifier defaults to the verkey of the first input, as in
+        [`build_payment_req_handler`].
+
+        [`build_payment_req_handler`]: ../../api/fn.build_payment_req_handler.html
+    */
+    pub fn transfer_request(&self, signatures: &Signatures, identifier: Option<Did>) -> Result<String, ErrorCode> {
+        if self.txn_digest.is_some() {
+            error!("Envelope holds fees, not a transfer");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        let payload = self.signed_payload(signatures)?;
+        build_payment_request(identifier, Ok((payload, self.taa_acceptance.clone())))
+    }
+
+    /**
+        Adds the fees of a fees envelope and its signatures to `request_json`.
+
+        `request_json` has to be the request the envelope was made for.
+    */
+    pub fn fees_request(&self, signatures: &Signatures, request_json: &str) -> Result<String, ErrorCode> {
+        let request_json_map = parse_request(request_json)?;
+        match self.txn_digest {
+            Some(ref digest) if *digest == txn_digest(&request_json_map)? => (),
+            Some(_) => {
+                error!("Envelope was made for another request");
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+            None => {
+                error!("Envelope holds a transfer, not fees");
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+        }
+
+        let payload = self.signed_payload(signatures)?;
+        serde_json::to_string(&request_with_fees(request_json_map, &payload))
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))
+    }
+
+    /**
+        Errors with `CommonInvalidStructure` when the messages of the envelope
+        don't match its content.
+    */
+    pub fn check_messages(&self) -> Result<(), ErrorCode> {
+        if self.build_messages()? != self.messages {
+            error!("Messages of the envelope don't match its content");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        Ok(())
+    }
+
+    /**
+        Inputs of the envelope which have no signature in `signatures`.
+    */
+    pub fn unsigned_inputs(&self, signatures: &Signatures) -> Inputs {
+        self.inputs.iter()
+            .filter(|input| !signatures.contains_key(&input.to_string()))
+            .cloned()
+            .collect()
+    }
+
+    /**
+        The payload with `signatures` put in the order of its inputs.
+
+        Errors with `CommonInvalidStructure` when an input isn't signed.
+    */
+    pub fn signed_payload(&self, signatures: &Signatures) -> Result<XferPayload, ErrorCode> {
+        self.check_messages()?;
+        let signatures = self.inputs.iter()
+            .map(|input| signatures.get(&input.to_string()).cloned().ok_or_else(|| {
+                error!("Missing signature for input {:?}", secret!(input));
+                ErrorCode::CommonInvalidStructure
+            }))
+            .collect::<Result<Vec<String>, ErrorCode>>()?;
+
+        let mut payload = XferPayload::new(self.inputs.clone(), self.outputs.clone(), self.extra.clone());
+        payload.signatures = Some(signatures);
+        Ok(payload)
+    }
+
+    /*
+        Methods "private" (aka not exported from this module)
+
+        KEEP all public methods above
+    */
+
+    fn new(payload: XferPayload, txn_digest: Option<String>) -> Result<SigningEnvelope, ErrorCode> {
+        let (payload, taa_acceptance) = payload.prepare_for_signing()?;
+        let mut envelope = SigningEnvelope {
+            inputs: payload.inputs,
+            outputs: payload.outputs,
+            extra: payload.extra,
+            taa_acceptance,
+            txn_digest,
+            messages: Vec::new(),
+        };
+        envelope.messages = envelope.build_messages()?;
+        debug!("Built signing envelope >>> {:?}", secret!(&envelope));
+        Ok(envelope)
+    }
+
+    fn build_messages(&self) -> Result<Vec<String>, ErrorCode> {
+        self.inputs.iter()
+            .map(|input| signature_message(input, &self.outputs, &self.txn_digest, &self.extra, &self.taa_acceptance))
+            .collect()
+    }
+
+}
+
+fn parse_request(request_json: &str) -> Result<SerdeMap, ErrorCode> {
+    let request: serde_json::Value = serde_json::from_str(request_json)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    let request_json_map = request.as_object()
+        .ok_or(ErrorCode::CommonInvalidStructure)
+        .map_err(map_err_err!())?
+        .to_owned();
+    validate_type_not_transfer(&request_json_map)?;
+    Ok(request_json_map)
+}
+
+#[cfg(test)]
+mod offline_signing_tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::mpsc::channel;
+    use logic::address::PaymentAddress;
+    use logic::input::Input;
+    use logic::output::Output;
+    use utils::test::crypto_api::CryptoApiHandler;
+
+    fn payload(extra: Option<Extra>) -> XferPayload {
+        let address = |address| PaymentAddress::new(address).unwrap();
+        let inputs = vec![
+            Input::new(address("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 1),
+            Input::new(address("pay:sov:2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V"), 2),
+        ];
+        let outputs = vec![Output::new(address("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs"), 10)];
+        XferPayload::new(inputs, outputs, extra)
+    }
+
+    fn request_json() -> String {
+        json!({
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "operation": {"type": "101", "data": {"name": "gvt", "version": "1.0", "attr_names": ["name"]}}
+        }).to_string()
+    }
+
+    fn sign_sync(envelope: &SigningEnvelope) -> Result<Signatures, ErrorCode> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        envelope.sign(&CryptoApiHandler {}, 1, Box::new(move |signatures| {
+            sender.lock().unwrap().send(signatures).unwrap();
+        }))?;
+        receiver.recv().unwrap()
+    }
+
+    fn sign_online_sync(payload: XferPayload, txn_digest: Option<String>) -> XferPayload {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        let cb = Box::new(move |result| { sender.lock().unwrap().send(result).unwrap(); });
+        match txn_digest {
+            Some(ref digest) => payload.sign_fees(&CryptoApiHandler {}, 1, &Some(digest.clone()), cb).unwrap(),
+            None => payload.sign_transfer(&CryptoApiHandler {}, 1, cb).unwrap(),
+        };
+        receiver.recv().unwrap().unwrap().0
+    }
+
+    #[test]
+    fn envelope_round_trip_through_json() {
+        let envelope = SigningEnvelope::for_transfer(payload(Some(json!({"data": "x"})))).unwrap();
+        let json = serde_json::to_string(&envelope).unwrap();
+
+        assert_eq!(envelope, serde_json::from_str::<SigningEnvelope>(&json).unwrap());
+        assert_eq!(2, envelope.messages.len());
+        assert!(!envelope.inputs[0].address.is_qualified());
+        assert!(!envelope.outputs[0].recipient.is_qualified());
+    }
+
+    #[test]
+    fn envelope_takes_taa_acceptance_out_of_extra() {
+        let taa_acceptance = json!({"mechanism": "at_submission", "taaDigest": "abc", "time": 123456789});
+        let envelope = SigningEnvelope::for_transfer(payload(Some(json!({"taaAcceptance": taa_acceptance.clone()})))).unwrap();
+
+        assert_eq!(None, envelope.extra);
+        assert_eq!(Some(taa_acceptance), envelope.taa_acceptance);
+    }
+
+    #[test]
+    fn transfer_signatures_match_online_signing() {
+        let envelope = SigningEnvelope::for_transfer(payload(None)).unwrap();
+        let signatures = sign_sync(&envelope).unwrap();
+
+        let request: serde_json::Value = serde_json::from_str(&envelope.transfer_request(&signatures, None).unwrap()).unwrap();
+        let online = sign_online_sync(payload(None), None);
+
+        assert_eq!(json!(online.signatures.unwrap()), request["operation"]["signatures"]);
+        assert_eq!(json!(online.inputs), request["operation"]["inputs"]);
+    }
+
+    #[test]
+    fn fees_signatures_match_online_signing() {
+        let request_json = request_json();
+        let envelope = SigningEnvelope::for_fees(payload(None), &request_json).unwrap();
+        let signatures = sign_sync(&envelope).unwrap();
+
+        let request: serde_json::Value = serde_json::from_str(&envelope.fees_request(&signatures, &request_json).unwrap()).unwrap();
+        let online = sign_online_sync(payload(None), envelope.txn_digest.clone());
+
+        assert_eq!(json!([online.inputs, online.outputs, online.signatures]), request["fees"]);
+        assert_eq!("gvt", request["operation"]["data"]["name"]);
+    }
+
+    #[test]
+    fn fees_request_for_another_request() {
+        let envelope = SigningEnvelope::for_fees(payload(None), &request_json()).unwrap();
+        let signatures = sign_sync(&envelope).unwrap();
+        let other_request = json!({"reqId": 1, "operation": {"type": "101"}}).to_string();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, envelope.fees_request(&signatures, &other_request).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, envelope.transfer_request(&signatures, None).unwrap_err());
+    }
+
+    #[test]
+    fn transfer_envelope_is_not_fees() {
+        let envelope = SigningEnvelope::for_transfer(payload(None)).unwrap();
+        let signatures = sign_sync(&envelope).unwrap();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, envelope.fees_request(&signatures, &request_json()).unwrap_err());
+    }
+
+    #[test]
+    fn merge_with_missing_signature() {
+        let envelope = SigningEnvelope::for_transfer(payload(None)).unwrap();
+        let mut signatures = sign_sync(&envelope).unwrap();
+        signatures.remove(&envelope.inputs[1].to_string());
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, envelope.transfer_request(&signatures, None).unwrap_err());
+    }
+
+    #[test]
+    fn tampered_envelope_is_refused() {
+        let mut envelope = SigningEnvelope::for_transfer(payload(None)).unwrap();
+        let signatures = sign_sync(&envelope).unwrap();
+        envelope.outputs[0].amount = 1000;
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, sign_sync(&envelope).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, envelope.transfer_request(&signatures, None).unwrap_err());
+    }
+
+    #[test]
+    fn envelope_for_invalid_payload() {
+        let mut no_outputs = payload(None);
+        no_outputs.outputs.clear();
+        assert_eq!(ErrorCode::CommonInvalidStructure, SigningEnvelope::for_transfer(no_outputs.clone()).unwrap_err());
+        assert!(SigningEnvelope::for_fees(no_outputs, &request_json()).is_ok());
+
+        let mut no_inputs = payload(None);
+        no_inputs.inputs.clear();
+        assert_eq!(ErrorCode::CommonInvalidStructure, SigningEnvelope::for_fees(no_inputs, &request_json()).unwrap_err());
+
+        let transfer_request = json!({"operation": {"type": "10001"}}).to_string();
+        assert_eq!(ErrorCode::CommonInvalidStructure, SigningEnvelope::for_fees(payload(None), &transfer_request).unwrap_err());
+    }
+}
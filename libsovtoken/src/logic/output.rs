@@ -4,6 +4,7 @@
 
 use serde::{de, Deserialize};
 use std::fmt;
+use logic::address::PaymentAddress;
 use logic::type_aliases::TokenAmount;
 
 pub type Outputs = Vec<Output>;
@@ -24,6 +25,11 @@ pub struct OutputConfig {
 /**
     Struct which holds a payment address, token amount, and extra data.
 
+    The recipient is a [`PaymentAddress`], so an output with an invalid address
+    can not be deserialized.
+
+    [`PaymentAddress`]: ../address/struct.PaymentAddress.html
+
     ```text
     // (address, token_amount)
     ("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7", 5)
@@ -57,8 +63,9 @@ pub struct OutputConfig {
 
     ```
     use sovtoken::utils::json_conversion::JsonSerialize;
+    use sovtoken::logic::address::PaymentAddress;
     use sovtoken::logic::output::Output;
-    let address = String::from("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7");
+    let address = PaymentAddress::new("pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap();
     let output = Output::new(address, 5);
     let json = Output::to_json(&output).unwrap();
     assert_eq!(json, r#"{"address":"pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7","amount":5}"#);
@@ -68,12 +75,12 @@ pub struct OutputConfig {
 #[derive(Debug, Eq, PartialEq, Clone, Serialize)]
 pub struct Output {
     #[serde(rename = "address")]
-    pub recipient: String,
+    pub recipient: PaymentAddress,
     pub amount: TokenAmount
 }
 
 impl Output {
-    pub fn new(address: String, amount: TokenAmount) -> Output {
+    pub fn new(address: PaymentAddress, amount: TokenAmount) -> Output {
         return Output { recipient: address, amount };
     }
 }
@@ -93,8 +100,8 @@ impl<'de> Deserialize<'de> for Output {
                 let mut address = None;
                 let mut amount = None;
 
-                while let Some(key) = map.next_key()? {
-                    match key {
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
                         "recipient" => { address = map.next_value()?; },
                         "address" => { address = map.next_value()?; },
                         "amount" => { amount =  map.next_value()?; },
@@ -145,7 +152,7 @@ mod output_tests {
     }
 
     fn output() -> Output {
-        let address = String::from("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7");
+        let address = PaymentAddress::new("a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7").unwrap();
         return Output::new(address, 10);
     }
 
@@ -161,6 +168,13 @@ mod output_tests {
         assert_valid_deserialize(json, expected);
     }
 
+    #[test]
+    fn deserialize_output_from_value() {
+        let json = json!({"address": "a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81fo7", "amount": 10});
+        let deserialized: Output = serde_json::from_value(json).unwrap();
+        assert_eq!(output(), deserialized);
+    }
+
     #[test]
     fn deserialize_invalid_output_object() {
         let json = json!({
@@ -169,6 +183,15 @@ mod output_tests {
         assert_invalid_deserialize(json, "missing field `amount`");
     }
 
+    #[test]
+    fn deserialize_output_with_invalid_address() {
+        let json = json!({
+            "address": "pay:sov:a8QAXMjRwEGoGLmMFEc5sTcntZxEF1BpqAs8GoKFa9Ck81f",
+            "amount": 10
+        });
+        assert_invalid_deserialize(json, "Invalid payment address");
+    }
+
     #[test]
     fn serialize_valid_output_object() {
         let output = output();
@@ -8,6 +8,7 @@ use std::str;
 use serde_json;
 use serde_json::{Value as SJsonValue};
 use logic::address;
+use logic::address::PaymentAddress;
 use logic::type_aliases::{TokenAmount, TxnSeqNo};
 
 /**
@@ -29,7 +30,7 @@ pub enum ResponseOperations {
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct UTXO {
-    pub recipient: String,
+    pub recipient: PaymentAddress,
     pub receipt: String,
     pub amount: TokenAmount,
     pub extra: String,
@@ -101,6 +102,59 @@ pub struct StateProof {
     pub proof_nodes : Option<String>
 }
 
+impl StateProof {
+    /**
+        The typed `multi_signature`. The field itself stays json so libindy
+        gets the exact value the nodes signed.
+    */
+    pub fn parse_multi_signature(&self) -> Result<MultiSignature, ErrorCode> {
+        let multi_signature = self.multi_signature.as_ref().ok_or_else(|| {
+            error!("The state proof has no multi_signature");
+            ErrorCode::CommonInvalidStructure
+        })?;
+
+        serde_json::from_value(multi_signature.clone())
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))
+    }
+}
+
+/**
+    The BLS multi signature of the nodes over the state of a ledger
+
+    # parameters
+    participants - the aliases of the nodes which signed
+    signature - the base58 BLS multi signature
+    value - what was signed
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct MultiSignature {
+    pub participants: Vec<String>,
+    pub signature: String,
+    pub value: MultiSignatureValue,
+}
+
+/**
+    The value signed by a [`MultiSignature`]
+
+    # parameters
+    ledger_id - the ledger the roots belong to
+    pool_state_root_hash - the root of the pool state trie
+    state_root_hash - the root of the state trie, the `root_hash` of the state proof
+    txn_root_hash - the root of the transaction log
+    timestamp - POSIX time of the nodes when they signed
+
+    [`MultiSignature`]: struct.MultiSignature.html
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct MultiSignatureValue {
+    pub ledger_id: u64,
+    pub pool_state_root_hash: String,
+    pub state_root_hash: String,
+    pub txn_root_hash: String,
+    pub timestamp: u64,
+}
+
 /**
  Variants of representation for items to verify against SP Trie
  Right now 2 options are specified:
@@ -167,6 +221,13 @@ pub fn extract_result_and_state_proof_from_node_reply(reply_from_node: *const c_
     let json_reply: SJsonValue = serde_json::from_str::<SJsonValue>(&reply)
         .or(Err(ErrorCode::CommonInvalidStructure))?;
 
+    extract_result_and_state_proof(&json_reply)
+}
+
+/**
+    Splits a parsed reply into its result and the state proof of the result.
+*/
+pub fn extract_result_and_state_proof(json_reply: &SJsonValue) -> Result<(SJsonValue, StateProof), ErrorCode> {
     let result: SJsonValue = match json_reply.get(RESULT) {
         Some(r) => r.clone(),
         None => return Err(ErrorCode::CommonInvalidStructure)
@@ -277,4 +338,24 @@ mod common_tests {
         assert_eq!(expected_result.get("identifier").unwrap(), result.get("identifier").unwrap());
         assert_eq!(expected_result.get("fees").unwrap(), result.get("fees").unwrap());
     }
+
+    #[test]
+    fn test_parse_multi_signature() {
+        let valid_json = r#"{ "op" : "REPLY", "result": {"reqId": 83955, "state_proof": {"proof_nodes": "29qFIGZlZXOT0pF7IjEiOjQsIjEwMDAxIjo4fQ==", "root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms", "multi_signature": {"participants": ["Gamma", "Delta", "Beta"], "value": {"timestamp": 1530059419, "state_root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms", "ledger_id": 2, "txn_root_hash": "AKboMiJZJm247Sa7GsKQo5Ba8ukgxTQ3DsLc2pyVuDkU", "pool_state_root_hash": "J3ATG63R2JKHDCdpKpQf81FTNyQg2Vgz7Pu1ZHZw6zNy"}, "signature": "Qk67ePVhxdjHivAf8H4Loy1hN5zfb1dq79VSJKYx485EAXmj44PASpp8gj2faysdN8CNzSoUVvXgd3U4P2CA7VkwD7FHKUuviAFJfRQ68FnpUS8hVuqn6PAuv9RGUobohcJnKJ8CVKxr5i3Zn2JNXbk7AqeYRZQ2egq8fdoP3woPW7"}}, "type": "20001"}}"#;
+        let reply: SJsonValue = serde_json::from_str(valid_json).unwrap();
+
+        let (_, mut state_proof) = extract_result_and_state_proof(&reply).unwrap();
+        let multi_signature = state_proof.parse_multi_signature().unwrap();
+
+        assert_eq!(vec!["Gamma", "Delta", "Beta"], multi_signature.participants);
+        assert_eq!(1530059419, multi_signature.value.timestamp);
+        assert_eq!(2, multi_signature.value.ledger_id);
+        assert_eq!(state_proof.root_hash, Some(multi_signature.value.state_root_hash));
+
+        state_proof.multi_signature = Some(json!({"participants": [], "signature": "", "value": {}}));
+        assert_eq!(ErrorCode::CommonInvalidStructure, state_proof.parse_multi_signature().unwrap_err());
+
+        state_proof.multi_signature = None;
+        assert_eq!(ErrorCode::CommonInvalidStructure, state_proof.parse_multi_signature().unwrap_err());
+    }
 }
\ No newline at end of file
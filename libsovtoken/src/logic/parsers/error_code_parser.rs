@@ -1,18 +1,244 @@
+//! Converts the reasons of rejected requests to error codes
+
+use std::cell::RefCell;
+
+use serde_json;
+
 use ErrorCode;
+use logic::parsers::common::ResponseOperations;
+use logic::type_aliases::ReqId;
+use utils::base58::FromBase58;
+use utils::current_error::{set_current_error, ErrorDetails};
 
 const INSUFFICIENT_FUNDS_ERROR: &str = "InsufficientFundsError";
 const EXTRA_FUNDS_ERROR: &str = "ExtraFundsError";
 const INVALID_FUNDS: &str = "InvalidFundsError";
 
+thread_local! {
+    static LAST_LEDGER_ERROR: RefCell<Option<LedgerErrorDetails>> = RefCell::new(None);
+}
+
+/**
+    What the ledger rejected a request for, classified by its reason.
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RejectionKind {
+    InsufficientFunds,
+    ExtraFunds,
+    InvalidFunds,
+    DoubleSpend,
+    InsufficientFees,
+    InvalidSignature,
+    UnknownIdentifier,
+    TaaMissing,
+    TaaMismatch,
+    Unknown,
+}
+
+impl RejectionKind {
+    pub fn from_reason(reason: &str) -> RejectionKind {
+        let lower = reason.to_lowercase();
+        let mentions = |words: &[&str]| words.iter().any(|word| lower.contains(word));
+
+        if mentions(&["author agreement", "taa acceptance", "taaacceptance", "taa_acceptance"]) {
+            if mentions(&["required", "missing", "not accepted", "no acceptance"]) {
+                RejectionKind::TaaMissing
+            } else {
+                RejectionKind::TaaMismatch
+            }
+        } else if mentions(&["alreadyspent", "already spent", "double spend", "double-spend"]) {
+            RejectionKind::DoubleSpend
+        } else if mentions(&["fees are not required"]) {
+            RejectionKind::ExtraFunds
+        } else if mentions(&["insufficientfees", "insufficient fees", "fees are required", "not enough fees"]) {
+            RejectionKind::InsufficientFees
+        } else if reason.contains(INSUFFICIENT_FUNDS_ERROR) {
+            RejectionKind::InsufficientFunds
+        } else if reason.contains(EXTRA_FUNDS_ERROR) {
+            RejectionKind::ExtraFunds
+        } else if reason.contains(INVALID_FUNDS) {
+            RejectionKind::InvalidFunds
+        } else if mentions(&["unknownidentifier", "unknown identifier", "cannot be found", "verkey not found"]) {
+            RejectionKind::UnknownIdentifier
+        } else if mentions(&["invalidsignature", "invalid signature", "valid signatures", "couldnotauthenticate", "could not authenticate"]) {
+            RejectionKind::InvalidSignature
+        } else {
+            RejectionKind::Unknown
+        }
+    }
+
+    pub fn error_code(self) -> ErrorCode {
+        match self {
+            RejectionKind::InsufficientFunds | RejectionKind::InsufficientFees => ErrorCode::PaymentInsufficientFundsError,
+            RejectionKind::ExtraFunds => ErrorCode::PaymentExtraFundsError,
+            RejectionKind::InvalidFunds | RejectionKind::DoubleSpend => ErrorCode::PaymentSourceDoesNotExistError,
+            RejectionKind::InvalidSignature | RejectionKind::UnknownIdentifier => ErrorCode::LedgerSecurityError,
+            RejectionKind::TaaMissing | RejectionKind::TaaMismatch => ErrorCode::LedgerInvalidTransaction,
+            RejectionKind::Unknown => ErrorCode::CommonInvalidStructure,
+        }
+    }
+}
+
+/**
+    Details of the last REJECT or REQNACK parsed on a thread
+
+    # parameters
+    op - REJECT or REQNACK
+    kind - what the request was rejected for
+    reason - the full reason given by the ledger
+    req_id - the request which was rejected
+    input - the source or address the reason names, if any
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerErrorDetails {
+    pub op: ResponseOperations,
+    pub kind: RejectionKind,
+    pub reason: Option<String>,
+    pub req_id: Option<ReqId>,
+    pub input: Option<String>,
+}
+
 pub fn parse_error_code_from_string(reason: &str) -> ErrorCode {
     error!("{}", reason);
-    if reason.contains(INSUFFICIENT_FUNDS_ERROR) {
-        ErrorCode::PaymentInsufficientFundsError
-    } else if reason.contains(EXTRA_FUNDS_ERROR) {
-        ErrorCode::PaymentExtraFundsError
-    } else if reason.contains(INVALID_FUNDS) {
-        ErrorCode::PaymentSourceDoesNotExistError
-    } else {
-        ErrorCode::CommonInvalidStructure
-    }
-}
\ No newline at end of file
+    RejectionKind::from_reason(reason).error_code()
+}
+
+/**
+    Converts a REJECT or REQNACK to its error code and keeps its details for
+    [`last_ledger_error`] and as the current error.
+
+    A rejection without reason is `CommonInvalidStructure`.
+
+    [`last_ledger_error`]: fn.last_ledger_error.html
+*/
+pub fn error_code_from_rejection(op: ResponseOperations, reason: Option<String>, req_id: Option<ReqId>) -> ErrorCode {
+    let kind = reason.as_ref().map_or(RejectionKind::Unknown, |reason| RejectionKind::from_reason(reason));
+    let input = reason.as_ref().and_then(|reason| offending_input(reason));
+    let error_code = if reason.is_some() { kind.error_code() } else { ErrorCode::CommonInvalidStructure };
+
+    let details = LedgerErrorDetails { op, kind, reason, req_id, input };
+    let mut current = ErrorDetails::new("The ledger rejected the request")
+        .cause(format!("{:?}", details.kind));
+    if let Some(ref reason) = details.reason {
+        current = current.cause(reason);
+    }
+    set_current_error(current.details(serde_json::to_value(&details).unwrap_or_default()));
+    LAST_LEDGER_ERROR.with(|last| *last.borrow_mut() = Some(details));
+
+    error_code
+}
+
+/**
+    The details of the last rejection parsed on this thread.
+*/
+pub fn last_ledger_error() -> Option<LedgerErrorDetails> {
+    LAST_LEDGER_ERROR.with(|last| last.borrow().clone())
+}
+
+/**
+    [`last_ledger_error`] as json.
+
+    [`last_ledger_error`]: fn.last_ledger_error.html
+*/
+pub fn last_ledger_error_json() -> Option<String> {
+    last_ledger_error().and_then(|details| serde_json::to_string(&details).ok())
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/*
+    The first source (`address:seqNo`, optionally qualified) or qualified
+    payment address in `reason`.
+*/
+fn offending_input(reason: &str) -> Option<String> {
+    let words: Vec<&str> = reason
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != ':')
+        .map(|word| word.trim_matches(':'))
+        .collect();
+
+    let source = words.iter().find(|word| match word.rsplitn(2, ':').collect::<Vec<&str>>()[..] {
+        [seq_no, address] => !seq_no.is_empty() && seq_no.chars().all(|c| c.is_ascii_digit()) && is_address(address),
+        _ => false,
+    });
+
+    source
+        .or_else(|| words.iter().find(|word| word.starts_with("pay:sov:") && is_address(word)))
+        .map(|word| word.to_string())
+}
+
+fn is_address(word: &str) -> bool {
+    let unqualified = word.rsplit(':').next().unwrap_or(word);
+    unqualified.len() >= 32 && unqualified.from_base58().is_ok()
+}
+
+#[cfg(test)]
+mod error_code_parser_tests {
+    use super::*;
+
+    #[test]
+    fn classify_funds_reasons() {
+        assert_eq!(ErrorCode::PaymentInsufficientFundsError, parse_error_code_from_string("InsufficientFundsError(10, 11)"));
+        assert_eq!(ErrorCode::PaymentExtraFundsError, parse_error_code_from_string("ExtraFundsError(11, 10)"));
+        assert_eq!(ErrorCode::PaymentSourceDoesNotExistError, parse_error_code_from_string("InvalidFundsError(...)"));
+        assert_eq!(ErrorCode::CommonInvalidStructure, parse_error_code_from_string("client request invalid: InvalidClientRequest()"));
+    }
+
+    #[test]
+    fn classify_plenum_reasons() {
+        let cases = [
+            ("client request invalid: UTXOAlreadySpentError('2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:3 is spent')", RejectionKind::DoubleSpend),
+            ("client request invalid: InvalidClientRequest('fees are required for this txn type',)", RejectionKind::InsufficientFees),
+            ("client request invalid: InvalidClientRequest('fees are not required for this txn type',)", RejectionKind::ExtraFunds),
+            ("client request invalid: InvalidSignature()", RejectionKind::InvalidSignature),
+            ("client request invalid: insufficient number of valid signatures, 1 is required but 0 valid found", RejectionKind::InvalidSignature),
+            ("client request invalid: CouldNotAuthenticate('Can not find verkey for 6ouriXMZkLeHsuXrN1X1fd',)", RejectionKind::InvalidSignature),
+            ("client request invalid: UnknownIdentifier('6ouriXMZkLeHsuXrN1X1fd',)", RejectionKind::UnknownIdentifier),
+            ("Txn Author Agreement acceptance is required for ledger with id 1001", RejectionKind::TaaMissing),
+            ("Txn Author Agreement acceptance digest does not match the latest one", RejectionKind::TaaMismatch),
+            ("the ledger is on fire", RejectionKind::Unknown),
+        ];
+
+        for &(reason, kind) in cases.iter() {
+            assert_eq!(kind, RejectionKind::from_reason(reason), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn rejection_details_are_kept() {
+        let reason = "client request invalid: UTXOAlreadySpentError('2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:3 is spent')";
+        let error_code = error_code_from_rejection(ResponseOperations::REJECT, Some(reason.to_string()), Some(1532));
+
+        assert_eq!(ErrorCode::PaymentSourceDoesNotExistError, error_code);
+        assert_eq!(Some(LedgerErrorDetails {
+            op: ResponseOperations::REJECT,
+            kind: RejectionKind::DoubleSpend,
+            reason: Some(reason.to_string()),
+            req_id: Some(1532),
+            input: Some("2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:3".to_string()),
+        }), last_ledger_error());
+
+        let details: serde_json::Value = serde_json::from_str(&last_ledger_error_json().unwrap()).unwrap();
+        assert_eq!(json!("DoubleSpend"), details["kind"]);
+        assert_eq!(json!(1532), details["reqId"]);
+    }
+
+    #[test]
+    fn rejection_without_reason() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, error_code_from_rejection(ResponseOperations::REQNACK, None, None));
+        assert_eq!(RejectionKind::Unknown, last_ledger_error().unwrap().kind);
+    }
+
+    #[test]
+    fn offending_input_of_reason() {
+        assert_eq!(Some("txo:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:3".to_string()),
+                   offending_input("InvalidFundsError('txo:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:3')"));
+        assert_eq!(Some("pay:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es".to_string()),
+                   offending_input("no outputs for pay:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es"));
+        assert_eq!(None, offending_input("InsufficientFundsError(10, 11) for 6ouriXMZkLeHsuXrN1X1fd:5"));
+    }
+}
@@ -2,6 +2,9 @@
 //! json for the API
 
 pub mod common;
+pub mod parse_get_auth_rule;
+pub mod parse_get_txn;
+pub mod parse_get_txn_author_agreement;
 pub mod parse_get_utxo_response;
 pub mod parse_get_txn_fees;
 pub mod parse_payment_response;
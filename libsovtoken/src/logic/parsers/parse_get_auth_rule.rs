@@ -0,0 +1,219 @@
+//! types used to parse the reply of a GET_AUTH_RULE request
+
+use ErrorCode;
+use logic::parsers::common::ResponseOperations;
+use logic::parsers::error_code_parser;
+use logic::type_aliases::{ProtocolVersion, ReqId};
+
+/**
+    Structure for parsing the reply of GET_AUTH_RULE
+
+    # parameters
+    op - the operation type received
+    protocol_version - the protocol version of the format of the transaction
+    result - the payload with the auth rules
+    reason - why the request was rejected
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseGetAuthRuleResponse {
+    pub op: ResponseOperations,
+    #[serde(rename = "protocol_version")]
+    pub protocol_version: Option<ProtocolVersion>,
+    pub result: Option<ParseGetAuthRuleResult>,
+    pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
+}
+
+/**
+    Structure of the result value within the GET_AUTH_RULE reply
+
+    # parameters
+    identifier - The DID this request was submitted from
+    req_id - Unique ID number of the request with transaction
+    txn_type - the type of transaction that was submitted
+    data - the auth rules
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseGetAuthRuleResult {
+    pub identifier: Option<String>,
+    pub req_id: Option<ReqId>,
+    #[serde(rename = "type")]
+    pub txn_type: String,
+    pub data: Vec<AuthRule>,
+}
+
+/**
+    An auth rule: who may do `auth_action` on `field` of transactions of type
+    `auth_type`. `*` in `field`, `old_value` or `new_value` matches any value.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuthRule {
+    pub auth_type: String,
+    pub auth_action: String,
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<String>,
+    #[serde(default)]
+    pub new_value: Option<String>,
+    pub constraint: Constraint,
+}
+
+/**
+    The constraint of an [`AuthRule`].
+
+    [`AuthRule`]: struct.AuthRule.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "constraint_id")]
+pub enum Constraint {
+    #[serde(rename = "ROLE")]
+    Role(RoleConstraint),
+    #[serde(rename = "AND")]
+    And(CombinationConstraint),
+    #[serde(rename = "OR")]
+    Or(CombinationConstraint),
+    #[serde(rename = "FORBIDDEN")]
+    Forbidden(ForbiddenConstraint),
+}
+
+/**
+    `sig_count` signatures of `role` are needed. The fee is set by the alias
+    in `metadata.fees`.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RoleConstraint {
+    pub role: Option<String>,
+    pub sig_count: u32,
+    #[serde(default)]
+    pub need_to_be_owner: bool,
+    #[serde(default)]
+    pub metadata: Option<ConstraintMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConstraintMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fees: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CombinationConstraint {
+    pub auth_constraints: Vec<Constraint>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ForbiddenConstraint {}
+
+/**
+    Gets the auth rules out of a GET_AUTH_RULE reply.
+
+    A rejected request is converted to its error code.
+*/
+pub fn from_response(base: ParseGetAuthRuleResponse) -> Result<Vec<AuthRule>, ErrorCode> {
+    match base.op {
+        ResponseOperations::REPLY => {
+            let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
+            Ok(result.data)
+        }
+        ResponseOperations::REQNACK | ResponseOperations::REJECT => {
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_get_auth_rule_tests {
+    use super::*;
+    use serde_json;
+    use utils::json_conversion::JsonDeserialize;
+
+    static GET_AUTH_RULE_RESPONSE_JSON: &'static str = r#"{
+        "op": "REPLY",
+        "result": {
+            "type": "121",
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "data": [
+                {
+                    "auth_type": "1",
+                    "auth_action": "ADD",
+                    "field": "role",
+                    "new_value": "2",
+                    "constraint": {
+                        "constraint_id": "OR",
+                        "auth_constraints": [
+                            {"constraint_id": "ROLE", "role": "0", "sig_count": 1, "need_to_be_owner": false, "metadata": {}},
+                            {"constraint_id": "ROLE", "role": "2", "sig_count": 1, "need_to_be_owner": false, "metadata": {"fees": "add_new_steward"}}
+                        ]
+                    }
+                },
+                {
+                    "auth_type": "100",
+                    "auth_action": "EDIT",
+                    "field": "*",
+                    "old_value": "*",
+                    "new_value": "*",
+                    "constraint": {"constraint_id": "FORBIDDEN"}
+                }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn parse_auth_rules() {
+        let response = ParseGetAuthRuleResponse::from_json(GET_AUTH_RULE_RESPONSE_JSON).unwrap();
+        let rules = from_response(response).unwrap();
+
+        assert_eq!(2, rules.len());
+        assert_eq!("1", rules[0].auth_type);
+        assert_eq!(Some("2".to_string()), rules[0].new_value);
+        assert_eq!(None, rules[0].old_value);
+        assert_eq!(Some("*".to_string()), rules[1].old_value);
+        assert_eq!(Constraint::Forbidden(ForbiddenConstraint {}), rules[1].constraint);
+
+        match rules[0].constraint {
+            Constraint::Or(ref or) => {
+                assert_eq!(2, or.auth_constraints.len());
+                assert_eq!(Constraint::Role(RoleConstraint {
+                    role: Some("2".to_string()),
+                    sig_count: 1,
+                    need_to_be_owner: false,
+                    metadata: Some(ConstraintMetadata { fees: Some("add_new_steward".to_string()) }),
+                }), or.auth_constraints[1]);
+            },
+            ref constraint => panic!("Expected an OR constraint, got {:?}", constraint),
+        }
+    }
+
+    #[test]
+    fn parse_rejected_auth_rules() {
+        let response: ParseGetAuthRuleResponse = serde_json::from_value(json!({
+            "op": "REQNACK",
+            "reason": "client request invalid: InvalidClientRequest()"
+        })).unwrap();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, from_response(response).unwrap_err());
+    }
+
+    #[test]
+    fn parse_auth_rules_with_unknown_constraint() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "121",
+                "data": [{
+                    "auth_type": "1",
+                    "auth_action": "ADD",
+                    "field": "role",
+                    "new_value": "2",
+                    "constraint": {"constraint_id": "XOR"}
+                }]
+            }
+        });
+
+        assert!(serde_json::from_value::<ParseGetAuthRuleResponse>(response).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! types used to parse the replies to GET_TXN requests of the token ledger
+
+use ErrorCode;
+use logic::history::{TokenTransaction, TokenTxnKind};
+use logic::input::Inputs;
+use logic::output::Outputs;
+use logic::parsers::common::ResponseOperations;
+use logic::parsers::error_code_parser;
+use logic::type_aliases::{ReqId, TokenAmount, TxnSeqNo};
+
+/**
+    for the reply to a [`GetTxnOperationRequest`]
+
+    [`GetTxnOperationRequest`]: ../../config/get_txn_config/struct.GetTxnOperationRequest.html
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseGetTxnResponse {
+    pub op: ResponseOperations,
+    pub result: Option<ParseGetTxnResponseResult>,
+    pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
+}
+
+/**
+    the nested type "result" in ParseGetTxnResponse, `data` is null when the
+    ledger has no transaction with the seq_no
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseGetTxnResponseResult {
+    pub seq_no: Option<TxnSeqNo>,
+    pub data: Option<LedgerTxn>,
+}
+
+/**
+    the nested type "data" in ParseGetTxnResponseResult
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerTxn {
+    pub txn: LedgerTxnBody,
+    pub txn_metadata: LedgerTxnMetadata,
+}
+
+/**
+    the nested type "txn" in LedgerTxn
+*/
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LedgerTxnBody {
+    #[serde(rename = "type")]
+    pub txn_type: String,
+    #[serde(default)]
+    pub data: LedgerTxnData,
+}
+
+/**
+    the nested type "data" in LedgerTxnBody, with the fields of a MINT_PUBLIC,
+    a XFER_PUBLIC or the fees of a request
+*/
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LedgerTxnData {
+    #[serde(default)]
+    pub inputs: Inputs,
+    #[serde(default)]
+    pub outputs: Outputs,
+    pub fees: Option<TokenAmount>,
+    #[serde(rename = "ref")]
+    pub reference: Option<String>,
+    pub extra: Option<String>,
+}
+
+/**
+    the nested type "txnMetadata" in LedgerTxn, genesis transactions have no
+    `txnTime`
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerTxnMetadata {
+    pub seq_no: TxnSeqNo,
+    pub txn_time: Option<u64>,
+}
+
+/**
+    Converts ParseGetTxnResponse to the TokenTransaction it holds.
+
+    Returns `None` when the ledger has no transaction with the requested
+    seq_no, or when the transaction doesn't move tokens.
+*/
+pub fn from_response(base: ParseGetTxnResponse) -> Result<Option<TokenTransaction>, ErrorCode> {
+    match base.op {
+        ResponseOperations::REPLY => {
+            let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
+            let ledger_txn = match result.data {
+                Some(ledger_txn) => ledger_txn,
+                None => return Ok(None),
+            };
+
+            let kind = match TokenTxnKind::from_txn_type(&ledger_txn.txn.txn_type) {
+                Some(kind) => kind,
+                None => {
+                    debug!("Transaction {} of type {} doesn't move tokens", ledger_txn.txn_metadata.seq_no, ledger_txn.txn.txn_type);
+                    return Ok(None);
+                }
+            };
+
+            let data = ledger_txn.txn.data;
+            Ok(Some(TokenTransaction {
+                seq_no: ledger_txn.txn_metadata.seq_no,
+                txn_time: ledger_txn.txn_metadata.txn_time,
+                kind,
+                inputs: data.inputs,
+                outputs: data.outputs,
+                fees: data.fees,
+                reference: data.reference,
+                extra: data.extra,
+            }))
+        }
+        ResponseOperations::REQNACK | ResponseOperations::REJECT => {
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_get_txn_tests {
+    use super::*;
+    use logic::address::PaymentAddress;
+    use logic::input::Input;
+    use utils::json_conversion::JsonDeserialize;
+
+    fn reply(txn_type: &str, data: &str) -> String {
+        format!(r#"{{
+            "op": "REPLY",
+            "result": {{
+                "identifier": "Th7MpTaRZVRYnPiabds81Y",
+                "reqId": 1532360723205721420,
+                "seqNo": 4,
+                "type": "3",
+                "data": {{
+                    "auditPath": [],
+                    "reqSignature": {{}},
+                    "rootHash": "BHWhyWrYLyYREHeadHZGSvCp9pbxTE5jE9jM2cV3RM2z",
+                    "txn": {{
+                        "data": {},
+                        "metadata": {{
+                            "digest": "a922288dc7b7ffa5a4dc93050d80a8134c70d68e8654600b0237e80210349d80",
+                            "reqId": 3787223578
+                        }},
+                        "protocolVersion": 2,
+                        "type": "{}"
+                    }},
+                    "txnMetadata": {{"seqNo": 4, "txnTime": 1532341475}},
+                    "ver": "1"
+                }}
+            }}
+        }}"#, data, txn_type)
+    }
+
+    fn parse(response: &str) -> Result<Option<TokenTransaction>, ErrorCode> {
+        from_response(ParseGetTxnResponse::from_json(response).unwrap())
+    }
+
+    #[test]
+    fn parse_xfer() {
+        let txn = parse(&reply("10001", r#"{
+            "inputs": [{"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "seqNo": 2}],
+            "outputs": [{"address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", "amount": 9}],
+            "extra": "for the coffee"
+        }"#)).unwrap().unwrap();
+
+        assert_eq!(TokenTxnKind::Xfer, txn.kind);
+        assert_eq!((4, Some(1532341475)), (txn.seq_no, txn.txn_time));
+        assert_eq!(vec![Input::new(PaymentAddress::new("2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es").unwrap(), 2)], txn.inputs);
+        assert_eq!(9, txn.outputs[0].amount);
+        assert_eq!(Some(String::from("for the coffee")), txn.extra);
+    }
+
+    #[test]
+    fn parse_mint_and_fees() {
+        let mint = parse(&reply("10000", r#"{"outputs": [{"address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", "amount": 10}]}"#))
+            .unwrap().unwrap();
+        assert_eq!((TokenTxnKind::Mint, 0, 1), (mint.kind, mint.inputs.len(), mint.outputs.len()));
+
+        let fees = parse(&reply("10101", r#"{
+            "inputs": [{"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "seqNo": 2}],
+            "outputs": [{"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "amount": 9}],
+            "fees": 4,
+            "ref": "1:13"
+        }"#)).unwrap().unwrap();
+        assert_eq!((TokenTxnKind::Fees, Some(4), Some(String::from("1:13"))), (fees.kind, fees.fees, fees.reference));
+    }
+
+    #[test]
+    fn parse_missing_or_other_txn() {
+        let missing = r#"{"op": "REPLY", "result": {"identifier": "Th7MpTaRZVRYnPiabds81Y", "reqId": 1, "seqNo": null, "type": "3", "data": null}}"#;
+        assert_eq!(None, parse(missing).unwrap());
+
+        assert_eq!(None, parse(&reply("1", r#"{"dest": "8Wv7NMbsMiNSmNa3iC6fG7"}"#)).unwrap());
+    }
+
+    #[test]
+    fn parse_rejected() {
+        let rejected = r#"{"op": "REQNACK", "reqId": 1, "reason": "client request invalid: InvalidClientRequest()"}"#;
+        assert_eq!(ErrorCode::CommonInvalidStructure, parse(rejected).unwrap_err());
+    }
+}
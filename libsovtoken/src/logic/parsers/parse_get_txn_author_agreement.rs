@@ -0,0 +1,146 @@
+//! types used to parse the replies to GET_TXN_AUTHR_AGRMT and GET_TXN_AUTHR_AGRMT_AML requests
+
+use serde::de::DeserializeOwned;
+use serde_json;
+
+use ErrorCode;
+use logic::parsers::common::ResponseOperations;
+use logic::parsers::error_code_parser;
+use logic::type_aliases::ReqId;
+use utils::txn_author_agreement::{AcceptanceMechanisms, TxnAuthorAgreement};
+
+/**
+    for the replies to GET_TXN_AUTHR_AGRMT and GET_TXN_AUTHR_AGRMT_AML requests,
+    with `T` the type of the `data` of their result
+*/
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseGetTaaResponse<T> {
+    pub op: ResponseOperations,
+    pub result: Option<ParseGetTaaResponseResult<T>>,
+    pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
+}
+
+/**
+    the nested type "result" in ParseGetTaaResponse, `data` is null when the
+    ledger has no agreement or acceptance mechanisms
+*/
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ParseGetTaaResponseResult<T> {
+    pub data: Option<T>,
+}
+
+/**
+    Parses the agreement out of a GET_TXN_AUTHR_AGRMT reply, `None` when the
+    ledger has none.
+
+    Errors with `CommonInvalidStructure` when the digest of the agreement
+    doesn't match its text and version.
+*/
+pub fn parse_get_txn_author_agreement_response(response_json: &str) -> Result<Option<TxnAuthorAgreement>, ErrorCode> {
+    let agreement: Option<TxnAuthorAgreement> = from_response_json(response_json)?;
+    if let Some(ref agreement) = agreement {
+        agreement.digest()?;
+    }
+    Ok(agreement)
+}
+
+/**
+    Parses the acceptance mechanisms out of a GET_TXN_AUTHR_AGRMT_AML reply,
+    `None` when the ledger has none.
+*/
+pub fn parse_get_acceptance_mechanisms_response(response_json: &str) -> Result<Option<AcceptanceMechanisms>, ErrorCode> {
+    from_response_json(response_json)
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn from_response_json<T: DeserializeOwned>(response_json: &str) -> Result<Option<T>, ErrorCode> {
+    let base: ParseGetTaaResponse<T> = serde_json::from_str(response_json)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+
+    match base.op {
+        ResponseOperations::REPLY => {
+            let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
+            Ok(result.data)
+        }
+        ResponseOperations::REQNACK | ResponseOperations::REJECT => {
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
+        }
+    }
+}
+
+#[cfg(test)]
+mod parse_get_txn_author_agreement_tests {
+    use super::*;
+
+    #[test]
+    fn parse_agreement() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "6",
+                "identifier": "L5AD5g65TDQr1PPHHRoiGf",
+                "reqId": 1574140837389745000u64,
+                "seqNo": 2,
+                "txnTime": 1574140838,
+                "data": {
+                    "text": "some agreement text",
+                    "version": "1.0.0",
+                    "digest": "050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e",
+                    "ratification_ts": 1574140800
+                }
+            }
+        }).to_string();
+
+        let agreement = parse_get_txn_author_agreement_response(&response).unwrap().unwrap();
+        assert_eq!(Some(1574140800), agreement.ratification_ts);
+        assert_eq!(None, agreement.retirement_ts);
+        assert_eq!("050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e", agreement.digest().unwrap());
+    }
+
+    #[test]
+    fn parse_agreement_with_wrong_digest() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {"type": "6", "data": {"text": "other agreement text", "version": "1.0.0", "digest": "050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e"}}
+        }).to_string();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, parse_get_txn_author_agreement_response(&response).unwrap_err());
+    }
+
+    #[test]
+    fn parse_no_agreement() {
+        let response = r#"{"op": "REPLY", "result": {"type": "6", "data": null}}"#;
+        assert_eq!(None, parse_get_txn_author_agreement_response(response).unwrap());
+
+        let rejected = r#"{"op": "REQNACK", "reqId": 1, "reason": "client request invalid"}"#;
+        assert_eq!(ErrorCode::CommonInvalidStructure, parse_get_txn_author_agreement_response(rejected).unwrap_err());
+    }
+
+    #[test]
+    fn parse_acceptance_mechanisms() {
+        let response = json!({
+            "op": "REPLY",
+            "result": {
+                "type": "7",
+                "data": {
+                    "aml": {"at_submission": "The agreement was reviewed by the user and accepted at the time of submission"},
+                    "version": "1.0",
+                    "amlContext": "http://aml-context-descr"
+                }
+            }
+        }).to_string();
+
+        let mechanisms = parse_get_acceptance_mechanisms_response(&response).unwrap().unwrap();
+        assert!(mechanisms.aml.contains_key("at_submission"));
+        assert_eq!(Some(String::from("http://aml-context-descr")), mechanisms.aml_context);
+    }
+}
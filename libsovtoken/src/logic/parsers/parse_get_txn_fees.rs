@@ -3,16 +3,19 @@ use std::collections::HashMap;
 
 use base64;
 use serde_json;
-use serde_json::Error;
+use serde_json::Value as SJsonValue;
 use ErrorCode;
 use libc::c_char;
 
 use logic::parsers::common::{ResponseOperations, StateProof,
-                             extract_result_and_state_proof_from_node_reply,
+                             extract_result_and_state_proof, extract_result_and_state_proof_from_node_reply,
                              KeyValuesInSP, KeyValueSimpleData, ParsedSP};
+use utils::current_error::invalid_reply;
 use utils::json_conversion::JsonDeserialize;
 use utils::ffi_support::c_pointer_from_string;
 use utils::constants::txn_fields::FEES;
+use logic::parsers::error_code_parser;
+use logic::state_proof;
 use logic::type_aliases::{ProtocolVersion, TokenAmount, ReqId};
 
 /**
@@ -22,6 +25,8 @@ use logic::type_aliases::{ProtocolVersion, TokenAmount, ReqId};
     op - the operation type received
     protocol_version - the protocol version of the format of the transaction
     result - the payload containing data relevant to the GET_FEES transaction
+    reason - why the request was rejected
+    req_id - the rejected request
 */
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -29,7 +34,10 @@ use logic::type_aliases::{ProtocolVersion, TokenAmount, ReqId};
 pub struct ParseGetTxnFeesResponse {
     pub op : ResponseOperations,
     pub protocol_version: Option<ProtocolVersion>,
-    pub result : ParseGetTxnFeesResult
+    pub result : Option<ParseGetTxnFeesResult>,
+    pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
 }
 
 /**
@@ -56,30 +64,52 @@ pub struct ParseGetTxnFeesResult {
     pub state_proof : Option<StateProof>
 }
 
-pub fn parse_fees_from_get_txn_fees_response(response : String) -> Result<String, Error> {
+pub fn parse_fees_from_get_txn_fees_response(response : String) -> Result<String, ErrorCode> {
     trace!("logic::parsers::parse_fees_from_get_txn_fees_response >> response: {:?}", response);
-    let fees_response : ParseGetTxnFeesResponse =
-            ParseGetTxnFeesResponse::from_json(&response).map_err(map_err_err!())?;
-    let res = serde_json::to_string(&fees_response.result.fees).map_err(map_err_err!());
+    let fees = fees_from_get_txn_fees_response(&response)?;
+    let res = serde_json::to_string(&fees).map_err(map_err_err!()).or(Err(ErrorCode::CommonInvalidState));
     trace!("logic::parsers::parse_fees_from_get_txn_fees_response << result: {:?}", res);
     return res;
 }
 
+/**
+    Gets the map of fee aliases to amounts out of a GET_FEES reply.
+
+    A rejected request is converted to its error code.
+*/
+pub fn fees_from_get_txn_fees_response(response : &str) -> Result<HashMap<String, TokenAmount>, ErrorCode> {
+    let fees_response : ParseGetTxnFeesResponse = ParseGetTxnFeesResponse::from_json(response)
+        .map_err(invalid_reply)?;
+    from_response(fees_response)
+}
+
+/**
+    Gets the fees out of a GET_FEES reply.
+
+    A rejected request is converted to its error code.
+*/
+pub fn from_response(base: ParseGetTxnFeesResponse) -> Result<HashMap<String, TokenAmount>, ErrorCode> {
+    match base.op {
+        ResponseOperations::REPLY => {
+            let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
+            Ok(result.fees)
+        }
+        ResponseOperations::REQNACK | ResponseOperations::REJECT => {
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
+        }
+    }
+}
+
 pub fn get_fees_state_proof_extractor(reply_from_node: *const c_char, parsed_sp: *mut *const c_char) -> ErrorCode {
     // TODO: The following errors should have logs
     let (result, state_proof) = match extract_result_and_state_proof_from_node_reply(reply_from_node) {
         Ok((r, s)) => (r, s),
         Err(_) => return ErrorCode::CommonInvalidStructure
     };
-    let fees = match result.get(FEES) {
-        Some(f) => f.to_owned(),
-        None => return ErrorCode::CommonInvalidStructure
+    let kvs_to_verify = match get_fees_state_key_values(&result) {
+        Ok(kvs) => KeyValuesInSP::Simple(kvs),
+        Err(err) => return err
     };
-
-    // TODO: Make sure JSON serialisation preserves order
-    let kvs_to_verify = KeyValuesInSP::Simple(KeyValueSimpleData {
-        kvs: vec![(base64::encode(FEES), Some(fees.to_string()))]
-    });
     let proof_nodes = match state_proof.proof_nodes {
         Some(o) => o,
         None => return ErrorCode::CommonInvalidStructure
@@ -110,12 +140,42 @@ pub fn get_fees_state_proof_extractor(reply_from_node: *const c_char, parsed_sp:
     }
 }
 
+/**
+    The keys and values the state proof of a GET_FEES result proves: the
+    json of the fees.
+*/
+pub fn get_fees_state_key_values(result: &SJsonValue) -> Result<KeyValueSimpleData, ErrorCode> {
+    let fees = result.get(FEES).ok_or(ErrorCode::CommonInvalidStructure)?;
+
+    // TODO: Make sure JSON serialisation preserves order
+    Ok(KeyValueSimpleData {
+        kvs: vec![(base64::encode(FEES), Some(fees.to_string()))]
+    })
+}
+
+/**
+    Checks the fees of a GET_FEES reply against its state proof, without
+    libindy. Only a `REPLY` has a state proof.
+
+    Errors with `CommonInvalidStructure` when the state proof is missing or
+    doesn't verify.
+*/
+pub fn verify_get_fees_state_proof(reply: &SJsonValue) -> Result<(), ErrorCode> {
+    if reply["op"] != "REPLY" {
+        return Ok(());
+    }
+
+    let (result, proof) = extract_result_and_state_proof(reply)?;
+    state_proof::verify_state_proof(&proof, &get_fees_state_key_values(&result)?)
+}
+
 #[cfg(test)]
 mod parse_fees_responses_test {
     use base64;
-    use super::{parse_fees_from_get_txn_fees_response, get_fees_state_proof_extractor,
+    use super::{parse_fees_from_get_txn_fees_response, fees_from_get_txn_fees_response, get_fees_state_proof_extractor,
                 ErrorCode, ParsedSP, KeyValuesInSP, KeyValueSimpleData};
-    use serde_json::{Value, Error};
+    use logic::parsers::error_code_parser::{last_ledger_error, RejectionKind};
+    use serde_json::Value;
     use serde_json;
     use std::ffi::CString;
     use utils::ffi_support::string_from_char_ptr;
@@ -174,13 +234,23 @@ mod parse_fees_responses_test {
 
 
         //convert to Error
-        let invalid_fees_json : Result<String, Error> = parse_fees_from_get_txn_fees_response(
+        let invalid_fees_json : Result<String, ErrorCode> = parse_fees_from_get_txn_fees_response(
             invalid_json_response.to_string());
 
         let json_error_bool: bool = invalid_fees_json.is_err();
         assert!(json_error_bool);
     }
 
+    #[test]
+    fn rejected_get_fees_response() {
+        let rejected = r#"{"op": "REQNACK", "reqId": 47660, "identifier": "6ouriXMZkLeHsuXrN1X1fd", "reason": "client request invalid: InvalidSignature()"}"#;
+
+        assert_eq!(ErrorCode::LedgerSecurityError, fees_from_get_txn_fees_response(rejected).unwrap_err());
+        let details = last_ledger_error().unwrap();
+        assert_eq!(RejectionKind::InvalidSignature, details.kind);
+        assert_eq!(Some(47660), details.req_id);
+    }
+
     #[test]
     fn test_reply_without_fees() {
         let invalid_json = r#"{ "op" : "REPLY", "result": {"reqId": 83955, "state_proof": {"proof_nodes": "29qFIGZlZXOT0pF7IjEiOjQsIjEwMDAxIjo4fQ==", "root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms", "multi_signature": {"participants": ["Gamma", "Delta", "Beta"], "value": {"timestamp": 1530059419, "state_root_hash": "5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms", "ledger_id": 2, "txn_root_hash": "AKboMiJZJm247Sa7GsKQo5Ba8ukgxTQ3DsLc2pyVuDkU", "pool_state_root_hash": "J3ATG63R2JKHDCdpKpQf81FTNyQg2Vgz7Pu1ZHZw6zNy"}, "signature": "Qk67ePVhxdjHivAf8H4Loy1hN5zfb1dq79VSJKYx485EAXmj44PASpp8gj2faysdN8CNzSoUVvXgd3U4P2CA7VkwD7FHKUuviAFJfRQ68FnpUS8hVuqn6PAuv9RGUobohcJnKJ8CVKxr5i3Zn2JNXbk7AqeYRZQ2egq8fdoP3woPW7"}}, "type": "20001", "identifier": "6ouriXMZkLeHsuXrN1X1fd"}}"#;
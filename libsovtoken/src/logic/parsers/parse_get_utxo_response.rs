@@ -1,14 +1,19 @@
 //! types used for parse_get_utxo_response_handler
 
 use base64;
+use std::collections::HashSet;
 use ErrorCode;
 use libc::c_char;
 use logic::parsers::common::{ResponseOperations, TXO, StateProof, ParsedSP, KeyValuesInSP,
-                             KeyValueSimpleData, extract_result_and_state_proof_from_node_reply};
+                             KeyValueSimpleData, extract_result_and_state_proof,
+                             extract_result_and_state_proof_from_node_reply};
 use logic::parsers::error_code_parser;
 use logic::type_aliases::{TokenAmount, TxnSeqNo, ProtocolVersion, ReqId};
 use logic::address;
+use logic::address::PaymentAddress;
+use logic::state_proof;
 use serde_json;
+use serde_json::Value as SJsonValue;
 use utils::constants::txn_fields::OUTPUTS;
 use utils::ffi_support::c_pointer_from_string;
 
@@ -17,7 +22,7 @@ type UTXOs = Vec<UTXOInner>;
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct UTXOInner {
-    pub address: String,
+    pub address: PaymentAddress,
     pub seq_no: TxnSeqNo,
     pub amount: TokenAmount
 }
@@ -33,6 +38,8 @@ pub struct ParseGetUtxoResponse {
     pub protocol_version: Option<ProtocolVersion>,
     pub result : Option<ParseGetUtxoResponseResult>,
     pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
 }
 
 /**
@@ -48,6 +55,8 @@ pub struct ParseGetUtxoResponseResult {
     pub identifier: String,
     pub req_id : ReqId,
     pub outputs : UTXOs,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<TxnSeqNo>,
     #[serde(rename = "state_proof", skip_serializing_if = "Option::is_none")]
     pub state_proof : Option<StateProof>
 }
@@ -59,7 +68,7 @@ pub struct ParseGetUtxoResponseResult {
 
     used by [`ParseGetUtxoReply`]
 */
-#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct UTXO {
     pub payment_address: String,
@@ -80,6 +89,18 @@ pub type ParseGetUtxoReply = Vec<UTXO>;
     after this call
 */
 pub fn from_response(base : ParseGetUtxoResponse) -> Result<ParseGetUtxoReply, ErrorCode> {
+    from_response_with_next(base).map(|(utxos, _)| utxos)
+}
+
+/**
+    Same as [`from_response`], but also returns the `next` cursor of a paged reply.
+
+    `next` is the seq_no to pass as `from` to get the next page. It is `None`
+    when the reply holds the last page.
+
+    [`from_response`]: fn.from_response.html
+*/
+pub fn from_response_with_next(base : ParseGetUtxoResponse) -> Result<(ParseGetUtxoReply, Option<TxnSeqNo>), ErrorCode> {
     match base.op {
         ResponseOperations::REPLY => {
             let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
@@ -94,13 +115,49 @@ pub fn from_response(base : ParseGetUtxoResponse) -> Result<ParseGetUtxoReply, E
                 utxos.push(utxo);
             }
 
-            Ok(utxos)
+            Ok((utxos, result.next))
         }
         ResponseOperations::REQNACK | ResponseOperations::REJECT => {
-            let reason = base.reason.ok_or(ErrorCode::CommonInvalidStructure)?;
-            Err(error_code_parser::parse_error_code_from_string(&reason))
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
+        }
+    }
+}
+
+/**
+    Merges the pages of a paged GET_UTXO into one ParseGetUtxoReply.
+
+    Keep requesting pages with the `next` cursor of the previous page until
+    it is `None`, then pass all replies here. Every page has to be for the
+    same address, otherwise `CommonInvalidStructure` is returned. A source
+    which is on more than one page is only listed once.
+*/
+pub fn merge_responses(responses: Vec<ParseGetUtxoResponse>) -> Result<ParseGetUtxoReply, ErrorCode> {
+    let mut address: Option<String> = None;
+    let mut sources: HashSet<String> = HashSet::new();
+    let mut utxos: ParseGetUtxoReply = Vec::new();
+
+    for response in responses {
+        if let Some(ref result) = response.result {
+            let page_address = address::strip_qualifier_from_address(&result.address);
+            match address {
+                Some(ref address) if *address != page_address => {
+                    error!("Can't merge GET_UTXO replies for {:?} and {:?}", secret!(address), secret!(&page_address));
+                    return Err(ErrorCode::CommonInvalidStructure);
+                }
+                Some(_) => {}
+                None => address = Some(page_address),
+            }
+        }
+
+        let (page, _) = from_response_with_next(response)?;
+        for utxo in page {
+            if sources.insert(utxo.source.clone()) {
+                utxos.push(utxo);
+            }
         }
     }
+
+    Ok(utxos)
 }
 
 // Assumes a valid address. The delimeter `:` has to be the same as used on ledger
@@ -115,29 +172,10 @@ pub fn get_utxo_state_proof_extractor(reply_from_node: *const c_char, parsed_sp:
         Err(_) => return ErrorCode::CommonInvalidStructure
     };
 
-    // TODO: No validation of outputs being done. This has to fixed by creating an `Address` with
-    // a single private field called `address` and with implementation defining `new` and a getter.
-    // The `new` method will do the validation.
-
-    let outputs: UTXOs = match result.get(OUTPUTS) {
-        Some(outs) => {
-            let outputs: UTXOs = match serde_json::from_value(outs.to_owned()) {
-                Ok(o) => o,
-                Err(_) => return ErrorCode::CommonInvalidStructure
-            };
-            outputs
-        },
-        None => return ErrorCode::CommonInvalidStructure
+    let kvs_to_verify = match get_utxo_state_key_values(&result) {
+        Ok(kvs) => KeyValuesInSP::Simple(kvs),
+        Err(err) => return err
     };
-
-    let mut kvs: Vec<(String, Option<String>)> = Vec::new();
-
-    for output in outputs {
-        kvs.push((get_utxo_state_key(&output.address, output.seq_no),
-                  Some(output.amount.to_string())));
-    }
-
-    let kvs_to_verify = KeyValuesInSP::Simple(KeyValueSimpleData { kvs });
     let proof_nodes = match state_proof.proof_nodes {
         Some(o) => o,
         None => return ErrorCode::CommonInvalidStructure
@@ -168,11 +206,45 @@ pub fn get_utxo_state_proof_extractor(reply_from_node: *const c_char, parsed_sp:
     }
 }
 
+/**
+    The keys and values the state proof of a GET_UTXO result proves: the
+    amount of each of its outputs.
+*/
+pub fn get_utxo_state_key_values(result: &SJsonValue) -> Result<KeyValueSimpleData, ErrorCode> {
+    let outputs: UTXOs = match result.get(OUTPUTS) {
+        Some(outs) => serde_json::from_value(outs.to_owned()).map_err(|_| ErrorCode::CommonInvalidStructure)?,
+        None => return Err(ErrorCode::CommonInvalidStructure)
+    };
+
+    let kvs = outputs.into_iter()
+        .map(|output| (get_utxo_state_key(output.address.unqualified(), output.seq_no), Some(output.amount.to_string())))
+        .collect();
+
+    Ok(KeyValueSimpleData { kvs })
+}
+
+/**
+    Checks the outputs of a GET_UTXO reply against its state proof, without
+    libindy. Only a `REPLY` has a state proof.
+
+    Errors with `CommonInvalidStructure` when the state proof is missing or
+    doesn't verify.
+*/
+pub fn verify_get_utxo_state_proof(reply: &SJsonValue) -> Result<(), ErrorCode> {
+    if reply["op"] != "REPLY" {
+        return Ok(());
+    }
+
+    let (result, proof) = extract_result_and_state_proof(reply)?;
+    state_proof::verify_state_proof(&proof, &get_utxo_state_key_values(&result)?)
+}
+
 #[cfg(test)]
 mod parse_get_utxo_responses_tests {
     use super::*;
     use std::ffi::CString;
 
+    use logic::address::address_tests::gen_random_base58_address;
     use logic::parsers::common::{ResponseOperations, StateProof};
     use utils::base58::IntoBase58;
     use utils::json_conversion::{JsonDeserialize, JsonSerialize};
@@ -241,12 +313,12 @@ mod parse_get_utxo_responses_tests {
         };
 
         outputs.push(UTXOInner {
-            address: rand_string(32),
+            address: PaymentAddress::new(&gen_random_base58_address()).unwrap(),
             seq_no: 1,
             amount: 10
         });
         outputs.push(UTXOInner {
-            address: rand_string(32),
+            address: PaymentAddress::new(&gen_random_base58_address()).unwrap(),
             seq_no: 2,
             amount: 20
         });
@@ -259,6 +331,7 @@ mod parse_get_utxo_responses_tests {
             identifier,
             req_id: 123457890,
             outputs,
+            next: None,
             state_proof: Some(state_proof)
         };
 
@@ -267,6 +340,7 @@ mod parse_get_utxo_responses_tests {
             protocol_version: Some(1),
             result: Some(result),
             reason: None,
+            req_id: None,
         };
 
         let reply: ParseGetUtxoReply = from_response(response).unwrap();
@@ -307,6 +381,7 @@ mod parse_get_utxo_responses_tests {
             identifier,
             req_id: 123457890,
             outputs,
+            next: None,
             state_proof: Some(state_proof)
         };
 
@@ -315,6 +390,7 @@ mod parse_get_utxo_responses_tests {
             protocol_version: Some(1),
             result: Some(result),
             reason: None,
+            req_id: None,
         };
 
         let reply: ParseGetUtxoReply = from_response(response).unwrap();
@@ -342,6 +418,85 @@ mod parse_get_utxo_responses_tests {
         assert!(reply_json.is_ok());
     }
 
+    fn page(address: &str, seq_nos: &[TxnSeqNo], next: Option<TxnSeqNo>) -> ParseGetUtxoResponse {
+        let outputs: Vec<serde_json::Value> = seq_nos.iter().map(|seq_no| json!([address, seq_no, 10])).collect();
+        let mut result = json!({
+            "type": "10002",
+            "address": address,
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "outputs": outputs
+        });
+        if let Some(next) = next {
+            result["next"] = json!(next);
+        }
+        serde_json::from_value(json!({"op": "REPLY", "protocol_version": 2, "result": result})).unwrap()
+    }
+
+    fn seq_nos(utxos: &[UTXO]) -> Vec<TxnSeqNo> {
+        utxos.iter().map(|utxo| TXO::from_libindy_string(&utxo.source).unwrap().seq_no).collect()
+    }
+
+    #[test]
+    fn success_parse_get_utxo_reply_with_next() {
+        let address = "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q";
+
+        let (utxos, next) = from_response_with_next(page(address, &[1, 2], Some(3))).unwrap();
+        assert_eq!(vec![1, 2], seq_nos(&utxos));
+        assert_eq!(Some(3), next);
+
+        let (_, next) = from_response_with_next(page(address, &[3], None)).unwrap();
+        assert_eq!(None, next);
+    }
+
+    #[test]
+    fn success_parse_get_utxo_response_without_next_from_json() {
+        let response: ParseGetUtxoResponse = ParseGetUtxoResponse::from_json(PARSE_GET_UTXO_RESPONSE_JSON).unwrap();
+        let (utxos, next) = from_response_with_next(response).unwrap();
+        assert_eq!(1, utxos.len());
+        assert_eq!(None, next);
+    }
+
+    #[test]
+    fn merge_pages() {
+        let address = "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q";
+        let pages = vec![
+            page(address, &[1, 2], Some(2)),
+            page(address, &[2, 3], Some(4)),
+            page(address, &[4], None),
+        ];
+
+        let utxos = merge_responses(pages).unwrap();
+        assert_eq!(vec![1, 2, 3, 4], seq_nos(&utxos));
+        assert!(utxos.iter().all(|utxo| utxo.payment_address == format!("pay:sov:{}", address)));
+    }
+
+    #[test]
+    fn merge_pages_of_different_addresses() {
+        let pages = vec![
+            page("dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", &[1], Some(2)),
+            page("2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", &[2], None),
+        ];
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, merge_responses(pages).unwrap_err());
+    }
+
+    #[test]
+    fn merge_pages_with_rejected_page() {
+        let address = "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q";
+        let rejected: ParseGetUtxoResponse = serde_json::from_value(json!({
+            "op": "REJECT",
+            "reason": "client request invalid: InvalidClientRequest()"
+        })).unwrap();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, merge_responses(vec![page(address, &[1], Some(2)), rejected]).unwrap_err());
+    }
+
+    #[test]
+    fn merge_no_pages() {
+        assert!(merge_responses(Vec::new()).unwrap().is_empty());
+    }
+
     #[test]
     fn test_utxo_state_key() {
         let address = "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q";
@@ -391,6 +546,36 @@ mod parse_get_utxo_responses_tests {
         assert_eq!(return_error, ErrorCode::CommonInvalidStructure);
     }
 
+    #[test]
+    fn test_parse_state_proof_invalid_output_address() {
+        let invalid_json = r#"{
+            "op": "REPLY",
+            "protocol_version": 1,
+            "result":
+                {
+                    "type": "10002",
+                    "address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es",
+                    "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+                    "reqId": 15424,
+                    "outputs": [["2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7", 4, 1]],
+                    "state_proof":
+                    {
+                        "root_hash": "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1",
+                        "proof_nodes": "+QHF4hOgCBgvwaPO",
+                        "multi_signature": {}
+                    }
+                }
+        }"#;
+
+        let json_str = CString::new(invalid_json).unwrap();
+        let json_str_ptr = json_str.as_ptr();
+
+        let mut new_str_ptr = ::std::ptr::null();
+
+        let return_error = get_utxo_state_proof_extractor(json_str_ptr, &mut new_str_ptr);
+        assert_eq!(return_error, ErrorCode::CommonInvalidStructure);
+    }
+
     #[test]
     fn test_parse_state_proof_success() {
         let valid_json = r#"{
@@ -580,4 +765,43 @@ mod parse_get_utxo_responses_tests {
         assert_eq!(parsed_sp[0].kvs_to_verify, expected_parsed_sp[0].kvs_to_verify);
         assert_eq!(parsed_sp[0].multi_signature, expected_parsed_sp[0].multi_signature);
     }
-}
\ No newline at end of file
+
+    fn verified_reply(amount: TokenAmount) -> SJsonValue {
+        json!({
+            "op": "REPLY",
+            "result": {
+                "type": "10002",
+                "address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es",
+                "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+                "reqId": 15424,
+                "outputs": [
+                    ["2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", 4, 1],
+                    ["2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", 16, amount]
+                ],
+                "state_proof": {
+                    "root_hash": "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1",
+                    "proof_nodes": "+QHF4hOgCBgvwaPO/KIJjOyzhA9dx8yXqPgqKY9sqKPIAZgHujTsgICA2cQggsExxCCCwTGAgICAgICAgICAgICAgICAgICAgICAgICAgICCwTH4VrQAJqUzRQSFdRSktjYXdSeGRXNkdWc2puWkJhMWVjR2RDc3NuN0toV1lKWkdUWGdMN0VzOjoEREJ+KujHB//IMaixsQMlj9+4DLVQHzu4WJczS7X8ED+G2AoMk4QTH20sQPm8C23HQjM7dFR6HIi99DdtySfD9VnTGsoDyHJeCRAIf9srqEpWYrQ1nq9jBE67eMCBK+ewpvMu2UxCCCwTHEIILBMcQggsExxCCCwTHEIILBMcQggsExxCCCwTGAgICAgICA+DnEIILBMcQggsExxCCCwTHEIILBMcQggsExxCCCwTHEIILBMcQggsExxCCCwTHEIILBMYCAgICAgID4cYCAgKDXpPuRat5Zsa2SRHuGjslN7/QaBcZvwSae8dKLWybem4CAoAzQlchQvYEDh57N1ilzx/G5Gj05oHksuf4nOK/6KGqfoF/sqT9NVI/hFuFzQ8LUFSymIKOpOG9nepF29+TB2bWOgICAgICAgICA",
+                    "multi_signature": null
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_verify_state_proof_success() {
+        verify_get_utxo_state_proof(&verified_reply(1)).unwrap();
+        verify_get_utxo_state_proof(&json!({"op": "REQNACK", "reason": "invalid address"})).unwrap();
+    }
+
+    #[test]
+    fn test_verify_state_proof_with_changed_amount() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_get_utxo_state_proof(&verified_reply(2)).unwrap_err());
+    }
+
+    #[test]
+    fn test_verify_state_proof_absent() {
+        let mut reply = verified_reply(1);
+        reply["result"]["state_proof"]["proof_nodes"] = SJsonValue::Null;
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_get_utxo_state_proof(&reply).unwrap_err());
+    }
+}
@@ -1,7 +1,6 @@
 //! types used for parse_payment_response_handler
 
 use ErrorCode;
-use logic::address::add_qualifer_to_address;
 use logic::input::Inputs;
 use logic::output::Outputs;
 use logic::parsers::common::{ResponseOperations,
@@ -10,7 +9,7 @@ use logic::parsers::common::{ResponseOperations,
                              TransactionMetaData,
                              RequireSignature};
 use logic::parsers::error_code_parser;
-use logic::type_aliases::{ProtocolVersion};
+use logic::type_aliases::{ProtocolVersion, ReqId};
 
 /**
     for parse_payment_response_handler input resp_json
@@ -22,6 +21,8 @@ pub struct ParsePaymentResponse {
     pub protocol_version: Option<ProtocolVersion>,
     pub result: Option<ParsePaymentResponseResult>,
     pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
 }
 
 /**
@@ -92,9 +93,8 @@ pub fn from_response(base: ParsePaymentResponse) -> Result<ParsePaymentReply, Er
             let result = base.result.ok_or(ErrorCode::CommonInvalidStructure)?;
             let mut utxos: Vec<UTXO> = vec![];
             for unspent_output in result.txn.data.outputs {
-                let address = unspent_output.recipient;
+                let qualified_address = unspent_output.recipient.with_qualifier();
                 let amount  = unspent_output.amount;
-                let qualified_address: String = add_qualifer_to_address(&address);
                 let seq_no: u64 = result.tnx_meta_data.seq_no;
                 let txo = (TXO { address: qualified_address.to_string(), seq_no }).to_libindy_string()?;
                 let utxo: UTXO = UTXO { recipient: qualified_address, receipt: txo, amount, extra: "".to_string() };
@@ -104,8 +104,7 @@ pub fn from_response(base: ParsePaymentResponse) -> Result<ParsePaymentReply, Er
             Ok(utxos)
         }
         ResponseOperations::REJECT | ResponseOperations::REQNACK => {
-            let reason = base.reason.ok_or(ErrorCode::CommonInvalidStructure)?;
-            Err(error_code_parser::parse_error_code_from_string(&reason))
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
         }
     }
 }
@@ -1,6 +1,5 @@
 //! types used for parse_response_with_fees_handler
 
-use logic::address::add_qualifer_to_address;
 use logic::input::Inputs;
 use logic::output::Outputs;
 use logic::parsers::common::{ResponseOperations,
@@ -10,7 +9,7 @@ use logic::parsers::common::{ResponseOperations,
                              RequireSignature};
 use logic::parsers::error_code_parser;
 use ErrorCode;
-use logic::type_aliases::{ProtocolVersion, TokenAmount, TxnSeqNo, TxnVersion};
+use logic::type_aliases::{ProtocolVersion, ReqId, TokenAmount, TxnSeqNo, TxnVersion};
 
 /**
     for parse_response_with_fees_handler input resp_json
@@ -24,6 +23,8 @@ pub struct ParseResponseWithFees {
     pub result: Option<ParseResponseWithFeesRequest>,
     pub protocol_version: Option<ProtocolVersion>,
     pub reason: Option<String>,
+    #[serde(rename = "reqId")]
+    pub req_id: Option<ReqId>,
 }
 
 /**
@@ -123,11 +124,11 @@ pub fn from_response(base : ParseResponseWithFees) -> Result<Option<ParseRespons
 
             for output in outputs {
                 let amount: TokenAmount = output.amount;
-                let qualified_address: String = add_qualifer_to_address(&output.recipient);
+                let qualified_address = output.recipient.with_qualifier();
 
                 let txo = (TXO { address: qualified_address.to_string(), seq_no }).to_libindy_string()?;
 
-                let utxo: UTXO = UTXO { recipient: qualified_address.to_string(), receipt: txo, amount, extra: "".to_string()};
+                let utxo: UTXO = UTXO { recipient: qualified_address, receipt: txo, amount, extra: "".to_string()};
 
                 utxos.push(utxo);
             }
@@ -135,8 +136,7 @@ pub fn from_response(base : ParseResponseWithFees) -> Result<Option<ParseRespons
             Ok(Some(utxos))
         }
         ResponseOperations::REQNACK | ResponseOperations::REJECT => {
-            let reason = base.reason.ok_or(ErrorCode::CommonInvalidStructure)?;
-            Err(error_code_parser::parse_error_code_from_string(&reason))
+            Err(error_code_parser::error_code_from_rejection(base.op, base.reason, base.req_id))
         }
     }
 
@@ -340,7 +340,7 @@ mod parse_response_with_fees_handler_tests {
                             "outputs":
                             [
                                 {"address": "2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "amount": 9},
-                                {"address": "11S4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es", "amount": 19}
+                                {"address": "2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb", "amount": 19}
                             ],
                             "fees": 4,
                             "ref": "1:13"
@@ -8,7 +8,6 @@ use ErrorCode;
 use logic::parsers::common::UTXO;
 use logic::parsers::common::TXO;
 use logic::type_aliases::TxnSeqNo;
-use logic::address;
 
 /**
     for parse_get_utxo_response_handler input parameter resp_json
@@ -88,17 +87,17 @@ fn parse_verify(resp: &str) -> Result<VerifyResult, ErrorCode> {
 
     if let Some(inputs) = data.inputs {
         for input in inputs {
-            let address = address::address_from_unqualified_address(&input.address.to_string())?;
+            let address = input.address.qualified().to_string();
             sources.push(TXO { address, seq_no: input.seq_no }.to_libindy_string()?)
         }
     }
 
     if let Some(outputs) = data.outputs {
         for output in outputs {
-            let address = address::address_from_unqualified_address(&output.recipient.to_string())?;
+            let address = output.recipient.with_qualifier();
             receipts.push(UTXO {
-                recipient: address.clone(),
-                receipt: TXO { address, seq_no }.to_libindy_string()?,
+                receipt: TXO { address: address.to_string(), seq_no }.to_libindy_string()?,
+                recipient: address,
                 amount: output.amount,
                 extra: extra.as_ref().unwrap_or(&"".to_string()).to_string(),
             })
@@ -0,0 +1,236 @@
+/*!
+    Transfers whose inputs belong to several parties.
+
+    Each party holds the keys of some input addresses in its own wallet. The
+    [`SigningEnvelope`] of the payload goes around, every party signs the inputs
+    it owns with [`PartiallySignedPayload::sign_owned`] and the partially
+    signed payloads are merged with [`PartiallySignedPayload::combine`]. Once
+    [`PartiallySignedPayload::unsigned_inputs`] is empty, the request is built
+    out of the envelope and the signatures.
+
+    [`SigningEnvelope`]: ../offline_signing/struct.SigningEnvelope.html
+    [`PartiallySignedPayload::sign_owned`]: struct.PartiallySignedPayload.html#method.sign_owned
+    [`PartiallySignedPayload::combine`]: struct.PartiallySignedPayload.html#method.combine
+    [`PartiallySignedPayload::unsigned_inputs`]: struct.PartiallySignedPayload.html#method.unsigned_inputs
+*/
+
+use IndyHandle;
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::Inputs;
+use logic::offline_signing::{SigningEnvelope, Signatures};
+use logic::xfer_payload::{InputSigner, XferPayload};
+
+/**
+    A [`SigningEnvelope`] with the signatures collected so far.
+
+    [`SigningEnvelope`]: ../offline_signing/struct.SigningEnvelope.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartiallySignedPayload {
+    pub envelope: SigningEnvelope,
+    pub signatures: Signatures,
+}
+
+impl<A: CryptoAPI> InputSigner<A> for PartiallySignedPayload {}
+
+impl PartiallySignedPayload {
+    pub fn new(envelope: SigningEnvelope) -> PartiallySignedPayload {
+        PartiallySignedPayload { envelope, signatures: Signatures::new() }
+    }
+
+    /**
+        Signs the unsigned inputs whose address is in `owned`.
+
+        The keys of `owned` have to be in the wallet. Errors with
+        `CommonInvalidStructure` when there is no such input.
+    */
+    pub fn sign_owned<A: CryptoAPI>(
+        self,
+        crypto_api: &'static A,
+        wallet_handle: IndyHandle,
+        owned: &[PaymentAddress],
+        cb: Box<Fn(Result<PartiallySignedPayload, ErrorCode>) + Send + Sync>
+    ) -> Result<(), ErrorCode> {
+        trace!("logic::partial_signing::sign_owned >> wallet_handle: {:?}, owned: {:?}", wallet_handle, secret!(owned));
+        self.envelope.check_messages()?;
+
+        let inputs: Inputs = self.unsigned_inputs().into_iter()
+            .filter(|input| owned.iter().any(|address| address.unqualified() == input.address.unqualified()))
+            .collect();
+        if inputs.is_empty() {
+            error!("None of the unsigned inputs belongs to {:?}", secret!(owned));
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        debug!("Signing inputs >>> {:?}", secret!(&inputs));
+
+        let envelope = self.envelope.clone();
+        let res = PartiallySignedPayload::sign_inputs(
+            crypto_api,
+            wallet_handle,
+            &inputs,
+            &envelope.outputs,
+            &envelope.txn_digest,
+            &envelope.extra,
+            &envelope.taa_acceptance,
+            Box::new(move |signatures| {
+                let partial = signatures.map(|signatures| {
+                    let mut signed = self.signatures.clone();
+                    signed.extend(signatures);
+                    PartiallySignedPayload { envelope: self.envelope.clone(), signatures: signed }
+                });
+                cb(partial);
+            })
+        );
+
+        trace!("logic::partial_signing::sign_owned << result: {:?}", res);
+        res
+    }
+
+    /**
+        Merges the signatures of two parties.
+
+        Errors with `CommonInvalidStructure` when the envelopes differ or when
+        an input got two different signatures.
+    */
+    pub fn combine(mut self, other: &PartiallySignedPayload) -> Result<PartiallySignedPayload, ErrorCode> {
+        if self.envelope != other.envelope {
+            error!("Can't combine signatures of different envelopes");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+
+        for (input, signature) in &other.signatures {
+            if let Some(existing) = self.signatures.get(input) {
+                if existing != signature {
+                    error!("Input {:?} has two different signatures", secret!(input));
+                    return Err(ErrorCode::CommonInvalidStructure);
+                }
+            }
+            self.signatures.insert(input.clone(), signature.clone());
+        }
+
+        Ok(self)
+    }
+
+    /**
+        Inputs which nobody signed yet, in the order of the payload.
+    */
+    pub fn unsigned_inputs(&self) -> Inputs {
+        self.envelope.unsigned_inputs(&self.signatures)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.unsigned_inputs().is_empty()
+    }
+
+    /**
+        The signed payload, with the signatures in the order of its inputs.
+
+        Errors with `CommonInvalidStructure` while an input is unsigned.
+    */
+    pub fn signed_payload(&self) -> Result<XferPayload, ErrorCode> {
+        self.envelope.signed_payload(&self.signatures)
+    }
+}
+
+#[cfg(test)]
+mod partial_signing_tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::mpsc::channel;
+    use logic::input::Input;
+    use logic::output::Output;
+    use logic::signature_verification::verify_payload;
+    use utils::test::crypto_api::CryptoApiHandler;
+
+    fn alice() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap()
+    }
+
+    fn bob() -> PaymentAddress {
+        PaymentAddress::new("pay:sov:2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V").unwrap()
+    }
+
+    fn partial() -> PartiallySignedPayload {
+        let inputs = vec![Input::new(alice(), 1), Input::new(bob(), 2), Input::new(alice(), 3)];
+        let outputs = vec![Output::new(PaymentAddress::new("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs").unwrap(), 10)];
+        PartiallySignedPayload::new(SigningEnvelope::for_transfer(XferPayload::new(inputs, outputs, None)).unwrap())
+    }
+
+    fn sign_owned_sync(partial: PartiallySignedPayload, owned: &[PaymentAddress]) -> Result<PartiallySignedPayload, ErrorCode> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        partial.sign_owned(&CryptoApiHandler {}, 1, owned, Box::new(move |partial| {
+            sender.lock().unwrap().send(partial).unwrap();
+        }))?;
+        receiver.recv().unwrap()
+    }
+
+    fn seq_nos(inputs: &Inputs) -> Vec<u64> {
+        inputs.iter().map(|input| input.seq_no).collect()
+    }
+
+    #[test]
+    fn sign_owned_inputs_only() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+
+        assert_eq!(2, signed_by_alice.signatures.len());
+        assert_eq!(vec![2], seq_nos(&signed_by_alice.unsigned_inputs()));
+        assert!(!signed_by_alice.is_complete());
+        assert_eq!(ErrorCode::CommonInvalidStructure, signed_by_alice.signed_payload().unwrap_err());
+    }
+
+    #[test]
+    fn combine_partial_signatures_in_input_order() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+        let signed_by_bob = sign_owned_sync(partial(), &[bob()]).unwrap();
+
+        let combined = signed_by_bob.combine(&signed_by_alice).unwrap();
+        assert!(combined.is_complete());
+
+        let payload = combined.signed_payload().unwrap();
+        let signers: Vec<String> = payload.signatures.clone().unwrap().iter()
+            .map(|signature| signature.split('|').next().unwrap().to_string())
+            .collect();
+        assert_eq!(vec![alice().verkey(), bob().verkey(), alice().verkey()], signers);
+        assert!(verify_payload(&CryptoApiHandler {}, &payload, &None, &None).unwrap().is_valid());
+    }
+
+    #[test]
+    fn sign_remaining_inputs_of_partial() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+        let signed = sign_owned_sync(signed_by_alice, &[bob()]).unwrap();
+
+        assert!(signed.is_complete());
+        assert_eq!(3, signed.signatures.len());
+    }
+
+    #[test]
+    fn sign_without_owned_inputs() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, sign_owned_sync(signed_by_alice, &[alice()]).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, sign_owned_sync(partial(), &[]).unwrap_err());
+    }
+
+    #[test]
+    fn combine_conflicting_signatures() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+        let mut forged = signed_by_alice.clone();
+        for signature in forged.signatures.values_mut() {
+            signature.push('x');
+        }
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, signed_by_alice.combine(&forged).unwrap_err());
+    }
+
+    #[test]
+    fn combine_different_envelopes() {
+        let signed_by_alice = sign_owned_sync(partial(), &[alice()]).unwrap();
+        let mut other = partial();
+        other.envelope.txn_digest = Some("digest".to_string());
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, signed_by_alice.combine(&other).unwrap_err());
+    }
+}
@@ -13,9 +13,13 @@ use logic::address;
     CreatePaymentHandler contains methods for creating a fully formatted address based on inputted
     seed.  If seed is empty then a randomly generated seed is used by libsodium
 
+    When the config has a derivation `path` or `index`, the key is created from the seed derived
+    from the master seed, see [`PaymentAddressConfig::key_config`].
+
     In production runtime environment, the expectation is T is CryptoSdk
     and in testing environments its anything else as long as it implements CryptoAPI
 
+    [`PaymentAddressConfig::key_config`]: ../config/payment_address_config/struct.PaymentAddressConfig.html#method.key_config
 */
 pub struct CreatePaymentHandler<T> where T: CryptoAPI {
     injected_api: T
@@ -32,6 +36,8 @@ impl<T: CryptoAPI> CreatePaymentHandler<T> {
             pay:sov:{32 byte address}{4 byte checksum}
     */
     pub fn create_payment_address(&self, wallet_id: i32, config: PaymentAddressConfig) -> Result<String, ErrorCode> {
+        let config = config.key_config()?;
+
         trace!("calling self.injected_api.indy_create_key");
         let verkey = self.injected_api.indy_create_key(wallet_id, config)?;
 
@@ -39,6 +45,33 @@ impl<T: CryptoAPI> CreatePaymentHandler<T> {
         return address::qualified_address_from_verkey(&verkey);
     }
 
+    /**
+        The sovrin address of the key of `seed`, without creating the key.
+    */
+    pub fn payment_address_of_seed(&self, seed: &str) -> Result<String, ErrorCode> {
+        let verkey = self.injected_api.indy_verkey_of_seed(seed)?;
+        address::qualified_address_from_verkey(&verkey)
+    }
+
+    /**
+        To restore the sovrin address `index` of a BIP-39 `mnemonic` and `passphrase`,
+        which can be empty. The address is the hardened child `index` of
+        [`DEFAULT_ACCOUNT_PATH`], so the first one is the address a config with
+        only the mnemonic creates.
+
+        [`DEFAULT_ACCOUNT_PATH`]: ../hd_derivation/constant.DEFAULT_ACCOUNT_PATH.html
+    */
+    pub fn create_payment_address_from_mnemonic(&self, wallet_id: i32, mnemonic: &str, passphrase: &str, index: u32) -> Result<String, ErrorCode> {
+        let config = PaymentAddressConfig {
+            seed: String::new(),
+            index: Some(index),
+            mnemonic: Some(mnemonic.to_string()),
+            passphrase: Some(passphrase.to_string()),
+            ..Default::default()
+        };
+        self.create_payment_address(wallet_id, config)
+    }
+
     /**
         To get a sovrin address asynchronously.
         the format of the string sent via the callback is:
@@ -48,6 +81,10 @@ impl<T: CryptoAPI> CreatePaymentHandler<T> {
                                                     wallet_id: i32,
                                                     config: PaymentAddressConfig,
                                                     mut cb: F) -> ErrorCode where F: FnMut(String, ErrorCode) + Send {
+        let config = match config.key_config() {
+            Ok(config) => config,
+            Err(ec) => return ec,
+        };
 
         let cb_closure = move |err: ErrorCode, verkey: String| {
             let res = if ErrorCode::Success == err {
@@ -87,6 +124,8 @@ mod payments_tests {
     use logic::address::*;
     use logic::address::address_tests::gen_random_base58_verkey;
     use utils::constants::general::PAYMENT_ADDRESS_QUALIFIER;
+    use hex::ToHex;
+    use logic::keystore_crypto::KeystoreCrypto;
     use utils::base58::FromBase58;
 
     use super::*;
@@ -130,7 +169,7 @@ mod payments_tests {
     #[test]
     fn success_create_payment_with_seed_returns_address() {
         let seed = "000000000000000000000000Address1".to_string();
-        let config: PaymentAddressConfig = PaymentAddressConfig { seed };
+        let config: PaymentAddressConfig = PaymentAddressConfig { seed, ..Default::default() };
         let handler = CreatePaymentHandler::new(CreatePaymentSDKMockHandler {});
 
         let address = handler.create_payment_address(WALLET_ID, config).unwrap();
@@ -142,7 +181,7 @@ mod payments_tests {
     #[test]
     fn success_create_payment_with_no_seed_returns_address() {
         let seed = String::new();
-        let config: PaymentAddressConfig = PaymentAddressConfig { seed };
+        let config: PaymentAddressConfig = PaymentAddressConfig { seed, ..Default::default() };
 
         let handler = CreatePaymentHandler::new(CreatePaymentSDKMockHandler {});
         let address = handler.create_payment_address(WALLET_ID, config).unwrap();
@@ -150,11 +189,44 @@ mod payments_tests {
         validate_address(address);
     }
 
+    // The key of a derived address is the SLIP-0010 key at the path, m/0'/1' of test vector 1,
+    // whose 16 bytes seed is given in base64
+    #[test]
+    fn create_payment_address_from_master_seed() {
+        let seed = "AAECAwQFBgcICQoLDA0ODw==".to_string();
+        let config = PaymentAddressConfig { seed, path: Some("m/0'".to_string()), index: Some(1), ..Default::default() };
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+
+        let address = handler.create_payment_address(WALLET_ID, config).unwrap();
+        let verkey = verkey_from_unqualified_address(&address[ADDRESS_QUAL_LEN..]).unwrap();
+        assert_eq!("1932a5270f335bed617d5b935c80aedb1a35bd9fc1e31acafd5372c30f5c1187", verkey.from_base58().unwrap().to_hex());
+
+        let config = PaymentAddressConfig { seed: String::new(), index: Some(1), ..Default::default() };
+        assert_eq!(ErrorCode::CommonInvalidStructure, handler.create_payment_address(WALLET_ID, config).unwrap_err());
+    }
+
+    #[test]
+    fn create_payment_address_from_mnemonic() {
+        let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+
+        let first = handler.create_payment_address_from_mnemonic(WALLET_ID, mnemonic, "", 0).unwrap();
+        let config = PaymentAddressConfig { mnemonic: Some(mnemonic.to_string()), ..Default::default() };
+        assert_eq!(first, CreatePaymentHandler::new(KeystoreCrypto::new()).create_payment_address(WALLET_ID, config).unwrap());
+        validate_address(first.clone());
+
+        assert_ne!(first, handler.create_payment_address_from_mnemonic(WALLET_ID, mnemonic, "", 1).unwrap());
+        assert_ne!(first, handler.create_payment_address_from_mnemonic(WALLET_ID, mnemonic, "TREZOR", 0).unwrap());
+
+        let unknown_word = mnemonic.replace("about", "aboot");
+        assert_eq!(ErrorCode::CommonInvalidStructure, handler.create_payment_address_from_mnemonic(WALLET_ID, &unknown_word, "", 0).unwrap_err());
+    }
+
     // Happy path test assumes the CB is valid and it is successfully called
     #[test]
     fn success_create_payment_async() {
         let seed = String::new();
-        let config: PaymentAddressConfig = PaymentAddressConfig { seed };
+        let config: PaymentAddressConfig = PaymentAddressConfig { seed, ..Default::default() };
 
         let handler = CreatePaymentHandler::new(CreatePaymentSDKMockHandler {});
 
@@ -0,0 +1,297 @@
+/*!
+    Recovery of the payment addresses of a restored master seed or mnemonic.
+
+    The addresses of an account are derived one index after the other and the
+    UTXOs of each are read with GET_UTXO requests built by
+    [`GetUtxoOperationRequest`], through a submitter the caller plugs in. The
+    scan stops after `gap_limit` addresses in a row without UTXOs, the gap
+    wallets leave when they hand out addresses in order.
+
+    [`GetUtxoOperationRequest`]: ../config/get_utxo_config/struct.GetUtxoOperationRequest.html
+*/
+
+use hex::ToHex;
+
+use ErrorCode;
+use logic::address::PaymentAddress;
+use logic::config::get_utxo_config::GetUtxoOperationRequest;
+use logic::config::payment_address_config::PaymentAddressConfig;
+use logic::hd_derivation::{DerivationPath, ExtendedKey, DEFAULT_ACCOUNT_PATH};
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::parsers::parse_get_utxo_response::{self, ParseGetUtxoReply, ParseGetUtxoResponse};
+use logic::payments::CreatePaymentHandler;
+use logic::type_aliases::TxnSeqNo;
+use utils::json_conversion::JsonDeserialize;
+
+/**
+    The number of addresses in a row without UTXOs after which the scan stops,
+    when the caller has no better idea.
+*/
+pub const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/**
+    A derived address which holds UTXOs
+
+    # parameters
+    index - the index the address is derived at
+    address - the payment address
+    utxos - the UTXOs of the address, from every page of its GET_UTXO
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredAddress {
+    pub index: u32,
+    pub address: PaymentAddress,
+    pub utxos: ParseGetUtxoReply,
+}
+
+/**
+    What a recovery scan found
+
+    # parameters
+    addresses - the addresses with UTXOs, by index
+    next_index - the index after the last address with UTXOs, where new
+                 addresses of the account should be derived from
+*/
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Recovery {
+    pub addresses: Vec<RecoveredAddress>,
+    pub next_index: u32,
+}
+
+/**
+    Scans the addresses of the account of `config` for UTXOs.
+
+    `config` names the master `seed` or the `mnemonic` and `passphrase`, and
+    the account `path`, [`DEFAULT_ACCOUNT_PATH`] when there is none. The scan
+    sets the `index` itself, so `config` must not have one.
+
+    The key of the account is derived from the master seed once, and the
+    verkeys of the scanned addresses are derived by the CryptoAPI of `handler`
+    without a wallet. Only the keys of the addresses with UTXOs are created in the wallet of
+    `wallet_id`, which should be the one the seed is restored into. A key
+    which is already in the wallet, from an earlier scan, is left as it is.
+    `submit` sends a request to the ledger and returns its reply.
+
+    Errors with `CommonInvalidStructure` when `gap_limit` is 0 or `config`
+    can't derive addresses, and with the error of a failed key derivation or
+    creation, submission or rejected GET_UTXO.
+
+    [`DEFAULT_ACCOUNT_PATH`]: ../hd_derivation/constant.DEFAULT_ACCOUNT_PATH.html
+*/
+pub fn recover_addresses<T, F>(
+    handler: &CreatePaymentHandler<T>,
+    wallet_id: i32,
+    config: &PaymentAddressConfig,
+    gap_limit: u32,
+    mut submit: F
+) -> Result<Recovery, ErrorCode>
+    where T: CryptoAPI, F: FnMut(&str) -> Result<String, ErrorCode>
+{
+    trace!("logic::recovery::recover_addresses >> path: {:?}, gap_limit: {}", config.path, gap_limit);
+
+    if gap_limit == 0 {
+        error!("The gap limit of a recovery scan should be at least 1");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    if config.index.is_some() {
+        error!("The recovery scan derives the index of the addresses itself");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let account = config.path.as_ref().map_or(DEFAULT_ACCOUNT_PATH, String::as_str).parse::<DerivationPath>()?;
+    let account_key = ExtendedKey::master(&config.master_seed()?)?.derive(&account)?;
+
+    let mut addresses: Vec<RecoveredAddress> = Vec::new();
+    let mut gap = 0;
+    let mut index = 0;
+
+    while gap < gap_limit {
+        let key_config = PaymentAddressConfig { seed: account_key.child(index)?.key().to_hex(), ..Default::default() };
+        let address = PaymentAddress::new(&handler.payment_address_of_seed(&key_config.seed)?)?;
+        let utxos = address_utxos(address.qualified(), &mut submit)?;
+        debug!("Recovery scan found {} UTXOs at index {}", utxos.len(), index);
+
+        if utxos.is_empty() {
+            gap += 1;
+        } else {
+            gap = 0;
+            import_key(handler, wallet_id, key_config)?;
+            addresses.push(RecoveredAddress { index, address, utxos });
+        }
+        index += 1;
+    }
+
+    let next_index = addresses.last().map_or(0, |found| found.index + 1);
+    trace!("logic::recovery::recover_addresses << found: {}, next_index: {}", addresses.len(), next_index);
+    Ok(Recovery { addresses, next_index })
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/*
+    Creates the key of `key_config` in the wallet, unless it is there already.
+*/
+fn import_key<T: CryptoAPI>(handler: &CreatePaymentHandler<T>, wallet_id: i32, key_config: PaymentAddressConfig) -> Result<(), ErrorCode> {
+    match handler.create_payment_address(wallet_id, key_config) {
+        Ok(_) | Err(ErrorCode::WalletItemAlreadyExists) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/*
+    The UTXOs of every page of the GET_UTXO of `address`.
+*/
+fn address_utxos<F>(address: &str, submit: &mut F) -> Result<ParseGetUtxoReply, ErrorCode>
+    where F: FnMut(&str) -> Result<String, ErrorCode>
+{
+    let mut utxos: ParseGetUtxoReply = Vec::new();
+    let mut from: Option<TxnSeqNo> = None;
+
+    loop {
+        let request_json = GetUtxoOperationRequest::new(address.to_string(), from)
+            .serialize_to_string()
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))?;
+
+        let response = ParseGetUtxoResponse::from_json(&submit(&request_json)?)
+            .map_err(map_err_err!())
+            .or(Err(ErrorCode::CommonInvalidStructure))?;
+        let (page, next) = parse_get_utxo_response::from_response_with_next(response)?;
+        utxos.extend(page);
+
+        // a ledger which hands back the same cursor would be asked forever
+        match next {
+            Some(next) if Some(next) != from => from = Some(next),
+            _ => return Ok(utxos),
+        }
+    }
+}
+
+#[cfg(test)]
+mod recovery_tests {
+    use super::*;
+    use logic::address::PaymentAddress;
+    use logic::keystore_crypto::KeystoreCrypto;
+    use logic::type_aliases::TokenAmount;
+    use utils::test::crypto_api::CryptoApiHandler;
+    use utils::test::mock_ledger::MockLedger;
+
+    const WALLET_ID: i32 = 1;
+    const MNEMONIC: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    // a wallet which can't take keys, where a scan can only create none
+    struct LockedWallet {}
+
+    impl CryptoAPI for LockedWallet {
+        fn indy_verkey_of_seed(&self, seed: &str) -> Result<String, ErrorCode> {
+            KeystoreCrypto::new().indy_verkey_of_seed(seed)
+        }
+
+        fn indy_create_key(&self, _: i32, _: PaymentAddressConfig) -> Result<String, ErrorCode> {
+            Err(ErrorCode::WalletAccessFailed)
+        }
+
+        fn indy_create_key_async<F: 'static>(&self, _: i32, _: PaymentAddressConfig, _: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
+            ErrorCode::WalletAccessFailed
+        }
+
+        fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, _: i32, _: String, _: String, _: F) -> ErrorCode {
+            ErrorCode::WalletAccessFailed
+        }
+    }
+
+    fn mnemonic_config() -> PaymentAddressConfig {
+        PaymentAddressConfig { mnemonic: Some(MNEMONIC.to_string()), ..Default::default() }
+    }
+
+    fn address_at(index: u32) -> PaymentAddress {
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+        let address = handler.create_payment_address(WALLET_ID, PaymentAddressConfig { index: Some(index), ..mnemonic_config() }).unwrap();
+        PaymentAddress::new(&address).unwrap()
+    }
+
+    fn recover(ledger: &mut MockLedger, config: &PaymentAddressConfig, gap_limit: u32) -> Result<Recovery, ErrorCode> {
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+        recover_addresses(&handler, WALLET_ID, config, gap_limit, |request_json| Ok(ledger.submit(request_json)))
+    }
+
+    #[test]
+    fn recover_addresses_within_gap_limit() {
+        let mut ledger = MockLedger::new();
+        ledger.mint_to(&address_at(1), 10);
+        ledger.mint_to(&address_at(3), 20);
+        ledger.mint_to(&address_at(6), 30);
+
+        let recovery = recover(&mut ledger, &mnemonic_config(), 2).unwrap();
+        let found: Vec<(u32, TokenAmount)> = recovery.addresses.iter().map(|found| (found.index, found.utxos[0].amount)).collect();
+        assert_eq!(vec![(1, 10), (3, 20)], found);
+        assert_eq!(address_at(3), recovery.addresses[1].address);
+        assert_eq!(4, recovery.next_index);
+
+        let recovery = recover(&mut ledger, &mnemonic_config(), DEFAULT_GAP_LIMIT).unwrap();
+        assert_eq!((3, 7), (recovery.addresses.len(), recovery.next_index));
+    }
+
+    #[test]
+    fn recover_twice_into_same_wallet() {
+        let mut ledger = MockLedger::new();
+        ledger.mint_to(&address_at(0), 10);
+        ledger.mint_to(&address_at(2), 20);
+
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+        let first = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 2, |request_json| Ok(ledger.submit(request_json))).unwrap();
+        let second = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 2, |request_json| Ok(ledger.submit(request_json))).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(3, second.next_index);
+    }
+
+    #[test]
+    fn recover_paged_utxos() {
+        let mut ledger = MockLedger::new();
+        for amount in 1..4 {
+            ledger.mint_to(&address_at(0), amount);
+        }
+        ledger.set_utxo_page_size(Some(2));
+
+        let recovery = recover(&mut ledger, &mnemonic_config(), 1).unwrap();
+        let amounts: Vec<TokenAmount> = recovery.addresses[0].utxos.iter().map(|utxo| utxo.amount).collect();
+        assert_eq!(vec![1, 2, 3], amounts);
+    }
+
+    #[test]
+    fn recover_nothing() {
+        let mut ledger = MockLedger::new();
+        let recovery = recover(&mut ledger, &mnemonic_config(), 3).unwrap();
+        assert_eq!(Recovery { addresses: Vec::new(), next_index: 0 }, recovery);
+
+        let handler = CreatePaymentHandler::new(LockedWallet {});
+        let recovery = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 3, |request_json| Ok(ledger.submit(request_json))).unwrap();
+        assert!(recovery.addresses.is_empty());
+
+        ledger.mint_to(&address_at(1), 10);
+        let result = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 3, |request_json| Ok(ledger.submit(request_json)));
+        assert_eq!(ErrorCode::WalletAccessFailed, result.unwrap_err());
+    }
+
+    #[test]
+    fn recover_errors() {
+        let mut ledger = MockLedger::new();
+        assert_eq!(ErrorCode::CommonInvalidStructure, recover(&mut ledger, &mnemonic_config(), 0).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, recover(&mut ledger, &PaymentAddressConfig { index: Some(0), ..mnemonic_config() }, 1).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, recover(&mut ledger, &PaymentAddressConfig { path: Some("m/0'".to_string()), ..Default::default() }, 1).unwrap_err());
+
+        let handler = CreatePaymentHandler::new(KeystoreCrypto::new());
+        let result = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 1, |_| Err(ErrorCode::PoolLedgerTimeout));
+        assert_eq!(ErrorCode::PoolLedgerTimeout, result.unwrap_err());
+
+        let handler = CreatePaymentHandler::new(CryptoApiHandler {});
+        let result = recover_addresses(&handler, WALLET_ID, &mnemonic_config(), 1, |request_json| Ok(ledger.submit(request_json)));
+        assert_eq!(ErrorCode::CommonInvalidState, result.unwrap_err());
+    }
+}
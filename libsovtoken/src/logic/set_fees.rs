@@ -6,6 +6,7 @@ use logic::config::set_fees_config::{SetFees, SetFeesMap};
 use logic::did::Did;
 use serde_json;
 use utils::constants::general::{JsonCallback, JsonCallbackUnwrapped};
+use utils::current_error::{invalid_parameter, missing_parameter};
 use utils::ffi_support::string_from_char_ptr;
 
 type DeserializedArguments = (Option<Did>, SetFees, JsonCallbackUnwrapped);
@@ -16,7 +17,7 @@ pub fn deserialize_inputs(
     cb: JsonCallback
 ) -> Result<DeserializedArguments, ErrorCode> {
     trace!("logic::set_fees::deserialize_inputs >> did: {:?}, fees_json: {:?}", secret!(&did), secret!(&fees_json));
-    let cb = cb.ok_or(ErrorCode::CommonInvalidStructure)?;
+    let cb = cb.ok_or_else(|| missing_parameter("cb"))?;
 
     let did = Did::from_pointer(did).map(|did| {
         did.validate().or(Err(ErrorCode::CommonInvalidStructure))
@@ -25,14 +26,14 @@ pub fn deserialize_inputs(
     let did = opt_res_to_res_opt!(did)?;
 
     let set_fees_json = string_from_char_ptr(fees_json)
-        .ok_or(ErrorCode::CommonInvalidStructure).map_err(map_err_err!())?;
+        .ok_or_else(|| missing_parameter("fees_json"))?;
 
-    let set_fees_map: SetFeesMap = serde_json::from_str(&set_fees_json).map_err(map_err_err!())
-        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    let set_fees_map: SetFeesMap = serde_json::from_str(&set_fees_json)
+        .map_err(invalid_parameter("fees_json"))?;
 
     let set_fees = SetFees::new(set_fees_map)
-        .validate().map_err(map_err_err!())
-        .or(Err(ErrorCode::CommonInvalidStructure))?;
+        .validate()
+        .map_err(invalid_parameter("fees_json"))?;
 
     let res = Ok((did, set_fees, cb));
     trace!("logic::set_fees::deserialize_inputs << res: {:?}", res);
@@ -0,0 +1,298 @@
+/*!
+    Local verification of the input signatures of XFER payloads and fees.
+
+    The message of every input is rebuilt with [`signature_message`], the way
+    the inputs were signed, and its signature is checked against the verkey of
+    the input address. Nothing is sent to the ledger, so a request can be
+    checked before it is sent and a transaction read from the ledger can be
+    checked without trusting the node which returned it.
+
+    [`signature_message`]: ../xfer_payload/fn.signature_message.html
+*/
+
+use serde_json;
+
+use ErrorCode;
+use logic::address::verkey_from_unqualified_address;
+use logic::api_internals::add_request_fees::{txn_digest, SerdeMap};
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::Input;
+use logic::output::Outputs;
+use logic::xfer_payload::{signature_message, Extra, XferPayload};
+use utils::constants::txn_fields::FEES;
+use utils::txn_author_agreement::TaaAcceptance;
+
+/**
+    Whether the signature of `input` is valid.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InputVerification {
+    pub input: Input,
+    pub valid: bool,
+}
+
+/**
+    The verification of every input, in the order of the payload.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub inputs: Vec<InputVerification>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.inputs.iter().all(|input| input.valid)
+    }
+
+    /**
+        Inputs whose signature is not valid.
+    */
+    pub fn failed_inputs(&self) -> Vec<Input> {
+        self.inputs.iter()
+            .filter(|input| !input.valid)
+            .map(|input| input.input.clone())
+            .collect()
+    }
+}
+
+/**
+    Verifies the signatures of a signed [`XferPayload`].
+
+    `txn_digest` is the digest of the request for fees and `None` for a
+    transfer. `taa_acceptance` is the one of the request, if any.
+
+    Errors with `CommonInvalidStructure` when the payload doesn't have a
+    signature for each input or an input address is invalid.
+
+    [`XferPayload`]: ../xfer_payload/struct.XferPayload.html
+*/
+pub fn verify_payload<A: CryptoAPI>(
+    crypto_api: &A,
+    payload: &XferPayload,
+    txn_digest: &Option<String>,
+    taa_acceptance: &Option<TaaAcceptance>
+) -> Result<VerificationReport, ErrorCode> {
+    trace!("logic::signature_verification::verify_payload >> payload: {:?}, txn_digest: {:?}", secret!(payload), txn_digest);
+
+    let signatures = payload.signatures.as_ref().ok_or_else(|| {
+        error!("Payload is not signed");
+        ErrorCode::CommonInvalidStructure
+    })?;
+    if signatures.len() != payload.inputs.len() {
+        error!("Payload has {} signatures for {} inputs", signatures.len(), payload.inputs.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let inputs = payload.inputs.iter().zip(signatures.iter())
+        .map(|(input, signature)| {
+            let valid = verify_input(crypto_api, input, signature, &payload.outputs, txn_digest, &payload.extra, taa_acceptance)?;
+            Ok(InputVerification { input: input.clone(), valid })
+        })
+        .collect::<Result<Vec<InputVerification>, ErrorCode>>()?;
+
+    let report = VerificationReport { inputs };
+    trace!("logic::signature_verification::verify_payload << report: {:?}", report);
+    Ok(report)
+}
+
+/**
+    Verifies the signatures of an XFER_PUBLIC request.
+*/
+pub fn verify_transfer_request<A: CryptoAPI>(crypto_api: &A, request_json: &str) -> Result<VerificationReport, ErrorCode> {
+    let request = parse_request(request_json)?;
+    let operation = request.get("operation").cloned().ok_or_else(|| {
+        error!("Request has no operation");
+        ErrorCode::CommonInvalidStructure
+    })?;
+    let payload: XferPayload = serde_json::from_value(operation)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+
+    verify_payload(crypto_api, &payload, &None, &request.get("taaAcceptance").cloned())
+}
+
+/**
+    Verifies the signatures of the fees of a request.
+
+    The fee inputs sign the digest of the request, so a change to any other
+    part of the request makes them invalid too.
+*/
+pub fn verify_fees_request<A: CryptoAPI>(crypto_api: &A, request_json: &str) -> Result<VerificationReport, ErrorCode> {
+    let mut request = parse_request(request_json)?;
+    let fees = request.remove(FEES).ok_or_else(|| {
+        error!("Request has no fees");
+        ErrorCode::CommonInvalidStructure
+    })?;
+    let (inputs, outputs, signatures): (Vec<Input>, Outputs, Vec<String>) = serde_json::from_value(fees)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+
+    let mut payload = XferPayload::new(inputs, outputs, None);
+    payload.signatures = Some(signatures);
+
+    verify_payload(crypto_api, &payload, &Some(txn_digest(&request)?), &None)
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn verify_input<A: CryptoAPI>(
+    crypto_api: &A,
+    input: &Input,
+    signature: &str,
+    outputs: &Outputs,
+    txn_digest: &Option<String>,
+    extra: &Option<Extra>,
+    taa_acceptance: &Option<TaaAcceptance>
+) -> Result<bool, ErrorCode> {
+    let verkey = verkey_from_unqualified_address(input.address.unqualified())?;
+    let message = signature_message(input, outputs, txn_digest, extra, taa_acceptance)?;
+    debug!("Verifying signature of input >>> {:?} over message >>> {:?}", secret!(input), secret!(&message));
+
+    crypto_api.indy_crypto_verify(verkey, message, signature.to_string())
+}
+
+fn parse_request(request_json: &str) -> Result<SerdeMap, ErrorCode> {
+    let request: serde_json::Value = serde_json::from_str(request_json)
+        .map_err(map_err_err!())
+        .or(Err(ErrorCode::CommonInvalidStructure))?;
+    request.as_object()
+        .cloned()
+        .ok_or(ErrorCode::CommonInvalidStructure)
+        .map_err(map_err_err!())
+}
+
+#[cfg(test)]
+mod signature_verification_tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::sync::mpsc::channel;
+    use logic::address::PaymentAddress;
+    use logic::offline_signing::SigningEnvelope;
+    use logic::output::Output;
+    use utils::test::crypto_api::CryptoApiHandler;
+
+    fn payload(extra: Option<Extra>) -> XferPayload {
+        let address = |address| PaymentAddress::new(address).unwrap();
+        let inputs = vec![
+            Input::new(address("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 1),
+            Input::new(address("pay:sov:2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V"), 2),
+        ];
+        let outputs = vec![Output::new(address("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs"), 10)];
+        XferPayload::new(inputs, outputs, extra)
+    }
+
+    fn request_json() -> String {
+        json!({
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "operation": {"type": "101", "data": {"name": "gvt", "version": "1.0", "attr_names": ["name"]}}
+        }).to_string()
+    }
+
+    fn sign_sync(payload: XferPayload) -> (XferPayload, Option<TaaAcceptance>) {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        payload.sign_transfer(&CryptoApiHandler {}, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        })).unwrap();
+        receiver.recv().unwrap().unwrap()
+    }
+
+    fn transfer_request(extra: Option<Extra>) -> String {
+        let envelope = SigningEnvelope::for_transfer(payload(extra)).unwrap();
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        envelope.sign(&CryptoApiHandler {}, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        })).unwrap();
+        envelope.transfer_request(&receiver.recv().unwrap().unwrap(), None).unwrap()
+    }
+
+    fn fees_request() -> String {
+        let request_json = request_json();
+        let envelope = SigningEnvelope::for_fees(payload(None), &request_json).unwrap();
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        envelope.sign(&CryptoApiHandler {}, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        })).unwrap();
+        envelope.fees_request(&receiver.recv().unwrap().unwrap(), &request_json).unwrap()
+    }
+
+    fn tamper(request_json: &str, pointer: &str, value: serde_json::Value) -> String {
+        let mut request: serde_json::Value = serde_json::from_str(request_json).unwrap();
+        *request.pointer_mut(pointer).unwrap() = value;
+        request.to_string()
+    }
+
+    #[test]
+    fn verify_signed_payload() {
+        let (signed, taa_acceptance) = sign_sync(payload(Some(json!({"data": "x"}))));
+
+        let report = verify_payload(&CryptoApiHandler {}, &signed, &None, &taa_acceptance).unwrap();
+
+        assert!(report.is_valid());
+        assert_eq!(signed.inputs, report.inputs.iter().map(|input| input.input.clone()).collect::<Vec<Input>>());
+    }
+
+    #[test]
+    fn verify_payload_with_changed_output() {
+        let (mut signed, _) = sign_sync(payload(None));
+        signed.outputs[0].amount = 11;
+
+        let report = verify_payload(&CryptoApiHandler {}, &signed, &None, &None).unwrap();
+        assert!(!report.is_valid());
+        assert_eq!(signed.inputs, report.failed_inputs());
+    }
+
+    #[test]
+    fn verify_payload_reports_each_input() {
+        let (mut signed, _) = sign_sync(payload(None));
+        signed.signatures.as_mut().unwrap()[1] = "forged".to_string();
+
+        let report = verify_payload(&CryptoApiHandler {}, &signed, &None, &None).unwrap();
+        assert_eq!(vec![true, false], report.inputs.iter().map(|input| input.valid).collect::<Vec<bool>>());
+    }
+
+    #[test]
+    fn verify_payload_without_all_signatures() {
+        let (mut signed, _) = sign_sync(payload(None));
+        signed.signatures.as_mut().unwrap().pop();
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_payload(&CryptoApiHandler {}, &signed, &None, &None).unwrap_err());
+
+        signed.signatures = None;
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_payload(&CryptoApiHandler {}, &signed, &None, &None).unwrap_err());
+    }
+
+    #[test]
+    fn verify_transfer_request_with_taa_acceptance() {
+        let taa_acceptance = json!({"mechanism": "at_submission", "taaDigest": "abc", "time": 123456789});
+        let request_json = transfer_request(Some(json!({"data": "x", "taaAcceptance": taa_acceptance})));
+
+        assert!(verify_transfer_request(&CryptoApiHandler {}, &request_json).unwrap().is_valid());
+
+        let tampered = tamper(&request_json, "/taaAcceptance/time", json!(1));
+        assert!(!verify_transfer_request(&CryptoApiHandler {}, &tampered).unwrap().is_valid());
+    }
+
+    #[test]
+    fn verify_fees_of_request() {
+        let request_json = fees_request();
+        assert!(verify_fees_request(&CryptoApiHandler {}, &request_json).unwrap().is_valid());
+
+        let tampered = tamper(&request_json, "/operation/data/name", json!("other"));
+        let report = verify_fees_request(&CryptoApiHandler {}, &tampered).unwrap();
+        assert_eq!(2, report.failed_inputs().len());
+    }
+
+    #[test]
+    fn verify_invalid_requests() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_fees_request(&CryptoApiHandler {}, &request_json()).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_transfer_request(&CryptoApiHandler {}, "{}").unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_transfer_request(&CryptoApiHandler {}, "[").unwrap_err());
+    }
+}
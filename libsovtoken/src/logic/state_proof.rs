@@ -0,0 +1,445 @@
+/*!
+    State proofs of read replies.
+
+    The state of the ledger is a Merkle-Patricia trie. A read reply carries
+    the `root_hash` of the trie (base58) and the `proof_nodes` (base64 of the
+    RLP list of nodes) on the paths from the root to the keys it read. A node
+    is referenced by the sha3-256 of its RLP, unless the RLP is shorter than
+    32 bytes, in which case it is embedded in its parent. The values stored
+    in the trie are the RLP list of the value.
+
+    A verified proof only shows the values belong to the state with that root,
+    the `multi_signature` over the root is not checked here. Its timestamp is,
+    by [`check_freshness`], once [`set_max_proof_age`] set a policy: a stale
+    proof can show spent UTXOs or old fees even when validly signed.
+
+    [`check_freshness`]: fn.check_freshness.html
+    [`set_max_proof_age`]: fn.set_max_proof_age.html
+*/
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64;
+use sha3::{Digest, Sha3_256};
+
+use serde_json::Value;
+
+use ErrorCode;
+use logic::parsers::common::{KeyValueSimpleData, StateProof, extract_result_and_state_proof};
+use utils::base58::FromBase58;
+use utils::current_error::{set_current_error, ErrorDetails};
+use utils::rlp::{self, RlpItem, RlpValue};
+
+lazy_static! {
+    static ref MAX_PROOF_AGE: RwLock<Option<u64>> = RwLock::new(None);
+}
+
+/**
+    Sets how many seconds old the multi signature of a state proof may be.
+    `None`, the default, accepts proofs of any age.
+*/
+pub fn set_max_proof_age(max_age: Option<u64>) {
+    match MAX_PROOF_AGE.write() {
+        Ok(mut current) => *current = max_age,
+        Err(err) => *err.into_inner() = max_age,
+    }
+}
+
+pub fn max_proof_age() -> Option<u64> {
+    match MAX_PROOF_AGE.read() {
+        Ok(current) => *current,
+        Err(err) => *err.into_inner(),
+    }
+}
+
+/**
+    Checks the nodes signed the state of `state_proof` at most `max_age`
+    seconds before `now`. A timestamp ahead of `now` is fresh.
+
+    Errors with `LedgerInvalidTransaction` when the proof is older, with the
+    current error saying the proof is stale, and with
+    `CommonInvalidStructure` when it has no valid multi signature or the
+    multi signature is over another root than the `root_hash` of the proof,
+    whose timestamp then says nothing about the proof.
+*/
+pub fn check_freshness(state_proof: &StateProof, max_age: u64, now: u64) -> Result<(), ErrorCode> {
+    let signed = state_proof.parse_multi_signature()?.value;
+    if state_proof.root_hash.as_ref() != Some(&signed.state_root_hash) {
+        error!("The multi signature is over the root {:?}, not the root {:?} of the state proof", signed.state_root_hash, state_proof.root_hash);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let age = now.saturating_sub(signed.timestamp);
+
+    if age > max_age {
+        error!("The state proof is {} seconds old, at most {} are allowed", age, max_age);
+        set_current_error(ErrorDetails::new("The state proof is stale")
+            .cause(format!("The state proof is {} seconds old, at most {} are allowed", age, max_age))
+            .details(json!({"timestamp": signed.timestamp, "maxAge": max_age})));
+        return Err(ErrorCode::LedgerInvalidTransaction);
+    }
+
+    Ok(())
+}
+
+/**
+    Checks the state proof of a read reply is at most `max_age` seconds old.
+    Only a `REPLY` has a state proof.
+*/
+pub fn check_reply_freshness(reply: &Value, max_age: u64) -> Result<(), ErrorCode> {
+    if reply["op"] != "REPLY" {
+        return Ok(());
+    }
+
+    let (_, state_proof) = extract_result_and_state_proof(reply)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_err(|_| ErrorCode::CommonInvalidState)?
+        .as_secs();
+    check_freshness(&state_proof, max_age, now)
+}
+
+/**
+    Looks `key` up in the trie with `root_hash` built from `proof_nodes`.
+
+    Errors with `CommonInvalidStructure` when the proof can't be decoded or
+    doesn't have a node on the path to `key`.
+*/
+pub fn get_value(proof_nodes: &str, root_hash: &str, key: &[u8]) -> Result<Option<Vec<u8>>, ErrorCode> {
+    let proof = decode_proof(proof_nodes)?;
+    let proof = rlp::decode(&proof)?;
+    let trie = ProofTrie::new(&proof, root_hash)?;
+    trie.get(key)
+}
+
+/**
+    Checks each of the base64 keys in `kvs` has its value in the trie with
+    `root_hash`. A key without value has to be absent from the trie.
+*/
+pub fn verify_key_values(proof_nodes: &str, root_hash: &str, kvs: &KeyValueSimpleData) -> Result<bool, ErrorCode> {
+    let proof = decode_proof(proof_nodes)?;
+    let proof = rlp::decode(&proof)?;
+    let trie = ProofTrie::new(&proof, root_hash)?;
+
+    for (key, expected) in &kvs.kvs {
+        let key = base64::decode(key).map_err(map_err_err!()).map_err(|_| ErrorCode::CommonInvalidStructure)?;
+        let value = trie.get(&key)?;
+
+        if value.as_ref().map(Vec::as_slice) != expected.as_ref().map(String::as_bytes) {
+            debug!("Value {:?} of key {:?} is not {:?}", value, String::from_utf8_lossy(&key), expected);
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/**
+    Checks `kvs` against the `state_proof` of a reply.
+
+    Errors with `CommonInvalidStructure` when the state proof is incomplete
+    or doesn't verify.
+*/
+pub fn verify_state_proof(state_proof: &StateProof, kvs: &KeyValueSimpleData) -> Result<(), ErrorCode> {
+    let (proof_nodes, root_hash) = match (&state_proof.proof_nodes, &state_proof.root_hash) {
+        (Some(proof_nodes), Some(root_hash)) => (proof_nodes, root_hash),
+        _ => {
+            error!("The state proof has no proof_nodes or root_hash");
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+    };
+
+    if !verify_key_values(proof_nodes, root_hash, kvs)? {
+        error!("The values are not in the state with root {}", root_hash);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    Ok(())
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+struct ProofTrie<'a, 'b: 'a> {
+    nodes: HashMap<Vec<u8>, &'a RlpItem<'b>>,
+    root: &'a RlpItem<'b>,
+}
+
+impl<'a, 'b> ProofTrie<'a, 'b> {
+    fn new(proof: &'a RlpItem<'b>, root_hash: &str) -> Result<Self, ErrorCode> {
+        let root_hash = root_hash.from_base58().map_err(|_| ErrorCode::CommonInvalidStructure)?;
+
+        let nodes: HashMap<Vec<u8>, &'a RlpItem<'b>> = proof.as_list()
+            .ok_or(ErrorCode::CommonInvalidStructure)?
+            .iter()
+            .map(|node| (Sha3_256::digest(node.raw).to_vec(), node))
+            .collect();
+
+        let root = match nodes.get(&root_hash) {
+            Some(root) => *root,
+            None => {
+                error!("The proof has no node for the root hash");
+                return Err(ErrorCode::CommonInvalidStructure);
+            }
+        };
+
+        Ok(ProofTrie { nodes, root })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ErrorCode> {
+        match self.lookup(self.root, &nibbles(key))? {
+            Some(stored) => {
+                let stored = rlp::decode(stored)?;
+                match stored.as_list() {
+                    Some(items) if items.len() == 1 => items[0].as_bytes()
+                        .map(|value| Some(value.to_vec()))
+                        .ok_or(ErrorCode::CommonInvalidStructure),
+                    _ => Err(ErrorCode::CommonInvalidStructure),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn lookup(&self, node: &'a RlpItem<'b>, path: &[u8]) -> Result<Option<&'b [u8]>, ErrorCode> {
+        let items = match node.value {
+            RlpValue::Bytes([]) => return Ok(None),
+            RlpValue::Bytes(_) => return Err(ErrorCode::CommonInvalidStructure),
+            RlpValue::List(ref items) => items,
+        };
+
+        match items.len() {
+            17 => match path.split_first() {
+                Some((nibble, rest)) => self.lookup(self.child(&items[*nibble as usize])?, rest),
+                None => {
+                    let value = items[16].as_bytes().ok_or(ErrorCode::CommonInvalidStructure)?;
+                    Ok(if value.is_empty() { None } else { Some(value) })
+                }
+            },
+            2 => {
+                let (node_path, is_leaf) = decode_hex_prefix(items[0].as_bytes().ok_or(ErrorCode::CommonInvalidStructure)?)?;
+
+                if is_leaf {
+                    Ok(if path == &node_path[..] { items[1].as_bytes() } else { None })
+                } else if path.starts_with(&node_path) {
+                    self.lookup(self.child(&items[1])?, &path[node_path.len()..])
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => {
+                error!("A trie node has {} items", items.len());
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+        }
+    }
+
+    fn child(&self, reference: &'a RlpItem<'b>) -> Result<&'a RlpItem<'b>, ErrorCode> {
+        match reference.value {
+            RlpValue::Bytes(hash) if hash.len() == 32 => match self.nodes.get(hash) {
+                Some(node) => Ok(*node),
+                None => {
+                    error!("The proof has no node on the path to the key");
+                    Err(ErrorCode::CommonInvalidStructure)
+                }
+            },
+            _ => Ok(reference),
+        }
+    }
+}
+
+fn decode_proof(proof_nodes: &str) -> Result<Vec<u8>, ErrorCode> {
+    base64::decode(proof_nodes).map_err(map_err_err!()).map_err(|_| ErrorCode::CommonInvalidStructure)
+}
+
+fn nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|byte| vec![byte >> 4, byte & 0x0f]).collect()
+}
+
+/*
+    The path of a leaf or extension starts with a flag nibble: 2 marks a leaf,
+    1 an odd number of nibbles. An even path is padded with a zero nibble.
+*/
+fn decode_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool), ErrorCode> {
+    let nibbles = nibbles(encoded);
+
+    let flag = *nibbles.first().ok_or(ErrorCode::CommonInvalidStructure)?;
+    if flag > 3 {
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let skip = if flag & 1 == 1 { 1 } else { 2 };
+    Ok((nibbles[skip..].to_vec(), flag & 2 == 2))
+}
+
+#[cfg(test)]
+mod state_proof_tests {
+    use super::*;
+    use utils::base58::IntoBase58;
+    use utils::current_error::current_error;
+
+    fn rlp_bytes(bytes: &[u8]) -> Vec<u8> {
+        if bytes.len() == 1 && bytes[0] < 0x80 {
+            return bytes.to_vec();
+        }
+        let mut encoded = rlp_len(bytes.len(), 0x80);
+        encoded.extend_from_slice(bytes);
+        encoded
+    }
+
+    fn rlp_list(items: &[Vec<u8>]) -> Vec<u8> {
+        let payload: Vec<u8> = items.concat();
+        let mut encoded = rlp_len(payload.len(), 0xc0);
+        encoded.extend_from_slice(&payload);
+        encoded
+    }
+
+    fn rlp_len(len: usize, offset: u8) -> Vec<u8> {
+        if len < 56 {
+            vec![offset + len as u8]
+        } else if len < 256 {
+            vec![offset + 56, len as u8]
+        } else {
+            vec![offset + 57, (len >> 8) as u8, len as u8]
+        }
+    }
+
+    fn hash(node: &[u8]) -> Vec<u8> {
+        rlp_bytes(&Sha3_256::digest(node))
+    }
+
+    fn leaf(path: &[u8], value: &str) -> Vec<u8> {
+        let mut hex_prefix = vec![0x20];
+        hex_prefix.extend_from_slice(path);
+        rlp_list(&[rlp_bytes(&hex_prefix), rlp_bytes(&rlp_list(&[rlp_bytes(value.as_bytes())]))])
+    }
+
+    /*
+        A branch under the extension "ke" holding the keys "key1", whose leaf
+        is embedded, and "key2", whose leaf is referenced by its hash.
+    */
+    fn proof() -> (String, String) {
+        let leaf_1 = leaf(b"", "1");
+        let leaf_2 = leaf(b"", "a value long enough for a hashed leaf");
+
+        let mut branch_items = vec![rlp_bytes(b""); 17];
+        branch_items[1] = leaf_1;
+        branch_items[2] = hash(&leaf_2);
+        let branch = rlp_list(&branch_items);
+
+        let y_branch = {
+            let mut items = vec![rlp_bytes(b""); 17];
+            items[3] = hash(&branch);
+            rlp_list(&items)
+        };
+
+        let extension = rlp_list(&[rlp_bytes(&[0x00, b'k', b'e', 0x79]), hash(&y_branch)]);
+        let root_hash = Sha3_256::digest(&extension).to_vec().into_base58();
+
+        let nodes = rlp_list(&[extension, y_branch, branch, leaf_2]);
+        (base64::encode(&nodes), root_hash)
+    }
+
+    fn kvs(pairs: &[(&str, Option<&str>)]) -> KeyValueSimpleData {
+        KeyValueSimpleData {
+            kvs: pairs.iter().map(|&(key, value)| (base64::encode(key), value.map(String::from))).collect()
+        }
+    }
+
+    #[test]
+    fn get_values_of_embedded_and_hashed_leaves() {
+        let (proof_nodes, root_hash) = proof();
+
+        assert_eq!(Some(b"1".to_vec()), get_value(&proof_nodes, &root_hash, b"key1").unwrap());
+        assert_eq!(Some(b"a value long enough for a hashed leaf".to_vec()), get_value(&proof_nodes, &root_hash, b"key2").unwrap());
+        assert_eq!(None, get_value(&proof_nodes, &root_hash, b"key4").unwrap());
+        assert_eq!(None, get_value(&proof_nodes, &root_hash, b"lock").unwrap());
+    }
+
+    #[test]
+    fn verify_key_values_of_trie() {
+        let (proof_nodes, root_hash) = proof();
+
+        assert!(verify_key_values(&proof_nodes, &root_hash, &kvs(&[("key1", Some("1")), ("key4", None)])).unwrap());
+        assert!(!verify_key_values(&proof_nodes, &root_hash, &kvs(&[("key1", Some("2"))])).unwrap());
+        assert!(!verify_key_values(&proof_nodes, &root_hash, &kvs(&[("key1", None)])).unwrap());
+        assert!(!verify_key_values(&proof_nodes, &root_hash, &kvs(&[("key4", Some("1"))])).unwrap());
+    }
+
+    #[test]
+    fn verify_key_values_with_wrong_root() {
+        let (proof_nodes, _) = proof();
+        let root_hash = "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1";
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, verify_key_values(&proof_nodes, root_hash, &kvs(&[("key1", Some("1"))])).unwrap_err());
+    }
+
+    #[test]
+    fn get_value_with_missing_node() {
+        let (proof_nodes, root_hash) = proof();
+        let nodes = base64::decode(&proof_nodes).unwrap();
+        let nodes = rlp::decode(&nodes).unwrap();
+        let without_leaf: Vec<Vec<u8>> = nodes.as_list().unwrap()[..3].iter().map(|node| node.raw.to_vec()).collect();
+        let proof_nodes = base64::encode(&rlp_list(&without_leaf));
+
+        assert_eq!(Some(b"1".to_vec()), get_value(&proof_nodes, &root_hash, b"key1").unwrap());
+        assert_eq!(ErrorCode::CommonInvalidStructure, get_value(&proof_nodes, &root_hash, b"key2").unwrap_err());
+    }
+
+    #[test]
+    fn get_value_with_invalid_proof() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, get_value("not base64!", "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1", b"key").unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, get_value(&base64::encode(&[0x83, 0x01]), "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1", b"key").unwrap_err());
+    }
+    fn signed_at(timestamp: u64) -> StateProof {
+        StateProof {
+            multi_signature: Some(json!({
+                "participants": ["Beta", "Delta", "Gamma"],
+                "signature": "Qz5rGskoz8xuRLdaAoA5m1He4dBbfg3RBKQ5wmvRper4yTmuKEbbXZ5jidVXYzrJymHcN3xiRYqDSkZ3JbggzWj4NQATsYRSPSc6xP768vAMHA1iNSgxhGV5uW47MSeYihrV9e9YLDjYyzuyUHkBhbWrxMoo8jtowvDMQMZ7qHMhfd",
+                "value": {
+                    "pool_state_root_hash": "DyMrH7X17UW4k9KcsAUPLKL479dsZ6dvj3bvEAEyYNxZ",
+                    "ledger_id": 1001,
+                    "state_root_hash": "EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1",
+                    "txn_root_hash": "9i1knJtwTD3NToyCrHoh93HBrTnaq6CeL7F1KtZUBaBz",
+                    "timestamp": timestamp
+                }
+            })),
+            root_hash: Some(String::from("EuHbjY9oaqAXBDxLBM4KcBLASs7RK35maoHjQMbDvmw1")),
+            proof_nodes: None,
+        }
+    }
+
+    #[test]
+    fn check_freshness_of_state_proofs() {
+        check_freshness(&signed_at(1530212673), 60, 1530212673 + 60).unwrap();
+        check_freshness(&signed_at(1530212673), 60, 1530212673 - 5).unwrap();
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, check_freshness(&signed_at(1530212673), 60, 1530212673 + 61).unwrap_err());
+        let error = current_error().unwrap();
+        assert_eq!("The state proof is stale", error.message);
+        assert_eq!(Some(json!({"timestamp": 1530212673, "maxAge": 60})), error.details);
+
+        let unsigned = StateProof { multi_signature: None, root_hash: None, proof_nodes: None };
+        assert_eq!(ErrorCode::CommonInvalidStructure, check_freshness(&unsigned, 60, 1530212673).unwrap_err());
+    }
+
+    #[test]
+    fn check_freshness_of_state_proof_signed_over_another_root() {
+        let other_root = StateProof { root_hash: Some(String::from("5BU5Rc3sRtTJB6tVprGiTSqiRaa9o6ei11MjH4Vu16ms")), ..signed_at(1530212673) };
+        assert_eq!(ErrorCode::CommonInvalidStructure, check_freshness(&other_root, 60, 1530212673).unwrap_err());
+
+        let without_root = StateProof { root_hash: None, ..signed_at(1530212673) };
+        assert_eq!(ErrorCode::CommonInvalidStructure, check_freshness(&without_root, 60, 1530212673).unwrap_err());
+    }
+
+    #[test]
+    fn check_reply_freshness_of_old_reply() {
+        let reply = json!({"op": "REPLY", "result": {"state_proof": signed_at(1530212673)}});
+
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, check_reply_freshness(&reply, 3600).unwrap_err());
+        check_reply_freshness(&json!({"op": "REQNACK", "reason": "invalid address"}), 3600).unwrap();
+    }
+}
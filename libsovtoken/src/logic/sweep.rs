@@ -0,0 +1,368 @@
+/*!
+    Sweeping every token of retired or compromised addresses to a new one.
+
+    The UTXOs of the source addresses are spent in XFER_PUBLIC transactions of
+    at most `max_inputs` inputs, each with a single output to the destination
+    and no change, paying the XFER_PUBLIC fee from the GET_FEES map. Unlike a
+    [consolidation] round, the sources are drained completely, so the payloads
+    don't depend on each other and can be sent in any order.
+
+    [consolidation]: ../consolidation/index.html
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use ErrorCode;
+use IndyHandle;
+use logic::address::PaymentAddress;
+use logic::coin_selection::spendable_inputs;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::{Input, Inputs};
+use logic::output::Output;
+use logic::parsers::common::TXO;
+use logic::parsers::parse_get_utxo_response::UTXO;
+use logic::type_aliases::{TokenAmount, TxnSeqNo};
+use logic::xfer_payload::{Extra, XferPayload};
+use utils::constants::txn_types::XFER_PUBLIC;
+
+/**
+    An address to sweep with the UTXOs it holds, as returned by
+    [`parse_get_utxo_response_handler`].
+
+    [`parse_get_utxo_response_handler`]: ../../api/fn.parse_get_utxo_response_handler.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SweepSource {
+    pub address: PaymentAddress,
+    pub utxos: Vec<UTXO>,
+}
+
+/**
+    The payloads of a sweep and the UTXOs it leaves behind
+
+    # parameters
+    payloads - the XFER_PUBLIC payloads which drain the sources
+    left_out - the UTXOs of the sources which aren't in any payload, because
+               they can't cover the fee of their transaction
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepPlan {
+    pub payloads: Vec<XferPayload>,
+    pub left_out: Vec<UTXO>,
+}
+
+/**
+    Plans the unsigned payloads which sweep every UTXO of `sources` to
+    `destination`.
+
+    `fees` is the map returned by [`parse_get_txn_fees_response_handler`]. When
+    it has no XFER_PUBLIC entry, transfers are free. `extra`, which can carry
+    the TAA acceptance, goes into every payload.
+
+    The sources are dealt out biggest first over as few transactions as
+    `max_inputs` allows, so each one gets about the same amount to pay its fee
+    with. A transaction whose sources can't pay more than the fee is left out,
+    those tokens can't be moved at the current fee, and its UTXOs are in the
+    `left_out` of the plan.
+
+    Errors with `CommonInvalidStructure` when there are no sources,
+    `max_inputs` is 0, a UTXO doesn't belong to its source address or the
+    destination is one of the sources.
+
+    [`parse_get_txn_fees_response_handler`]: ../../api/fn.parse_get_txn_fees_response_handler.html
+*/
+pub fn plan_sweep(
+    sources: &[SweepSource],
+    destination: &PaymentAddress,
+    max_inputs: usize,
+    fees: &HashMap<String, TokenAmount>,
+    extra: Option<Extra>
+) -> Result<SweepPlan, ErrorCode> {
+    trace!("logic::sweep::plan_sweep >> sources: {:?}, destination: {:?}, max_inputs: {:?}, fees: {:?}, extra: {:?}", secret!(sources), secret!(destination), max_inputs, fees, secret!(&extra));
+
+    if sources.is_empty() {
+        error!("Nothing to sweep without source addresses");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    if max_inputs == 0 {
+        error!("Can't sweep with transactions without inputs");
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    let destination_key = destination.unqualified();
+    let mut utxos: Vec<UTXO> = Vec::new();
+    for source in sources {
+        if source.address.unqualified() == destination_key {
+            error!("Can't sweep {} to itself", secret!(destination));
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        if let Some(utxo) = source.utxos.iter().find(|utxo| !belongs_to(utxo, &source.address)) {
+            error!("Source {} doesn't belong to {}", secret!(&utxo.source), secret!(&source.address));
+            return Err(ErrorCode::CommonInvalidStructure);
+        }
+        utxos.extend(source.utxos.iter().cloned());
+    }
+
+    let fee = fees.get(XFER_PUBLIC).cloned().unwrap_or(0);
+    debug!("Fee for {} >>> {}", XFER_PUBLIC, fee);
+
+    let mut inputs = spendable_inputs(&utxos)?;
+    inputs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.seq_no.cmp(&b.0.seq_no)));
+
+    let batch_count = (inputs.len() + max_inputs - 1) / max_inputs;
+    let mut batches: Vec<Vec<(Input, TokenAmount)>> = vec![Vec::new(); batch_count];
+    for (position, input) in inputs.into_iter().enumerate() {
+        batches[position % batch_count].push(input);
+    }
+
+    let mut payloads: Vec<XferPayload> = Vec::new();
+    for batch in batches {
+        let total = batch.iter()
+            .try_fold(0 as TokenAmount, |sum, &(_, amount)| sum.checked_add(amount))
+            .ok_or_else(|| {
+                error!("Sum of the batch overflows");
+                ErrorCode::CommonInvalidStructure
+            })?;
+        if total <= fee {
+            debug!("Leaving out a batch of {} which doesn't cover the fee of {}", total, fee);
+            continue;
+        }
+
+        let inputs: Inputs = batch.into_iter().map(|(input, _)| input).collect();
+        let outputs = vec![Output::new(destination.with_qualifier(), total - fee)];
+        payloads.push(XferPayload::new(inputs, outputs, extra.clone()));
+    }
+
+    let swept: HashSet<(String, TxnSeqNo)> = payloads.iter()
+        .flat_map(|payload| payload.inputs.iter())
+        .map(|input| (input.address.unqualified().to_string(), input.seq_no))
+        .collect();
+    let mut listed: HashSet<&str> = HashSet::new();
+    let left_out: Vec<UTXO> = utxos.iter()
+        .filter(|utxo| listed.insert(&utxo.source))
+        .filter(|utxo| source_key(utxo).map_or(true, |key| !swept.contains(&key)))
+        .cloned()
+        .collect();
+
+    let plan = SweepPlan { payloads, left_out };
+    trace!("logic::sweep::plan_sweep << plan: {:?}", secret!(&plan));
+    Ok(plan)
+}
+
+/**
+    Plans the sweep of `sources` to `destination` with [`plan_sweep`] and
+    signs every payload with [`XferPayload::sign_transfer`].
+
+    `cb` gets the plan with the signed payloads, in the order they were
+    planned, once all of them are signed, or the first error. Errors without
+    calling `cb` when the sweep can't be planned or a payload can't be sent to
+    be signed.
+
+    [`plan_sweep`]: fn.plan_sweep.html
+    [`XferPayload::sign_transfer`]: ../xfer_payload/struct.XferPayload.html#method.sign_transfer
+*/
+pub fn sweep<A: CryptoAPI>(
+    sources: &[SweepSource],
+    destination: &PaymentAddress,
+    max_inputs: usize,
+    fees: &HashMap<String, TokenAmount>,
+    extra: Option<Extra>,
+    crypto_api: &'static A,
+    wallet_handle: IndyHandle,
+    cb: Box<Fn(Result<SweepPlan, ErrorCode>) + Send + Sync>
+) -> Result<(), ErrorCode> {
+    let SweepPlan { payloads, left_out } = plan_sweep(sources, destination, max_inputs, fees, extra)?;
+    if payloads.is_empty() {
+        cb(Ok(SweepPlan { payloads, left_out }));
+        return Ok(());
+    }
+
+    let payload_count = payloads.len();
+    let signed: Arc<Mutex<(Vec<Option<XferPayload>>, bool)>> = Arc::new(Mutex::new((vec![None; payload_count], false)));
+    let cb = Arc::new(cb);
+
+    for (position, payload) in payloads.into_iter().enumerate() {
+        let signed_by_cb = signed.clone();
+        let cb = cb.clone();
+        let left_out = left_out.clone();
+        let res = payload.sign_transfer(crypto_api, wallet_handle, Box::new(move |result| {
+            let mut signed = signed_by_cb.lock().unwrap();
+            let (ref mut payloads, ref mut done) = *signed;
+            if *done {
+                return;
+            }
+
+            match result {
+                Ok((payload, _)) => {
+                    payloads[position] = Some(payload);
+                    if payloads.iter().all(Option::is_some) {
+                        *done = true;
+                        cb(Ok(SweepPlan { payloads: payloads.drain(..).map(Option::unwrap).collect(), left_out: left_out.clone() }));
+                    }
+                }
+                Err(err) => {
+                    error!("Got an error while signing the sweep: {:?}", err);
+                    *done = true;
+                    cb(Err(err));
+                }
+            }
+        }));
+
+        if let Err(err) = res {
+            // the payloads already sent are signed, but the sweep is incomplete
+            signed.lock().unwrap().1 = true;
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/*
+    `(unqualified address, seq_no)` of the source of `utxo`, which is how the
+    inputs of the payloads are matched with the UTXOs.
+*/
+fn source_key(utxo: &UTXO) -> Option<(String, TxnSeqNo)> {
+    let txo = TXO::from_libindy_string(&utxo.source).ok()?;
+    let address = PaymentAddress::new(&txo.address).ok()?;
+    Some((address.unqualified().to_string(), txo.seq_no))
+}
+
+fn belongs_to(utxo: &UTXO, address: &PaymentAddress) -> bool {
+    PaymentAddress::new(&utxo.payment_address)
+        .map(|utxo_address| utxo_address.unqualified() == address.unqualified())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod sweep_tests {
+    use super::*;
+    use serde_json;
+    use serde_json::Value as SJsonValue;
+    use std::sync::mpsc::channel;
+    use logic::build_payment::build_payment_request;
+    use logic::config::payment_address_config::PaymentAddressConfig;
+    use utils::test::default::{utxo, KEYSTORE};
+    use utils::test::mock_ledger::MockLedger;
+
+    fn new_address() -> PaymentAddress {
+        let verkey = KEYSTORE.indy_create_key(1, PaymentAddressConfig::default()).unwrap();
+        PaymentAddress::from_verkey(&verkey).unwrap().with_qualifier()
+    }
+
+    fn source(address: &PaymentAddress, seq_nos_and_amounts: &[(u64, TokenAmount)]) -> SweepSource {
+        let utxos = seq_nos_and_amounts.iter().map(|&(seq_no, amount)| utxo(&address.to_string(), seq_no, amount)).collect();
+        SweepSource { address: address.clone(), utxos }
+    }
+
+    fn fees(fee: TokenAmount) -> HashMap<String, TokenAmount> {
+        let mut fees = HashMap::new();
+        fees.insert(XFER_PUBLIC.to_string(), fee);
+        fees
+    }
+
+    fn amounts(payloads: &[XferPayload]) -> Vec<TokenAmount> {
+        payloads.iter().map(|payload| payload.outputs[0].amount).collect()
+    }
+
+    fn sign_sweep(sources: &[SweepSource], destination: &PaymentAddress, max_inputs: usize, fees: &HashMap<String, TokenAmount>) -> Result<SweepPlan, ErrorCode> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        sweep(sources, destination, max_inputs, fees, None, &*KEYSTORE, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        }))?;
+        receiver.recv().unwrap()
+    }
+
+    #[test]
+    fn plan_sweep_of_several_addresses() {
+        let (first, second, destination) = (new_address(), new_address(), new_address());
+        let sources = [source(&first, &[(1, 40), (2, 1), (3, 2)]), source(&second, &[(4, 30), (5, 3)])];
+
+        let SweepPlan { payloads, left_out } = plan_sweep(&sources, &destination, 3, &fees(1), None).unwrap();
+        assert!(left_out.is_empty());
+        assert_eq!(2, payloads.len());
+        assert_eq!(vec![1, 5, 2], payloads[0].inputs.iter().map(|input| input.seq_no).collect::<Vec<u64>>());
+        assert_eq!(vec![4, 3], payloads[1].inputs.iter().map(|input| input.seq_no).collect::<Vec<u64>>());
+        assert_eq!(vec![43, 31], amounts(&payloads));
+
+        for payload in payloads {
+            assert_eq!(vec![Output::new(destination.clone(), payload.outputs[0].amount)], payload.outputs);
+        }
+    }
+
+    #[test]
+    fn plan_sweep_leaves_out_dust() {
+        let address = new_address();
+        let dust = source(&address, &[(1, 1), (2, 1), (3, 0)]);
+        let plan = plan_sweep(&[dust.clone()], &new_address(), 1, &fees(1), None).unwrap();
+        assert!(plan.payloads.is_empty());
+        assert_eq!(dust.utxos, plan.left_out);
+
+        let extra = json!({"note": "sweep"});
+        let plan = plan_sweep(&[source(&address, &[(1, 1), (2, 1), (3, 5)])], &new_address(), 2, &fees(2), Some(extra.clone())).unwrap();
+        assert_eq!(vec![4], amounts(&plan.payloads));
+        assert_eq!(Some(extra), plan.payloads[0].extra);
+        assert_eq!(source(&address, &[(1, 1)]).utxos, plan.left_out);
+
+        let plan = plan_sweep(&[source(&address, &[(1, 1), (2, 1)])], &new_address(), 1, &HashMap::new(), None).unwrap();
+        assert_eq!(vec![1, 1], amounts(&plan.payloads));
+    }
+
+    #[test]
+    fn plan_sweep_errors() {
+        let (address, destination) = (new_address(), new_address());
+        let sources = [source(&address, &[(1, 10)])];
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, plan_sweep(&[], &destination, 1, &fees(0), None).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, plan_sweep(&sources, &destination, 0, &fees(0), None).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, plan_sweep(&sources, &address.strip_qualifier().unwrap(), 1, &fees(0), None).unwrap_err());
+
+        let foreign = SweepSource { address: destination.clone(), utxos: sources[0].utxos.clone() };
+        assert_eq!(ErrorCode::CommonInvalidStructure, plan_sweep(&[foreign], &new_address(), 1, &fees(0), None).unwrap_err());
+    }
+
+    #[test]
+    fn sweep_drains_addresses_on_ledger() {
+        let mut ledger = MockLedger::new();
+        ledger.set_fees(fees(2));
+
+        let (first, second, destination) = (new_address(), new_address(), new_address());
+        let mut sources: Vec<SweepSource> = Vec::new();
+        for (address, amounts) in [(&first, vec![5, 7, 9]), (&second, vec![11, 13])].iter() {
+            let mut utxos = Vec::new();
+            for amount in amounts {
+                let reply: SJsonValue = serde_json::from_str(&ledger.mint_to(address, *amount)).unwrap();
+                utxos.push((reply["result"]["txnMetadata"]["seqNo"].as_u64().unwrap(), *amount));
+            }
+            sources.push(source(address, &utxos));
+        }
+
+        let plan = sign_sweep(&sources, &destination, 2, &fees(2)).unwrap();
+        assert_eq!((3, 0), (plan.payloads.len(), plan.left_out.len()));
+        for payload in plan.payloads {
+            let request_json = build_payment_request(None, Ok((payload, None))).unwrap();
+            assert!(ledger.submit(&request_json).contains("REPLY"));
+        }
+
+        assert_eq!((0, 0), (ledger.balance(&first), ledger.balance(&second)));
+        assert_eq!(45 - 3 * 2, ledger.balance(&destination));
+    }
+
+    #[test]
+    fn sweep_without_keys() {
+        let unknown = PaymentAddress::new("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd").unwrap();
+        let result = sign_sweep(&[source(&unknown, &[(1, 10)])], &new_address(), 1, &fees(0));
+        assert!(result.is_err());
+
+        let dust = source(&unknown, &[(1, 1)]);
+        assert_eq!(SweepPlan { payloads: Vec::new(), left_out: dust.utxos.clone() }, sign_sweep(&[dust], &new_address(), 1, &fees(1)).unwrap());
+    }
+}
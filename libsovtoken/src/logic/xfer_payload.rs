@@ -17,13 +17,12 @@ use std::collections::HashMap;
 use IndyHandle;
 use ErrorCode;
 
-use logic::address;
 use logic::indy_sdk_api::crypto_api::CryptoAPI;
 use logic::input::{Input, Inputs};
 use logic::output::Outputs;
 use logic::hash::Hash;
-use utils::constants::txn_types::{ATTRIB, GET_ATTRIB};
-use utils::txn_author_agreement::{TaaAcceptance, extract_taa_acceptance_from_extra};
+use utils::constants::txn_types::TxnType;
+use utils::txn_author_agreement::{TaaAcceptance, check_current_acceptance, extract_taa_acceptance_from_extra};
 
 /**
  * Holds `inputs` and `outputs`
@@ -37,6 +36,7 @@ use utils::txn_author_agreement::{TaaAcceptance, extract_taa_acceptance_from_ext
  * ```
  *  # extern crate sovtoken;
  *  # fn main() {
+ *      use sovtoken::logic::address::PaymentAddress;
  *      use sovtoken::logic::input::Input;
  *      use sovtoken::logic::output::Output;
  *      use sovtoken::logic::xfer_payload::XferPayload;
@@ -44,8 +44,8 @@ use utils::txn_author_agreement::{TaaAcceptance, extract_taa_acceptance_from_ext
  *
  *      // Need an actual wallet_handle
  *      let wallet_handle = 1;
- *      let address_input = String::from("pay:sov:SBD8oNfQNm1aEGE6KkYI1khYEGqG5zmEqrEw7maqKitIs121");
- *      let address_output = String::from("pay:sov:FekbDoBkdsj3nH2a2nNhhedoPju2UmyKrr1ZzMZGT0KENbvp");
+ *      let address_input = PaymentAddress::new("pay:sov:E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap();
+ *      let address_output = PaymentAddress::new("pay:sov:TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs").unwrap();
  *      let inputs = vec![Input::new(address_input, 1)];
  *      let outputs = vec![Output::new(address_output, 20)];
  *
@@ -90,7 +90,7 @@ impl XferPayload {
     /** 
      * Signs [`Inputs`]
      * 
-     * Validates that inputs and outputs both have a valid `address`.
+     * Checks that inputs and outputs both have a qualified `address`.
      * Signs each [`Input`] with [`sign_input`]
      * 
      * [`Input`]: Input
@@ -104,25 +104,41 @@ impl XferPayload {
         self.sign(crypto_api, wallet_handle, &None, cb)
     }
 
-    fn sign<A: CryptoAPI>(mut self, crypto_api: &'static A, wallet_handle: IndyHandle, txn_digest: &Option<String>, cb: Box<Fn(Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
+    /**
+     * Puts the payload in the form its inputs are signed in.
+     *
+     * Strips the qualifier of the inputs and outputs and takes the TAA
+     * acceptance out of `extra`. The acceptance has to accept the agreement
+     * set with [`set_current_agreement`], if one is.
+     *
+     * [`set_current_agreement`]: ../../utils/txn_author_agreement/fn.set_current_agreement.html
+     */
+    pub fn prepare_for_signing(mut self) -> Result<(XferPayload, Option<TaaAcceptance>), ErrorCode> {
         for output in &mut self.outputs {
-            output.recipient = address::unqualified_address_from_address(&output.recipient)?;
+            output.recipient = output.recipient.strip_qualifier()?;
         }
         debug!("Indicator stripped from outputs");
 
         for input in &mut self.inputs {
-            input.address = address::unqualified_address_from_address(&input.address)?;
+            input.address = input.address.strip_qualifier()?;
         }
 
         debug!("Indicator stripped from inputs");
 
         let (extra, taa_acceptance) = extract_taa_acceptance_from_extra(self.extra.clone())?;
+        check_current_acceptance(&taa_acceptance)?;
         self.extra = extra;
 
-        XferPayload::sign_inputs(crypto_api, wallet_handle, &self.inputs.clone(), &self.outputs.clone(), txn_digest, &self.extra.clone(), &taa_acceptance.clone(), Box::new(move |signatures| {
+        Ok((self, taa_acceptance))
+    }
+
+    fn sign<A: CryptoAPI>(self, crypto_api: &'static A, wallet_handle: IndyHandle, txn_digest: &Option<String>, cb: Box<Fn(Result<(XferPayload, Option<TaaAcceptance>), ErrorCode>) + Send + Sync>) -> Result<(), ErrorCode> {
+        let (payload, taa_acceptance) = self.prepare_for_signing()?;
+
+        XferPayload::sign_inputs(crypto_api, wallet_handle, &payload.inputs.clone(), &payload.outputs.clone(), txn_digest, &payload.extra.clone(), &taa_acceptance.clone(), Box::new(move |signatures| {
             match signatures {
                 Ok(signatures) => {
-                    let payload = Self::clone_payload_add_signatures(&self, signatures);
+                    let payload = Self::clone_payload_add_signatures(&payload, signatures);
                     info!("Built XFER payload: {:?}", payload);
                     cb(Ok((payload, taa_acceptance.clone())));
                 }
@@ -155,7 +171,7 @@ impl XferPayload {
     }
 }
 
-trait InputSigner<A: CryptoAPI> {
+pub trait InputSigner<A: CryptoAPI> {
     fn sign_inputs(crypto_api: &'static A, wallet_handle: IndyHandle, inputs: &Inputs, outputs: &Outputs, txn_digest: &Option<String>, extra: &Option<Extra>, taa_acceptance: &Option<TaaAcceptance>, cb: Box<Fn(Result<HashMap<String, String>, ErrorCode>) + Send + Sync>)
                    -> Result<(), ErrorCode>
     {
@@ -189,7 +205,8 @@ trait InputSigner<A: CryptoAPI> {
     /**
      * Signs an [`Input`] with indy_crypto_sign
      *
-     * Validates the `input`'s `address`, but not the `outputs`.
+     * The `input`'s `address` has been validated when it was built,
+     * so its verkey is used as is.
      * The message that will be signed is
      * `[[<address>, <seq_no>], [<Output>, <Output>, ...]]`
      *
@@ -207,19 +224,11 @@ trait InputSigner<A: CryptoAPI> {
     ) -> Result<(), ErrorCode>
     {
         trace!("logic::xfer_payload::input_signer::sign_input >> input: {:?}, outputs: {:?}, wallet_handle {:?}", secret!(&input), secret!(&outputs), wallet_handle);
-        let verkey = address::verkey_from_unqualified_address(&input.address.clone())?;
+        let verkey = input.address.verkey().to_string();
 
         debug!("Received verkey for payment address >>> {:?}", secret!(&verkey));
 
-        let vals: Vec<serde_json::Value> = vec![
-            Some(json!([input])),
-            Some(json!(outputs)),
-            txn_digest.clone().map(|e| json!(e)),
-            extra.clone().map(|e| json!(e)),
-            taa_acceptance.clone().map(|e| json!(e)),
-        ].into_iter().filter_map(|e| e).collect();
-
-        let message = serialize_signature(json!(vals))?;
+        let message = signature_message(input, outputs, txn_digest, extra, taa_acceptance)?;
 
         debug!("Message to sign >>> {:?}", secret!(&message));
 
@@ -247,12 +256,39 @@ trait InputSigner<A: CryptoAPI> {
     }
 }
 
+/**
+ * Builds the message which is signed for `input`
+ *
+ * `[[<input>], [<Output>, ...], <txn_digest>, <extra>, <taa_acceptance>]`
+ * serialized with [`serialize_signature`], leaving out the parts which are
+ * `None`.
+ *
+ * [`serialize_signature`]: fn.serialize_signature.html
+ */
+pub fn signature_message(
+    input: &Input,
+    outputs: &Outputs,
+    txn_digest: &Option<String>,
+    extra: &Option<Extra>,
+    taa_acceptance: &Option<TaaAcceptance>
+) -> Result<String, ErrorCode> {
+    let vals: Vec<serde_json::Value> = vec![
+        Some(json!([input])),
+        Some(json!(outputs)),
+        txn_digest.clone().map(|e| json!(e)),
+        extra.clone().map(|e| json!(e)),
+        taa_acceptance.clone().map(|e| json!(e)),
+    ].into_iter().filter_map(|e| e).collect();
+
+    serialize_signature(json!(vals))
+}
+
 pub fn serialize_signature(v: serde_json::Value) -> Result<String, ErrorCode> {
-    let _type = v["operation"]["type"].clone();
-    do_serialize_signature(v, true, _type.as_str())
+    let _type = v["operation"]["type"].as_str().and_then(TxnType::from_code);
+    do_serialize_signature(v, true, _type)
 }
 
-fn do_serialize_signature(v: serde_json::Value, is_top_level: bool, _type: Option<&str>) -> Result<String, ErrorCode> {
+fn do_serialize_signature(v: serde_json::Value, is_top_level: bool, _type: Option<TxnType>) -> Result<String, ErrorCode> {
     match v {
         serde_json::Value::Bool(value) => Ok(if value { "True".to_string() } else { "False".to_string() }),
         serde_json::Value::Number(value) => Ok(value.to_string()),
@@ -276,7 +312,7 @@ fn do_serialize_signature(v: serde_json::Value, is_top_level: bool, _type: Optio
                 }
 
                 let mut value = map[key].clone();
-                if (_type == Some(ATTRIB) || _type == Some(GET_ATTRIB)) && (key == "raw" || key == "hash" || key == "enc") {
+                if _type.map_or(false, TxnType::signs_attribute_hashes) && (key == "raw" || key == "hash" || key == "enc") {
                     // do it only for attribute related request
                     let mut ctx = Hash::new_context()?;
 
@@ -300,58 +336,75 @@ fn do_serialize_signature(v: serde_json::Value, is_top_level: bool, _type: Optio
 #[cfg(test)]
 mod test_xfer_payload {
     use super::*;
+    use logic::address::PaymentAddress;
     use logic::config::payment_address_config::PaymentAddressConfig;
     use logic::output::Output;
     use std::sync::mpsc::channel;
+    use utils::test::crypto_api::{signature, CryptoApiHandler};
+
+    fn address(address: &str) -> PaymentAddress {
+        return PaymentAddress::new(address).unwrap();
+    }
 
-    struct CryptoApiHandler {}
+    /**
+        A wallet which holds none of the keys of the inputs.
+    */
+    struct WalletWithoutKeys {}
 
-    impl CryptoAPI for CryptoApiHandler {
+    impl CryptoAPI for WalletWithoutKeys {
         fn indy_create_key(&self, _: IndyHandle, _: PaymentAddressConfig) -> Result<String, ErrorCode> {
-            return Err(ErrorCode::CommonInvalidState);
+            Err(ErrorCode::WalletInvalidHandle)
         }
 
-        fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, _wallet_handle: IndyHandle, verkey: String, _message: String, mut cb: F) -> ErrorCode {
-            cb(Ok(verkey + "signed"));
-            return ErrorCode::Success;
+        fn indy_create_key_async<F: 'static>(&self, _: i32, _: PaymentAddressConfig, _: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
+            ErrorCode::WalletInvalidHandle
         }
 
-        fn indy_create_key_async<F: 'static>(&self, _wallet_id: i32, _config: PaymentAddressConfig, _closure: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
-            return ErrorCode::CommonInvalidState;
+        fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, _wallet_handle: IndyHandle, _verkey: String, _message: String, mut cb: F) -> ErrorCode {
+            cb(Err(ErrorCode::WalletItemNotFound));
+            ErrorCode::Success
         }
     }
 
     fn inputs_outputs_valid() -> (Inputs, Outputs) {
         let outputs = vec![
-            Output::new(String::from("TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs"), 10),
-            Output::new(String::from("2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb"), 22),
+            Output::new(address("TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs"), 10),
+            Output::new(address("2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb"), 22),
         ];
 
         let inputs = vec![
-            Input::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 1),
-            Input::new(String::from("2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V"), 1),
+            Input::new(address("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 1),
+            Input::new(address("2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V"), 1),
         ];
 
         return (inputs, outputs);
     }
 
+    fn signature_of(verkey: &str, input: &Input, outputs: &Outputs) -> String {
+        signature(verkey, &signature_message(input, outputs, &None, &None, &None).unwrap())
+    }
+
     fn inputs_outputs_valid_qualified() -> (Inputs, Outputs) {
         let (inputs, outputs) = inputs_outputs_valid();
-        let inps = inputs.iter().map(|ref mut i| Input::new(address::add_qualifer_to_address(&i.address), i.seq_no)).collect::<Vec<Input>>();
-        let outs = outputs.iter().map(|ref mut o| Output::new(address::add_qualifer_to_address(&o.recipient), o.amount)).collect::<Vec<Output>>();
+        let inps = inputs.iter().map(|ref mut i| Input::new(i.address.with_qualifier(), i.seq_no)).collect::<Vec<Input>>();
+        let outs = outputs.iter().map(|ref mut o| Output::new(o.recipient.with_qualifier(), o.amount)).collect::<Vec<Output>>();
 
         return (inps, outs);
     }
 
     fn sign_input_sync(input: &Input, outputs: &Outputs, extra: &Option<Extra>) -> Result<String, ErrorCode> {
+        sign_input_sync_with(&CryptoApiHandler {}, input, outputs, extra)
+    }
+
+    fn sign_input_sync_with<A: CryptoAPI>(crypto_api: &'static A, input: &Input, outputs: &Outputs, extra: &Option<Extra>) -> Result<String, ErrorCode> {
         let wallet_handle = 1;
         let (sender, receiver) = channel();
         let sender = Mutex::new(sender);
         let cb = move |result, _| {
             sender.lock().unwrap().send(result).unwrap();
         };
-        XferPayload::sign_input(
-            &CryptoApiHandler {},
+        <XferPayload as InputSigner<A>>::sign_input(
+            crypto_api,
             wallet_handle,
             input,
             outputs,
@@ -365,22 +418,25 @@ mod test_xfer_payload {
     }
 
     fn sign_inputs_sync(inputs: &Inputs, outputs: &Outputs) -> Result<Vec<String>, ErrorCode> {
+        sign_inputs_sync_with(&CryptoApiHandler {}, inputs, outputs)
+    }
+
+    fn sign_inputs_sync_with<A: CryptoAPI>(crypto_api: &'static A, inputs: &Inputs, outputs: &Outputs) -> Result<Vec<String>, ErrorCode> {
         let wallet_handle = 1;
         let (sender, receiver) = channel();
         let sender = Mutex::new(sender);
         let cb = move |result| { sender.lock().unwrap().send(result).unwrap(); };
-        XferPayload::sign_inputs(&CryptoApiHandler {}, wallet_handle, inputs, outputs, &None, &None, &None,
+        <XferPayload as InputSigner<A>>::sign_inputs(crypto_api, wallet_handle, inputs, outputs, &None, &None, &None,
                                  Box::new(cb))?;
         receiver.recv().unwrap().map(|map| map.values().cloned().collect())
     }
 
     #[test]
     fn sign_input_invalid_address_input() {
-        let (mut inputs, outputs) = inputs_outputs_valid();
+        let (inputs, outputs) = inputs_outputs_valid();
 
-        String::remove(&mut inputs[0].address, 5);
-        let signed_input = sign_input_sync(&inputs[0], &outputs, &None).unwrap_err();
-        assert_eq!(ErrorCode::CommonInvalidStructure, signed_input);
+        let signed_input = sign_input_sync_with(&WalletWithoutKeys {}, &inputs[0], &outputs, &None).unwrap_err();
+        assert_eq!(ErrorCode::WalletItemNotFound, signed_input);
     }
 
     #[test]
@@ -389,17 +445,16 @@ mod test_xfer_payload {
 
         // Question: Why are signatures dummy values?
         let signature = sign_input_sync(&inputs[0], &outputs, &None).unwrap();
-        let expected = String::from("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnksigned");
+        let expected = signature_of("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnk", &inputs[0], &outputs);
         assert_eq!(expected, signature);
     }
 
     #[test]
     fn sign_multi_input_invalid_input_address() {
-        let (mut inputs, outputs) = inputs_outputs_valid();
-        String::remove(&mut inputs[0].address, 5);
+        let (inputs, outputs) = inputs_outputs_valid();
 
-        let signatures = sign_inputs_sync(&inputs, &outputs).unwrap_err();
-        assert_eq!(ErrorCode::CommonInvalidStructure, signatures);
+        let signatures = sign_inputs_sync_with(&WalletWithoutKeys {}, &inputs, &outputs).unwrap_err();
+        assert_eq!(ErrorCode::WalletItemNotFound, signatures);
     }
 
     #[test]
@@ -407,15 +462,15 @@ mod test_xfer_payload {
         let (inputs, outputs) = inputs_outputs_valid();
 
         let signed_inputs = sign_inputs_sync(&inputs, &outputs).unwrap();
-        assert!(signed_inputs.contains(&"31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnksigned".to_string()));
-        assert!(signed_inputs.contains(&"GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdtesigned".to_string()));
+        assert!(signed_inputs.contains(&signature_of("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnk", &inputs[0], &outputs)));
+        assert!(signed_inputs.contains(&signature_of("GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdte", &inputs[1], &outputs)));
     }
 
     #[test]
     fn sign_payload_invalid_output_address() {
         let wallet_handle = 1;
-        let (inputs, mut outputs) = inputs_outputs_valid_qualified();
-        String::remove(&mut outputs[0].recipient, 5);
+        let (inputs, _) = inputs_outputs_valid_qualified();
+        let (_, outputs) = inputs_outputs_valid();
 
         let payload = XferPayload::new(inputs, outputs, None);
         let (sender, _receiver) = channel();
@@ -429,8 +484,8 @@ mod test_xfer_payload {
     #[test]
     fn sign_payload_invalid_input_address() {
         let wallet_handle = 1;
-        let (mut inputs, outputs) = inputs_outputs_valid_qualified();
-        String::remove(&mut inputs[0].address, 13);
+        let (_, outputs) = inputs_outputs_valid_qualified();
+        let (inputs, _) = inputs_outputs_valid();
 
         let (sender, _receiver) = channel();
         let sender = Mutex::new(sender);
@@ -511,18 +566,10 @@ mod test_xfer_payload {
         let (inputs, outputs) = inputs_outputs_valid_qualified();
 
         // Question: Why are signatures dummy values?
-        let expected_inputs = vec![
-            Input::new(String::from("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm"), 1),
-            Input::new(String::from("2oWxuFMbhPewEbCEeKnvjcpVq8qpHHrN5y4aU81MWG5dYfeM7V"), 1),
-        ];
-
-        let expected_outputs = vec![
-            Output::new(String::from("TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs"), 10),
-            Output::new(String::from("2FKYJkgXRZtjhFpTMHhuyfc17BHZWcFPyF2MWy2SZMBaSo64fb"), 22),
-        ];
+        let (expected_inputs, expected_outputs) = inputs_outputs_valid();
 
-        let expected_signatures = Some(vec![String::from("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnksigned"),
-                                            String::from("GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdtesigned")]);
+        let expected_signatures = Some(vec![signature_of("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnk", &expected_inputs[0], &expected_outputs),
+                                            signature_of("GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdte", &expected_inputs[1], &expected_outputs)]);
 
 
         let (sender, receiver) = channel();
@@ -547,9 +594,10 @@ mod test_xfer_payload {
         let (mut inputs, outputs) = inputs_outputs_valid_qualified();
         inputs.reverse();
 
+        let (expected_inputs, expected_outputs) = inputs_outputs_valid();
         let expected_signatures = vec![
-            String::from("GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdtesigned"),
-            String::from("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnksigned"),
+            signature_of("GyPZzuu8S1KMs5p6iE1wBzjQsFtaB7eigssW4YbdXdte", &expected_inputs[1], &expected_outputs),
+            signature_of("31VzUm5vZRfWPk38W3YJaNjrkUeD6tELmjxv42cp7Vnk", &expected_inputs[0], &expected_outputs),
         ];
         let payload = XferPayload::new(inputs, outputs, None);
 
@@ -10,7 +10,8 @@ use std::sync::mpsc::{channel, Receiver};
 
 use indy_sys::{ResponseEmptyCB,
                ResponseStringCB,
-               ResponseSliceCB};
+               ResponseSliceCB,
+               ResponseBoolCB};
 
 use {ErrorCode, IndyHandle};
 use utils::sequence::SequenceUtils;
@@ -112,6 +113,36 @@ impl ClosureHandler {
 
         (command_handle, Some(_callback))
     }
+
+    pub fn cb_ec_bool() -> (Receiver<(ErrorCode, bool)>, IndyHandle, Option<ResponseBoolCB>) {
+        let (sender, receiver) = channel();
+
+        let closure = Box::new(move |err, val| {
+            sender.send((err, val)).unwrap_or_else(log_error);
+        });
+
+        let (command_handle, cb) = ClosureHandler::convert_cb_ec_bool(closure);
+
+        (receiver, command_handle, cb)
+    }
+
+    pub fn convert_cb_ec_bool(closure: Box<FnMut(ErrorCode, bool) + Send>) -> (IndyHandle, Option<ResponseBoolCB>) {
+        lazy_static! {
+            static ref CALLBACKS: Mutex<HashMap<i32, Box<FnMut(ErrorCode, bool) + Send>>> = Default::default();
+        }
+
+        extern "C" fn _callback(command_handle: IndyHandle, err: i32, val: bool) {
+            let mut callbacks = CALLBACKS.lock().unwrap();
+            let mut cb = callbacks.remove(&command_handle).unwrap();
+            cb(ErrorCode::from(err), val)
+        }
+
+        let mut callbacks = CALLBACKS.lock().unwrap();
+        let command_handle = SequenceUtils::get_next_id();
+        callbacks.insert(command_handle, closure);
+
+        (command_handle, Some(_callback))
+    }
 }
 
 #[cfg(test)]
@@ -8,6 +8,7 @@ pub static TXO_QUALIFIER: &str = "txo:sov:";
 
 pub const PROTOCOL_VERSION: u32 = 2;
 pub const LEDGER_ID: &str = "1001";
+pub const TOKEN_LEDGER_ID: u32 = 1001;
 
 /**
 Defines a callback to communicate results to Indy-sdk as type
@@ -6,4 +6,7 @@ pub const FEES: &'static str = "fees";
 pub const INPUTS: &'static str = "inputs";
 pub const OUTPUTS: &'static str = "outputs";
 pub const RESULT: &'static str = "result";
-pub const STATE_PROOF: &'static str = "state_proof";
\ No newline at end of file
+pub const STATE_PROOF: &'static str = "state_proof";pub const ROOT_HASH: &'static str = "rootHash";
+pub const AUDIT_PATH: &'static str = "auditPath";
+pub const TXN_METADATA: &'static str = "txnMetadata";
+pub const SEQ_NO: &'static str = "seqNo";
@@ -1,7 +1,13 @@
-//! Contains constants for transaction types
+//! Contains constants for transaction types and [`TxnType`], which they are the wire codes of
+//!
+//! [`TxnType`]: enum.TxnType.html
 
+use std::fmt;
+use std::str::FromStr;
 
-// TODO: Make them part of an Enum
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use ErrorCode;
 
 /**
     # description
@@ -22,6 +28,12 @@ pub const XFER_PUBLIC: &'static str = "10001";
 */
 pub const GET_UTXO: &'static str = "10002";
 
+/**
+    #description
+    The transaction the fees of a request are written to the token ledger as
+*/
+pub const FEE_TXN: &str = "10101";
+
 /**
     #description
     A transaction type submitted by Sovrin Trustees to set the Fees to process a transaction
@@ -35,9 +47,275 @@ pub const ATTRIB: &'static str = "100";
 
 pub const GET_ATTRIB: &'static str = "104";
 
+/**
+    #description
+    A transaction type for getting any transaction of a ledger by its seq_no
+*/
+pub const GET_TXN: &str = "3";
+
+pub const SCHEMA: &str = "101";
+
+pub const CRED_DEF: &str = "102";
+
+pub const REVOC_REG_DEF: &str = "113";
+
+pub const REVOC_REG_ENTRY: &str = "114";
+
+/**
+    #description
+    A transaction type submitted by Trustees to change who may do an action and which fee alias it costs
+*/
+pub const AUTH_RULE: &str = "120";
+
+/**
+    #description
+    A transaction type submitted by anyone to get the auth rules
+*/
+pub const GET_AUTH_RULE: &str = "121";
+
 /**
     #description
     A transaction type submitted by anyone to get the current Fees costs of every transaction
 */
 
-pub const GET_FEES: &'static str = "20001";
\ No newline at end of file
+pub const GET_FEES: &'static str = "20001";
+
+/**
+    The transaction types of the token plugins and the indy transaction types
+    the library deals with.
+
+    Serializes to and from the wire code, e.g. `"10001"` for `XferPublic`.
+
+    ```
+    use sovtoken::utils::constants::txn_types::TxnType;
+
+    let txn_type: TxnType = "100".parse().unwrap();
+    assert_eq!(TxnType::Attrib, txn_type);
+    assert!(txn_type.can_carry_fees());
+    assert!(!TxnType::XferPublic.can_carry_fees());
+    assert_eq!("20001", TxnType::GetFees.code());
+    ```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxnType {
+    Node,
+    Nym,
+    GetTxn,
+    TxnAuthorAgreement,
+    TxnAuthorAgreementAml,
+    GetTxnAuthorAgreement,
+    GetTxnAuthorAgreementAml,
+    Attrib,
+    Schema,
+    CredDef,
+    GetAttrib,
+    GetNym,
+    GetSchema,
+    GetCredDef,
+    PoolUpgrade,
+    PoolConfig,
+    RevocRegDef,
+    RevocRegEntry,
+    GetRevocRegDef,
+    GetRevocReg,
+    GetRevocRegDelta,
+    PoolRestart,
+    AuthRule,
+    GetAuthRule,
+    AuthRules,
+    MintPublic,
+    XferPublic,
+    GetUtxo,
+    FeeTxn,
+    SetFees,
+    GetFees,
+}
+
+impl TxnType {
+    pub const ALL: &'static [TxnType] = &[
+        TxnType::Node, TxnType::Nym, TxnType::GetTxn,
+        TxnType::TxnAuthorAgreement, TxnType::TxnAuthorAgreementAml,
+        TxnType::GetTxnAuthorAgreement, TxnType::GetTxnAuthorAgreementAml,
+        TxnType::Attrib, TxnType::Schema, TxnType::CredDef,
+        TxnType::GetAttrib, TxnType::GetNym, TxnType::GetSchema, TxnType::GetCredDef,
+        TxnType::PoolUpgrade, TxnType::PoolConfig,
+        TxnType::RevocRegDef, TxnType::RevocRegEntry,
+        TxnType::GetRevocRegDef, TxnType::GetRevocReg, TxnType::GetRevocRegDelta,
+        TxnType::PoolRestart, TxnType::AuthRule, TxnType::GetAuthRule, TxnType::AuthRules,
+        TxnType::MintPublic, TxnType::XferPublic, TxnType::GetUtxo, TxnType::FeeTxn,
+        TxnType::SetFees, TxnType::GetFees,
+    ];
+
+    /**
+        The code of the type in the `type` of an operation
+    */
+    pub fn code(self) -> &'static str {
+        match self {
+            TxnType::Node => "0",
+            TxnType::Nym => NYM,
+            TxnType::GetTxn => GET_TXN,
+            TxnType::TxnAuthorAgreement => "4",
+            TxnType::TxnAuthorAgreementAml => "5",
+            TxnType::GetTxnAuthorAgreement => "6",
+            TxnType::GetTxnAuthorAgreementAml => "7",
+            TxnType::Attrib => ATTRIB,
+            TxnType::Schema => SCHEMA,
+            TxnType::CredDef => CRED_DEF,
+            TxnType::GetAttrib => GET_ATTRIB,
+            TxnType::GetNym => "105",
+            TxnType::GetSchema => "107",
+            TxnType::GetCredDef => "108",
+            TxnType::PoolUpgrade => "109",
+            TxnType::PoolConfig => "111",
+            TxnType::RevocRegDef => REVOC_REG_DEF,
+            TxnType::RevocRegEntry => REVOC_REG_ENTRY,
+            TxnType::GetRevocRegDef => "115",
+            TxnType::GetRevocReg => "116",
+            TxnType::GetRevocRegDelta => "117",
+            TxnType::PoolRestart => "118",
+            TxnType::AuthRule => AUTH_RULE,
+            TxnType::GetAuthRule => GET_AUTH_RULE,
+            TxnType::AuthRules => "122",
+            TxnType::MintPublic => MINT_PUBLIC,
+            TxnType::XferPublic => XFER_PUBLIC,
+            TxnType::GetUtxo => GET_UTXO,
+            TxnType::FeeTxn => FEE_TXN,
+            TxnType::SetFees => SET_FEES,
+            TxnType::GetFees => GET_FEES,
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<TxnType> {
+        TxnType::ALL.iter().cloned().find(|txn_type| txn_type.code() == code)
+    }
+
+    /**
+        true for the requests which only read from the ledger
+    */
+    pub fn is_read(self) -> bool {
+        match self {
+            TxnType::GetTxn
+            | TxnType::GetTxnAuthorAgreement
+            | TxnType::GetTxnAuthorAgreementAml
+            | TxnType::GetAttrib
+            | TxnType::GetNym
+            | TxnType::GetSchema
+            | TxnType::GetCredDef
+            | TxnType::GetRevocRegDef
+            | TxnType::GetRevocReg
+            | TxnType::GetRevocRegDelta
+            | TxnType::GetAuthRule
+            | TxnType::GetUtxo
+            | TxnType::GetFees => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_write(self) -> bool {
+        !self.is_read()
+    }
+
+    /**
+        true for the writes to the domain ledger, which the ledger charges fees
+        for through their auth rules. A XFER_PUBLIC pays its fees out of its
+        own inputs instead.
+    */
+    pub fn can_carry_fees(self) -> bool {
+        match self {
+            TxnType::Nym
+            | TxnType::Attrib
+            | TxnType::Schema
+            | TxnType::CredDef
+            | TxnType::RevocRegDef
+            | TxnType::RevocRegEntry => true,
+            _ => false,
+        }
+    }
+
+    /**
+        true when the `raw`, `hash` and `enc` values of the operation are
+        signed by their sha256 instead of their value
+    */
+    pub fn signs_attribute_hashes(self) -> bool {
+        match self {
+            TxnType::Attrib | TxnType::GetAttrib => true,
+            _ => false,
+        }
+    }
+
+    /**
+        true when the request has to be signed by a quorum of trustees
+    */
+    pub fn needs_trustee_quorum(self) -> bool {
+        match self {
+            TxnType::MintPublic | TxnType::SetFees => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for TxnType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for TxnType {
+    type Err = ErrorCode;
+
+    fn from_str(code: &str) -> Result<TxnType, ErrorCode> {
+        TxnType::from_code(code).ok_or_else(|| {
+            error!("Unknown transaction type {}", code);
+            ErrorCode::CommonInvalidStructure
+        })
+    }
+}
+
+impl Serialize for TxnType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for TxnType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<TxnType, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        TxnType::from_code(&code).ok_or_else(|| de::Error::custom(format!("Unknown transaction type {}", code)))
+    }
+}
+
+#[cfg(test)]
+mod txn_types_tests {
+    use super::*;
+    use serde_json;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique_and_parse_back() {
+        let codes: HashSet<&str> = TxnType::ALL.iter().map(|txn_type| txn_type.code()).collect();
+        assert_eq!(TxnType::ALL.len(), codes.len());
+
+        for txn_type in TxnType::ALL {
+            assert_eq!(Ok(*txn_type), txn_type.code().parse());
+        }
+        assert_eq!(Err(ErrorCode::CommonInvalidStructure), "30000".parse::<TxnType>());
+    }
+
+    #[test]
+    fn serialize_to_wire_code() {
+        assert_eq!(json!("10001"), serde_json::to_value(TxnType::XferPublic).unwrap());
+        assert_eq!(TxnType::RevocRegEntry, serde_json::from_value::<TxnType>(json!("114")).unwrap());
+        assert!(serde_json::from_value::<TxnType>(json!("30000")).is_err());
+        assert!(serde_json::from_value::<TxnType>(json!(1)).is_err());
+    }
+
+    #[test]
+    fn metadata() {
+        assert!(TxnType::GetUtxo.is_read());
+        assert!(TxnType::Nym.is_write());
+        assert!(!TxnType::GetNym.can_carry_fees());
+        assert!(!TxnType::MintPublic.can_carry_fees());
+        assert!(TxnType::GetAttrib.signs_attribute_hashes());
+        assert!(TxnType::SetFees.needs_trustee_quorum());
+    }
+}
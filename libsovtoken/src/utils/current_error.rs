@@ -0,0 +1,154 @@
+//! The last error of each thread, for `sovtoken_get_current_error`
+//!
+//! The logic layer keeps the context of an error here, since the api only
+//! returns its `ErrorCode`.
+
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::fmt::Display;
+
+use libc::c_char;
+use serde_json::{self, Value};
+
+use ErrorCode;
+
+thread_local! {
+    static CURRENT_ERROR: RefCell<Option<(ErrorDetails, CString)>> = RefCell::new(None);
+}
+
+/**
+    The context of an error
+
+    # parameters
+    message - what failed
+    parameter - the api parameter which was invalid, if one was
+    causes - why it failed, the outermost cause first
+    details - structured details, e.g. of a rejection by the ledger
+*/
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ErrorDetails {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<String>,
+    pub causes: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ErrorDetails {
+    pub fn new(message: &str) -> ErrorDetails {
+        ErrorDetails { message: message.to_string(), parameter: None, causes: Vec::new(), details: None }
+    }
+
+    pub fn parameter(mut self, parameter: &str) -> ErrorDetails {
+        self.parameter = Some(parameter.to_string());
+        self
+    }
+
+    pub fn cause<C: Display>(mut self, cause: C) -> ErrorDetails {
+        self.causes.push(cause.to_string());
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> ErrorDetails {
+        self.details = Some(details);
+        self
+    }
+}
+
+/**
+    Makes `details` the current error of this thread.
+*/
+pub fn set_current_error(details: ErrorDetails) {
+    error!("{:?}", details);
+    let json = serde_json::to_string(&details).unwrap_or_else(|_| String::from("{}"));
+    let json = CString::new(json).unwrap_or_default();
+    CURRENT_ERROR.with(|current| *current.borrow_mut() = Some((details, json)));
+}
+
+pub fn current_error() -> Option<ErrorDetails> {
+    CURRENT_ERROR.with(|current| current.borrow().as_ref().map(|(details, _)| details.clone()))
+}
+
+/**
+    The current error of this thread as json, or null. The string is owned
+    by the thread and stays valid until its next error.
+*/
+pub fn current_error_c_json() -> *const c_char {
+    CURRENT_ERROR.with(|current| match *current.borrow() {
+        Some((_, ref json)) => json.as_ptr(),
+        None => ::std::ptr::null(),
+    })
+}
+
+/**
+    Records that `parameter` couldn't be deserialized, for `map_err`.
+
+    ```ignore
+    let inputs: Inputs = serde_json::from_str(&inputs_json).map_err(invalid_parameter("inputs_json"))?;
+    ```
+*/
+pub fn invalid_parameter<E: Display>(parameter: &'static str) -> impl Fn(E) -> ErrorCode {
+    move |cause| {
+        set_current_error(ErrorDetails::new(&format!("Invalid {}", parameter)).parameter(parameter).cause(cause));
+        ErrorCode::CommonInvalidStructure
+    }
+}
+
+/**
+    Records that a reply of the ledger couldn't be deserialized, for `map_err`.
+*/
+pub fn invalid_reply<E: Display>(cause: E) -> ErrorCode {
+    set_current_error(ErrorDetails::new("Invalid reply").cause(cause));
+    ErrorCode::CommonInvalidStructure
+}
+
+/**
+    Records that `parameter` is null, for `ok_or_else`.
+*/
+pub fn missing_parameter(parameter: &'static str) -> ErrorCode {
+    set_current_error(ErrorDetails::new(&format!("Missing {}", parameter)).parameter(parameter));
+    ErrorCode::CommonInvalidStructure
+}
+
+#[cfg(test)]
+mod current_error_tests {
+    use super::*;
+    use std::thread;
+    use utils::ffi_support::string_from_char_ptr;
+
+    #[test]
+    fn invalid_parameter_is_current() {
+        let result: Result<Value, ErrorCode> = serde_json::from_str::<Value>("{").map_err(invalid_parameter("inputs_json"));
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, result.unwrap_err());
+        let error = current_error().unwrap();
+        assert_eq!("Invalid inputs_json", error.message);
+        assert_eq!(Some(String::from("inputs_json")), error.parameter);
+        assert_eq!(1, error.causes.len());
+    }
+
+    #[test]
+    fn current_error_as_json() {
+        set_current_error(ErrorDetails::new("Invalid payment address").cause("the qualifier is missing").details(json!({"address": "2jS4"})));
+
+        let json: Value = serde_json::from_str(&string_from_char_ptr(current_error_c_json()).unwrap()).unwrap();
+        assert_eq!(json!({
+            "message": "Invalid payment address",
+            "causes": ["the qualifier is missing"],
+            "details": {"address": "2jS4"}
+        }), json);
+    }
+
+    #[test]
+    fn current_error_per_thread() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, missing_parameter("cb"));
+
+        thread::spawn(|| {
+            assert!(current_error().is_none());
+            assert!(current_error_c_json().is_null());
+        }).join().unwrap();
+
+        assert_eq!(Some(String::from("cb")), current_error().unwrap().parameter);
+    }
+}
@@ -19,10 +19,13 @@
 pub mod base58;
 pub mod callbacks;
 pub mod constants;
+pub mod current_error;
 pub mod general;
+pub mod msgpack;
 pub mod random;
 pub mod sequence;
 pub mod results;
+pub mod rlp;
 #[cfg(any(test, feature = "integration"))]
 pub mod test;
 pub mod txn_author_agreement;
\ No newline at end of file
@@ -0,0 +1,190 @@
+//! MessagePack serialization of json values
+
+use serde_json::Value;
+
+/**
+    Serializes a json value to MessagePack the way the ledger does.
+
+    Map keys are sorted, integers take their smallest encoding, floats are
+    64 bit and strings use the str family (the ledger packs with
+    `use_bin_type=True`).
+*/
+pub fn to_msgpack(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_value(&mut bytes, value);
+    bytes
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn write_value(bytes: &mut Vec<u8>, value: &Value) {
+    match *value {
+        Value::Null => bytes.push(0xc0),
+        Value::Bool(false) => bytes.push(0xc2),
+        Value::Bool(true) => bytes.push(0xc3),
+        Value::Number(ref number) => {
+            if let Some(n) = number.as_u64() {
+                write_uint(bytes, n);
+            } else if let Some(n) = number.as_i64() {
+                write_int(bytes, n);
+            } else if let Some(f) = number.as_f64() {
+                bytes.push(0xcb);
+                write_be(bytes, f.to_bits(), 8);
+            }
+        }
+        Value::String(ref s) => write_str(bytes, s),
+        Value::Array(ref array) => {
+            write_len(bytes, array.len(), 0x90, 0xdc);
+            for item in array {
+                write_value(bytes, item);
+            }
+        }
+        Value::Object(ref map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            write_len(bytes, entries.len(), 0x80, 0xde);
+            for (key, item) in entries {
+                write_str(bytes, key);
+                write_value(bytes, item);
+            }
+        }
+    }
+}
+
+fn write_uint(bytes: &mut Vec<u8>, n: u64) {
+    if n < 0x80 {
+        bytes.push(n as u8);
+    } else if n <= 0xff {
+        bytes.push(0xcc);
+        write_be(bytes, n, 1);
+    } else if n <= 0xffff {
+        bytes.push(0xcd);
+        write_be(bytes, n, 2);
+    } else if n <= 0xffff_ffff {
+        bytes.push(0xce);
+        write_be(bytes, n, 4);
+    } else {
+        bytes.push(0xcf);
+        write_be(bytes, n, 8);
+    }
+}
+
+fn write_int(bytes: &mut Vec<u8>, n: i64) {
+    if n >= -32 {
+        bytes.push(n as u8);
+    } else if n >= -0x80 {
+        bytes.push(0xd0);
+        write_be(bytes, n as u64, 1);
+    } else if n >= -0x8000 {
+        bytes.push(0xd1);
+        write_be(bytes, n as u64, 2);
+    } else if n >= -0x8000_0000 {
+        bytes.push(0xd2);
+        write_be(bytes, n as u64, 4);
+    } else {
+        bytes.push(0xd3);
+        write_be(bytes, n as u64, 8);
+    }
+}
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    let len = s.len();
+    if len < 32 {
+        bytes.push(0xa0 | len as u8);
+    } else if len <= 0xff {
+        bytes.push(0xd9);
+        write_be(bytes, len as u64, 1);
+    } else if len <= 0xffff {
+        bytes.push(0xda);
+        write_be(bytes, len as u64, 2);
+    } else {
+        bytes.push(0xdb);
+        write_be(bytes, len as u64, 4);
+    }
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+/*
+    Arrays and maps share their encoding of the length, `fix` is the tag of
+    lengths below 16 and `tag16` the tag with a 16 bit length, followed by
+    the one with a 32 bit length.
+*/
+fn write_len(bytes: &mut Vec<u8>, len: usize, fix: u8, tag16: u8) {
+    if len < 16 {
+        bytes.push(fix | len as u8);
+    } else if len <= 0xffff {
+        bytes.push(tag16);
+        write_be(bytes, len as u64, 2);
+    } else {
+        bytes.push(tag16 + 1);
+        write_be(bytes, len as u64, 4);
+    }
+}
+
+fn write_be(bytes: &mut Vec<u8>, n: u64, size: usize) {
+    for i in (0..size).rev() {
+        bytes.push((n >> (8 * i)) as u8);
+    }
+}
+
+#[cfg(test)]
+mod msgpack_tests {
+    use super::*;
+
+    fn assert_packs(value: Value, expected: &[u8]) {
+        assert_eq!(expected.to_vec(), to_msgpack(&value));
+    }
+
+    #[test]
+    fn pack_scalars() {
+        assert_packs(json!(null), &[0xc0]);
+        assert_packs(json!(true), &[0xc3]);
+        assert_packs(json!(false), &[0xc2]);
+        assert_packs(json!(1.5), &[0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn pack_integers_smallest() {
+        assert_packs(json!(0), &[0x00]);
+        assert_packs(json!(127), &[0x7f]);
+        assert_packs(json!(128), &[0xcc, 0x80]);
+        assert_packs(json!(256), &[0xcd, 0x01, 0x00]);
+        assert_packs(json!(65536), &[0xce, 0x00, 0x01, 0x00, 0x00]);
+        assert_packs(json!(4294967296u64), &[0xcf, 0, 0, 0, 0x01, 0, 0, 0, 0]);
+        assert_packs(json!(-1), &[0xff]);
+        assert_packs(json!(-32), &[0xe0]);
+        assert_packs(json!(-33), &[0xd0, 0xdf]);
+        assert_packs(json!(-129), &[0xd1, 0xff, 0x7f]);
+        assert_packs(json!(-32769), &[0xd2, 0xff, 0xff, 0x7f, 0xff]);
+    }
+
+    #[test]
+    fn pack_strings() {
+        assert_packs(json!("a"), &[0xa1, 0x61]);
+
+        let long = "x".repeat(40);
+        let mut expected = vec![0xd9, 40];
+        expected.extend_from_slice(long.as_bytes());
+        assert_packs(json!(long), &expected);
+    }
+
+    #[test]
+    fn pack_map_with_sorted_keys() {
+        assert_packs(
+            json!({"b": [1, 2], "a": {"c": "d"}}),
+            &[0x82, 0xa1, 0x61, 0x81, 0xa1, 0x63, 0xa1, 0x64, 0xa1, 0x62, 0x92, 0x01, 0x02]
+        );
+    }
+
+    #[test]
+    fn pack_long_array() {
+        let mut expected = vec![0xdc, 0x00, 0x10];
+        expected.extend_from_slice(&[0x01; 16]);
+        assert_packs(json!([1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]), &expected);
+    }
+}
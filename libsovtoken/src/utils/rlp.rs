@@ -0,0 +1,140 @@
+//! Decoding of Recursive Length Prefix (RLP) data
+
+use ErrorCode;
+
+/**
+    A decoded RLP item, with the bytes it was decoded from.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RlpItem<'a> {
+    pub raw: &'a [u8],
+    pub value: RlpValue<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RlpValue<'a> {
+    Bytes(&'a [u8]),
+    List(Vec<RlpItem<'a>>),
+}
+
+impl<'a> RlpItem<'a> {
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.value {
+            RlpValue::Bytes(bytes) => Some(bytes),
+            RlpValue::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[RlpItem<'a>]> {
+        match self.value {
+            RlpValue::Bytes(_) => None,
+            RlpValue::List(ref items) => Some(items),
+        }
+    }
+}
+
+/**
+    Decodes `data`, which has to be exactly one item.
+
+    Errors with `CommonInvalidStructure` on truncated data or trailing bytes.
+*/
+pub fn decode<'a>(data: &'a [u8]) -> Result<RlpItem<'a>, ErrorCode> {
+    let (item, rest) = decode_item(data)?;
+    if !rest.is_empty() {
+        error!("{} bytes after the rlp item", rest.len());
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+    Ok(item)
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn decode_item<'a>(data: &'a [u8]) -> Result<(RlpItem<'a>, &'a [u8]), ErrorCode> {
+    let prefix = *data.first().ok_or(ErrorCode::CommonInvalidStructure)?;
+
+    let (header_len, payload_len, is_list) = match prefix {
+        0x00..=0x7f => return Ok((RlpItem { raw: &data[..1], value: RlpValue::Bytes(&data[..1]) }, &data[1..])),
+        0x80..=0xb7 => (1, (prefix - 0x80) as usize, false),
+        0xb8..=0xbf => {
+            let len_of_len = (prefix - 0xb7) as usize;
+            (1 + len_of_len, read_len(data, len_of_len)?, false)
+        }
+        0xc0..=0xf7 => (1, (prefix - 0xc0) as usize, true),
+        _ => {
+            let len_of_len = (prefix - 0xf7) as usize;
+            (1 + len_of_len, read_len(data, len_of_len)?, true)
+        }
+    };
+
+    let end = header_len.checked_add(payload_len)
+        .filter(|end| *end <= data.len())
+        .ok_or(ErrorCode::CommonInvalidStructure)?;
+    let raw = &data[..end];
+    let mut payload = &data[header_len..end];
+
+    let value = if is_list {
+        let mut items = Vec::new();
+        while !payload.is_empty() {
+            let (item, rest) = decode_item(payload)?;
+            items.push(item);
+            payload = rest;
+        }
+        RlpValue::List(items)
+    } else {
+        RlpValue::Bytes(payload)
+    };
+
+    Ok((RlpItem { raw, value }, &data[end..]))
+}
+
+fn read_len(data: &[u8], len_of_len: usize) -> Result<usize, ErrorCode> {
+    if len_of_len > 8 || data.len() < 1 + len_of_len {
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    Ok(data[1..1 + len_of_len].iter().fold(0, |len, byte| (len << 8) | *byte as usize))
+}
+
+#[cfg(test)]
+mod rlp_tests {
+    use super::*;
+
+    #[test]
+    fn decode_strings() {
+        assert_eq!(Some(&b"\x0f"[..]), decode(&[0x0f]).unwrap().as_bytes());
+        assert_eq!(Some(&b"dog"[..]), decode(b"\x83dog").unwrap().as_bytes());
+        assert_eq!(Some(&b""[..]), decode(&[0x80]).unwrap().as_bytes());
+
+        let long = [b'a'; 56];
+        let mut encoded = vec![0xb8, 56];
+        encoded.extend_from_slice(&long);
+        assert_eq!(Some(&long[..]), decode(&encoded).unwrap().as_bytes());
+    }
+
+    #[test]
+    fn decode_lists() {
+        let encoded = b"\xc8\x83cat\x83dog";
+        let item = decode(encoded).unwrap();
+        let list = item.as_list().unwrap();
+
+        assert_eq!(&encoded[..], item.raw);
+        assert_eq!(Some(&b"cat"[..]), list[0].as_bytes());
+        assert_eq!(&b"\x83dog"[..], list[1].raw);
+
+        // [ [], [[]], [ [], [[]] ] ]
+        let nested = decode(&[0xc7, 0xc0, 0xc1, 0xc0, 0xc3, 0xc0, 0xc1, 0xc0]).unwrap();
+        assert_eq!(3, nested.as_list().unwrap().len());
+    }
+
+    #[test]
+    fn decode_invalid() {
+        assert_eq!(ErrorCode::CommonInvalidStructure, decode(&[]).unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, decode(b"\x84dog").unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, decode(b"\x83dogs").unwrap_err());
+        assert_eq!(ErrorCode::CommonInvalidStructure, decode(&[0xb9, 0x01]).unwrap_err());
+    }
+}
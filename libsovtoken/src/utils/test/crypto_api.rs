@@ -0,0 +1,53 @@
+/*!
+    A [`CryptoAPI`] which needs neither libindy nor keys, for *TESTS ONLY*.
+
+    [`CryptoApiHandler`] creates random verkeys and "signs" a message with
+    [`signature`], the verkey and the message side by side. Its verification
+    recomputes them, so a signature only verifies for the message it was made
+    for, which is enough to test which messages get signed.
+
+    [`CryptoAPI`]: ../../../logic/indy_sdk_api/crypto_api/trait.CryptoAPI.html
+    [`CryptoApiHandler`]: struct.CryptoApiHandler.html
+    [`signature`]: fn.signature.html
+*/
+
+use {ErrorCode, IndyHandle};
+use logic::address::VERKEY_LEN;
+use logic::config::payment_address_config::PaymentAddressConfig;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use utils::base58::IntoBase58;
+use utils::random::rand_bytes;
+
+pub struct CryptoApiHandler {}
+
+impl CryptoAPI for CryptoApiHandler {
+    fn indy_create_key(&self, _: IndyHandle, _: PaymentAddressConfig) -> Result<String, ErrorCode> {
+        Ok(rand_bytes(VERKEY_LEN).into_base58())
+    }
+
+    fn indy_create_key_async<F: 'static>(&self, wallet_id: i32, config: PaymentAddressConfig, mut closure: F) -> ErrorCode where F: FnMut(ErrorCode, String) + Send {
+        match self.indy_create_key(wallet_id, config) {
+            Ok(verkey) => closure(ErrorCode::Success, verkey),
+            Err(ec) => closure(ec, String::new()),
+        };
+        ErrorCode::Success
+    }
+
+    fn indy_crypto_sign<F: FnMut(Result<String, ErrorCode>) + 'static + Send>(&self, _wallet_handle: IndyHandle, verkey: String, message: String, mut cb: F) -> ErrorCode {
+        cb(Ok(signature(&verkey, &message)));
+        ErrorCode::Success
+    }
+
+    fn indy_crypto_verify(&self, verkey: String, message: String, signature_: String) -> Result<bool, ErrorCode> {
+        Ok(signature_ == signature(&verkey, &message))
+    }
+}
+
+/**
+    The signature [`CryptoApiHandler`] makes of `message` with `verkey`.
+
+    [`CryptoApiHandler`]: struct.CryptoApiHandler.html
+*/
+pub fn signature(verkey: &str, message: &str) -> String {
+    format!("{}|{}", verkey, message)
+}
@@ -9,15 +9,26 @@ use utils::constants::txn_types;
 use utils::ffi_support::c_pointer_from_string;
 use utils::random::rand_string;
 
+use logic::address::PaymentAddress;
+use logic::keystore_crypto::KeystoreCrypto;
 use logic::parsers::common::TXO;
+use logic::parsers::parse_get_utxo_response::UTXO;
 use logic::input::{Input, Inputs};
 use logic::output::{Output, Outputs};
+use logic::type_aliases::{TokenAmount, TxnSeqNo};
 use logic::xfer_payload::XferPayload;
 
+lazy_static! {
+    /**
+        In memory keystore shared by the tests which sign with real keys.
+    */
+    pub static ref KEYSTORE: KeystoreCrypto = KeystoreCrypto::new();
+}
+
 
 pub fn inputs_json_pointer() -> *const c_char {
-    let txo_1 = TXO { address: "pay:sov:d0kitWxupHvZ4i0NHJhoj79RcUeyt3YlwAc8Hbcy87iRLSZC".to_string(), seq_no: 2 };
-    let txo_2 = TXO { address: "pay:sov:d0kitWxupHvZ4i0NHJhoj79RcUeyt3YlwAc8Hbcy87iRLSZC".to_string(), seq_no: 2 };
+    let txo_1 = TXO { address: "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd".to_string(), seq_no: 2 };
+    let txo_2 = TXO { address: "pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd".to_string(), seq_no: 2 };
     json_c_pointer!([
         txo_1.to_libindy_string().unwrap(),
         txo_2.to_libindy_string().unwrap()
@@ -25,8 +36,8 @@ pub fn inputs_json_pointer() -> *const c_char {
 }
 
 pub fn inputs() -> Inputs {
-    let address1 = String::from("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd");
-    let address2 = String::from("pay:sov:BUoojqSQTLuvjkun4y2YoseVF76UZ3uYfHF1dbQyZVbCuTwQo");
+    let address1 = PaymentAddress::new("pay:sov:iTQzpdRdugkJ2gLD5vW5c159dncSL9jbAtu3WfPcb8qWD9bUd").unwrap();
+    let address2 = PaymentAddress::new("pay:sov:BUoojqSQTLuvjkun4y2YoseVF76UZ3uYfHF1dbQyZVbCuTwQo").unwrap();
 
     vec![
         Input::new(address1, 1),
@@ -35,8 +46,8 @@ pub fn inputs() -> Inputs {
 }
 
 pub fn outputs() -> Outputs {
-    let address1 = String::from("pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A");
-    let address2 = String::from("pay:sov:YissN67riFhQ8W6viqtJoCRHFkXtqxaxeL9UyvCoz8sXq5B5A");
+    let address1 = PaymentAddress::new("pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A").unwrap();
+    let address2 = PaymentAddress::new("pay:sov:YissN67riFhQ8W6viqtJoCRHFkXtqxaxeL9UyvCoz8sXq5B5A").unwrap();
 
    vec![
         Output::new(address1, 10),
@@ -47,7 +58,7 @@ pub fn outputs() -> Outputs {
 pub fn outputs_json_pointer() -> *const c_char {
     json_c_pointer!([
         {
-            "address": "pay:sov:ql33nBkjGw6szxPT6LLRUIejn9TZAYkVRPd0QJzfJ8FdhZWs",
+            "address": "pay:sov:2naF9c9ZJnSRtpaptpyZxi18tfozozqFRTmxHk9M6wbBc68T9A",
             "amount": 10
         }
     ])
@@ -89,13 +100,13 @@ pub fn xfer_payload_signed() -> XferPayload {
     let sig2 = "2rUrhusR7TmkFs9cyNeHoq2EZ6LQH2RvKSZnJMPJHRSEDAb3aj4GxkvX79JASiHLxMmtz1stu4ysjXpUYZGVCSvr";
 
     let inputs = vec![
-        Input::new(String::from(ver1), 1),
-        Input::new(String::from(ver2), 2)
+        Input::new(PaymentAddress::new(ver1).unwrap(), 1),
+        Input::new(PaymentAddress::new(ver2).unwrap(), 2)
     ];
 
     let outputs = vec![
-        Output::new(String::from(ver3), 10),
-        Output::new(String::from(ver4), 22)
+        Output::new(PaymentAddress::new(ver3).unwrap(), 10),
+        Output::new(PaymentAddress::new(ver4).unwrap(), 22)
     ];
 
     let signatures = Some(vec![
@@ -111,6 +122,18 @@ pub fn xfer_payload_signed() -> XferPayload {
     }
 }
 
+/**
+    The UTXO of `address` at `seq_no`, as parse_get_utxo_response_handler returns it.
+*/
+pub fn utxo(address: &str, seq_no: TxnSeqNo, amount: TokenAmount) -> UTXO {
+    UTXO {
+        payment_address: address.to_string(),
+        source: TXO { address: address.to_string(), seq_no }.to_libindy_string().unwrap(),
+        amount,
+        extra: "".to_string(),
+    }
+}
+
 pub fn create_address_config() -> *const c_char {
     json_c_pointer!({})
 }
@@ -0,0 +1,791 @@
+/*!
+    In-process stand-in for an indy pool running the sovtoken plugin, for
+    *TESTS ONLY*.
+
+    [`MockLedger`] takes the json of the requests our builders produce and
+    answers with the replies our parsers consume, without a pool:
+
+    * MINT_PUBLIC and SET_FEES need valid signatures of a quorum of trustees.
+    * XFER_PUBLIC spends sources whose input signatures are valid. The inputs
+      have to hold exactly the outputs and the XFER_PUBLIC fee.
+    * Domain writes (NYM, ATTRIB, SCHEMA, ...) carry `fees` when the ledger
+      charges fees for their type, and must not carry any otherwise.
+    * GET_UTXO, GET_FEES and GET_TXN of the token ledger read the state.
+
+    Fees are keyed by txn type and the domain requests themselves aren't
+    authenticated. There is no merkle tree: the `rootHash` of a transaction is
+    the digest of its request and its `auditPath` is empty.
+
+    A request which is malformed or not signed as it must be is answered with
+    a REQNACK, one which conflicts with the state of the ledger (a spent or
+    unknown source, inputs which don't match the outputs and fees) with a
+    REJECT, and the reasons are the ones the ledger gives.
+
+    [`MockLedger`]: struct.MockLedger.html
+*/
+
+use serde::de::DeserializeOwned;
+use serde_json;
+use serde_json::Value as SJsonValue;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::channel;
+use time;
+
+use logic::address::PaymentAddress;
+use logic::api_internals::add_request_fees::{txn_digest, SerdeMap};
+use logic::config::output_mint_config::MintRequest;
+use logic::config::payment_address_config::PaymentAddressConfig;
+use logic::did::Did;
+use logic::indy_sdk_api::crypto_api::CryptoAPI;
+use logic::input::{Input, Inputs};
+use logic::keystore_crypto::KeystoreCrypto;
+use logic::output::{Output, Outputs};
+use logic::parsers::common::ResponseOperations;
+use logic::request::DEFAULT_LIBSOVTOKEN_DID;
+use logic::signature_verification::{verify_fees_request, verify_transfer_request, VerificationReport};
+use logic::type_aliases::{ReqId, TokenAmount, TxnSeqNo};
+use logic::xfer_payload::serialize_signature;
+use utils::constants::general::{PROTOCOL_VERSION, TOKEN_LEDGER_ID};
+use utils::constants::txn_fields::{FEES, INPUTS, OUTPUTS};
+use utils::constants::txn_types::TxnType;
+
+const DOMAIN_LEDGER_ID: u32 = 1;
+const CONFIG_LEDGER_ID: u32 = 2;
+const MINT_TRUSTEE_DID: &str = "LnXR1rPnncTPZvRdmJKhJQ";
+const MINT_TRUSTEE_SEED: &str = "000000000000000000000000Trustee2";
+
+/**
+    `(unqualified address, seq_no)` of a source
+*/
+type SourceKey = (String, TxnSeqNo);
+
+/**
+    The state of the ledger and the requests it accepts.
+
+    ```
+    use sovtoken::utils::test::mock_ledger::MockLedger;
+
+    let mut ledger = MockLedger::new();
+    let reply = ledger.submit(r#"{"reqId": 1, "identifier": "V4SGRU86Z58d6TV7PBUe6f", "operation": {"type": "20001"}}"#);
+    assert!(reply.contains("REPLY"));
+    ```
+*/
+pub struct MockLedger {
+    trustees: HashMap<String, String>,
+    trustee_quorum: usize,
+    fees: HashMap<String, TokenAmount>,
+    utxos: HashMap<SourceKey, TokenAmount>,
+    spent: HashSet<SourceKey>,
+    token_txns: Vec<SJsonValue>,
+    seq_nos: HashMap<u32, TxnSeqNo>,
+    utxo_page_size: Option<usize>,
+}
+
+impl MockLedger {
+    /**
+        An empty ledger without trustees, fees or sources, where MINT_PUBLIC
+        and SET_FEES need the signature of one trustee.
+    */
+    pub fn new() -> MockLedger {
+        MockLedger {
+            trustees: HashMap::new(),
+            trustee_quorum: 1,
+            fees: HashMap::new(),
+            utxos: HashMap::new(),
+            spent: HashSet::new(),
+            token_txns: Vec::new(),
+            seq_nos: HashMap::new(),
+            utxo_page_size: None,
+        }
+    }
+
+    /**
+        Adds a trustee, whose requests are signed by the key of `verkey`.
+    */
+    pub fn add_trustee(&mut self, did: &str, verkey: &str) {
+        self.trustees.insert(did.to_string(), verkey.to_string());
+    }
+
+    /**
+        How many trustees have to sign a MINT_PUBLIC or SET_FEES.
+    */
+    pub fn set_trustee_quorum(&mut self, quorum: usize) {
+        self.trustee_quorum = quorum;
+    }
+
+    /**
+        Replaces the fees by txn type, without a SET_FEES.
+    */
+    pub fn set_fees(&mut self, fees: HashMap<String, TokenAmount>) {
+        self.fees = fees;
+    }
+
+    pub fn fees(&self) -> &HashMap<String, TokenAmount> {
+        &self.fees
+    }
+
+    /**
+        Pages the outputs of GET_UTXO replies by `page_size`, with the seq_no
+        of the next page in `next`. `None` replies with all outputs.
+    */
+    pub fn set_utxo_page_size(&mut self, page_size: Option<usize>) {
+        self.utxo_page_size = page_size;
+    }
+
+    /**
+        The amount of the unspent sources of `address`.
+    */
+    pub fn balance(&self, address: &PaymentAddress) -> TokenAmount {
+        self.utxos.iter()
+            .filter(|((unqualified, _), _)| unqualified == address.unqualified())
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    /**
+        Mints `amount` to `address` and returns the reply of the ledger.
+
+        The MINT_PUBLIC is signed by a trustee of the ledger's own, which is
+        added by the first mint and makes a quorum of one on its own.
+    */
+    pub fn mint_to(&mut self, address: &PaymentAddress, amount: TokenAmount) -> String {
+        let keystore = KeystoreCrypto::new();
+        let verkey = keystore.indy_create_key(0, PaymentAddressConfig { seed: MINT_TRUSTEE_SEED.to_string(), ..Default::default() })
+            .expect("The seed of the mint trustee is valid");
+        self.add_trustee(MINT_TRUSTEE_DID, &verkey);
+
+        let request_json = MintRequest::new(vec![Output::new(address.clone(), amount)], Some(Did::new(MINT_TRUSTEE_DID.to_string())), None)
+            .serialize_to_string()
+            .expect("A MINT_PUBLIC serializes");
+        let mut request: SJsonValue = serde_json::from_str(&request_json).expect("A MINT_PUBLIC is json");
+        let message = serialize_signature(request.clone()).expect("A MINT_PUBLIC can be signed");
+
+        let (sender, receiver) = channel();
+        keystore.indy_crypto_sign(0, verkey, message, move |signature| sender.send(signature).unwrap());
+        request["signatures"][MINT_TRUSTEE_DID] = json!(receiver.recv().unwrap().expect("The mint trustee signs"));
+        self.submit(&request.to_string())
+    }
+
+    /**
+        Orders `request_json` and returns the reply of the ledger.
+
+        The state only changes when the reply is a REPLY.
+    */
+    pub fn submit(&mut self, request_json: &str) -> String {
+        trace!("utils::test::mock_ledger::submit >> request_json: {:?}", secret!(request_json));
+        let reply = match serde_json::from_str::<SerdeMap>(request_json) {
+            Ok(request) => {
+                let result = self.order(request_json, &request);
+                reply(&request, result)
+            }
+            Err(_) => reply(&SerdeMap::new(), Err(invalid_request("the request is not a json object"))),
+        };
+        trace!("utils::test::mock_ledger::submit << reply: {:?}", reply);
+        reply
+    }
+
+    /*
+        Methods "private" (aka not exported from this module)
+
+        KEEP all public methods above
+    */
+
+    fn order(&mut self, request_json: &str, request: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let operation: SerdeMap = field(request, "operation")?;
+        let code: String = field(&operation, "type")?;
+        let txn_type = TxnType::from_code(&code)
+            .ok_or_else(|| invalid_request(&format!("invalid type {}", code)))?;
+
+        match txn_type {
+            TxnType::MintPublic => self.mint(request, &operation),
+            TxnType::XferPublic => self.transfer(request_json, request, &operation),
+            TxnType::SetFees => self.update_fees(request, &operation),
+            TxnType::GetUtxo => self.get_utxo(request, &operation),
+            TxnType::GetFees => Ok(json!({
+                "type": code,
+                "identifier": identifier(request),
+                "reqId": req_id(request),
+                "fees": self.fees,
+            })),
+            TxnType::GetTxn => self.get_txn(request, &operation),
+            _ if txn_type.can_carry_fees() => self.domain_write(request_json, request, &operation, txn_type),
+            _ => Err(invalid_request(&format!("type {} is not supported by the mock ledger", code))),
+        }
+    }
+
+    fn mint(&mut self, request: &SerdeMap, operation: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let signatures = self.check_trustee_quorum(request)?;
+        let outputs: Outputs = field(operation, OUTPUTS)?;
+        if outputs.is_empty() {
+            return Err(invalid_request("outputs are empty"));
+        }
+        check_outputs(&outputs)?;
+
+        let data = json!({"outputs": outputs, "extra": operation.get("extra")});
+        let txn = ledger_txn(request, TxnType::MintPublic, data, self.next_token_seq_no(), signatures)?;
+        Ok(self.add_token_txn(txn, &outputs))
+    }
+
+    fn transfer(&mut self, request_json: &str, request: &SerdeMap, operation: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let inputs: Inputs = field(operation, INPUTS)?;
+        let outputs: Outputs = field(operation, OUTPUTS)?;
+        let signatures: Vec<String> = field(operation, "signatures")?;
+        if inputs.is_empty() {
+            return Err(invalid_request("inputs are empty"));
+        }
+        check_outputs(&outputs)?;
+
+        let report = verify_transfer_request(&KeystoreCrypto::new(), request_json)
+            .map_err(|_| invalid_request("every input needs a signature"))?;
+        check_input_signatures(&report)?;
+
+        let fee = self.fee(TxnType::XferPublic);
+        self.spend(&inputs, &outputs, fee)?;
+
+        let data = json!({"inputs": inputs, "outputs": outputs, "extra": operation.get("extra")});
+        let txn = ledger_txn(request, TxnType::XferPublic, data, self.next_token_seq_no(), input_signatures(&inputs, signatures))?;
+        Ok(self.add_token_txn(txn, &outputs))
+    }
+
+    fn domain_write(&mut self, request_json: &str, request: &SerdeMap, operation: &SerdeMap, txn_type: TxnType) -> Result<SJsonValue, Rejection> {
+        let fee = self.fee(txn_type);
+        let fees = match (request.get(FEES), fee) {
+            (None, 0) => None,
+            (None, _) => return Err(invalid_request("fees are required for this txn type")),
+            (Some(_), 0) => return Err(invalid_request("fees are not required for this txn type")),
+            (Some(fees), _) => Some(fees.clone()),
+        };
+
+        let paid = match fees {
+            Some(fees) => {
+                let (inputs, outputs, signatures): (Inputs, Outputs, Vec<String>) = serde_json::from_value(fees)
+                    .map_err(|_| invalid_request("invalid fees"))?;
+                check_outputs(&outputs)?;
+
+                let report = verify_fees_request(&KeystoreCrypto::new(), request_json)
+                    .map_err(|_| invalid_request("every fee input needs a signature"))?;
+                check_input_signatures(&report)?;
+
+                self.spend(&inputs, &outputs, fee)?;
+                Some((inputs, outputs, signatures))
+            }
+            None => None,
+        };
+
+        let seq_no = self.next_seq_no(DOMAIN_LEDGER_ID);
+        let mut data = operation.clone();
+        data.remove("type");
+        let mut txn = ledger_txn(request, txn_type, SJsonValue::Object(data), seq_no, request_signature(request))?;
+
+        if let Some((inputs, outputs, signatures)) = paid {
+            let data = json!({
+                "fees": fee,
+                "inputs": inputs,
+                "outputs": outputs,
+                "ref": format!("{}:{}", DOMAIN_LEDGER_ID, seq_no),
+            });
+            let fee_txn = ledger_txn(request, TxnType::FeeTxn, data, self.next_token_seq_no(), input_signatures(&inputs, signatures))?;
+            txn[FEES] = self.add_token_txn(fee_txn, &outputs);
+        }
+        Ok(txn)
+    }
+
+    fn update_fees(&mut self, request: &SerdeMap, operation: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let signatures = self.check_trustee_quorum(request)?;
+        let fees: HashMap<String, TokenAmount> = field(operation, FEES)?;
+        self.fees.extend(fees.clone());
+
+        let seq_no = self.next_seq_no(CONFIG_LEDGER_ID);
+        ledger_txn(request, TxnType::SetFees, json!({"fees": fees}), seq_no, signatures)
+    }
+
+    fn get_utxo(&self, request: &SerdeMap, operation: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let address: String = field(operation, "address")?;
+        let address = PaymentAddress::new(&address)
+            .map_err(|_| invalid_request("invalid address"))?;
+        let from = operation.get("from").and_then(SJsonValue::as_u64).unwrap_or(0);
+
+        let mut outputs: Vec<(TxnSeqNo, TokenAmount)> = self.utxos.iter()
+            .filter(|((unqualified, seq_no), _)| unqualified == address.unqualified() && *seq_no >= from)
+            .map(|((_, seq_no), amount)| (*seq_no, *amount))
+            .collect();
+        outputs.sort();
+
+        let next = match self.utxo_page_size {
+            Some(page_size) if outputs.len() > page_size => {
+                let next = outputs[page_size].0;
+                outputs.truncate(page_size);
+                Some(next)
+            }
+            _ => None,
+        };
+
+        let outputs: Vec<SJsonValue> = outputs.into_iter()
+            .map(|(seq_no, amount)| json!({"address": address.unqualified(), "seqNo": seq_no, "amount": amount}))
+            .collect();
+        let mut result = json!({
+            "type": TxnType::GetUtxo.code(),
+            "address": address.unqualified(),
+            "identifier": identifier(request),
+            "reqId": req_id(request),
+            "outputs": outputs,
+        });
+        if let Some(next) = next {
+            result["next"] = json!(next);
+        }
+        Ok(result)
+    }
+
+    fn get_txn(&self, request: &SerdeMap, operation: &SerdeMap) -> Result<SJsonValue, Rejection> {
+        let ledger_id = operation.get("ledgerId").and_then(SJsonValue::as_u64).unwrap_or(u64::from(DOMAIN_LEDGER_ID));
+        if ledger_id != u64::from(TOKEN_LEDGER_ID) {
+            return Err(invalid_request("the mock ledger only keeps the transactions of the token ledger"));
+        }
+        let seq_no: TxnSeqNo = field(operation, "data")?;
+
+        let data = seq_no.checked_sub(1).and_then(|index| self.token_txns.get(index as usize)).cloned();
+        Ok(json!({
+            "type": TxnType::GetTxn.code(),
+            "identifier": identifier(request),
+            "reqId": req_id(request),
+            "seqNo": data.as_ref().map(|_| seq_no),
+            "data": data,
+        }))
+    }
+
+    /*
+        The signatures of trustees on `request`, with at least a quorum of
+        them valid.
+    */
+    fn check_trustee_quorum(&self, request: &SerdeMap) -> Result<Vec<(String, String)>, Rejection> {
+        let mut signatures: Vec<(String, String)> = request.get("signatures")
+            .and_then(SJsonValue::as_object)
+            .map(|signatures| signatures.iter()
+                .filter_map(|(did, signature)| signature.as_str().map(|signature| (did.clone(), signature.to_string())))
+                .collect())
+            .unwrap_or_default();
+        for (did, signature) in request_signature(request) {
+            if !signatures.iter().any(|(signer, _)| *signer == did) {
+                signatures.push((did, signature));
+            }
+        }
+
+        let message = serialize_signature(SJsonValue::Object(request.clone()))
+            .map_err(|_| invalid_request("the request can't be serialized"))?;
+        let crypto = KeystoreCrypto::new();
+        let valid: Vec<(String, String)> = signatures.into_iter()
+            .filter(|(did, signature)| self.trustees.get(did).map_or(false, |verkey| {
+                crypto.indy_crypto_verify(verkey.clone(), message.clone(), signature.clone()).unwrap_or(false)
+            }))
+            .collect();
+
+        if valid.len() < self.trustee_quorum {
+            return Err(Rejection::reqnack(format!(
+                "client request invalid: insufficient number of valid signatures, {} is required but {} valid found",
+                self.trustee_quorum,
+                valid.len()
+            )));
+        }
+        Ok(valid)
+    }
+
+    /*
+        Spends `inputs`, which have to hold exactly `outputs` and `fee`.
+    */
+    fn spend(&mut self, inputs: &[Input], outputs: &Outputs, fee: TokenAmount) -> Result<(), Rejection> {
+        let mut keys: Vec<SourceKey> = Vec::new();
+        let mut provided: TokenAmount = 0;
+
+        for input in inputs {
+            let key = source_key(input);
+            if keys.contains(&key) {
+                return Err(invalid_request(&format!("duplicate input {}:{}", key.0, key.1)));
+            }
+            if self.spent.contains(&key) {
+                return Err(Rejection::reject(format!("client request invalid: UTXOAlreadySpentError('{}:{} is spent',)", key.0, key.1)));
+            }
+            match self.utxos.get(&key) {
+                Some(amount) => provided += amount,
+                None => return Err(Rejection::reject(format!("client request invalid: InvalidFundsError('{}:{} does not exist',)", key.0, key.1))),
+            }
+            keys.push(key);
+        }
+
+        let required = outputs.iter().map(|output| output.amount).sum::<TokenAmount>() + fee;
+        if provided < required {
+            return Err(Rejection::reject(format!("client request invalid: InsufficientFundsError({}, {})", required, provided)));
+        }
+        if provided > required {
+            return Err(Rejection::reject(format!("client request invalid: ExtraFundsError({}, {})", required, provided)));
+        }
+
+        for key in keys {
+            self.utxos.remove(&key);
+            self.spent.insert(key);
+        }
+        Ok(())
+    }
+
+    fn fee(&self, txn_type: TxnType) -> TokenAmount {
+        self.fees.get(txn_type.code()).cloned().unwrap_or(0)
+    }
+
+    fn next_token_seq_no(&self) -> TxnSeqNo {
+        self.token_txns.len() as TxnSeqNo + 1
+    }
+
+    fn next_seq_no(&mut self, ledger_id: u32) -> TxnSeqNo {
+        let seq_no = self.seq_nos.entry(ledger_id).or_insert(0);
+        *seq_no += 1;
+        *seq_no
+    }
+
+    /*
+        Appends `txn` to the token ledger and adds the sources of its outputs.
+    */
+    fn add_token_txn(&mut self, txn: SJsonValue, outputs: &Outputs) -> SJsonValue {
+        let seq_no = self.next_token_seq_no();
+        for output in outputs {
+            self.utxos.insert((output.recipient.unqualified().to_string(), seq_no), output.amount);
+        }
+        self.token_txns.push(txn.clone());
+        txn
+    }
+}
+
+impl Default for MockLedger {
+    fn default() -> Self {
+        MockLedger::new()
+    }
+}
+
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+/*
+    A REQNACK when the request is malformed or not signed as it must be, a
+    REJECT when it conflicts with the state of the ledger.
+*/
+struct Rejection {
+    op: ResponseOperations,
+    reason: String,
+}
+
+impl Rejection {
+    fn reqnack(reason: String) -> Rejection {
+        Rejection { op: ResponseOperations::REQNACK, reason }
+    }
+
+    fn reject(reason: String) -> Rejection {
+        Rejection { op: ResponseOperations::REJECT, reason }
+    }
+}
+
+fn invalid_request(reason: &str) -> Rejection {
+    Rejection::reqnack(format!("client request invalid: InvalidClientRequest('{}',)", reason))
+}
+
+fn reply(request: &SerdeMap, result: Result<SJsonValue, Rejection>) -> String {
+    let reply = match result {
+        Ok(result) => json!({"op": ResponseOperations::REPLY, "result": result}),
+        Err(rejection) => json!({
+            "op": rejection.op,
+            "identifier": request.get("identifier"),
+            "reqId": request.get("reqId"),
+            "reason": rejection.reason,
+        }),
+    };
+    reply.to_string()
+}
+
+fn field<T: DeserializeOwned>(map: &SerdeMap, name: &str) -> Result<T, Rejection> {
+    let value = map.get(name).cloned().unwrap_or(SJsonValue::Null);
+    serde_json::from_value(value).map_err(|_| invalid_request(&format!("missing or invalid {}", name)))
+}
+
+fn identifier(request: &SerdeMap) -> String {
+    request.get("identifier")
+        .and_then(SJsonValue::as_str)
+        .unwrap_or(DEFAULT_LIBSOVTOKEN_DID)
+        .to_string()
+}
+
+fn req_id(request: &SerdeMap) -> ReqId {
+    request.get("reqId").and_then(SJsonValue::as_u64).unwrap_or(0)
+}
+
+fn source_key(input: &Input) -> SourceKey {
+    (input.address.unqualified().to_string(), input.seq_no)
+}
+
+fn check_outputs(outputs: &Outputs) -> Result<(), Rejection> {
+    let mut addresses = HashSet::new();
+    for output in outputs {
+        if output.amount == 0 {
+            return Err(invalid_request(&format!("the output to {} has no amount", output.recipient.unqualified())));
+        }
+        if !addresses.insert(output.recipient.unqualified()) {
+            return Err(invalid_request(&format!("more than one output to {}", output.recipient.unqualified())));
+        }
+    }
+    Ok(())
+}
+
+fn check_input_signatures(report: &VerificationReport) -> Result<(), Rejection> {
+    match report.failed_inputs().first() {
+        Some(input) => Err(Rejection::reqnack(format!(
+            "client request invalid: InvalidSignature('{}:{}',)",
+            input.address.unqualified(),
+            input.seq_no
+        ))),
+        None => Ok(()),
+    }
+}
+
+/*
+    The `signature` of the identifier of `request`, if any.
+*/
+fn request_signature(request: &SerdeMap) -> Vec<(String, String)> {
+    match (request.get("identifier").and_then(SJsonValue::as_str), request.get("signature").and_then(SJsonValue::as_str)) {
+        (Some(did), Some(signature)) => vec![(did.to_string(), signature.to_string())],
+        _ => Vec::new(),
+    }
+}
+
+fn input_signatures(inputs: &[Input], signatures: Vec<String>) -> Vec<(String, String)> {
+    inputs.iter()
+        .map(|input| input.address.unqualified().to_string())
+        .zip(signatures)
+        .collect()
+}
+
+/*
+    The transaction the ledger orders `request` as, the way GET_TXN and the
+    replies to writes return it.
+*/
+fn ledger_txn(request: &SerdeMap, txn_type: TxnType, data: SJsonValue, seq_no: TxnSeqNo, signatures: Vec<(String, String)>) -> Result<SJsonValue, Rejection> {
+    let digest = txn_digest(request).map_err(|_| invalid_request("the request can't be serialized"))?;
+    let values: Vec<SJsonValue> = signatures.into_iter()
+        .map(|(from, value)| json!({"from": from, "value": value}))
+        .collect();
+
+    Ok(json!({
+        "txn": {
+            "data": data,
+            "metadata": {"digest": digest, "from": identifier(request), "reqId": req_id(request)},
+            "protocolVersion": PROTOCOL_VERSION,
+            "type": txn_type.code(),
+        },
+        "txnMetadata": {"seqNo": seq_no, "txnTime": time::get_time().sec},
+        "reqSignature": {"type": "ED25519", "values": values},
+        "ver": "1",
+        "rootHash": digest,
+        "auditPath": [],
+    }))
+}
+
+#[cfg(test)]
+mod mock_ledger_tests {
+    use super::*;
+    use std::sync::Mutex;
+    use ErrorCode;
+    use logic::config::get_fees_config::GetFeesRequest;
+    use logic::config::get_txn_config::GetTxnOperationRequest;
+    use logic::config::get_utxo_config::GetUtxoOperationRequest;
+    use logic::config::set_fees_config::SetFees;
+    use logic::history::TokenTxnKind;
+    use logic::offline_signing::SigningEnvelope;
+    use logic::parsers::error_code_parser::{last_ledger_error, RejectionKind};
+    use logic::parsers::{parse_get_txn, parse_get_txn_fees, parse_get_utxo_response, parse_payment_response, parse_response_with_fees_handler};
+    use logic::xfer_payload::XferPayload;
+    use utils::json_conversion::JsonDeserialize;
+    use utils::test::default::KEYSTORE;
+
+    const TRUSTEES: [&str; 2] = ["V4SGRU86Z58d6TV7PBUe6f", "Th7MpTaRZVRYnPiabds81Y"];
+
+    fn new_key() -> String {
+        KEYSTORE.indy_create_key(1, PaymentAddressConfig { seed: String::new(), ..Default::default() }).unwrap()
+    }
+
+    fn new_address() -> PaymentAddress {
+        PaymentAddress::from_verkey(&new_key()).unwrap()
+    }
+
+    fn sign(verkey: &str, message: String) -> String {
+        let (sender, receiver) = channel();
+        KEYSTORE.indy_crypto_sign(1, verkey.to_string(), message, move |signature| sender.send(signature).unwrap());
+        receiver.recv().unwrap().unwrap()
+    }
+
+    fn ledger_with_trustees() -> (MockLedger, Vec<String>) {
+        let mut ledger = MockLedger::new();
+        let verkeys: Vec<String> = TRUSTEES.iter().map(|_| new_key()).collect();
+        for (did, verkey) in TRUSTEES.iter().zip(verkeys.iter()) {
+            ledger.add_trustee(did, verkey);
+        }
+        (ledger, verkeys)
+    }
+
+    fn multi_sign(request_json: String, verkeys: &[String]) -> String {
+        let mut request: SJsonValue = serde_json::from_str(&request_json).unwrap();
+        let message = serialize_signature(request.clone()).unwrap();
+        for (did, verkey) in TRUSTEES.iter().zip(verkeys) {
+            request["signatures"][did] = json!(sign(verkey, message.clone()));
+        }
+        request.to_string()
+    }
+
+    fn mint(ledger: &mut MockLedger, verkeys: &[String], outputs: Vec<Output>) -> String {
+        let did = Did::new(TRUSTEES[0].to_string());
+        let request_json = MintRequest::new(outputs, Some(did), None).serialize_to_string().unwrap();
+        ledger.submit(&multi_sign(request_json, verkeys))
+    }
+
+    fn sign_envelope(envelope: &SigningEnvelope) -> HashMap<String, String> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        envelope.sign(&*KEYSTORE, 1, Box::new(move |result| {
+            sender.lock().unwrap().send(result).unwrap();
+        })).unwrap();
+        receiver.recv().unwrap().unwrap()
+    }
+
+    fn transfer(inputs: Vec<Input>, outputs: Vec<Output>) -> String {
+        let envelope = SigningEnvelope::for_transfer(XferPayload::new(inputs, outputs, None)).unwrap();
+        envelope.transfer_request(&sign_envelope(&envelope), None).unwrap()
+    }
+
+    fn nym_request() -> String {
+        json!({
+            "identifier": "V4SGRU86Z58d6TV7PBUe6f",
+            "reqId": 1532360723205721420u64,
+            "protocolVersion": 2,
+            "operation": {"type": "1", "dest": "8Wv7NMbsMiNSmNa3iC6fG7"}
+        }).to_string()
+    }
+
+    fn with_fees(request_json: &str, inputs: Vec<Input>, outputs: Vec<Output>) -> String {
+        let envelope = SigningEnvelope::for_fees(XferPayload::new(inputs, outputs, None), request_json).unwrap();
+        envelope.fees_request(&sign_envelope(&envelope), request_json).unwrap()
+    }
+
+    fn rejection_kind(reply: &str) -> RejectionKind {
+        let response = parse_payment_response::ParsePaymentResponse::from_json(reply).unwrap();
+        assert!(parse_payment_response::from_response(response).is_err());
+        last_ledger_error().unwrap().kind
+    }
+
+    fn utxos(ledger: &mut MockLedger, address: &PaymentAddress, from: Option<TxnSeqNo>) -> (Vec<(String, TokenAmount)>, Option<TxnSeqNo>) {
+        let request_json = GetUtxoOperationRequest::new(address.qualified().to_string(), from).serialize_to_string().unwrap();
+        let response = parse_get_utxo_response::ParseGetUtxoResponse::from_json(&ledger.submit(&request_json)).unwrap();
+        let (utxos, next) = parse_get_utxo_response::from_response_with_next(response).unwrap();
+        (utxos.into_iter().map(|utxo| (utxo.source, utxo.amount)).collect(), next)
+    }
+
+    #[test]
+    fn mint_needs_trustee_quorum() {
+        let (mut ledger, verkeys) = ledger_with_trustees();
+        ledger.set_trustee_quorum(2);
+        let address = new_address();
+
+        let reply = mint(&mut ledger, &verkeys[..1], vec![Output::new(address.clone(), 10)]);
+        assert_eq!(RejectionKind::InvalidSignature, rejection_kind(&reply));
+        assert_eq!(RejectionKind::InvalidSignature, rejection_kind(&ledger.mint_to(&address, 10)));
+        assert_eq!(0, ledger.balance(&address));
+
+        let reply: SJsonValue = serde_json::from_str(&mint(&mut ledger, &verkeys, vec![Output::new(address.clone(), 10)])).unwrap();
+        assert_eq!((json!("REPLY"), json!(1)), (reply["op"].clone(), reply["result"]["txnMetadata"]["seqNo"].clone()));
+        assert_eq!(10, ledger.balance(&address));
+        assert_eq!(vec![10], utxos(&mut ledger, &address, None).0.iter().map(|utxo| utxo.1).collect::<Vec<TokenAmount>>());
+    }
+
+    #[test]
+    fn get_utxo_in_pages() {
+        let mut ledger = MockLedger::new();
+        let address = new_address();
+        for amount in 1..4 {
+            ledger.mint_to(&address, amount);
+        }
+
+        let (all, next) = utxos(&mut ledger, &address, None);
+        assert_eq!((3, None), (all.len(), next));
+
+        ledger.set_utxo_page_size(Some(2));
+        let (first, next) = utxos(&mut ledger, &address, None);
+        assert_eq!((vec![1, 2], Some(3)), (first.iter().map(|utxo| utxo.1).collect::<Vec<TokenAmount>>(), next));
+        let (last, next) = utxos(&mut ledger, &address, next);
+        assert_eq!((vec![all[2].clone()], None), (last, next));
+    }
+
+    #[test]
+    fn transfer_applies_utxo_and_fee_rules() {
+        let mut ledger = MockLedger::new();
+        let (from, to) = (new_address(), new_address());
+        ledger.mint_to(&from, 10);
+        let mut fees = HashMap::new();
+        fees.insert(TxnType::XferPublic.code().to_string(), 1);
+        ledger.set_fees(fees);
+
+        let source = Input::new(from.clone(), 1);
+        let reply = ledger.submit(&transfer(vec![source.clone()], vec![Output::new(to.clone(), 10)]));
+        assert_eq!(RejectionKind::InsufficientFunds, rejection_kind(&reply));
+        let reply = ledger.submit(&transfer(vec![source.clone()], vec![Output::new(to.clone(), 8)]));
+        assert_eq!(RejectionKind::ExtraFunds, rejection_kind(&reply));
+
+        let mut forged: SJsonValue = serde_json::from_str(&transfer(vec![source.clone()], vec![Output::new(to.clone(), 9)])).unwrap();
+        forged["operation"]["outputs"][0]["amount"] = json!(10);
+        assert_eq!(RejectionKind::InvalidSignature, rejection_kind(&ledger.submit(&forged.to_string())));
+
+        let request_json = transfer(vec![source.clone()], vec![Output::new(to.clone(), 9)]);
+        let response = parse_payment_response::ParsePaymentResponse::from_json(&ledger.submit(&request_json)).unwrap();
+        assert_eq!(1, parse_payment_response::from_response(response).unwrap().len());
+        assert_eq!((0, 9), (ledger.balance(&from), ledger.balance(&to)));
+
+        assert_eq!(RejectionKind::DoubleSpend, rejection_kind(&ledger.submit(&request_json)));
+        let unknown = transfer(vec![Input::new(from.clone(), 7)], vec![Output::new(to.clone(), 9)]);
+        assert_eq!(RejectionKind::InvalidFunds, rejection_kind(&ledger.submit(&unknown)));
+    }
+
+    #[test]
+    fn domain_request_pays_fees() {
+        let (mut ledger, verkeys) = ledger_with_trustees();
+        let address = new_address();
+        ledger.mint_to(&address, 10);
+
+        let mut fees = HashMap::new();
+        fees.insert(TxnType::Nym.code().to_string(), 3);
+        let set_fees = SetFees::new(fees).as_request(Some(Did::new(TRUSTEES[0].to_string())));
+        ledger.submit(&multi_sign(set_fees.serialize_to_string().unwrap(), &verkeys));
+        let get_fees = GetFeesRequest::new().as_request(None).serialize_to_string().unwrap();
+        assert_eq!(Some(&3), parse_get_txn_fees::fees_from_get_txn_fees_response(&ledger.submit(&get_fees)).unwrap().get("1"));
+
+        let without_fees = ledger.submit(&nym_request());
+        assert_eq!(RejectionKind::InsufficientFees, rejection_kind(&without_fees));
+
+        let request_json = with_fees(&nym_request(), vec![Input::new(address.clone(), 1)], vec![Output::new(address.clone(), 7)]);
+        let response = parse_response_with_fees_handler::ParseResponseWithFees::from_json(&ledger.submit(&request_json)).unwrap();
+        let change = parse_response_with_fees_handler::from_response(response).unwrap().unwrap();
+        assert_eq!(7, change[0].amount);
+        assert_eq!(7, ledger.balance(&address));
+
+        let get_txn = GetTxnOperationRequest::new(2, None).serialize_to_string().unwrap();
+        let response = parse_get_txn::ParseGetTxnResponse::from_json(&ledger.submit(&get_txn)).unwrap();
+        let fee_txn = parse_get_txn::from_response(response).unwrap().unwrap();
+        assert_eq!((TokenTxnKind::Fees, Some(3), Some(String::from("1:1"))), (fee_txn.kind, fee_txn.fees, fee_txn.reference));
+    }
+
+    #[test]
+    fn malformed_requests_are_refused() {
+        let mut ledger = MockLedger::new();
+
+        let reply: SJsonValue = serde_json::from_str(&ledger.submit("not json")).unwrap();
+        assert_eq!(json!("REQNACK"), reply["op"]);
+
+        let reply = ledger.submit(r#"{"reqId": 1, "operation": {"type": "10001", "inputs": [], "outputs": [], "signatures": []}}"#);
+        let response = parse_payment_response::ParsePaymentResponse::from_json(&reply).unwrap();
+        assert_eq!(ErrorCode::CommonInvalidStructure, parse_payment_response::from_response(response).unwrap_err());
+    }
+}
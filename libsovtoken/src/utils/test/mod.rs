@@ -3,4 +3,6 @@ A set of utils that should only be unsed in testing functions.
 */
 
 pub mod callbacks;
-pub mod default;
\ No newline at end of file
+pub mod crypto_api;
+pub mod default;
+pub mod mock_ledger;
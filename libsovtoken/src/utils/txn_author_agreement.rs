@@ -1,9 +1,198 @@
+//! The Transaction Author Agreement (TAA) and its acceptance in the `extra` of requests
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use hex::ToHex;
 use serde_json;
+use sha2::{Digest, Sha256};
+use time;
+
 use ErrorCode;
+use utils::current_error::{set_current_error, ErrorDetails};
 
 pub type TaaAcceptance = serde_json::Value;
 
 const META_FIELD_NAME: &str = "taaAcceptance";
+const SECONDS_PER_DAY: u64 = 86400;
+
+lazy_static! {
+    static ref CURRENT_AGREEMENT: RwLock<Option<(TxnAuthorAgreement, Option<AcceptanceMechanisms>)>> = RwLock::new(None);
+}
+
+/**
+    A Transaction Author Agreement, as in the `data` of a GET_TXN_AUTHR_AGRMT reply
+
+    # parameters
+    text - the agreement, which the ledger leaves out for a retired agreement
+    version - the version of the agreement
+    digest - the [`taa_digest`] of the text and version
+    ratification_ts - when the agreement came into force
+    retirement_ts - when the agreement was retired, if it was
+
+    [`taa_digest`]: fn.taa_digest.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TxnAuthorAgreement {
+    pub text: Option<String>,
+    pub version: String,
+    pub digest: Option<String>,
+    pub ratification_ts: Option<u64>,
+    pub retirement_ts: Option<u64>,
+}
+
+impl TxnAuthorAgreement {
+    /**
+        The digest of the agreement, computed from its text when the ledger
+        didn't give it.
+
+        Errors with `CommonInvalidStructure` when the given digest doesn't
+        match the text and version, or there is neither.
+    */
+    pub fn digest(&self) -> Result<String, ErrorCode> {
+        let computed = self.text.as_ref().map(|text| taa_digest(text, &self.version));
+        match (&self.digest, computed) {
+            (Some(digest), Some(ref computed)) if digest != computed => {
+                error!("The digest {} of the agreement doesn't match its text and version {}", digest, self.version);
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+            (Some(digest), _) => Ok(digest.clone()),
+            (None, Some(computed)) => Ok(computed),
+            (None, None) => {
+                error!("The agreement has neither text nor digest");
+                Err(ErrorCode::CommonInvalidStructure)
+            }
+        }
+    }
+}
+
+/**
+    The acceptance mechanisms list (AML), as in the `data` of a
+    GET_TXN_AUTHR_AGRMT_AML reply. `aml` maps each mechanism to its description.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AcceptanceMechanisms {
+    pub aml: HashMap<String, String>,
+    pub version: String,
+    pub aml_context: Option<String>,
+}
+
+/**
+    The fields of a [`TaaAcceptance`]
+
+    [`TaaAcceptance`]: type.TaaAcceptance.html
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Acceptance {
+    pub mechanism: String,
+    pub taa_digest: String,
+    pub time: u64,
+}
+
+/**
+    The digest of an agreement, the hex of the sha256 of `version` followed
+    by `text`, the same as the ledger computes it.
+*/
+pub fn taa_digest(text: &str, version: &str) -> String {
+    let mut hasher = Sha256::default();
+    hasher.input(version.as_bytes());
+    hasher.input(text.as_bytes());
+    hasher.result().to_hex()
+}
+
+/**
+    `time` rounded down to the start of its day (UTC). The ledger only takes
+    acceptance times with the precision of a day.
+*/
+pub fn round_to_day(time: u64) -> u64 {
+    time / SECONDS_PER_DAY * SECONDS_PER_DAY
+}
+
+/**
+    Builds the acceptance of `agreement` with `mechanism` at `time`.
+
+    Errors with
+    - `CommonInvalidStructure` when the digest of the agreement is invalid.
+    - `LedgerInvalidTransaction` when `mechanism` is not in `mechanisms` or
+      `time` is outside of the time the agreement is in force.
+*/
+pub fn build_acceptance(agreement: &TxnAuthorAgreement, mechanisms: Option<&AcceptanceMechanisms>, mechanism: &str, time: u64) -> Result<TaaAcceptance, ErrorCode> {
+    let acceptance = Acceptance {
+        mechanism: mechanism.to_string(),
+        taa_digest: agreement.digest()?,
+        time: round_to_day(time),
+    };
+    check_acceptance_fields(&acceptance, agreement, mechanisms)?;
+
+    serde_json::to_value(&acceptance).map_err(|_| ErrorCode::CommonInvalidState)
+}
+
+/**
+    Checks that `acceptance` accepts `agreement` with one of `mechanisms`,
+    the way the ledger checks it.
+
+    Errors with `CommonInvalidStructure` when the acceptance is malformed and
+    with `LedgerInvalidTransaction` when it doesn't accept the agreement.
+*/
+pub fn check_acceptance(acceptance: &TaaAcceptance, agreement: &TxnAuthorAgreement, mechanisms: Option<&AcceptanceMechanisms>) -> Result<(), ErrorCode> {
+    let acceptance: Acceptance = serde_json::from_value(acceptance.clone())
+        .map_err(|err| {
+            set_current_error(ErrorDetails::new("Invalid TAA acceptance").cause(err));
+            ErrorCode::CommonInvalidStructure
+        })?;
+
+    if acceptance.time != round_to_day(acceptance.time) {
+        taa_error("The time of the acceptance isn't rounded to the day", &acceptance);
+        return Err(ErrorCode::CommonInvalidStructure);
+    }
+
+    check_acceptance_fields(&acceptance, agreement, mechanisms)
+}
+
+/**
+    Makes `agreement` the agreement the TAA acceptances of payments and fees
+    are checked against by [`check_current_acceptance`], or stops the checks
+    when `None`.
+
+    [`check_current_acceptance`]: fn.check_current_acceptance.html
+*/
+pub fn set_current_agreement(agreement: Option<(TxnAuthorAgreement, Option<AcceptanceMechanisms>)>) {
+    match CURRENT_AGREEMENT.write() {
+        Ok(mut current) => *current = agreement,
+        Err(err) => *err.into_inner() = agreement,
+    }
+}
+
+pub fn current_agreement() -> Option<(TxnAuthorAgreement, Option<AcceptanceMechanisms>)> {
+    match CURRENT_AGREEMENT.read() {
+        Ok(current) => current.clone(),
+        Err(err) => err.into_inner().clone(),
+    }
+}
+
+/**
+    Checks `acceptance` against the agreement set with [`set_current_agreement`].
+    Anything passes when none is set.
+
+    [`set_current_agreement`]: fn.set_current_agreement.html
+*/
+pub fn check_current_acceptance(acceptance: &Option<TaaAcceptance>) -> Result<(), ErrorCode> {
+    let (agreement, mechanisms) = match current_agreement() {
+        Some(current) => current,
+        None => return Ok(()),
+    };
+
+    match *acceptance {
+        Some(ref acceptance) => check_acceptance(acceptance, &agreement, mechanisms.as_ref()),
+        None => {
+            set_current_error(ErrorDetails::new("The TAA acceptance is missing")
+                .cause(format!("The ledger requires the acceptance of version {} of the agreement", agreement.version)));
+            Err(ErrorCode::LedgerInvalidTransaction)
+        }
+    }
+}
 
 pub fn extract_taa_acceptance_from_extra(extra: Option<serde_json::Value>) -> Result<(Option<serde_json::Value>, Option<TaaAcceptance>), ErrorCode> {
     match extra {
@@ -19,6 +208,52 @@ pub fn extract_taa_acceptance_from_extra(extra: Option<serde_json::Value>) -> Re
     }
 }
 
+/*
+    Methods "private" (aka not exported from this module)
+
+    KEEP all public methods above
+*/
+
+fn check_acceptance_fields(acceptance: &Acceptance, agreement: &TxnAuthorAgreement, mechanisms: Option<&AcceptanceMechanisms>) -> Result<(), ErrorCode> {
+    if acceptance.taa_digest != agreement.digest()? {
+        taa_error("The acceptance is for a different agreement", acceptance);
+        return Err(ErrorCode::LedgerInvalidTransaction);
+    }
+
+    if let Some(mechanisms) = mechanisms {
+        if !mechanisms.aml.contains_key(&acceptance.mechanism) {
+            taa_error("The acceptance mechanism isn't in the acceptance mechanisms list", acceptance);
+            return Err(ErrorCode::LedgerInvalidTransaction);
+        }
+    }
+
+    if let Some(ratification_ts) = agreement.ratification_ts {
+        if acceptance.time < round_to_day(ratification_ts) {
+            taa_error("The agreement was accepted before it was ratified", acceptance);
+            return Err(ErrorCode::LedgerInvalidTransaction);
+        }
+    }
+
+    let now = time::get_time().sec as u64;
+    if acceptance.time > now {
+        taa_error("The agreement was accepted in the future", acceptance);
+        return Err(ErrorCode::LedgerInvalidTransaction);
+    }
+
+    if agreement.retirement_ts.map_or(false, |retirement_ts| retirement_ts <= now) {
+        taa_error("The agreement is retired", acceptance);
+        return Err(ErrorCode::LedgerInvalidTransaction);
+    }
+
+    Ok(())
+}
+
+fn taa_error(cause: &str, acceptance: &Acceptance) {
+    set_current_error(ErrorDetails::new("Invalid TAA acceptance")
+        .cause(cause)
+        .details(json!(acceptance)));
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -82,4 +317,76 @@ mod test {
         assert_eq!(None, extra);
         assert_eq!(None, taa_acceptance);
     }
+
+    const TEXT: &str = "some agreement text";
+    const VERSION: &str = "1.0.0";
+    const DIGEST: &str = "050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e";
+
+    fn agreement() -> TxnAuthorAgreement {
+        TxnAuthorAgreement {
+            text: Some(TEXT.to_string()),
+            version: VERSION.to_string(),
+            digest: None,
+            ratification_ts: Some(1574035200 + 3600),
+            retirement_ts: None,
+        }
+    }
+
+    fn mechanisms() -> AcceptanceMechanisms {
+        serde_json::from_value(json!({
+            "aml": {"at_submission": "The agreement was reviewed by the user and accepted at the time of submission"},
+            "version": "1.0",
+            "amlContext": "http://aml-context-descr"
+        })).unwrap()
+    }
+
+    #[test]
+    pub fn taa_digest_works() {
+        assert_eq!(DIGEST, taa_digest(TEXT, VERSION));
+        assert_eq!(DIGEST, agreement().digest().unwrap());
+
+        let mut agreement = agreement();
+        agreement.digest = Some(taa_digest(TEXT, "1.0.1"));
+        assert_eq!(ErrorCode::CommonInvalidStructure, agreement.digest().unwrap_err());
+    }
+
+    #[test]
+    pub fn build_acceptance_rounds_time() {
+        let acceptance = build_acceptance(&agreement(), Some(&mechanisms()), "at_submission", 1574121599).unwrap();
+
+        assert_eq!(json!({"mechanism": "at_submission", "taaDigest": DIGEST, "time": 1574035200}), acceptance);
+        check_acceptance(&acceptance, &agreement(), Some(&mechanisms())).unwrap();
+    }
+
+    #[test]
+    pub fn build_acceptance_with_unknown_mechanism() {
+        let error = build_acceptance(&agreement(), Some(&mechanisms()), "on_file", 1574121599).unwrap_err();
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, error);
+    }
+
+    #[test]
+    pub fn check_acceptance_of_other_agreement() {
+        let mut acceptance = build_acceptance(&agreement(), None, "at_submission", 1574121599).unwrap();
+
+        acceptance["taaDigest"] = json!(taa_digest(TEXT, "0.9"));
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, check_acceptance(&acceptance, &agreement(), None).unwrap_err());
+
+        acceptance["taaDigest"] = json!(DIGEST);
+        acceptance["time"] = json!(1574121599);
+        assert_eq!(ErrorCode::CommonInvalidStructure, check_acceptance(&acceptance, &agreement(), None).unwrap_err());
+
+        acceptance["time"] = json!(1573948800);
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, check_acceptance(&acceptance, &agreement(), None).unwrap_err());
+
+        assert_eq!(ErrorCode::CommonInvalidStructure, check_acceptance(&json!({"taaDigest": DIGEST}), &agreement(), None).unwrap_err());
+    }
+
+    #[test]
+    pub fn check_acceptance_of_retired_agreement() {
+        let acceptance = build_acceptance(&agreement(), None, "at_submission", 1574121599).unwrap();
+
+        let mut agreement = agreement();
+        agreement.retirement_ts = Some(1574121599);
+        assert_eq!(ErrorCode::LedgerInvalidTransaction, check_acceptance(&acceptance, &agreement, None).unwrap_err());
+    }
 }
\ No newline at end of file
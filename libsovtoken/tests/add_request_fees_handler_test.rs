@@ -98,6 +98,49 @@ fn test_add_fees_to_request_valid() {
     assert_eq!(expected_fees_request.to_string(), result);
 }
 
+#[test]
+fn test_add_fees_to_request_with_balance_check() {
+    let (wallet, input_address) = init_wallet_with_address();
+
+    let fake_request = json!({
+       "operation": {
+           "type": "3"
+       }
+    });
+
+    let txo = TXO { address: input_address.clone(), seq_no: 1 }.to_libindy_string().unwrap();
+
+    let inputs = json!([txo]);
+
+    let outputs = json!([{
+            "recipient": "pay:sov:dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "amount": 20,
+    }]);
+
+    let utxos = json!([{"paymentAddress": input_address, "source": txo, "amount": 21, "extra": ""}]);
+
+    let extra = json!({"balanceCheck": {"utxos": utxos, "fee": 1}});
+    let result = call_add_request_fees(
+        wallet.handle,
+        inputs.to_string(),
+        outputs.to_string(),
+        Some(extra.to_string()),
+        fake_request.to_string()
+    ).unwrap();
+    let request: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert!(request.get("fees").is_some());
+
+    let extra = json!({"balanceCheck": {"utxos": utxos, "fee": 0}});
+    let err = call_add_request_fees(
+        wallet.handle,
+        inputs.to_string(),
+        outputs.to_string(),
+        Some(extra.to_string()),
+        fake_request.to_string()
+    ).unwrap_err();
+    assert_eq!(ErrorCode::PaymentExtraFundsError, err);
+}
+
 #[test]
 fn test_add_fees_to_request_works_for_invalid_request() {
     let (wallet, input_address) = init_wallet_with_address();
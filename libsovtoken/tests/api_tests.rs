@@ -1,11 +1,36 @@
 //!
 //! tests for API related functions
 
+#[macro_use] extern crate lazy_static;
+#[macro_use] extern crate serde_json;
 extern crate sovtoken;
 
-use sovtoken::api::sovtoken_init;
+use std::ffi::CString;
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+
+use sovtoken::api::{sovtoken_init, sovtoken_set_txn_author_agreement};
 use sovtoken::ErrorCode;
+use sovtoken::logic::address::PaymentAddress;
+use sovtoken::logic::input::Input;
+use sovtoken::logic::output::Output;
+use sovtoken::logic::xfer_payload::XferPayload;
+use sovtoken::utils::txn_author_agreement::{build_acceptance, set_current_agreement, taa_digest, TaaAcceptance, TxnAuthorAgreement};
+
+lazy_static! {
+    static ref AGREEMENT_LOCK: Mutex<()> = Default::default();
+}
+
+// the agreement is global, so the tests which set one take turns
+fn lock_agreement() -> MutexGuard<'static, ()> {
+    AGREEMENT_LOCK.lock().unwrap_or_else(|err| err.into_inner())
+}
 
+fn payment(extra: Option<serde_json::Value>) -> Result<(), ErrorCode> {
+    let input = Input::new(PaymentAddress::new("E9LNHk8shQ6xe2RfydzXDSsyhWC6vJaUeKE2mmc6mWraDfmKm").unwrap(), 1);
+    let output = Output::new(PaymentAddress::new("TKe9eXtchV71J2qXX5HwP8rbkTBStnEEkMwQkHie265VtRSbs").unwrap(), 10);
+    XferPayload::new(vec![input], vec![output], extra).prepare_for_signing().map(|_| ())
+}
 
 #[test]
 fn sovtoken_init_executes_successfully() {
@@ -14,4 +39,73 @@ fn sovtoken_init_executes_successfully() {
 
    assert_eq!(err, ErrorCode::Success as i32, "sovtoken_init did not return ErrorCode::Success");
 
-}
\ No newline at end of file
+}
+
+#[test]
+fn sovtoken_set_txn_author_agreement_gates_payments() {
+    let _lock = lock_agreement();
+    let response = CString::new(json!({
+        "op": "REPLY",
+        "result": {
+            "type": "6",
+            "identifier": "L5AD5g65TDQr1PPHHRoiGf",
+            "reqId": 1574140837389745000u64,
+            "seqNo": 2,
+            "txnTime": 1574140838,
+            "data": {
+                "text": "some agreement text",
+                "version": "1.0.0",
+                "digest": "050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e",
+                "ratification_ts": 1574140800
+            }
+        }
+    }).to_string()).unwrap();
+    let acceptance = json!({
+        "mechanism": "at_submission",
+        "taaDigest": "050e52a57837fff904d3d059c8a123e3a04177042bf467db2b2c27abd8045d5e",
+        "time": 1574208000
+    });
+    let mut other_acceptance = acceptance.clone();
+    other_acceptance["taaDigest"] = json!(taa_digest("some agreement text", "0.9.0"));
+
+    assert_eq!(ErrorCode::Success as i32, sovtoken_set_txn_author_agreement(response.as_ptr(), ptr::null()));
+    assert_eq!(Err(ErrorCode::LedgerInvalidTransaction), payment(None));
+    assert_eq!(Err(ErrorCode::LedgerInvalidTransaction), payment(Some(json!({"taaAcceptance": other_acceptance}))));
+    assert_eq!(Ok(()), payment(Some(json!({"taaAcceptance": acceptance}))));
+
+    let invalid_response = CString::new("{}").unwrap();
+    assert_eq!(ErrorCode::CommonInvalidStructure as i32, sovtoken_set_txn_author_agreement(invalid_response.as_ptr(), ptr::null()));
+    assert_eq!(Err(ErrorCode::LedgerInvalidTransaction), payment(None));
+
+    assert_eq!(ErrorCode::Success as i32, sovtoken_set_txn_author_agreement(ptr::null(), ptr::null()));
+    assert_eq!(Ok(()), payment(None));
+}
+
+#[test]
+fn set_current_agreement_gates_payments() {
+    let _lock = lock_agreement();
+    let agreement = TxnAuthorAgreement {
+        text: Some("some agreement text".to_string()),
+        version: "1.0.0".to_string(),
+        digest: None,
+        ratification_ts: Some(1574035200),
+        retirement_ts: None,
+    };
+    let mechanisms = serde_json::from_value(json!({
+        "aml": {"at_submission": "The agreement was reviewed by the user and accepted at the time of submission"},
+        "version": "1.0",
+        "amlContext": "http://aml-context-descr"
+    })).unwrap();
+    let acceptance = build_acceptance(&agreement, Some(&mechanisms), "at_submission", 1574121599).unwrap();
+    let mut other_mechanism = acceptance.clone();
+    other_mechanism["mechanism"] = json!("on_file");
+    let with_acceptance = |acceptance: TaaAcceptance| Some(json!({"taaAcceptance": acceptance}));
+
+    set_current_agreement(Some((agreement, Some(mechanisms))));
+    assert_eq!(Err(ErrorCode::LedgerInvalidTransaction), payment(None));
+    assert_eq!(Err(ErrorCode::LedgerInvalidTransaction), payment(with_acceptance(other_mechanism)));
+    assert_eq!(Ok(()), payment(with_acceptance(acceptance)));
+
+    set_current_agreement(None);
+    assert_eq!(Ok(()), payment(None));
+}
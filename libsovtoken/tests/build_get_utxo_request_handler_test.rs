@@ -37,6 +37,41 @@ fn build_get_payment_sources_request(wallet_handle: IndyHandle, did: &str, payme
     return ResultHandler::one(ErrorCode::from(error_code), receiver);
 }
 
+fn build_get_payment_sources_request_with_from(wallet_handle: IndyHandle, did: &str, payment_address: &str, from: i64) -> Result<String, ErrorCode> {
+    let (receiver, command_handle, cb) = callbacks::cb_ec_string();
+
+    let error_code = sovtoken::api::build_get_utxo_request_with_from_handler(
+        command_handle,
+        wallet_handle,
+        c_pointer_from_str(did),
+        c_pointer_from_str(payment_address),
+        from,
+        cb
+    );
+
+    return ResultHandler::one(ErrorCode::from(error_code), receiver);
+}
+
+fn parse_get_payment_sources_response_with_next(res: &str) -> Result<(String, i64), ErrorCode> {
+    let (receiver, command_handle, cb) = callbacks::cb_ec_string_i64();
+
+    let error_code = sovtoken::api::parse_get_utxo_response_with_next_handler(
+        command_handle,
+        c_pointer_from_str(res),
+        cb
+    );
+
+    if ErrorCode::from(error_code) != ErrorCode::Success {
+        return Err(ErrorCode::from(error_code));
+    }
+
+    let (error_code, utxo_json, next) = receiver.recv().unwrap();
+    if error_code != ErrorCode::Success {
+        return Err(error_code);
+    }
+    Ok((utxo_json, next))
+}
+
 fn parse_get_payment_sources_response(res: &str) -> Result<String, ErrorCode> {
     let (receiver, command_handle, cb) = callbacks::cb_ec_string();
 
@@ -87,6 +122,72 @@ fn build_get_utxo_json() {
     assert_eq!(&expected_operation, request_value.get("operation").unwrap());
 }
 
+#[test]
+fn build_get_utxo_json_with_from() {
+    let did = bs58::encode("1234567890123456").into_string();
+    let expected_operation = json!({
+        "type": "10002",
+        "address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+        "from": 15
+    });
+
+    let request = build_get_payment_sources_request_with_from(WALLET_ID, &did, &ADDRESS, 15).unwrap();
+
+    let request_value: serde_json::value::Value = serde_json::from_str(&request).unwrap();
+
+    assert_eq!(&expected_operation, request_value.get("operation").unwrap());
+}
+
+#[test]
+fn build_get_utxo_json_with_negative_from() {
+    let did = bs58::encode("1234567890123456").into_string();
+
+    let request = build_get_payment_sources_request_with_from(WALLET_ID, &did, &ADDRESS, -1).unwrap();
+
+    let request_value: serde_json::value::Value = serde_json::from_str(&request).unwrap();
+
+    assert!(request_value.get("operation").unwrap().get("from").is_none());
+}
+
+#[test]
+fn parse_get_utxo_response_with_next() {
+    let response = json!({
+        "op": "REPLY",
+        "protocol_version": 2,
+        "result": {
+            "type": "10002",
+            "address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "outputs": [["dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q", 1, 40]],
+            "next": 2
+        }
+    }).to_string();
+
+    let (utxo_json, next) = parse_get_payment_sources_response_with_next(&response).unwrap();
+    let utxos: Vec<serde_json::Value> = serde_json::from_str(&utxo_json).unwrap();
+    assert_eq!(1, utxos.len());
+    assert_eq!(2, next);
+}
+
+#[test]
+fn parse_get_utxo_response_without_next() {
+    let response = json!({
+        "op": "REPLY",
+        "protocol_version": 2,
+        "result": {
+            "type": "10002",
+            "address": "dctKSXBbv2My3TGGUgTFjkxu1A9JM3Sscd5FydY4dkxnfwA7q",
+            "identifier": "6ouriXMZkLeHsuXrN1X1fd",
+            "reqId": 15424,
+            "outputs": []
+        }
+    }).to_string();
+
+    let (_, next) = parse_get_payment_sources_response_with_next(&response).unwrap();
+    assert_eq!(-1, next);
+}
+
 #[test]
 pub fn build_and_submit_get_utxo_request() {
     let wallet = Wallet::new();
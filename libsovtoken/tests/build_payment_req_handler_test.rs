@@ -19,7 +19,7 @@ use sovtoken::logic::parsers::common::TXO;
 use sovtoken::{ErrorCode, IndyHandle};
 use sovtoken::utils::constants::txn_types::XFER_PUBLIC;
 use sovtoken::utils::results::ResultHandler;
-use sovtoken::utils::ffi_support::{c_pointer_from_string, c_pointer_from_str};
+use sovtoken::utils::ffi_support::{c_pointer_from_string, c_pointer_from_str, string_from_char_ptr};
 use sovtoken::utils::test::callbacks;
 
 mod utils;
@@ -142,6 +142,25 @@ fn errors_with_no_outputs_json() {
     assert_eq!(return_error, ErrorCode::CommonInvalidStructure as i32, "Expecting outputs_json for 'build_payment_req_handler'");
 }
 
+// the error of a failed call is kept as the current error of the thread
+#[test]
+fn errors_with_invalid_outputs_json_sets_current_error() {
+    let return_error = sovtoken::api::build_payment_req_handler(COMMAND_HANDLE,
+                                                                WALLET_HANDLE,
+                                                                ptr::null(),
+                                                                c_pointer_from_str(r#"["txo:sov:2jS4PHWQJKcawRxdW6GVsjnZBa1ecGdCssn7KhWYJZGTXgL7Es:1"]"#),
+                                                                c_pointer_from_str(INVALID_OUTPUT_JSON),
+                                                                ptr::null(),
+                                                                CB);
+    assert_eq!(return_error, ErrorCode::CommonInvalidStructure as i32);
+
+    let mut error_json_p: *const c_char = ptr::null();
+    sovtoken::api::sovtoken_get_current_error(&mut error_json_p);
+    let error: serde_json::Value = serde_json::from_str(&string_from_char_ptr(error_json_p).unwrap()).unwrap();
+    assert_eq!(json!("outputs_json"), error["parameter"]);
+    assert_eq!(1, error["causes"].as_array().unwrap().len());
+}
+
 // the build payment req handler method requires an submitter_did parameter and this test ensures that
 // a error is returned when no config is provided
 #[test]
@@ -224,6 +243,68 @@ fn success_signed_request() {
 
 }
 
+#[test]
+fn success_signed_request_with_balance_check() {
+    sovtoken::api::sovtoken_init();
+
+    let did = String::from("V4SGRU86Z58d6TV7PBUe6f");
+    let wallet = Wallet::new();
+
+    let (payment_addresses, addresses) = generate_payment_addresses(&wallet);
+    let txo_1 = TXO { address: payment_addresses[0].clone(), seq_no: 1 }.to_libindy_string().unwrap();
+    let txo_2 = TXO { address: payment_addresses[1].clone(), seq_no: 1 }.to_libindy_string().unwrap();
+
+    let inputs = json!([txo_1, txo_2]);
+    let outputs = json!([
+            {"recipient": payment_addresses[2], "amount": 10},
+            {"recipient": payment_addresses[3], "amount": 22}
+        ]);
+    let utxos = json!([
+            {"paymentAddress": payment_addresses[0], "source": txo_1, "amount": 15, "extra": ""},
+            {"paymentAddress": payment_addresses[1], "source": txo_2, "amount": 17, "extra": ""}
+        ]);
+    let extra = json!({"data": "x", "balanceCheck": {"utxos": utxos, "fee": 0}});
+
+    let request_string = build_payment_req(
+        wallet.handle,
+        &did,
+        &inputs.to_string(),
+        &outputs.to_string(),
+        Some(extra.to_string())
+    ).unwrap();
+
+    let request: serde_json::value::Value = serde_json::from_str(&request_string).unwrap();
+    let operation = request.get("operation").unwrap();
+    assert_eq!(&json!({"data": "x"}), operation.get("extra").unwrap());
+    assert_eq!(&json!([{"address": addresses[0], "seqNo": 1}, {"address": addresses[1], "seqNo": 1}]), operation.get("inputs").unwrap());
+}
+
+#[test]
+fn errors_with_failing_balance_check() {
+    sovtoken::api::sovtoken_init();
+
+    let did = String::from("V4SGRU86Z58d6TV7PBUe6f");
+    let wallet = Wallet::new();
+    let (payment_addresses, _) = generate_payment_addresses(&wallet);
+    let txo = TXO { address: payment_addresses[0].clone(), seq_no: 1 }.to_libindy_string().unwrap();
+
+    let inputs = json!([txo]);
+    let outputs = json!([{"recipient": payment_addresses[2], "amount": 10}]);
+    let utxos = json!([{"paymentAddress": payment_addresses[0], "source": txo, "amount": 10, "extra": ""}]);
+
+    let extra = json!({"balanceCheck": {"utxos": utxos, "fee": 1}});
+    let err = build_payment_req(wallet.handle, &did, &inputs.to_string(), &outputs.to_string(), Some(extra.to_string())).unwrap_err();
+    assert_eq!(ErrorCode::PaymentInsufficientFundsError, err);
+
+    let extra = json!({"balanceCheck": {"utxos": [], "fee": 0}});
+    let err = build_payment_req(wallet.handle, &did, &inputs.to_string(), &outputs.to_string(), Some(extra.to_string())).unwrap_err();
+    assert_eq!(ErrorCode::PaymentSourceDoesNotExistError, err);
+
+    let extra = json!({"balanceCheck": {"fee": 1}});
+    let err = build_payment_req(wallet.handle, &did, &inputs.to_string(), &outputs.to_string(), Some(extra.to_string())).unwrap_err();
+    assert_eq!(ErrorCode::CommonInvalidStructure, err);
+}
+
 #[test] // TODO: look carefully on changes
 fn success_signed_request_from_libindy_no_identifier() {
 
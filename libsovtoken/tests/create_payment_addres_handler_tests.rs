@@ -34,9 +34,13 @@ static INVALID_CONFIG_JSON: &'static str = r#"{ "horrible" : "only on tuedays"}"
 // ***** HELPER METHODS  *****
 
 fn create_payment_address(wallet: &utils::wallet::Wallet, config: PaymentAddressConfig) -> String {
+    let config_str = config.serialize_to_cstring().unwrap();
+    create_payment_address_from_json(wallet, config_str)
+}
+
+fn create_payment_address_from_json(wallet: &utils::wallet::Wallet, config_str: CString) -> String {
     let (receiver, command_handle, cb) = callbacks::cb_ec_string();
 
-    let config_str = config.serialize_to_cstring().unwrap();
     let config_str_ptr = config_str.as_ptr();
 
     let return_error = sovtoken::api::create_payment_address_handler(command_handle, wallet.handle, config_str_ptr, cb);
@@ -93,7 +97,7 @@ fn successfully_creates_payment_address_with_no_seed() {
 
     let wallet = utils::wallet::Wallet::new();
 
-    let config: PaymentAddressConfig = PaymentAddressConfig { seed: String::new() };
+    let config: PaymentAddressConfig = PaymentAddressConfig { seed: String::new(), ..Default::default() };
 
     let payment_address = create_payment_address(&wallet, config);
 
@@ -109,7 +113,7 @@ fn successfully_creates_payment_address_with_no_seed() {
 fn successfully_creates_payment_address_with_seed() {
     trace!("logging started for successfully_creates_payment_address_with_seed");
 
-    let config: PaymentAddressConfig = PaymentAddressConfig { seed: "00000000000000000000000000000000".to_string() };
+    let config: PaymentAddressConfig = PaymentAddressConfig { seed: "00000000000000000000000000000000".to_string(), ..Default::default() };
 
     let wallet = utils::wallet::Wallet::new();
 
@@ -120,4 +124,20 @@ fn successfully_creates_payment_address_with_seed() {
     debug!("******* got address of {}", payment_address);
 
     assert_eq!(expected_payment_address, payment_address, "callback did not receive expected payment address");
-}
\ No newline at end of file
+}
+
+// a config with only a mnemonic and a passphrase creates the first address
+// of the mnemonic, the one of the seed derived from it
+#[test]
+fn successfully_creates_payment_address_from_mnemonic() {
+    let mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    let config_str = CString::new(json!({"mnemonic": mnemonic, "passphrase": "TREZOR"}).to_string()).unwrap();
+
+    let wallet = utils::wallet::Wallet::new();
+    let payment_address = create_payment_address_from_json(&wallet, config_str);
+
+    let config = PaymentAddressConfig { mnemonic: Some(mnemonic.to_string()), passphrase: Some("TREZOR".to_string()), ..Default::default() };
+    let expected_payment_address = create_payment_address(&utils::wallet::Wallet::new(), config.key_config().unwrap());
+
+    assert_eq!(expected_payment_address, payment_address, "callback did not receive the address of the mnemonic");
+}
@@ -129,7 +129,7 @@ fn success_callback_is_called() {
     let (receiver, command_handle, cb) = callbacks::cb_ec_string();
 
     let seed = rand_string(VALID_SEED_LEN);
-    let config: PaymentAddressConfig = PaymentAddressConfig { seed, };
+    let config: PaymentAddressConfig = PaymentAddressConfig { seed, ..Default::default() };
 
     let config_str =  config.serialize_to_cstring().unwrap();
     let config_str_ptr = config_str.as_ptr();